@@ -0,0 +1,229 @@
+//! Derive macros for secret-storage-lite, use them through its `derive` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, Data, DataEnum, DeriveInput, Expr, ExprLit, Fields, Ident, Lit, Type,
+};
+
+/// Derives `PrimaryKey`, `Prefixer` and `KeyDeserialize` for a struct of key fields or a
+/// fieldless enum.
+///
+/// A struct is encoded like the tuple of its fields in declaration order. Every field type must
+/// be usable as a key that deserializes back into itself, like `Addr`, `String` or the integers.
+///
+/// An enum is encoded as the 1 byte discriminant of its variant, so the keys stay the same when
+/// variants are reordered only if the discriminants are written out, e.g. `Buy = 0`.
+///
+/// The type must be `Clone` and can't have generic parameters.
+#[proc_macro_derive(PrimaryKey)]
+pub fn derive_primary_key(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_primary_key(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_primary_key(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "PrimaryKey can't be derived for generic types",
+        ));
+    }
+    match &input.data {
+        Data::Struct(data) => expand_struct(name, &data.fields),
+        Data::Enum(data) => expand_enum(name, data),
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            name,
+            "PrimaryKey can only be derived for structs and enums",
+        )),
+    }
+}
+
+fn expand_struct(name: &Ident, fields: &Fields) -> syn::Result<TokenStream2> {
+    if fields.is_empty() {
+        return Err(syn::Error::new_spanned(
+            name,
+            "PrimaryKey can't be derived for structs without fields",
+        ));
+    }
+
+    let types: Vec<&Type> = fields.iter().map(|f| &f.ty).collect();
+    let accessors: Vec<TokenStream2> = match fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|f| {
+                let ident = &f.ident;
+                quote!(#ident)
+            })
+            .collect(),
+        _ => (0..types.len())
+            .map(|i| {
+                let index = syn::Index::from(i);
+                quote!(#index)
+            })
+            .collect(),
+    };
+    let bindings: Vec<_> = (0..types.len())
+        .map(|i| format_ident!("field{}", i))
+        .collect();
+    let construct = match fields {
+        Fields::Named(_) => quote!(#name { #(#accessors: #bindings),* }),
+        _ => quote!(#name ( #(#bindings),* )),
+    };
+
+    let krate = quote!(::secret_storage_lite);
+    let n = types.len();
+    // the associated types follow those of the equivalent tuple
+    let (prefix, sub_prefix, suffix, super_suffix) = if n == 1 {
+        (quote!(()), quote!(()), quote!(Self), quote!(Self))
+    } else {
+        let last = types[n - 1];
+        let super_suffix = if n == 2 {
+            quote!(Self)
+        } else {
+            tuple(&types[n - 2..])
+        };
+        (
+            tuple(&types[..n - 1]),
+            tuple(&types[..n - 2]),
+            quote!(#last),
+            super_suffix,
+        )
+    };
+    let all = tuple(&types);
+    let destructure = match bindings.as_slice() {
+        [single] => quote!(#single),
+        _ => quote!((#(#bindings),*)),
+    };
+
+    Ok(quote! {
+        impl<'a> #krate::PrimaryKey<'a> for #name {
+            type Prefix = #prefix;
+            type SubPrefix = #sub_prefix;
+            type Suffix = #suffix;
+            type SuperSuffix = #super_suffix;
+
+            fn key(&self) -> ::std::vec::Vec<#krate::Key<'_>> {
+                let mut keys = ::std::vec::Vec::new();
+                #(keys.extend(#krate::PrimaryKey::key(&self.#accessors));)*
+                keys
+            }
+        }
+
+        impl<'a> #krate::Prefixer<'a> for #name {
+            fn prefix(&self) -> ::std::vec::Vec<#krate::Key<'_>> {
+                let mut prefixes = ::std::vec::Vec::new();
+                #(prefixes.extend(#krate::Prefixer::prefix(&self.#accessors));)*
+                prefixes
+            }
+        }
+
+        impl #krate::KeyDeserialize for #name {
+            type Output = Self;
+
+            const KEY_ELEMS: u16 = <#all as #krate::KeyDeserialize>::KEY_ELEMS;
+
+            fn from_vec(value: ::std::vec::Vec<u8>) -> #krate::__private::StdResult<Self> {
+                let #destructure = <#all as #krate::KeyDeserialize>::from_vec(value)?;
+                ::std::result::Result::Ok(#construct)
+            }
+        }
+    })
+}
+
+fn expand_enum(name: &Ident, data: &DataEnum) -> syn::Result<TokenStream2> {
+    if data.variants.is_empty() {
+        return Err(syn::Error::new_spanned(
+            name,
+            "PrimaryKey can't be derived for enums without variants",
+        ));
+    }
+
+    // the discriminants are assigned like the compiler does, counting up from the last one set
+    let mut next: u16 = 0;
+    let mut variants = Vec::new();
+    let mut discriminants = Vec::new();
+    for variant in &data.variants {
+        if !variant.fields.is_empty() {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "PrimaryKey can only be derived for enums without fields",
+            ));
+        }
+        if let Some((_, expr)) = &variant.discriminant {
+            next = match expr {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Int(int), ..
+                }) => int.base10_parse()?,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        expr,
+                        "the discriminant of a key must be an integer literal",
+                    ))
+                }
+            };
+        }
+        let discriminant = u8::try_from(next).map_err(|_| {
+            syn::Error::new_spanned(variant, "the discriminant of a key must fit in a u8")
+        })?;
+        variants.push(&variant.ident);
+        discriminants.push(discriminant);
+        next += 1;
+    }
+
+    let krate = quote!(::secret_storage_lite);
+    let error = format!("Invalid {} key", name);
+
+    let discriminant = quote! {
+        match self {
+            #(#name::#variants => #discriminants,)*
+        }
+    };
+
+    Ok(quote! {
+        impl<'a> #krate::PrimaryKey<'a> for #name {
+            type Prefix = ();
+            type SubPrefix = ();
+            type Suffix = Self;
+            type SuperSuffix = Self;
+
+            fn key(&self) -> ::std::vec::Vec<#krate::Key<'_>> {
+                ::std::vec![#krate::Key::Val8([#discriminant])]
+            }
+        }
+
+        impl<'a> #krate::Prefixer<'a> for #name {
+            fn prefix(&self) -> ::std::vec::Vec<#krate::Key<'_>> {
+                ::std::vec![#krate::Key::Val8([#discriminant])]
+            }
+        }
+
+        impl #krate::KeyDeserialize for #name {
+            type Output = Self;
+
+            const KEY_ELEMS: u16 = 1;
+
+            fn from_vec(value: ::std::vec::Vec<u8>) -> #krate::__private::StdResult<Self> {
+                match value.as_slice() {
+                    #([#discriminants] => ::std::result::Result::Ok(#name::#variants),)*
+                    _ => ::std::result::Result::Err(
+                        #krate::__private::StdError::generic_err(#error),
+                    ),
+                }
+            }
+        }
+    })
+}
+
+/// The tuple of `types`, or the type itself if there is only one
+fn tuple(types: &[&Type]) -> TokenStream2 {
+    match types {
+        [single] => quote!(#single),
+        _ => quote!((#(#types),*)),
+    }
+}
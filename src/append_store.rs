@@ -0,0 +1,275 @@
+use cosmwasm_std::{StdError, StdResult, Storage};
+
+use crate::error::{StorageError, StorageResult};
+use crate::queue::{load_u32, save_u32};
+use crate::serialization::{Bincode2, Serde};
+use crate::Map;
+
+/// An append-only list of items, e.g. a transaction history, where every item is
+/// stored under its position and the number of items is kept in a separate counter
+pub struct AppendStore<'a, T, Ser = Bincode2> {
+    map: Map<'a, u32, T, Ser>,
+}
+
+impl<'a, T, Ser> AppendStore<'a, T, Ser> {
+    const LEN: &'static [u8] = b"_len";
+
+    pub const fn new(namespace: &'a str) -> Self {
+        Self {
+            map: Map::new(namespace),
+        }
+    }
+
+    /// Creates a store under a namespace only known at runtime, e.g. one built with `Namespace`
+    pub fn new_owned(namespace: impl Into<Vec<u8>>) -> Self {
+        Self {
+            map: Map::new_owned(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+
+    /// Returns the number of items in the store
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.with_namespace_suffix(Self::LEN, |ns| load_u32(store, ns))
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    fn set_len(&self, store: &mut dyn Storage, len: u32) {
+        self.with_namespace_suffix(Self::LEN, |ns| save_u32(store, ns, len))
+    }
+
+    fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
+        let namespace = &[self.namespace(), namespace].concat();
+        f(namespace)
+    }
+}
+
+impl<'a, T, Ser> AppendStore<'a, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    /// Appends an item to the end of the store, returning its index
+    pub fn push(&self, store: &mut dyn Storage, t: &T) -> StorageResult<u32> {
+        let len = self.len(store);
+        if len == u32::MAX {
+            return Err(StorageError::CapacityExceeded {
+                collection: "AppendStore",
+            });
+        }
+
+        self.map.save(store, len, t)?;
+        self.set_len(store, len + 1);
+
+        Ok(len)
+    }
+
+    /// Returns the item at `index`, or None if `index` is out of bounds
+    pub fn get(&self, store: &dyn Storage, index: u32) -> StdResult<Option<T>> {
+        if index >= self.len(store) {
+            return Ok(None);
+        }
+
+        Ok(self.map.load(store, index).map(Some)?)
+    }
+
+    /// Returns an iterator over the items in the store, from oldest to newest
+    pub fn iter<'c>(&'c self, store: &'c dyn Storage) -> AppendStoreIter<'a, 'c, T, Ser> {
+        AppendStoreIter {
+            append_store: self,
+            store,
+            start: 0,
+            end: self.len(store),
+        }
+    }
+
+    /// Returns the items of page `page`, from oldest to newest, where every page holds
+    /// `page_size` items
+    pub fn paging(&self, store: &dyn Storage, page: u32, page_size: u32) -> StdResult<Vec<T>> {
+        let start = page.saturating_mul(page_size);
+        self.iter(store)
+            .skip(start as usize)
+            .take(page_size as usize)
+            .collect()
+    }
+
+    /// Returns the items of page `page`, from newest to oldest, where every page holds
+    /// `page_size` items. Page zero holds the most recently pushed items
+    pub fn paging_rev(&self, store: &dyn Storage, page: u32, page_size: u32) -> StdResult<Vec<T>> {
+        let start = page.saturating_mul(page_size);
+        self.iter(store)
+            .rev()
+            .skip(start as usize)
+            .take(page_size as usize)
+            .collect()
+    }
+}
+
+/// An iterator over the items of an `AppendStore`, from oldest to newest, created by `AppendStore::iter`
+pub struct AppendStoreIter<'a, 'c, T, Ser = Bincode2> {
+    append_store: &'c AppendStore<'a, T, Ser>,
+    store: &'c dyn Storage,
+    start: u32,
+    end: u32,
+}
+
+impl<'a, 'c, T, Ser> Iterator for AppendStoreIter<'a, 'c, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    type Item = StdResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start >= self.end {
+            return None;
+        }
+        let index = self.start;
+        self.start += 1;
+        Some(
+            self.append_store
+                .map
+                .load(self.store, index)
+                .map_err(StdError::from),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.end - self.start) as usize;
+        (len, Some(len))
+    }
+
+    // skipping ahead does not need to load the skipped items
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.start = self
+            .start
+            .saturating_add(n.try_into().unwrap_or(u32::MAX))
+            .min(self.end);
+        self.next()
+    }
+}
+
+impl<'a, 'c, T, Ser> DoubleEndedIterator for AppendStoreIter<'a, 'c, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.start >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(
+            self.append_store
+                .map
+                .load(self.store, self.end)
+                .map_err(StdError::from),
+        )
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        self.end = self
+            .end
+            .saturating_sub(n.try_into().unwrap_or(u32::MAX))
+            .max(self.start);
+        self.next_back()
+    }
+}
+
+impl<'a, 'c, T, Ser> ExactSizeIterator for AppendStoreIter<'a, 'c, T, Ser> where Ser: Serde<T> {}
+
+#[cfg(test)]
+mod test {
+    use cosmwasm_std::testing::MockStorage;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::serialization::Json;
+
+    const HISTORY: AppendStore<u8> = AppendStore::new("history");
+
+    proptest! {
+        #[test]
+        fn behaves_like_a_vec(items: Vec<u8>) {
+            let mut store = MockStorage::new();
+
+            for (i, item) in items.iter().enumerate() {
+                prop_assert_eq!(HISTORY.push(&mut store, item).unwrap(), i as u32);
+            }
+
+            prop_assert_eq!(HISTORY.len(&store) as usize, items.len());
+            for (i, item) in items.iter().enumerate() {
+                prop_assert_eq!(HISTORY.get(&store, i as u32).unwrap(), Some(*item));
+            }
+            prop_assert_eq!(HISTORY.get(&store, items.len() as u32).unwrap(), None);
+
+            let all: Vec<u8> = HISTORY.iter(&store).map(Result::unwrap).collect();
+            prop_assert_eq!(&all, &items);
+            let all: Vec<u8> = HISTORY.iter(&store).rev().map(Result::unwrap).collect();
+            prop_assert_eq!(all, items.iter().rev().copied().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn paging_works() {
+        let mut store = MockStorage::new();
+        let history: AppendStore<u32> = AppendStore::new("txs");
+
+        assert!(history.is_empty(&store));
+        assert!(history.paging(&store, 0, 10).unwrap().is_empty());
+
+        for i in 0..7 {
+            history.push(&mut store, &i).unwrap();
+        }
+
+        assert_eq!(history.paging(&store, 0, 3).unwrap(), vec![0, 1, 2]);
+        assert_eq!(history.paging(&store, 2, 3).unwrap(), vec![6]);
+        assert!(history.paging(&store, 3, 3).unwrap().is_empty());
+
+        assert_eq!(history.paging_rev(&store, 0, 3).unwrap(), vec![6, 5, 4]);
+        assert_eq!(history.paging_rev(&store, 1, 3).unwrap(), vec![3, 2, 1]);
+        assert_eq!(history.paging_rev(&store, 2, 3).unwrap(), vec![0]);
+        assert!(history
+            .paging_rev(&store, u32::MAX, u32::MAX)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn iter_is_double_ended() {
+        let mut store = MockStorage::new();
+        let history: AppendStore<u32> = AppendStore::new("txs");
+
+        for i in 0..5 {
+            history.push(&mut store, &i).unwrap();
+        }
+
+        let mut iter = history.iter(&store);
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next().unwrap().unwrap(), 0);
+        assert_eq!(iter.next_back().unwrap().unwrap(), 4);
+        assert_eq!(iter.nth(1).unwrap().unwrap(), 2);
+        assert_eq!(iter.len(), 1);
+        assert_eq!(iter.next_back().unwrap().unwrap(), 3);
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn works_with_other_serializations() {
+        let mut store = MockStorage::new();
+        let history: AppendStore<String, Json> = AppendStore::new_owned("txs");
+
+        history.push(&mut store, &"deposit".to_string()).unwrap();
+        history.push(&mut store, &"withdraw".to_string()).unwrap();
+
+        let slots: Map<u32, String, Json> = Map::new("txs");
+        assert_eq!(slots.load(&store, 1).unwrap(), "withdraw");
+        assert_eq!(
+            history.paging_rev(&store, 0, 5).unwrap(),
+            vec!["withdraw".to_string(), "deposit".to_string()]
+        );
+    }
+}
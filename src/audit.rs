@@ -0,0 +1,211 @@
+//! Wrappers around `Item` and `Map` that describe every write as log attributes, which a handler
+//! appends to the `log` of its `HandleResponse` so indexers can follow state changes.
+//!
+//! A write logs the namespace, the key in hex for a map, and the sha256 hashes of the stored bytes
+//! before and after, `none` where there were none. The attributes are encrypted like those of
+//! `cosmwasm_std::log`: a hash of a value with few possible states, like a small balance, gives
+//! the value away to anyone who can read it.
+
+use cosmwasm_std::{log, LogAttribute, StdResult, Storage};
+use sha2::{Digest, Sha256};
+
+use crate::error::to_hex;
+use crate::helpers::split_namespace;
+use crate::keys::PrimaryKey;
+use crate::serialization::{Bincode2, Serde};
+use crate::{Item, Map};
+
+/// Describes the change of the bytes stored under `key` of `namespace` from `old` to `new`
+fn change_log(
+    namespace: &[u8],
+    key: Option<&[u8]>,
+    old: Option<&[u8]>,
+    new: Option<&[u8]>,
+) -> Vec<LogAttribute> {
+    let hash = |bytes: Option<&[u8]>| match bytes {
+        Some(bytes) => to_hex(&Sha256::digest(bytes)),
+        None => "none".to_string(),
+    };
+    let mut attributes = vec![log("storage_namespace", String::from_utf8_lossy(namespace))];
+    if let Some(key) = key {
+        attributes.push(log("storage_key", to_hex(key)));
+    }
+    attributes.push(log("storage_old_hash", hash(old)));
+    attributes.push(log("storage_new_hash", hash(new)));
+    attributes
+}
+
+/// An `Item` whose writes return log attributes describing them
+pub struct AuditedItem<'a, T, Ser = Bincode2> {
+    item: Item<'a, T, Ser>,
+}
+
+impl<'a, T, Ser> AuditedItem<'a, T, Ser> {
+    pub const fn new(storage_key: &'a str) -> Self {
+        AuditedItem {
+            item: Item::new(storage_key),
+        }
+    }
+}
+
+impl<'a, T, Ser: Serde<T>> AuditedItem<'a, T, Ser> {
+    /// The wrapped item, to read it. Writes through it are not logged
+    pub fn item(&self) -> &Item<'a, T, Ser> {
+        &self.item
+    }
+
+    pub fn load(&self, store: &dyn Storage) -> StdResult<T> {
+        Ok(self.item.load(store)?)
+    }
+
+    pub fn may_load(&self, store: &dyn Storage) -> StdResult<Option<T>> {
+        Ok(self.item.may_load(store)?)
+    }
+
+    /// Saves `data`, returns the attributes describing the change
+    pub fn save(&self, store: &mut dyn Storage, data: &T) -> StdResult<Vec<LogAttribute>> {
+        let old = self.item.may_load_raw(store);
+        let new = Ser::serialize(data)?;
+        self.item.save_raw(store, &new);
+        Ok(change_log(
+            self.item.as_slice(),
+            None,
+            old.as_deref(),
+            Some(&new),
+        ))
+    }
+
+    /// Removes the item, returns the attributes describing the change, none if it wasn't set
+    pub fn remove(&self, store: &mut dyn Storage) -> Vec<LogAttribute> {
+        match self.item.may_load_raw(store) {
+            Some(old) => {
+                self.item.remove(store);
+                change_log(self.item.as_slice(), None, Some(&old), None)
+            }
+            None => vec![],
+        }
+    }
+}
+
+/// A `Map` whose writes return log attributes describing them
+pub struct AuditedMap<'a, K, T, Ser = Bincode2> {
+    map: Map<'a, K, T, Ser>,
+}
+
+impl<'a, K, T, Ser> AuditedMap<'a, K, T, Ser> {
+    pub const fn new(namespace: &'a str) -> Self {
+        AuditedMap {
+            map: Map::new(namespace),
+        }
+    }
+}
+
+impl<'a, K: PrimaryKey<'a>, T, Ser: Serde<T>> AuditedMap<'a, K, T, Ser> {
+    /// The wrapped map, to read or iterate it. Writes through it are not logged
+    pub fn map(&self) -> &Map<'a, K, T, Ser> {
+        &self.map
+    }
+
+    pub fn load(&self, store: &dyn Storage, k: K) -> StdResult<T> {
+        Ok(self.map.load(store, k)?)
+    }
+
+    pub fn may_load(&self, store: &dyn Storage, k: K) -> StdResult<Option<T>> {
+        Ok(self.map.may_load(store, k)?)
+    }
+
+    pub fn has(&self, store: &dyn Storage, k: K) -> bool {
+        self.map.has(store, k)
+    }
+
+    /// Saves `data` under `k`, returns the attributes describing the change
+    pub fn save(&self, store: &mut dyn Storage, k: K, data: &T) -> StdResult<Vec<LogAttribute>> {
+        let path = self.map.key(k.clone());
+        let old = path.may_load_raw(store);
+        let new = Ser::serialize(data)?;
+        self.map.save_raw(store, k, &new)?;
+        Ok(change_log(
+            self.map.name(),
+            Some(split_namespace(&path).1),
+            old.as_deref(),
+            Some(&new),
+        ))
+    }
+
+    /// Removes `k`, returns the attributes describing the change, none if it wasn't set
+    pub fn remove(&self, store: &mut dyn Storage, k: K) -> Vec<LogAttribute> {
+        let path = self.map.key(k.clone());
+        match path.may_load_raw(store) {
+            Some(old) => {
+                self.map.remove(store, k);
+                change_log(
+                    self.map.name(),
+                    Some(split_namespace(&path).1),
+                    Some(&old),
+                    None,
+                )
+            }
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const CONFIG: AuditedItem<u64> = AuditedItem::new("config");
+    const BALANCES: AuditedMap<&str, u64> = AuditedMap::new("balances");
+
+    fn attribute<'l>(attributes: &'l [LogAttribute], key: &str) -> &'l str {
+        &attributes.iter().find(|a| a.key == key).unwrap().value
+    }
+
+    fn hash(value: u64) -> String {
+        to_hex(&Sha256::digest(Bincode2::serialize(&value).unwrap()))
+    }
+
+    #[test]
+    fn item_writes_are_logged() {
+        let mut store = MockStorage::new();
+
+        let attributes = CONFIG.save(&mut store, &1).unwrap();
+        assert_eq!(
+            attributes
+                .iter()
+                .map(|a| a.key.as_str())
+                .collect::<Vec<_>>(),
+            ["storage_namespace", "storage_old_hash", "storage_new_hash"]
+        );
+        assert_eq!(attribute(&attributes, "storage_namespace"), "config");
+        assert_eq!(attribute(&attributes, "storage_old_hash"), "none");
+        assert_eq!(attribute(&attributes, "storage_new_hash"), hash(1));
+        assert!(attributes.iter().all(|a| a.encrypted));
+
+        let attributes = CONFIG.save(&mut store, &2).unwrap();
+        assert_eq!(attribute(&attributes, "storage_old_hash"), hash(1));
+        assert_eq!(CONFIG.load(&store).unwrap(), 2);
+
+        let attributes = CONFIG.remove(&mut store);
+        assert_eq!(attribute(&attributes, "storage_old_hash"), hash(2));
+        assert_eq!(attribute(&attributes, "storage_new_hash"), "none");
+        assert!(CONFIG.remove(&mut store).is_empty());
+    }
+
+    #[test]
+    fn map_writes_are_logged() {
+        let mut store = MockStorage::new();
+
+        let attributes = BALANCES.save(&mut store, "alice", &5).unwrap();
+        assert_eq!(attribute(&attributes, "storage_namespace"), "balances");
+        assert_eq!(attribute(&attributes, "storage_key"), to_hex(b"alice"));
+        assert_eq!(attribute(&attributes, "storage_new_hash"), hash(5));
+        assert_eq!(BALANCES.map().load(&store, "alice").unwrap(), 5);
+
+        let attributes = BALANCES.remove(&mut store, "alice");
+        assert_eq!(attribute(&attributes, "storage_old_hash"), hash(5));
+        assert!(!BALANCES.has(&store, "alice"));
+        assert!(BALANCES.remove(&mut store, "alice").is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+use cosmwasm_std::{StdResult, Storage};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::StorageResult;
+use crate::keys::PrimaryKey;
+use crate::Map;
+
+/// BiMap maps left values to right values and back, e.g. names to addresses.
+///
+/// Every left value maps to at most one right value and the other way around, saving a pair
+/// replaces the pairs either value was part of. Both directions are stored as a `Map`, under
+/// their own namespace.
+pub struct BiMap<'a, L, R> {
+    left: Map<'a, L, R>,
+    right: Map<'a, R, L>,
+}
+
+impl<'a, L, R> BiMap<'a, L, R> {
+    pub const fn new(left_namespace: &'a str, right_namespace: &'a str) -> Self {
+        BiMap {
+            left: Map::new(left_namespace),
+            right: Map::new(right_namespace),
+        }
+    }
+}
+
+impl<'a, L, R> BiMap<'a, L, R>
+where
+    L: PrimaryKey<'a> + Serialize + DeserializeOwned,
+    R: PrimaryKey<'a> + Serialize + DeserializeOwned,
+{
+    /// Pairs `left` with `right`, removing the pairs either was part of before
+    pub fn save(&self, store: &mut dyn Storage, left: L, right: R) -> StorageResult<()> {
+        if let Some(old_right) = self.left.may_load(store, left.clone())? {
+            self.right.remove(store, old_right);
+        }
+        if let Some(old_left) = self.right.may_load(store, right.clone())? {
+            self.left.remove(store, old_left);
+        }
+        self.left.save(store, left.clone(), &right)?;
+        self.right.save(store, right, &left)
+    }
+
+    /// Removes the pair of `left`, returns its right value if there was one
+    pub fn remove_by_left(&self, store: &mut dyn Storage, left: L) -> StdResult<Option<R>> {
+        let right = self.left.may_load(store, left.clone())?;
+        if let Some(right) = &right {
+            self.right.remove(store, right.clone());
+            self.left.remove(store, left);
+        }
+        Ok(right)
+    }
+
+    /// Removes the pair of `right`, returns its left value if there was one
+    pub fn remove_by_right(&self, store: &mut dyn Storage, right: R) -> StdResult<Option<L>> {
+        let left = self.right.may_load(store, right.clone())?;
+        if let Some(left) = &left {
+            self.left.remove(store, left.clone());
+            self.right.remove(store, right);
+        }
+        Ok(left)
+    }
+
+    /// Returns the right value paired with `left`, or an error if there is none
+    pub fn load_by_left(&self, store: &dyn Storage, left: L) -> StdResult<R> {
+        Ok(self.left.load(store, left)?)
+    }
+
+    /// Returns the left value paired with `right`, or an error if there is none
+    pub fn load_by_right(&self, store: &dyn Storage, right: R) -> StdResult<L> {
+        Ok(self.right.load(store, right)?)
+    }
+
+    /// Returns the right value paired with `left`, None if there is none
+    pub fn may_load_by_left(&self, store: &dyn Storage, left: L) -> StdResult<Option<R>> {
+        Ok(self.left.may_load(store, left)?)
+    }
+
+    /// Returns the left value paired with `right`, None if there is none
+    pub fn may_load_by_right(&self, store: &dyn Storage, right: R) -> StdResult<Option<L>> {
+        Ok(self.right.may_load(store, right)?)
+    }
+
+    pub fn has_left(&self, store: &dyn Storage, left: L) -> bool {
+        self.left.has(store, left)
+    }
+
+    pub fn has_right(&self, store: &dyn Storage, right: R) -> bool {
+        self.right.has(store, right)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::HumanAddr as Addr;
+
+    const REGISTRY: BiMap<String, Addr> = BiMap::new("names", "addresses");
+
+    fn name(s: &str) -> String {
+        s.to_string()
+    }
+
+    #[test]
+    fn loads_both_ways() {
+        let mut store = MockStorage::new();
+        REGISTRY
+            .save(&mut store, name("alice"), Addr::from("secret1a"))
+            .unwrap();
+
+        assert_eq!(
+            REGISTRY.load_by_left(&store, name("alice")).unwrap(),
+            Addr::from("secret1a")
+        );
+        assert_eq!(
+            REGISTRY
+                .load_by_right(&store, Addr::from("secret1a"))
+                .unwrap(),
+            name("alice")
+        );
+        assert!(REGISTRY.load_by_left(&store, name("bob")).is_err());
+        assert_eq!(
+            REGISTRY
+                .may_load_by_right(&store, Addr::from("secret1b"))
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn save_replaces_old_pairs() {
+        let mut store = MockStorage::new();
+        REGISTRY
+            .save(&mut store, name("alice"), Addr::from("secret1a"))
+            .unwrap();
+        REGISTRY
+            .save(&mut store, name("bob"), Addr::from("secret1b"))
+            .unwrap();
+
+        // alice moves to a new address
+        REGISTRY
+            .save(&mut store, name("alice"), Addr::from("secret1c"))
+            .unwrap();
+        assert!(!REGISTRY.has_right(&store, Addr::from("secret1a")));
+
+        // bob's address is taken over by carol
+        REGISTRY
+            .save(&mut store, name("carol"), Addr::from("secret1b"))
+            .unwrap();
+        assert!(!REGISTRY.has_left(&store, name("bob")));
+        assert_eq!(
+            REGISTRY
+                .load_by_right(&store, Addr::from("secret1b"))
+                .unwrap(),
+            name("carol")
+        );
+    }
+
+    #[test]
+    fn removes_both_ways() {
+        let mut store = MockStorage::new();
+        REGISTRY
+            .save(&mut store, name("alice"), Addr::from("secret1a"))
+            .unwrap();
+        REGISTRY
+            .save(&mut store, name("bob"), Addr::from("secret1b"))
+            .unwrap();
+
+        assert_eq!(
+            REGISTRY.remove_by_left(&mut store, name("alice")).unwrap(),
+            Some(Addr::from("secret1a"))
+        );
+        assert!(!REGISTRY.has_right(&store, Addr::from("secret1a")));
+        assert_eq!(
+            REGISTRY.remove_by_left(&mut store, name("alice")).unwrap(),
+            None
+        );
+
+        assert_eq!(
+            REGISTRY
+                .remove_by_right(&mut store, Addr::from("secret1b"))
+                .unwrap(),
+            Some(name("bob"))
+        );
+        assert!(!REGISTRY.has_left(&store, name("bob")));
+    }
+}
@@ -0,0 +1,165 @@
+use cosmwasm_std::{StdResult, Storage};
+
+use crate::error::{StorageError, StorageResult};
+use crate::queue::{load_u32, save_u32};
+use crate::Map;
+
+/// Blob stores a value too large for a single key, split into chunks of `chunk_size` bytes
+/// stored under their position. The length in bytes is kept in a separate counter.
+///
+/// It stores bytes as they are, a typed value can be serialized with a backend first,
+/// e.g. `Json::serialize`. Appending only rewrites the last chunk.
+pub struct Blob<'a> {
+    chunks: Map<'a, u32, Vec<u8>>,
+    chunk_size: u32,
+}
+
+impl<'a> Blob<'a> {
+    const LEN: &'static [u8] = b"_len";
+
+    /// Panics if `chunk_size` is 0
+    pub const fn new(namespace: &'a str, chunk_size: u32) -> Self {
+        assert!(chunk_size > 0, "a Blob needs a chunk size");
+        Blob {
+            chunks: Map::new(namespace),
+            chunk_size,
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.chunks.namespace()
+    }
+
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    /// Returns the length of the value in bytes
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.with_namespace_suffix(Self::LEN, |ns| load_u32(store, ns))
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    fn set_len(&self, store: &mut dyn Storage, len: u32) {
+        self.with_namespace_suffix(Self::LEN, |ns| save_u32(store, ns, len))
+    }
+
+    fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
+        let namespace = &[self.namespace(), namespace].concat();
+        f(namespace)
+    }
+
+    /// Returns the number of chunks a value of `len` bytes is split into
+    fn chunk_count(&self, len: u32) -> u32 {
+        len.div_ceil(self.chunk_size)
+    }
+
+    /// Replaces the value with `data`, removing the chunks it no longer needs
+    pub fn save(&self, store: &mut dyn Storage, data: &[u8]) -> StorageResult<()> {
+        let len = Self::checked_len(data.len() as u64)?;
+        let old_count = self.chunk_count(self.len(store));
+        for (i, chunk) in (0..).zip(data.chunks(self.chunk_size as usize)) {
+            self.chunks.save_raw(store, i, chunk)?;
+        }
+        for i in self.chunk_count(len)..old_count {
+            self.chunks.remove(store, i);
+        }
+        self.set_len(store, len);
+        Ok(())
+    }
+
+    /// Returns the value, empty if none was saved
+    pub fn load(&self, store: &dyn Storage) -> StdResult<Vec<u8>> {
+        let len = self.len(store);
+        let mut data = Vec::with_capacity(len as usize);
+        for i in 0..self.chunk_count(len) {
+            data.extend(self.chunks.load_raw(store, i)?);
+        }
+        Ok(data)
+    }
+
+    /// Appends `data` to the value
+    pub fn append(&self, store: &mut dyn Storage, data: &[u8]) -> StorageResult<()> {
+        let old_len = self.len(store);
+        let len = Self::checked_len(old_len as u64 + data.len() as u64)?;
+
+        let mut rest = data;
+        let next = self.chunk_count(old_len);
+        let partial = old_len % self.chunk_size;
+        if partial > 0 && !rest.is_empty() {
+            // fill up the last chunk first
+            let last = next - 1;
+            let fill = rest.len().min((self.chunk_size - partial) as usize);
+            let mut chunk = self.chunks.load_raw(store, last)?;
+            chunk.extend_from_slice(&rest[..fill]);
+            self.chunks.save_raw(store, last, &chunk)?;
+            rest = &rest[fill..];
+        }
+        for (i, chunk) in (next..).zip(rest.chunks(self.chunk_size as usize)) {
+            self.chunks.save_raw(store, i, chunk)?;
+        }
+        self.set_len(store, len);
+        Ok(())
+    }
+
+    /// Removes the value and all its chunks
+    pub fn remove(&self, store: &mut dyn Storage) {
+        for i in 0..self.chunk_count(self.len(store)) {
+            self.chunks.remove(store, i);
+        }
+        self.set_len(store, 0);
+    }
+
+    fn checked_len(len: u64) -> StorageResult<u32> {
+        u32::try_from(len).map_err(|_| StorageError::CapacityExceeded { collection: "Blob" })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const DOC: Blob = Blob::new("doc", 4);
+
+    #[test]
+    fn save_and_load() {
+        let mut store = MockStorage::new();
+        assert!(DOC.is_empty(&store));
+        assert_eq!(DOC.load(&store).unwrap(), b"");
+
+        DOC.save(&mut store, b"hello world").unwrap();
+        assert_eq!(DOC.len(&store), 11);
+        assert_eq!(DOC.load(&store).unwrap(), b"hello world");
+        assert_eq!(DOC.chunks.load_raw(&store, 2).unwrap(), b"rld");
+
+        // a shorter value drops the chunks it doesn't need
+        DOC.save(&mut store, b"hi").unwrap();
+        assert_eq!(DOC.load(&store).unwrap(), b"hi");
+        assert!(!DOC.chunks.has(&store, 1));
+        assert!(!DOC.chunks.has(&store, 2));
+
+        DOC.remove(&mut store);
+        assert!(DOC.is_empty(&store));
+        assert!(!DOC.chunks.has(&store, 0));
+    }
+
+    #[test]
+    fn append_fills_the_last_chunk() {
+        let mut store = MockStorage::new();
+        DOC.append(&mut store, b"he").unwrap();
+        DOC.append(&mut store, b"llo").unwrap();
+        assert_eq!(DOC.chunks.load_raw(&store, 0).unwrap(), b"hell");
+        DOC.append(&mut store, b"").unwrap();
+        DOC.append(&mut store, b" world, again").unwrap();
+        assert_eq!(DOC.len(&store), 18);
+        assert_eq!(DOC.load(&store).unwrap(), b"hello world, again");
+        for i in 0..4 {
+            assert_eq!(DOC.chunks.load_raw(&store, i).unwrap().len(), 4);
+        }
+        assert_eq!(DOC.chunks.load_raw(&store, 4).unwrap(), b"in");
+    }
+}
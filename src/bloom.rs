@@ -0,0 +1,154 @@
+use std::marker::PhantomData;
+
+use cosmwasm_std::Storage;
+use sha2::{Digest, Sha256};
+
+use crate::helpers::namespaces_with_key;
+use crate::keys::PrimaryKey;
+
+/// BloomFilter answers whether a key may have been inserted, without storing the keys.
+///
+/// `maybe_contains` never returns false for an inserted key, but may return true for a key
+/// that wasn't. With `n` keys inserted, the chance of that is about `(1 - e^(-hashes * n / bits))
+/// ^ hashes`, smallest with `hashes` about `0.7 * bits / n`. The bits are stored in pages of
+/// `PAGE_BYTES`, so a lookup reads at most `hashes` entries however large the filter is.
+pub struct BloomFilter<'a, K> {
+    namespace: &'a [u8],
+    bits: u32,
+    hashes: u32,
+    key_type: PhantomData<K>,
+}
+
+impl<'a, K> BloomFilter<'a, K> {
+    pub const PAGE_BYTES: u32 = 256;
+
+    /// Creates a filter of `bits` bits, setting `hashes` bits for every key.
+    /// Changing either makes the keys inserted before unreliable.
+    ///
+    /// Panics if `bits` or `hashes` is zero
+    pub const fn new(namespace: &'a str, bits: u32, hashes: u32) -> Self {
+        assert!(bits > 0, "a BloomFilter needs at least one bit");
+        assert!(hashes > 0, "a BloomFilter needs at least one hash");
+        BloomFilter {
+            namespace: namespace.as_bytes(),
+            bits,
+            hashes,
+            key_type: PhantomData,
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.namespace
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn hashes(&self) -> u32 {
+        self.hashes
+    }
+
+    fn page_key(&self, page: u32) -> Vec<u8> {
+        namespaces_with_key(&[self.namespace], &page.to_be_bytes())
+    }
+}
+
+impl<'a, K: PrimaryKey<'a>> BloomFilter<'a, K> {
+    /// Returns the bit positions of `k`, by double hashing its sha256 digest
+    fn positions(&self, k: &K) -> impl Iterator<Item = u32> {
+        let digest = Sha256::digest(k.joined_key());
+        let h1 = u64::from_be_bytes(digest[..8].try_into().unwrap());
+        // odd, so the positions don't repeat early when bits is a power of two
+        let h2 = u64::from_be_bytes(digest[8..16].try_into().unwrap()) | 1;
+        let bits = self.bits as u64;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bits) as u32)
+    }
+
+    /// Sets the bits of `k`, returns false if they were all set already,
+    /// i.e. if `k` may have been inserted before
+    pub fn insert(&self, store: &mut dyn Storage, k: K) -> bool {
+        let mut inserted = false;
+        for position in self.positions(&k) {
+            let (page, byte, mask) = locate(position);
+            let key = self.page_key(page);
+            let mut bytes = store.get(&key).unwrap_or_default();
+            if bytes.get(byte).is_some_and(|b| b & mask != 0) {
+                continue;
+            }
+            // pages are only as long as their last set bit needs
+            if bytes.len() <= byte {
+                bytes.resize(byte + 1, 0);
+            }
+            bytes[byte] |= mask;
+            store.set(&key, &bytes);
+            inserted = true;
+        }
+        inserted
+    }
+
+    /// Returns false if `k` was never inserted, true if it may have been
+    pub fn maybe_contains(&self, store: &dyn Storage, k: K) -> bool {
+        self.positions(&k).all(|position| {
+            let (page, byte, mask) = locate(position);
+            store
+                .get(&self.page_key(page))
+                .and_then(|bytes| bytes.get(byte).copied())
+                .is_some_and(|b| b & mask != 0)
+        })
+    }
+}
+
+/// Returns the page, the byte in the page and the mask of the bit at `position`
+fn locate(position: u32) -> (u32, usize, u8) {
+    let page_bits = BloomFilter::<()>::PAGE_BYTES * 8;
+    let offset = position % page_bits;
+    (
+        position / page_bits,
+        (offset / 8) as usize,
+        1 << (offset % 8),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const ALLOWLIST: BloomFilter<u32> = BloomFilter::new("allowlist", 4096, 7);
+
+    #[test]
+    fn inserted_keys_are_found() {
+        let mut store = MockStorage::new();
+        for i in 0..300 {
+            assert!(ALLOWLIST.insert(&mut store, i));
+        }
+        for i in 0..300 {
+            assert!(ALLOWLIST.maybe_contains(&store, i));
+            assert!(!ALLOWLIST.insert(&mut store, i));
+        }
+
+        // about 1% false positives are expected with these parameters
+        let false_positives = (300..10_300)
+            .filter(|i| ALLOWLIST.maybe_contains(&store, *i))
+            .count();
+        assert!(false_positives < 300, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn empty_filter_contains_nothing() {
+        let mut store = MockStorage::new();
+        let names: BloomFilter<&str> = BloomFilter::new("names", 100, 3);
+        assert!(!names.maybe_contains(&store, "alice"));
+
+        names.insert(&mut store, "alice");
+        assert!(names.maybe_contains(&store, "alice"));
+        assert!(!ALLOWLIST.maybe_contains(&store, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one hash")]
+    fn needs_a_hash() {
+        BloomFilter::<u32>::new("bad", 100, 0);
+    }
+}
@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, KV};
+use cosmwasm_std::{ReadonlyStorage, Storage};
+
+/// CachedStorage wraps a `Storage` and remembers every value read or written through it,
+/// so loading the same key again, e.g. a config `Item` used by many helpers of a handler,
+/// doesn't read the underlying storage again.
+///
+/// Writes go straight through to the underlying storage, which therefore always holds the
+/// current state, and ranges are read from it directly. Meant to live for a single execution,
+/// as changes to the underlying storage made around the cache aren't seen.
+pub struct CachedStorage<'a> {
+    storage: &'a mut dyn Storage,
+    cache: RefCell<BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
+}
+
+impl<'a> CachedStorage<'a> {
+    pub fn new(storage: &'a mut dyn Storage) -> Self {
+        CachedStorage {
+            storage,
+            cache: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Forgets all cached values, the next reads go to the underlying storage again
+    pub fn clear(&mut self) {
+        self.cache.borrow_mut().clear();
+    }
+}
+
+impl<'a> ReadonlyStorage for CachedStorage<'a> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        if let Some(value) = self.cache.borrow().get(key) {
+            return value.clone();
+        }
+        let value = self.storage.get(key);
+        self.cache.borrow_mut().insert(key.to_vec(), value.clone());
+        value
+    }
+
+    #[cfg(feature = "iterator")]
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = KV> + 'b> {
+        self.storage.range(start, end, order)
+    }
+}
+
+impl<'a> Storage for CachedStorage<'a> {
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.storage.set(key, value);
+        self.cache
+            .get_mut()
+            .insert(key.to_vec(), Some(value.to_vec()));
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.storage.remove(key);
+        self.cache.get_mut().insert(key.to_vec(), None);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    use crate::{CountingStorage, Item};
+
+    const CONFIG: Item<u64> = Item::new("config");
+
+    #[test]
+    fn reads_once() {
+        let mut store = MockStorage::new();
+        CONFIG.save(&mut store, &1).unwrap();
+
+        let mut counting = CountingStorage::new(&mut store);
+        let mut cached = CachedStorage::new(&mut counting);
+        for _ in 0..3 {
+            assert_eq!(CONFIG.load(&cached).unwrap(), 1);
+        }
+        // missing keys are cached too
+        assert_eq!(Item::<u64>::new("missing").may_load(&cached).unwrap(), None);
+        assert_eq!(Item::<u64>::new("missing").may_load(&cached).unwrap(), None);
+
+        // writes go through and update the cache
+        CONFIG.save(&mut cached, &2).unwrap();
+        assert_eq!(CONFIG.load(&cached).unwrap(), 2);
+        CONFIG.remove(&mut cached);
+        assert_eq!(CONFIG.may_load(&cached).unwrap(), None);
+
+        drop(cached);
+        assert_eq!(counting.stats(b"config").gets, 1);
+        assert_eq!(counting.stats(b"config").sets, 1);
+        assert_eq!(counting.stats(b"config").removes, 1);
+        assert_eq!(counting.stats(b"missing").gets, 1);
+        drop(counting);
+        assert_eq!(CONFIG.may_load(&store).unwrap(), None);
+    }
+}
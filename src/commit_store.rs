@@ -0,0 +1,183 @@
+use cosmwasm_std::{BlockInfo, StdResult, Storage};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{StorageError, StorageResult};
+use crate::keys::PrimaryKey;
+use crate::merkle::Hash;
+use crate::Map;
+
+/// A salted hash committed to at `height`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Commitment {
+    pub hash: Hash,
+    pub height: u64,
+}
+
+/// Returns the hash to commit to for `preimage` and `salt`: the sha256 digest of the length of
+/// `preimage` as 4 big-endian bytes, `preimage` and `salt`.
+/// The length keeps bytes from moving between the preimage and the salt at reveal
+pub fn commitment_hash(preimage: &[u8], salt: &[u8]) -> Hash {
+    Sha256::new()
+        .chain_update((preimage.len() as u32).to_be_bytes())
+        .chain_update(preimage)
+        .chain_update(salt)
+        .finalize()
+        .into()
+}
+
+/// CommitStore holds commitments to values revealed later, e.g. sealed bids.
+///
+/// A commitment can be revealed from `min_delay` blocks after it was made, up to `max_delay`
+/// blocks after, and is removed once revealed.
+pub struct CommitStore<'a, K> {
+    commitments: Map<'a, K, Commitment>,
+    min_delay: u64,
+    max_delay: u64,
+}
+
+impl<'a, K> CommitStore<'a, K> {
+    /// Panics if `min_delay` is greater than `max_delay`
+    pub const fn new(namespace: &'a str, min_delay: u64, max_delay: u64) -> Self {
+        assert!(
+            min_delay <= max_delay,
+            "min_delay must not be greater than max_delay"
+        );
+        CommitStore {
+            commitments: Map::new(namespace),
+            min_delay,
+            max_delay,
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.commitments.namespace()
+    }
+}
+
+impl<'a, K: PrimaryKey<'a>> CommitStore<'a, K> {
+    /// Commits `k` to `hash`, as returned by `commitment_hash`.
+    /// Committing again replaces the commitment and restarts its reveal window
+    pub fn commit(
+        &self,
+        store: &mut dyn Storage,
+        block: &BlockInfo,
+        k: K,
+        hash: Hash,
+    ) -> StorageResult<()> {
+        let commitment = Commitment {
+            hash,
+            height: block.height,
+        };
+        self.commitments.save(store, k, &commitment)
+    }
+
+    /// Checks `preimage` and `salt` against the commitment of `k`, and removes it if they match.
+    ///
+    /// Returns an error and keeps the commitment if there is none, if the reveal window isn't
+    /// open at `block` or if they don't match
+    pub fn reveal(
+        &self,
+        store: &mut dyn Storage,
+        block: &BlockInfo,
+        k: K,
+        preimage: &[u8],
+        salt: &[u8],
+    ) -> StorageResult<()> {
+        let path = self.commitments.key(k);
+        let commitment = path.load(store)?;
+        let opens = commitment.height.saturating_add(self.min_delay);
+        let closes = commitment.height.saturating_add(self.max_delay);
+        if block.height < opens || block.height > closes {
+            return Err(StorageError::RevealWindow {
+                height: block.height,
+                opens,
+                closes,
+            });
+        }
+        if commitment_hash(preimage, salt) != commitment.hash {
+            return Err(StorageError::InvalidReveal);
+        }
+        path.remove(store);
+        Ok(())
+    }
+
+    /// Returns the commitment of `k`, None if there is none
+    pub fn commitment(&self, store: &dyn Storage, k: K) -> StdResult<Option<Commitment>> {
+        Ok(self.commitments.may_load(store, k)?)
+    }
+
+    /// Removes the commitment of `k`, e.g. once its reveal window closed
+    pub fn remove(&self, store: &mut dyn Storage, k: K) {
+        self.commitments.remove(store, k)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::{mock_env, MockStorage};
+    use cosmwasm_std::HumanAddr as Addr;
+
+    const BIDS: CommitStore<&Addr> = CommitStore::new("bids", 2, 10);
+
+    fn block(height: u64) -> BlockInfo {
+        let mut block = mock_env("creator", &[]).block;
+        block.height = height;
+        block
+    }
+
+    #[test]
+    fn reveals_within_the_window() {
+        let mut store = MockStorage::new();
+        let bidder = Addr::from("bidder");
+        let hash = commitment_hash(b"100", b"salt");
+        BIDS.commit(&mut store, &block(5), &bidder, hash).unwrap();
+        assert_eq!(
+            BIDS.commitment(&store, &bidder).unwrap(),
+            Some(Commitment { hash, height: 5 })
+        );
+
+        let err = BIDS
+            .reveal(&mut store, &block(6), &bidder, b"100", b"salt")
+            .unwrap_err();
+        assert_eq!(
+            err,
+            StorageError::RevealWindow {
+                height: 6,
+                opens: 7,
+                closes: 15
+            }
+        );
+
+        let err = BIDS
+            .reveal(&mut store, &block(7), &bidder, b"200", b"salt")
+            .unwrap_err();
+        assert_eq!(err, StorageError::InvalidReveal);
+        // bytes can't move from the salt into the preimage
+        assert!(BIDS
+            .reveal(&mut store, &block(7), &bidder, b"100s", b"alt")
+            .is_err());
+
+        BIDS.reveal(&mut store, &block(7), &bidder, b"100", b"salt")
+            .unwrap();
+        assert_eq!(BIDS.commitment(&store, &bidder).unwrap(), None);
+        assert!(BIDS
+            .reveal(&mut store, &block(7), &bidder, b"100", b"salt")
+            .is_err());
+    }
+
+    #[test]
+    fn window_closes() {
+        let mut store = MockStorage::new();
+        let bidder = Addr::from("bidder");
+        BIDS.commit(&mut store, &block(5), &bidder, commitment_hash(b"1", b""))
+            .unwrap();
+
+        assert!(BIDS
+            .reveal(&mut store, &block(16), &bidder, b"1", b"")
+            .is_err());
+        BIDS.reveal(&mut store, &block(15), &bidder, b"1", b"")
+            .unwrap();
+    }
+}
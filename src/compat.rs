@@ -0,0 +1,155 @@
+//! Collections laid out exactly like cw-storage-plus, for reading state written by contracts
+//! ported from it.
+//!
+//! The keys and namespaces of this crate are encoded like those of cw-storage-plus already:
+//! an `Item` is stored under its raw key, a `Map` entry under its length-prefixed namespace
+//! and key, and integer keys as big-endian bytes. Only the values differ, which cw-storage-plus
+//! stores as JSON rather than with `Bincode2`. The `migrate_from` methods rewrite such values
+//! into the layout of a collection of this crate.
+
+pub mod secret_toolkit;
+
+#[cfg(feature = "iterator")]
+use cosmwasm_std::Order;
+use cosmwasm_std::{StdResult, Storage};
+use serde::{de::DeserializeOwned, Serialize};
+
+#[cfg(feature = "iterator")]
+use crate::helpers::namespaces_with_key;
+#[cfg(feature = "iterator")]
+use crate::keys::PrimaryKey;
+use crate::serialization::{Json, Serde};
+
+/// An `Item` stored like a cw-storage-plus `Item`
+pub type Item<'a, T> = crate::Item<'a, T, Json>;
+
+/// A `Map` stored like a cw-storage-plus `Map`
+pub type Map<'a, K, T> = crate::Map<'a, K, T, Json>;
+
+impl<'a, T, Ser> crate::Item<'a, T, Ser>
+where
+    T: Serialize + DeserializeOwned,
+    Ser: Serde<T>,
+{
+    /// migrate_from moves the value of a cw-storage-plus item into this item, which may use the
+    /// same key. Returns false if the legacy item holds no value.
+    pub fn migrate_from(&self, store: &mut dyn Storage, legacy: &Item<T>) -> StdResult<bool> {
+        match legacy.may_load(store)? {
+            Some(value) => {
+                legacy.remove(store);
+                self.save(store, &value)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, K, T, Ser> crate::Map<'a, K, T, Ser>
+where
+    K: PrimaryKey<'a>,
+    T: Serialize + DeserializeOwned,
+    Ser: Serde<T>,
+{
+    /// migrate_from moves all entries of a cw-storage-plus map into this map, which may use the
+    /// same namespace. Returns the number of entries moved.
+    ///
+    /// All entries are loaded before the first one is written, so on error nothing is changed.
+    pub fn migrate_from(&self, store: &mut dyn Storage, legacy: &Map<'a, K, T>) -> StdResult<u32> {
+        let entries = legacy
+            .range_raw(store, None, None, Order::Ascending)
+            .map(|entry| {
+                let (key, value) = entry?;
+                Ok((key, Ser::serialize(&value)?))
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let legacy_prefix = namespaces_with_key(&[legacy.namespace()], &[]);
+        let prefix = namespaces_with_key(&[self.namespace()], &[]);
+        for (key, _) in &entries {
+            store.remove(&[legacy_prefix.as_slice(), key].concat());
+        }
+        for (key, value) in &entries {
+            store.set(&[prefix.as_slice(), key].concat(), value);
+        }
+        Ok(entries.len() as u32)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Config {
+        owner: String,
+    }
+
+    #[test]
+    fn reads_cw_storage_plus_layout() {
+        let mut store = MockStorage::new();
+        // as written by cw-storage-plus
+        store.set(b"config", br#"{"owner":"admin"}"#);
+        store.set(b"\x00\x08balancesalice", b"100");
+        store.set(b"\x00\x06scores\x00\x00\x00\x00\x00\x00\x00\x07", b"3");
+
+        const CONFIG: Item<Config> = Item::new("config");
+        const BALANCES: Map<&str, u64> = Map::new_unhashed("balances");
+        const SCORES: Map<u64, u32> = Map::new_unhashed("scores");
+
+        assert_eq!(
+            CONFIG.load(&store).unwrap(),
+            Config {
+                owner: "admin".to_string()
+            }
+        );
+        assert_eq!(BALANCES.load(&store, "alice").unwrap(), 100);
+        assert_eq!(SCORES.load(&store, 7).unwrap(), 3);
+    }
+
+    #[test]
+    fn migrate_item() {
+        let mut store = MockStorage::new();
+        const LEGACY: Item<Config> = Item::new("config");
+        const CONFIG: crate::Item<Config> = crate::Item::new("config");
+
+        assert!(!CONFIG.migrate_from(&mut store, &LEGACY).unwrap());
+
+        let config = Config {
+            owner: "admin".to_string(),
+        };
+        LEGACY.save(&mut store, &config).unwrap();
+        assert!(CONFIG.migrate_from(&mut store, &LEGACY).unwrap());
+        assert_eq!(CONFIG.load(&store).unwrap(), config);
+        // the value was rewritten in place
+        assert!(LEGACY.load(&store).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn migrate_map() {
+        let mut store = MockStorage::new();
+        const LEGACY: Map<&str, u64> = Map::new("balances");
+        const BALANCES: crate::Map<&str, u64> = crate::Map::new("balances");
+        const OLD_SCORES: Map<&str, u64> = Map::new("scores");
+        const SCORES: crate::Map<&str, u64> = crate::Map::new("scores_v2");
+
+        LEGACY.save(&mut store, "alice", &100).unwrap();
+        LEGACY.save(&mut store, "bob", &5).unwrap();
+
+        // in place
+        assert_eq!(BALANCES.migrate_from(&mut store, &LEGACY).unwrap(), 2);
+        assert_eq!(BALANCES.load(&store, "alice").unwrap(), 100);
+        assert_eq!(BALANCES.load(&store, "bob").unwrap(), 5);
+
+        // to another namespace
+        OLD_SCORES.save(&mut store, "carol", &7).unwrap();
+        OLD_SCORES.save(&mut store, "dave", &8).unwrap();
+        assert_eq!(SCORES.migrate_from(&mut store, &OLD_SCORES).unwrap(), 2);
+        assert_eq!(SCORES.load(&store, "carol").unwrap(), 7);
+        assert!(!OLD_SCORES.has(&store, "dave"));
+    }
+}
@@ -0,0 +1,374 @@
+//! Read-only views of the storage layouts of secret-toolkit, for contracts upgrading their state
+//! in place: the old data is read through these adapters, written into collections of this
+//! crate, and cleared.
+//!
+//! Every adapter is created with the raw prefix of its keys. `new(namespace)` matches the
+//! secret-toolkit collections created with `new(namespace)`, while `in_prefixed_storage(namespace)`
+//! matches the ones attached to a `PrefixedStorage::new(namespace, ..)`.
+
+use std::any::type_name;
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+use cosmwasm_std::{StdResult, Storage};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::error::StorageError;
+use crate::helpers::{may_deserialize, must_deserialize, namespaces_with_key};
+use crate::queue::load_u32;
+use crate::serialization::{Bincode2, Serde};
+
+fn prefixed_storage(namespace: &[u8]) -> Cow<'static, [u8]> {
+    Cow::Owned(namespaces_with_key(&[namespace], b""))
+}
+
+/// The values of a secret-toolkit `TypedStore`, each stored under its raw key
+pub struct TypedStore<'a, T, Ser = Bincode2> {
+    prefix: Cow<'a, [u8]>,
+    data_type: PhantomData<(T, Ser)>,
+}
+
+impl<'a, T, Ser> TypedStore<'a, T, Ser> {
+    /// A store whose keys start with `prefix`, e.g. `b""` for a store attached to the
+    /// root storage of the contract
+    pub const fn new(prefix: &'a [u8]) -> Self {
+        TypedStore {
+            prefix: Cow::Borrowed(prefix),
+            data_type: PhantomData,
+        }
+    }
+
+    pub fn in_prefixed_storage(namespace: &[u8]) -> Self {
+        TypedStore {
+            prefix: prefixed_storage(namespace),
+            data_type: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, Ser> TypedStore<'a, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    fn storage_key(&self, key: &[u8]) -> Vec<u8> {
+        [&self.prefix, key].concat()
+    }
+
+    /// load will return an error if no data is set at the given key, or on parse error
+    pub fn load(&self, store: &dyn Storage, key: &[u8]) -> StdResult<T> {
+        Ok(must_deserialize::<T, Ser>(
+            &store.get(&self.storage_key(key)),
+            &self.prefix,
+            key,
+        )?)
+    }
+
+    /// may_load will parse the data stored at the key if present, returns Ok(None) if no data there.
+    /// returns an error on issues parsing
+    pub fn may_load(&self, store: &dyn Storage, key: &[u8]) -> StdResult<Option<T>> {
+        Ok(may_deserialize::<T, Ser>(
+            &store.get(&self.storage_key(key)),
+            &self.prefix,
+            key,
+        )?)
+    }
+
+    pub fn remove(&self, store: &mut dyn Storage, key: &[u8]) {
+        store.remove(&self.storage_key(key));
+    }
+}
+
+/// The items of a secret-toolkit `AppendStore`, stored under their big-endian `u32` position,
+/// with the length under `len`
+pub struct AppendStore<'a, T, Ser = Bincode2> {
+    prefix: Cow<'a, [u8]>,
+    data_type: PhantomData<(T, Ser)>,
+}
+
+impl<'a, T, Ser> AppendStore<'a, T, Ser> {
+    const LEN: &'static [u8] = b"len";
+
+    pub const fn new(namespace: &'a [u8]) -> Self {
+        AppendStore {
+            prefix: Cow::Borrowed(namespace),
+            data_type: PhantomData,
+        }
+    }
+
+    pub fn in_prefixed_storage(namespace: &[u8]) -> Self {
+        AppendStore {
+            prefix: prefixed_storage(namespace),
+            data_type: PhantomData,
+        }
+    }
+
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        load_u32(store, &[&self.prefix, Self::LEN].concat())
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    fn storage_key(&self, pos: u32) -> Vec<u8> {
+        [&self.prefix, pos.to_be_bytes().as_slice()].concat()
+    }
+
+    /// Removes all items and the length
+    pub fn clear(&self, store: &mut dyn Storage) {
+        for pos in 0..self.len(store) {
+            store.remove(&self.storage_key(pos));
+        }
+        store.remove(&[&self.prefix, Self::LEN].concat());
+    }
+}
+
+impl<'a, T, Ser> AppendStore<'a, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    /// Returns the item at `pos`, or None if `pos` is out of bounds
+    pub fn get_at(&self, store: &dyn Storage, pos: u32) -> StdResult<Option<T>> {
+        if pos >= self.len(store) {
+            return Ok(None);
+        }
+        let key = pos.to_be_bytes();
+        Ok(
+            must_deserialize::<T, Ser>(&store.get(&self.storage_key(pos)), &self.prefix, &key)
+                .map(Some)?,
+        )
+    }
+
+    /// Returns an iterator over the items, from oldest to newest
+    pub fn iter<'c>(&'c self, store: &'c dyn Storage) -> impl Iterator<Item = StdResult<T>> + 'c {
+        (0..self.len(store)).filter_map(move |pos| self.get_at(store, pos).transpose())
+    }
+}
+
+/// The value of a `Keymap` entry, along with the position of its key in the index pages
+#[derive(Serialize, Deserialize)]
+struct InternalItem {
+    item_vec: Vec<u8>,
+    index_pos: u32,
+}
+
+/// The entries of a secret-toolkit `Keymap` using the default `Bincode2` serialization.
+///
+/// Every value is stored under its serialized key, and the serialized keys are kept in pages
+/// of `page_size` keys in insertion order, so the adapter must use the page size the `Keymap`
+/// was built with.
+pub struct Keymap<'a, K, T> {
+    prefix: Cow<'a, [u8]>,
+    page_size: u32,
+    data_type: PhantomData<(K, T)>,
+}
+
+impl<'a, K, T> Keymap<'a, K, T> {
+    const INDEXES: &'static [u8] = b"indexes";
+    const LEN: &'static [u8] = b"length";
+
+    pub const fn new(namespace: &'a [u8]) -> Self {
+        Keymap {
+            prefix: Cow::Borrowed(namespace),
+            page_size: 1,
+            data_type: PhantomData,
+        }
+    }
+
+    pub fn in_prefixed_storage(namespace: &[u8]) -> Self {
+        Keymap {
+            prefix: prefixed_storage(namespace),
+            page_size: 1,
+            data_type: PhantomData,
+        }
+    }
+
+    /// Sets the number of keys per index page, as passed to `KeymapBuilder::with_page_size`.
+    /// Panics if `page_size` is zero
+    pub const fn with_page_size(mut self, page_size: u32) -> Self {
+        if page_size == 0 {
+            panic!("zero index page size used in keymap")
+        }
+        self.page_size = page_size;
+        self
+    }
+
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        load_u32(store, &[&self.prefix, Self::LEN].concat())
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    fn page_key(&self, page: u32) -> Vec<u8> {
+        [&self.prefix, Self::INDEXES, page.to_be_bytes().as_slice()].concat()
+    }
+
+    fn page(&self, store: &dyn Storage, page: u32) -> StdResult<Vec<Vec<u8>>> {
+        let key = self.page_key(page);
+        Ok(
+            may_deserialize::<_, Bincode2>(&store.get(&key), &self.prefix, &key)?
+                .unwrap_or_default(),
+        )
+    }
+
+    /// The serialized keys of all entries, in insertion order
+    fn raw_keys(&self, store: &dyn Storage) -> StdResult<Vec<Vec<u8>>> {
+        let len = self.len(store);
+        let pages = len.div_ceil(self.page_size);
+        let mut keys = Vec::with_capacity(len as usize);
+        for page in 0..pages {
+            keys.extend(self.page(store, page)?);
+        }
+        keys.truncate(len as usize);
+        Ok(keys)
+    }
+
+    /// Removes all entries, the index pages and the length
+    pub fn clear(&self, store: &mut dyn Storage) -> StdResult<()> {
+        let len = self.len(store);
+        for key in self.raw_keys(store)? {
+            store.remove(&[&self.prefix, key.as_slice()].concat());
+        }
+        for page in 0..len.div_ceil(self.page_size) {
+            store.remove(&self.page_key(page));
+        }
+        store.remove(&[&self.prefix, Self::LEN].concat());
+        Ok(())
+    }
+}
+
+impl<'a, K, T> Keymap<'a, K, T>
+where
+    K: Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned,
+{
+    fn get_raw(&self, store: &dyn Storage, raw_key: &[u8]) -> StdResult<Option<T>> {
+        let item = may_deserialize::<InternalItem, Bincode2>(
+            &store.get(&[&self.prefix, raw_key].concat()),
+            &self.prefix,
+            raw_key,
+        )?;
+        item.map(|item| Bincode2::deserialize(&item.item_vec))
+            .transpose()
+    }
+
+    /// Returns the value stored under `key`, or None if there is none
+    pub fn get(&self, store: &dyn Storage, key: &K) -> StdResult<Option<T>> {
+        self.get_raw(store, &Bincode2::serialize(key)?)
+    }
+
+    /// Returns all entries in insertion order
+    pub fn entries(&self, store: &dyn Storage) -> StdResult<Vec<(K, T)>> {
+        self.raw_keys(store)?
+            .into_iter()
+            .map(|raw_key| {
+                let value =
+                    self.get_raw(store, &raw_key)?
+                        .ok_or_else(|| StorageError::NotFound {
+                            type_name: type_name::<T>(),
+                            namespace: String::from_utf8_lossy(&self.prefix).into_owned(),
+                            key: raw_key.clone(),
+                        })?;
+                Ok((Bincode2::deserialize(&raw_key)?, value))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::helpers::serialize;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::ReadonlyStorage;
+
+    #[test]
+    fn typed_store() {
+        let mut store = MockStorage::new();
+        // as written by `TypedStoreMut::attach(&mut PrefixedStorage::new(b"config", ..))`
+        store.set(
+            b"\x00\x06configowner",
+            &serialize(&"admin".to_string()).unwrap(),
+        );
+
+        let config: TypedStore<String> = TypedStore::in_prefixed_storage(b"config");
+        assert_eq!(config.load(&store, b"owner").unwrap(), "admin");
+        assert_eq!(config.may_load(&store, b"admin").unwrap(), None);
+
+        config.remove(&mut store, b"owner");
+        assert!(config.load(&store, b"owner").is_err());
+    }
+
+    #[test]
+    fn append_store() {
+        let mut store = MockStorage::new();
+        for (pos, tx) in [10u64, 20, 30].iter().enumerate() {
+            store.set(
+                &[b"txs".as_slice(), &(pos as u32).to_be_bytes()].concat(),
+                &serialize(tx).unwrap(),
+            );
+        }
+        store.set(b"txslen", &3u32.to_be_bytes());
+
+        let txs: AppendStore<u64> = AppendStore::new(b"txs");
+        assert_eq!(txs.len(&store), 3);
+        assert_eq!(txs.get_at(&store, 1).unwrap(), Some(20));
+        assert_eq!(txs.get_at(&store, 3).unwrap(), None);
+        assert_eq!(
+            txs.iter(&store).collect::<StdResult<Vec<_>>>().unwrap(),
+            vec![10, 20, 30]
+        );
+
+        txs.clear(&mut store);
+        assert!(txs.is_empty(&store));
+        assert_eq!(store.get(b"txs\x00\x00\x00\x00"), None);
+    }
+
+    #[test]
+    fn keymap() {
+        let mut store = MockStorage::new();
+        let entries = [("alice", 100u64), ("bob", 5), ("carol", 7)];
+        // two keys per index page
+        let pages: [Vec<Vec<u8>>; 2] = [
+            vec![serialize(&"alice").unwrap(), serialize(&"bob").unwrap()],
+            vec![serialize(&"carol").unwrap()],
+        ];
+        for (pos, (key, value)) in entries.iter().enumerate() {
+            let item = InternalItem {
+                item_vec: serialize(value).unwrap(),
+                index_pos: pos as u32,
+            };
+            store.set(
+                &[b"balances".as_slice(), &serialize(key).unwrap()].concat(),
+                &serialize(&item).unwrap(),
+            );
+        }
+        for (page, keys) in pages.iter().enumerate() {
+            store.set(
+                &[b"balancesindexes".as_slice(), &(page as u32).to_be_bytes()].concat(),
+                &serialize(keys).unwrap(),
+            );
+        }
+        store.set(b"balanceslength", &3u32.to_be_bytes());
+
+        let balances: Keymap<String, u64> = Keymap::new(b"balances").with_page_size(2);
+        assert_eq!(balances.len(&store), 3);
+        assert_eq!(balances.get(&store, &"bob".to_string()).unwrap(), Some(5));
+        assert_eq!(balances.get(&store, &"dave".to_string()).unwrap(), None);
+        assert_eq!(
+            balances.entries(&store).unwrap(),
+            vec![
+                ("alice".to_string(), 100),
+                ("bob".to_string(), 5),
+                ("carol".to_string(), 7)
+            ]
+        );
+
+        balances.clear(&mut store).unwrap();
+        assert!(balances.is_empty(&store));
+        assert_eq!(balances.get(&store, &"alice".to_string()).unwrap(), None);
+        assert_eq!(store.get(b"balancesindexes\x00\x00\x00\x01"), None);
+    }
+}
@@ -0,0 +1,77 @@
+use cosmwasm_std::{StdResult, Storage};
+
+use crate::error::{StorageError, StorageResult};
+use crate::Item;
+
+/// Counter stores a monotonically increasing `u64`, e.g. to hand out unique ids.
+/// A counter that was never incremented is at zero, so the first id handed out by `next` is one.
+pub struct Counter<'a> {
+    item: Item<'a, u64>,
+}
+
+impl<'a> Counter<'a> {
+    pub const fn new(storage_key: &'a str) -> Self {
+        Counter {
+            item: Item::new(storage_key),
+        }
+    }
+
+    /// Returns the current value of the counter, without incrementing it
+    pub fn current(&self, store: &dyn Storage) -> StdResult<u64> {
+        Ok(self.item.may_load(store)?.unwrap_or_default())
+    }
+
+    /// Increments the counter by one and returns the new value
+    pub fn next(&self, store: &mut dyn Storage) -> StorageResult<u64> {
+        self.increment_by(store, 1)
+    }
+
+    /// Increments the counter by `amount` and returns the new value,
+    /// returns an error and leaves the counter untouched if it would overflow
+    pub fn increment_by(&self, store: &mut dyn Storage, amount: u64) -> StorageResult<u64> {
+        let value = self
+            .current(store)?
+            .checked_add(amount)
+            .ok_or(StorageError::Overflow {
+                collection: "Counter",
+            })?;
+        self.item.save(store, &value)?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const IDS: Counter = Counter::new("ids");
+
+    #[test]
+    fn next_hands_out_increasing_ids() {
+        let mut store = MockStorage::new();
+
+        assert_eq!(IDS.current(&store).unwrap(), 0);
+        assert_eq!(IDS.next(&mut store).unwrap(), 1);
+        assert_eq!(IDS.next(&mut store).unwrap(), 2);
+        assert_eq!(IDS.current(&store).unwrap(), 2);
+
+        assert_eq!(IDS.increment_by(&mut store, 10).unwrap(), 12);
+        assert_eq!(IDS.current(&store).unwrap(), 12);
+    }
+
+    #[test]
+    fn overflow_errs() {
+        let mut store = MockStorage::new();
+
+        IDS.increment_by(&mut store, u64::MAX).unwrap();
+        assert_eq!(
+            IDS.next(&mut store).unwrap_err(),
+            StorageError::Overflow {
+                collection: "Counter"
+            }
+        );
+        // the counter is left untouched
+        assert_eq!(IDS.current(&store).unwrap(), u64::MAX);
+    }
+}
@@ -0,0 +1,212 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ops::AddAssign;
+
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, KV};
+use cosmwasm_std::{ReadonlyStorage, Storage};
+
+use crate::helpers::split_namespace;
+
+/// The storage operations done on a namespace
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StorageStats {
+    /// Number of reads, every entry returned by a range counts as one
+    pub gets: u32,
+    pub sets: u32,
+    pub removes: u32,
+    /// Total size of the keys and values read
+    pub bytes_read: u64,
+    /// Total size of the keys and values written
+    pub bytes_written: u64,
+}
+
+impl AddAssign for StorageStats {
+    fn add_assign(&mut self, other: Self) {
+        self.gets += other.gets;
+        self.sets += other.sets;
+        self.removes += other.removes;
+        self.bytes_read += other.bytes_read;
+        self.bytes_written += other.bytes_written;
+    }
+}
+
+/// CountingStorage wraps a `Storage` and records the operations done on every namespace,
+/// so tests can assert that a handler stays under a number of storage operations.
+///
+/// The namespace of a key is its length-prefixed top level namespace, as used by `Map` and the
+/// collections built on it. Keys without one, like those of an `Item` or the length of a
+/// collection (e.g. `b"queue_tail"`), count as their own namespace.
+pub struct CountingStorage<'a> {
+    storage: &'a mut dyn Storage,
+    stats: RefCell<BTreeMap<Vec<u8>, StorageStats>>,
+}
+
+impl<'a> CountingStorage<'a> {
+    pub fn new(storage: &'a mut dyn Storage) -> Self {
+        CountingStorage {
+            storage,
+            stats: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the operations done on `namespace`
+    pub fn stats(&self, namespace: &[u8]) -> StorageStats {
+        self.stats
+            .borrow()
+            .get(namespace)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the operations done on all namespaces
+    pub fn total(&self) -> StorageStats {
+        let mut total = StorageStats::default();
+        for stats in self.stats.borrow().values() {
+            total += *stats;
+        }
+        total
+    }
+
+    /// Returns all namespaces accessed, in key order
+    pub fn namespaces(&self) -> Vec<Vec<u8>> {
+        self.stats.borrow().keys().cloned().collect()
+    }
+
+    /// Forgets all recorded operations, e.g. after setting up the state of a test
+    pub fn reset(&mut self) {
+        self.stats.borrow_mut().clear();
+    }
+
+    fn record(&self, key: &[u8], stats: StorageStats) {
+        let namespace = match split_namespace(key) {
+            ([], _) => key,
+            (namespace, _) => namespace,
+        };
+        *self
+            .stats
+            .borrow_mut()
+            .entry(namespace.to_vec())
+            .or_default() += stats;
+    }
+}
+
+impl<'a> ReadonlyStorage for CountingStorage<'a> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let value = self.storage.get(key);
+        let bytes_read = key.len() + value.as_ref().map_or(0, Vec::len);
+        self.record(
+            key,
+            StorageStats {
+                gets: 1,
+                bytes_read: bytes_read as u64,
+                ..Default::default()
+            },
+        );
+        value
+    }
+
+    #[cfg(feature = "iterator")]
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = KV> + 'b> {
+        let iter = self
+            .storage
+            .range(start, end, order)
+            .inspect(move |(k, v)| {
+                self.record(
+                    k,
+                    StorageStats {
+                        gets: 1,
+                        bytes_read: (k.len() + v.len()) as u64,
+                        ..Default::default()
+                    },
+                )
+            });
+        Box::new(iter)
+    }
+}
+
+impl<'a> Storage for CountingStorage<'a> {
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.record(
+            key,
+            StorageStats {
+                sets: 1,
+                bytes_written: (key.len() + value.len()) as u64,
+                ..Default::default()
+            },
+        );
+        self.storage.set(key, value)
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.record(
+            key,
+            StorageStats {
+                removes: 1,
+                ..Default::default()
+            },
+        );
+        self.storage.remove(key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    use crate::{Item, Map};
+
+    const CONFIG: Item<u64> = Item::new("config");
+    const BALANCES: Map<&str, u64> = Map::new_unhashed("balances");
+
+    #[test]
+    fn counts_per_namespace() {
+        let mut store = MockStorage::new();
+        let mut counting = CountingStorage::new(&mut store);
+
+        CONFIG.save(&mut counting, &1).unwrap();
+        BALANCES.save(&mut counting, "alice", &10).unwrap();
+        BALANCES.save(&mut counting, "bob", &5).unwrap();
+        BALANCES.load(&counting, "alice").unwrap();
+        BALANCES.remove(&mut counting, "bob");
+
+        assert_eq!(
+            counting.namespaces(),
+            vec![b"balances".to_vec(), b"config".to_vec()]
+        );
+        let balances = counting.stats(b"balances");
+        assert_eq!((balances.gets, balances.sets, balances.removes), (1, 2, 1));
+        // "\0\x08balancesalice" and a u64
+        assert_eq!(balances.bytes_read, 15 + 8);
+        assert_eq!(counting.stats(b"config").sets, 1);
+        assert_eq!(counting.total().sets, 3);
+
+        counting.reset();
+        assert_eq!(counting.total(), StorageStats::default());
+        assert_eq!(CONFIG.load(&counting).unwrap(), 1);
+        assert_eq!(counting.total().gets, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn counts_range_entries() {
+        let mut store = MockStorage::new();
+        for name in ["alice", "bob", "carol"] {
+            BALANCES.save(&mut store, name, &1).unwrap();
+        }
+
+        let counting = CountingStorage::new(&mut store);
+        let two = BALANCES
+            .range(&counting, None, None, Order::Ascending)
+            .take(2)
+            .count();
+        assert_eq!(two, 2);
+        assert_eq!(counting.stats(b"balances").gets, 2);
+    }
+}
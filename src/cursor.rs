@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{StorageError, StorageResult};
+
+/// A batch of entries returned by a scan, and the cursor to continue it from
+pub type ScanBatch<K, T> = (Vec<(K, T)>, Cursor);
+
+/// Cursor records how far a scan over a map got, so a job too large for one transaction, like a
+/// migration or an airdrop, can save it in an `Item<Cursor>` and continue in the next one.
+///
+/// It holds the raw key of the last entry returned and a fingerprint of the namespace it was
+/// created for, so it can't be resumed on another map by mistake. Entries added behind the cursor
+/// while the scan is paused are not visited, entries added ahead of it are.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    fingerprint: [u8; 8],
+    last_key: Option<Vec<u8>>,
+    finished: bool,
+}
+
+impl Cursor {
+    /// Creates a cursor at the start of the map stored under `namespace`
+    pub fn new(namespace: &[u8]) -> Self {
+        Cursor {
+            fingerprint: fingerprint(namespace),
+            last_key: None,
+            finished: false,
+        }
+    }
+
+    /// Returns the raw key of the last entry returned, None if the scan hasn't started
+    pub fn last_key(&self) -> Option<&[u8]> {
+        self.last_key.as_deref()
+    }
+
+    /// Returns true once the scan returned the last entry
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns `StorageError::CursorMismatch` unless the cursor was created for `namespace`
+    pub fn validate(&self, namespace: &[u8]) -> StorageResult<()> {
+        if self.fingerprint != fingerprint(namespace) {
+            return Err(StorageError::CursorMismatch {
+                namespace: String::from_utf8_lossy(namespace).into_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The cursor after a batch of `returned` entries, the last of them at `last_key`,
+    /// out of at most `limit`
+    #[cfg(feature = "iterator")]
+    pub(crate) fn advance(&self, last_key: Option<Vec<u8>>, returned: usize, limit: u32) -> Self {
+        Cursor {
+            fingerprint: self.fingerprint,
+            last_key: last_key.or_else(|| self.last_key.clone()),
+            finished: returned < limit as usize,
+        }
+    }
+}
+
+fn fingerprint(namespace: &[u8]) -> [u8; 8] {
+    let digest = Sha256::digest(namespace);
+    digest[..8].try_into().unwrap()
+}
+
+#[cfg(test)]
+#[cfg(feature = "iterator")]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    use crate::{Item, Map};
+
+    const BALANCES: Map<&str, u64> = Map::new("balances");
+    const AIRDROP: Item<Cursor> = Item::new("airdrop");
+
+    #[test]
+    fn resumes_scans_across_transactions() {
+        let mut store = MockStorage::new();
+        for (i, name) in ["alice", "bob", "carol", "dave", "eve"].iter().enumerate() {
+            BALANCES.save(&mut store, name, &(i as u64)).unwrap();
+        }
+        AIRDROP
+            .save(&mut store, &Cursor::new(BALANCES.namespace()))
+            .unwrap();
+
+        let mut visited = vec![];
+        loop {
+            let cursor = AIRDROP.load(&store).unwrap();
+            if cursor.is_finished() {
+                break;
+            }
+            let (items, next) = BALANCES.range_from_cursor(&store, &cursor, 2).unwrap();
+            visited.extend(items.into_iter().map(|(name, _)| name));
+            AIRDROP.save(&mut store, &next).unwrap();
+            // added behind the cursor, so not visited
+            BALANCES.save(&mut store, "aaron", &0).unwrap();
+        }
+        assert_eq!(visited, ["alice", "bob", "carol", "dave", "eve"]);
+        assert_eq!(AIRDROP.load(&store).unwrap().last_key(), Some(&b"eve"[..]));
+
+        // a finished cursor returns nothing
+        let cursor = AIRDROP.load(&store).unwrap();
+        let (items, _) = BALANCES.range_from_cursor(&store, &cursor, 2).unwrap();
+        assert!(items.is_empty());
+    }
+
+    #[test]
+    fn cursors_of_other_maps_are_rejected() {
+        let store = MockStorage::new();
+        let other: Map<&str, u64> = Map::new("allowances");
+        let cursor = Cursor::new(other.namespace());
+
+        assert_eq!(
+            BALANCES.range_from_cursor(&store, &cursor, 2).unwrap_err(),
+            StorageError::CursorMismatch {
+                namespace: "balances".to_string()
+            }
+        );
+    }
+}
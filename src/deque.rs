@@ -0,0 +1,276 @@
+use cosmwasm_std::Storage;
+
+use crate::error::StorageResult;
+use crate::queue::Queue;
+use crate::serialization::{Bincode2, Serde};
+
+/// A double ended queue, a `Queue` supporting pushes and pops at both ends. It shares the storage
+/// layout of `Queue`: popped slots are deleted and the capacity is checked on every write.
+pub struct Deque<'a, T, Ser = Bincode2> {
+    queue: Queue<'a, T, Ser>,
+}
+
+impl<'a, T, Ser> Deque<'a, T, Ser> {
+    // Creates a Deque with capacity `u32::MAX - 1'
+    pub const fn new(namespace: &'a str) -> Self {
+        Self::with_capacity(namespace, u32::MAX - 1)
+    }
+
+    // Creates a Deque with a number of slots equal to `capacity`
+    // panics if requested capacity is zero or `u32::MAX`
+    pub const fn with_capacity(namespace: &'a str, capacity: u32) -> Self {
+        Self {
+            queue: Queue::with_capacity(namespace, capacity),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.queue.namespace()
+    }
+
+    pub fn max_capacity(&self) -> u32 {
+        self.queue.max_capacity()
+    }
+
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.queue.len(store)
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    pub fn free_capacity(&self, store: &dyn Storage) -> u32 {
+        self.queue.free_capacity(store)
+    }
+
+    pub fn is_full(&self, store: &dyn Storage) -> bool {
+        self.queue.is_full(store)
+    }
+}
+
+impl<'a, T, Ser> Deque<'a, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    /// Add an item to the back of the deque, returns true if the item is added or false if the deque is full
+    pub fn push_back(&self, store: &mut dyn Storage, t: &T) -> StorageResult<bool> {
+        self.queue.push_back(store, t)
+    }
+
+    /// Add an item to the front of the deque, returns true if the item is added or false if the deque is full
+    pub fn push_front(&self, store: &mut dyn Storage, t: &T) -> StorageResult<bool> {
+        self.queue.push_front(store, t)
+    }
+
+    /// Pop an item from the front of the deque and delete its slot, returns None if the deque is empty
+    pub fn pop_front(&self, store: &mut dyn Storage) -> StorageResult<Option<T>> {
+        self.queue.pop_front(store)
+    }
+
+    /// Pop an item from the back of the deque and delete its slot, returns None if the deque is empty
+    pub fn pop_back(&self, store: &mut dyn Storage) -> StorageResult<Option<T>> {
+        self.queue.pop_back(store)
+    }
+
+    /// Returns the item at the front of the deque without removing it, returns None if the deque is empty
+    pub fn front(&self, store: &dyn Storage) -> StorageResult<Option<T>> {
+        self.queue.peek_front(store)
+    }
+
+    /// Returns the item at the back of the deque without removing it, returns None if the deque is empty
+    pub fn back(&self, store: &dyn Storage) -> StorageResult<Option<T>> {
+        self.queue.peek_back(store)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use cosmwasm_std::testing::MockStorage;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::error::StorageError;
+    use crate::Map;
+
+    #[derive(Debug, Clone, Copy, proptest_derive::Arbitrary)]
+    enum Op {
+        PushBack(u8),
+        PushFront(u8),
+        PopBack,
+        PopFront,
+    }
+
+    struct Model {
+        max: usize,
+        q: VecDeque<u8>,
+    }
+
+    impl Model {
+        fn new(max: usize) -> Self {
+            Self {
+                max,
+                q: VecDeque::default(),
+            }
+        }
+
+        fn push_back(&mut self, i: u8) -> bool {
+            if self.q.len() == self.max {
+                return false;
+            }
+            self.q.push_back(i);
+            true
+        }
+
+        fn push_front(&mut self, i: u8) -> bool {
+            if self.q.len() == self.max {
+                return false;
+            }
+            self.q.push_front(i);
+            true
+        }
+
+        fn pop_back(&mut self) -> Option<u8> {
+            self.q.pop_back()
+        }
+
+        fn pop_front(&mut self) -> Option<u8> {
+            self.q.pop_front()
+        }
+
+        fn front(&self) -> Option<u8> {
+            self.q.front().copied()
+        }
+
+        fn back(&self) -> Option<u8> {
+            self.q.back().copied()
+        }
+
+        fn len(&self) -> usize {
+            self.q.len()
+        }
+
+        fn free_capacity(&self) -> usize {
+            self.max - self.q.len()
+        }
+
+        fn is_full(&self) -> bool {
+            self.q.len() == self.max
+        }
+    }
+
+    fn setup_deque<T>(size: u32) -> (Deque<'static, T>, MockStorage) {
+        let q: Deque<T> = Deque::with_capacity("test", size);
+        let store = MockStorage::new();
+        (q, store)
+    }
+
+    proptest! {
+        #[test]
+        fn impl_matches_model(size in 1u32..1000u32, ops: Vec<Op>) {
+            let mut model = Model::new(size as _);
+            let (deque, mut store) = setup_deque(size);
+            for op in ops {
+                match op {
+                    Op::PushBack(u) => {
+                        let model_res = model.push_back(u);
+                        let impl_res = deque.push_back(&mut store, &u).unwrap();
+                        prop_assert_eq!(model_res, impl_res, "push_back results differ");
+                    }
+                    Op::PushFront(u) => {
+                        let model_res = model.push_front(u);
+                        let impl_res = deque.push_front(&mut store, &u).unwrap();
+                        prop_assert_eq!(model_res, impl_res, "push_front results differ");
+                    }
+                    Op::PopBack => {
+                        let model_res = model.pop_back();
+                        let impl_res = deque.pop_back(&mut store).unwrap();
+                        prop_assert_eq!(model_res, impl_res, "pop_back results differ");
+                    }
+                    Op::PopFront => {
+                        let model_res = model.pop_front();
+                        let impl_res = deque.pop_front(&mut store).unwrap();
+                        prop_assert_eq!(model_res, impl_res, "pop_front results differ");
+                    }
+                }
+
+                prop_assert_eq!(deque.front(&store).unwrap(), model.front(), "front results differ");
+                prop_assert_eq!(deque.back(&store).unwrap(), model.back(), "back results differ");
+                prop_assert_eq!(deque.len(&store), model.len() as u32, "len results differ");
+                prop_assert_eq!(deque.free_capacity(&store), model.free_capacity() as u32, "free_capacity results differ");
+                prop_assert_eq!(deque.is_full(&store), model.is_full(), "is_full results differ");
+            }
+        }
+    }
+
+    #[test]
+    fn invariant_push_front_on_empty_deque_wraps_head() {
+        let (deque, mut store) = setup_deque(2);
+        assert!(deque.push_front(&mut store, &1u8).unwrap());
+        assert_eq!(deque.queue.head(&store), 2);
+        assert_eq!(deque.queue.tail(&store), 0);
+        assert_eq!(deque.len(&store), 1);
+        assert_eq!(deque.pop_back(&mut store).unwrap(), Some(1));
+        assert!(deque.is_empty(&store));
+    }
+
+    #[test]
+    fn invariant_peeking_does_not_change_head_or_tail() {
+        let (deque, mut store) = setup_deque(3);
+        assert!(deque.push_back(&mut store, &1u8).unwrap());
+        assert!(deque.push_back(&mut store, &2u8).unwrap());
+        let pre_head = deque.queue.head(&store);
+        let pre_tail = deque.queue.tail(&store);
+        assert_eq!(deque.front(&store).unwrap(), Some(1));
+        assert_eq!(deque.back(&store).unwrap(), Some(2));
+        assert_eq!(deque.queue.head(&store), pre_head);
+        assert_eq!(deque.queue.tail(&store), pre_tail);
+    }
+
+    #[test]
+    fn invariant_cannot_push_onto_full_deque() {
+        let (deque, mut store) = setup_deque(2);
+        assert!(deque.push_back(&mut store, &0u8).unwrap());
+        assert!(deque.push_front(&mut store, &0u8).unwrap());
+        assert_eq!(deque.push_back(&mut store, &0u8), Ok(false));
+        assert_eq!(deque.push_front(&mut store, &0u8), Ok(false));
+    }
+
+    #[test]
+    fn pops_delete_slots() {
+        let (deque, mut store) = setup_deque(3);
+        assert!(deque.push_back(&mut store, &1u8).unwrap());
+        assert!(deque.push_front(&mut store, &0u8).unwrap());
+        assert_eq!(deque.pop_back(&mut store).unwrap(), Some(1));
+        assert_eq!(deque.pop_front(&mut store).unwrap(), Some(0));
+
+        // a map under the same namespace reads the slots
+        let slots: Map<u32, u8> = Map::new("test");
+        assert!((0..4).all(|slot| !slots.has(&store, slot)));
+    }
+
+    #[test]
+    fn capacity_changes_are_detected() {
+        let (deque, mut store) = setup_deque(3);
+        assert!(deque.push_back(&mut store, &1u8).unwrap());
+
+        let upgraded: Deque<u8> = Deque::with_capacity("test", 5);
+        let mismatch = StorageError::CapacityMismatch {
+            stored: 3,
+            configured: 5,
+        };
+        assert_eq!(upgraded.push_front(&mut store, &0u8).unwrap_err(), mismatch);
+        assert_eq!(upgraded.pop_back(&mut store).unwrap_err(), mismatch);
+    }
+
+    #[test]
+    fn invariant_cannot_pop_off_empty_deque() {
+        let (deque, mut store) = setup_deque::<u8>(2);
+        assert!(deque.pop_back(&mut store).unwrap().is_none());
+        assert!(deque.pop_front(&mut store).unwrap().is_none());
+        assert!(deque.front(&store).unwrap().is_none());
+        assert!(deque.back(&store).unwrap().is_none());
+    }
+}
@@ -0,0 +1,252 @@
+use std::fmt;
+
+use cosmwasm_std::StdError;
+
+/// The result of the methods of `Item`, `Map`, `Set`, `Queue` and `Deque`, and of the other
+/// collection methods that can fail for a reason of their own
+pub type StorageResult<T> = Result<T, StorageError>;
+
+/// StorageError describes why a storage operation failed.
+///
+/// Every fallible method of `Item`, `Map`, `Set`, `Queue` and `Deque` returns a `StorageResult`,
+/// so contracts can match on the cause, e.g. a `NotFound` or `Deserialize` error from `load`.
+/// Their iterators yield `StdResult` items, like the storage iterators of `cosmwasm_std`. The
+/// other collections return a `StorageResult` from the methods that can fail for a reason of
+/// their own, like a full collection or a version mismatch. Every `StorageError` converts into
+/// the closest `StdError`, so it can be returned with `?` from anything returning `StdResult`.
+#[derive(Debug, PartialEq)]
+pub enum StorageError {
+    /// An error of `cosmwasm_std`, e.g. a failed query or a value that failed to serialize
+    Std(StdError),
+    /// No data of type `type_name` is stored under `key` in `namespace`
+    NotFound {
+        type_name: &'static str,
+        namespace: String,
+        key: Vec<u8>,
+    },
+    /// The stored data could not be parsed as `type_name`
+    Deserialize {
+        type_name: &'static str,
+        source: String,
+    },
+    /// A value of type `type_name` could not be serialized
+    Serialize {
+        type_name: &'static str,
+        source: String,
+    },
+    /// The collection holds as many items as it can
+    CapacityExceeded { collection: &'static str },
+    /// An `Item` of type `type_name` was initialized twice
+    AlreadyInitialized { type_name: &'static str },
+    /// A counter or other numeric value would overflow
+    Overflow { collection: &'static str },
+    /// Another entry already uses the same value of a unique index
+    UniqueConstraint { index: String },
+    /// A conditional write expected the entry at another version
+    VersionMismatch { expected: u64, actual: u64 },
+    /// No migration leads from schema version `from` to `to`
+    UnsupportedMigration { from: u32, to: u32 },
+    /// The items of a collection were written with another capacity than it is created with
+    CapacityMismatch { stored: u32, configured: u32 },
+    /// A balance holds less than the amount subtracted from it
+    InsufficientFunds { available: String, required: String },
+    /// A commitment was revealed at `height`, outside its window from `opens` to `closes`
+    RevealWindow {
+        height: u64,
+        opens: u64,
+        closes: u64,
+    },
+    /// A revealed value doesn't hash to its commitment
+    InvalidReveal,
+    /// Only `remaining` more can be used before the window resets at `resets_at`
+    RateLimitExceeded { remaining: u128, resets_at: u64 },
+    /// The `Lock` stored under `lock` is held already
+    LockHeld { lock: String },
+    /// The sender is not the owner, or there is none
+    NotOwner,
+    /// The sender is not the pending owner, or there is none
+    NoPendingTransfer,
+    /// The pending ownership transfer has expired
+    TransferExpired,
+    /// A scan found more than the `max_entries` it was allowed to read
+    TooManyEntries { max_entries: u32 },
+    /// A `Cursor` was created for a map under another namespace than `namespace`
+    CursorMismatch { namespace: String },
+    /// A key to write under is empty
+    EmptyKey,
+    /// A key to write under is `len` bytes long, more than the `max_len` allowed
+    KeyTooLong { len: usize, max_len: usize },
+    /// A value was saved `age` blocks or seconds ago, longer than the `max_age` accepted
+    Stale { age: u64, max_age: u64 },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Std(err) => write!(f, "{}", err),
+            StorageError::NotFound {
+                type_name,
+                namespace,
+                key,
+            } => write!(f, "{} not found", not_found_kind(type_name, namespace, key)),
+            StorageError::Deserialize { type_name, source } => {
+                write!(f, "failed to deserialize {}: {}", type_name, source)
+            }
+            StorageError::Serialize { type_name, source } => {
+                write!(f, "failed to serialize {}: {}", type_name, source)
+            }
+            StorageError::CapacityExceeded { collection } => write!(f, "{} is full", collection),
+            StorageError::AlreadyInitialized { type_name } => {
+                write!(f, "{} is already initialized", type_name)
+            }
+            StorageError::Overflow { collection } => write!(f, "{} overflow", collection),
+            StorageError::UniqueConstraint { index } => {
+                write!(f, "Violates unique constraint on index {}", index)
+            }
+            StorageError::VersionMismatch { expected, actual } => write!(
+                f,
+                "Version mismatch: expected version {} but found {}",
+                expected, actual
+            ),
+            StorageError::UnsupportedMigration { from, to } => {
+                write!(f, "No migration from version {} to version {}", from, to)
+            }
+            StorageError::CapacityMismatch { stored, configured } => write!(
+                f,
+                "Stored capacity {} does not match the configured capacity {}",
+                stored, configured
+            ),
+            StorageError::InsufficientFunds {
+                available,
+                required,
+            } => write!(
+                f,
+                "Insufficient funds: available {}, required {}",
+                available, required
+            ),
+            StorageError::RevealWindow {
+                height,
+                opens,
+                closes,
+            } => write!(
+                f,
+                "Reveal at height {} is outside the window from {} to {}",
+                height, opens, closes
+            ),
+            StorageError::InvalidReveal => {
+                write!(f, "Revealed value does not match the commitment")
+            }
+            StorageError::RateLimitExceeded {
+                remaining,
+                resets_at,
+            } => write!(
+                f,
+                "Rate limit exceeded: {} remaining until {}",
+                remaining, resets_at
+            ),
+            StorageError::LockHeld { lock } => write!(f, "Lock {} is already held", lock),
+            StorageError::NotOwner => write!(f, "Caller is not the owner"),
+            StorageError::NoPendingTransfer => {
+                write!(f, "No ownership transfer is pending for the caller")
+            }
+            StorageError::TransferExpired => write!(f, "Ownership transfer has expired"),
+            StorageError::TooManyEntries { max_entries } => {
+                write!(f, "More than {} entries to scan", max_entries)
+            }
+            StorageError::CursorMismatch { namespace } => {
+                write!(f, "Cursor was not created for namespace {:?}", namespace)
+            }
+            StorageError::EmptyKey => write!(f, "Key is empty"),
+            StorageError::KeyTooLong { len, max_len } => write!(
+                f,
+                "Key of {} bytes is longer than the maximum of {}",
+                len, max_len
+            ),
+            StorageError::Stale { age, max_age } => write!(
+                f,
+                "Value is stale: {} old, more than the maximum age of {}",
+                age, max_age
+            ),
+        }
+    }
+}
+
+fn not_found_kind(type_name: &str, namespace: &str, key: &[u8]) -> String {
+    format!(
+        "{} in namespace {:?} at key 0x{}",
+        type_name,
+        namespace,
+        to_hex(key)
+    )
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<StorageError> for StdError {
+    fn from(err: StorageError) -> Self {
+        match err {
+            StorageError::Std(err) => err,
+            // StdError::NotFound appends "not found" to the kind itself
+            StorageError::NotFound {
+                type_name,
+                namespace,
+                key,
+            } => StdError::not_found(not_found_kind(type_name, &namespace, &key)),
+            StorageError::Deserialize { type_name, source } => {
+                StdError::parse_err(type_name, source)
+            }
+            StorageError::Serialize { type_name, source } => {
+                StdError::serialize_err(type_name, source)
+            }
+            StorageError::NotOwner => StdError::unauthorized(),
+            _ => StdError::generic_err(err.to_string()),
+        }
+    }
+}
+
+impl From<StdError> for StorageError {
+    fn from(err: StdError) -> Self {
+        StorageError::Std(err)
+    }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn converts_into_std_error() {
+        let err: StdError = StorageError::NotFound {
+            type_name: "u64",
+            namespace: "balances".to_string(),
+            key: b"\x00\xffab".to_vec(),
+        }
+        .into();
+        match err {
+            StdError::NotFound { kind, .. } => {
+                assert_eq!(kind, r#"u64 in namespace "balances" at key 0x00ff6162"#)
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        let err: StdError = StorageError::Deserialize {
+            type_name: "u64",
+            source: "unexpected end of input".to_string(),
+        }
+        .into();
+        assert!(matches!(err, StdError::ParseErr { target, .. } if target == "u64"));
+
+        let err: StdError = StorageError::CapacityExceeded {
+            collection: "Stack",
+        }
+        .into();
+        match err {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg, "Stack is full"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+}
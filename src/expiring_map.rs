@@ -0,0 +1,255 @@
+use cosmwasm_std::{BlockInfo, StdResult, Storage};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::any::type_name;
+
+use crate::error::{StorageError, StorageResult};
+use crate::helpers::serialize;
+use crate::keys::PrimaryKey;
+use crate::queue::{load_u32, save_u32};
+use crate::IterableMap;
+
+/// Expiration is the point from which an entry of an `ExpiringMap` is treated as absent
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expiration {
+    /// expires once the block height reaches the given height
+    AtHeight(u64),
+    /// expires once the block time reaches the given time, in seconds since epoch
+    AtTime(u64),
+    /// never expires
+    Never,
+}
+
+impl Expiration {
+    pub fn is_expired(&self, block: &BlockInfo) -> bool {
+        match self {
+            Expiration::AtHeight(height) => block.height >= *height,
+            Expiration::AtTime(time) => block.time >= *time,
+            Expiration::Never => false,
+        }
+    }
+}
+
+/// The value stored for every key, alongside its expiration
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Expiring<T> {
+    expires: Expiration,
+    value: T,
+}
+
+/// ExpiringMap works like a `Map`, but every entry has an `Expiration` after which it is
+/// treated as absent.
+///
+/// Expired entries are not removed by reading them, `purge_expired` removes them a few at a time.
+pub struct ExpiringMap<'a, K, T> {
+    map: IterableMap<'a, K, Expiring<T>>,
+}
+
+impl<'a, K, T> ExpiringMap<'a, K, T> {
+    const CURSOR: &'static [u8] = b"_cursor";
+
+    pub const fn new(namespace: &'a str) -> Self {
+        ExpiringMap {
+            map: IterableMap::new(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+
+    fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
+        let namespace = &[self.namespace(), namespace].concat();
+        f(namespace)
+    }
+}
+
+impl<'a, K, T> ExpiringMap<'a, K, T>
+where
+    T: Serialize + DeserializeOwned,
+    K: PrimaryKey<'a>,
+{
+    /// save will serialize the model and store it until `expires`,
+    /// returns an error on serialization issues
+    pub fn save(
+        &self,
+        store: &mut dyn Storage,
+        k: K,
+        data: &T,
+        expires: Expiration,
+    ) -> StorageResult<()> {
+        // serializes just like an `Expiring<T>`, without having to clone `data`
+        let value = Expiring {
+            expires,
+            value: data,
+        };
+        self.map
+            .save_raw(store, &k.joined_key(), &serialize(&value)?)
+    }
+
+    pub fn remove(&self, store: &mut dyn Storage, k: K) -> StdResult<()> {
+        self.map.remove(store, k)?;
+        Ok(())
+    }
+
+    /// load will return an error if no unexpired data is set at the given key, or on parse error
+    pub fn load(&self, store: &dyn Storage, block: &BlockInfo, k: K) -> StdResult<T> {
+        let key = k.joined_key();
+        self.may_load(store, block, k)?.ok_or_else(|| {
+            StorageError::NotFound {
+                type_name: type_name::<T>(),
+                namespace: String::from_utf8_lossy(self.map.name()).into_owned(),
+                key,
+            }
+            .into()
+        })
+    }
+
+    /// may_load will parse the data stored at the key if present and not expired,
+    /// returns Ok(None) otherwise. returns an error on issues parsing
+    pub fn may_load(&self, store: &dyn Storage, block: &BlockInfo, k: K) -> StdResult<Option<T>> {
+        Ok(self
+            .map
+            .may_load(store, k)?
+            .filter(|e| !e.expires.is_expired(block))
+            .map(|e| e.value))
+    }
+
+    /// Returns the expiration of the entry at the given key, whether it already expired or not
+    pub fn expiration(&self, store: &dyn Storage, k: K) -> StdResult<Option<Expiration>> {
+        Ok(self.map.may_load(store, k)?.map(|e| e.expires))
+    }
+
+    /// has returns true if unexpired data is at this key
+    pub fn has(&self, store: &dyn Storage, block: &BlockInfo, k: K) -> StdResult<bool> {
+        Ok(self.may_load(store, block, k)?.is_some())
+    }
+
+    /// Checks at most `limit` entries, removing the ones that are expired, and returns the number
+    /// of removed entries. Every call continues where the previous one stopped, so calling this
+    /// regularly eventually removes all expired entries.
+    pub fn purge_expired(
+        &self,
+        store: &mut dyn Storage,
+        block: &BlockInfo,
+        limit: u32,
+    ) -> StdResult<u32> {
+        let mut index = self.with_namespace_suffix(Self::CURSOR, |ns| load_u32(store, ns));
+        let mut removed = 0;
+
+        for _ in 0..limit {
+            let len = self.map.len(store);
+            if len == 0 {
+                break;
+            }
+            if index >= len {
+                index = 0;
+            }
+
+            let (raw_key, entry) = self.map.load_raw_at(store, index)?;
+            if entry.expires.is_expired(block) {
+                // the last entry takes the place of the removed one, so check this index again
+                self.map.remove_raw(store, &raw_key)?;
+                removed += 1;
+            } else {
+                index += 1;
+            }
+        }
+
+        self.with_namespace_suffix(Self::CURSOR, |ns| save_u32(store, ns, index));
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::{mock_env, MockStorage};
+    use cosmwasm_std::StdError;
+
+    const SESSIONS: ExpiringMap<&str, u64> = ExpiringMap::new("sessions");
+
+    fn block(height: u64, time: u64) -> BlockInfo {
+        BlockInfo {
+            height,
+            time,
+            ..mock_env("creator", &[]).block
+        }
+    }
+
+    #[test]
+    fn expired_entries_are_absent() {
+        let mut store = MockStorage::new();
+
+        SESSIONS
+            .save(&mut store, "alice", &1, Expiration::AtHeight(100))
+            .unwrap();
+        SESSIONS
+            .save(&mut store, "bob", &2, Expiration::AtTime(5_000))
+            .unwrap();
+        SESSIONS
+            .save(&mut store, "carol", &3, Expiration::Never)
+            .unwrap();
+
+        let before = block(99, 4_999);
+        assert_eq!(SESSIONS.load(&store, &before, "alice").unwrap(), 1);
+        assert_eq!(SESSIONS.load(&store, &before, "bob").unwrap(), 2);
+        assert!(SESSIONS.has(&store, &before, "carol").unwrap());
+
+        let after = block(100, 5_000);
+        assert!(matches!(
+            SESSIONS.load(&store, &after, "alice"),
+            Err(StdError::NotFound { .. })
+        ));
+        assert_eq!(SESSIONS.may_load(&store, &after, "bob").unwrap(), None);
+        assert_eq!(SESSIONS.load(&store, &after, "carol").unwrap(), 3);
+
+        // the expired entries are still stored
+        assert_eq!(
+            SESSIONS.expiration(&store, "alice").unwrap(),
+            Some(Expiration::AtHeight(100))
+        );
+
+        // saving again renews the entry
+        SESSIONS
+            .save(&mut store, "alice", &4, Expiration::AtHeight(200))
+            .unwrap();
+        assert_eq!(SESSIONS.load(&store, &after, "alice").unwrap(), 4);
+    }
+
+    #[test]
+    fn purge_removes_expired_entries_incrementally() {
+        let mut store = MockStorage::new();
+
+        for (i, k) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+            let expires = if i % 2 == 0 {
+                Expiration::AtHeight(10)
+            } else {
+                Expiration::Never
+            };
+            SESSIONS.save(&mut store, k, &(i as u64), expires).unwrap();
+        }
+
+        let now = block(10, 0);
+        // only two entries are checked per call, removing "a" moves "e" into its place
+        assert_eq!(SESSIONS.purge_expired(&mut store, &now, 2).unwrap(), 2);
+        assert_eq!(
+            SESSIONS.expiration(&store, "c").unwrap(),
+            Some(Expiration::AtHeight(10))
+        );
+        let mut total = 2;
+        for _ in 0..5 {
+            total += SESSIONS.purge_expired(&mut store, &now, 2).unwrap();
+        }
+        assert_eq!(total, 3);
+
+        for k in ["a", "c", "e"] {
+            assert_eq!(SESSIONS.expiration(&store, k).unwrap(), None);
+        }
+        for k in ["b", "d"] {
+            assert!(SESSIONS.has(&store, &now, k).unwrap());
+        }
+
+        // nothing left to purge
+        assert_eq!(SESSIONS.purge_expired(&mut store, &now, 10).unwrap(), 0);
+    }
+}
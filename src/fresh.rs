@@ -0,0 +1,159 @@
+use cosmwasm_std::{Env, StdResult, Storage};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::error::{StorageError, StorageResult};
+use crate::helpers::serialize;
+use crate::rate_limiter::Window;
+use crate::Item;
+
+/// A value saved by a `Fresh`, with the block height and time it was saved at
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Stamped<T> {
+    pub value: T,
+    pub height: u64,
+    pub time: u64,
+}
+
+impl<T> Stamped<T> {
+    /// Returns how many blocks or seconds, as measured by `max_age`, passed since the value was saved
+    pub fn age(&self, env: &Env, max_age: Window) -> u64 {
+        let saved = match max_age {
+            Window::Blocks(_) => self.height,
+            Window::Seconds(_) => self.time,
+        };
+        max_age.now(&env.block).saturating_sub(saved)
+    }
+}
+
+/// Fresh works like an `Item`, but remembers when its value was saved, so readers can refuse
+/// values older than they accept, e.g. an exchange rate pushed by an oracle.
+///
+/// `load` returns the value however old it is, `load_fresh` returns `StorageError::Stale` if it
+/// was saved longer ago than `max_age`.
+pub struct Fresh<'a, T> {
+    item: Item<'a, Stamped<T>>,
+}
+
+impl<'a, T> Fresh<'a, T> {
+    pub const fn new(storage_key: &'a str) -> Self {
+        Fresh {
+            item: Item::new(storage_key),
+        }
+    }
+}
+
+impl<'a, T> Fresh<'a, T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    pub fn as_slice(&self) -> &[u8] {
+        self.item.as_slice()
+    }
+
+    pub fn remove(&self, store: &mut dyn Storage) {
+        self.item.remove(store)
+    }
+
+    /// save stores `data` along with the height and time of the current block
+    pub fn save(&self, store: &mut dyn Storage, env: &Env, data: &T) -> StdResult<()> {
+        // serializes just like a `Stamped<T>`, without having to clone `data`
+        let stamped = Stamped {
+            value: data,
+            height: env.block.height,
+            time: env.block.time,
+        };
+        self.item.save_raw(store, &serialize(&stamped)?);
+        Ok(())
+    }
+
+    /// load returns the value however old it is, returns an error if no value is set
+    pub fn load(&self, store: &dyn Storage) -> StdResult<T> {
+        Ok(self.item.load(store)?.value)
+    }
+
+    pub fn may_load(&self, store: &dyn Storage) -> StdResult<Option<T>> {
+        Ok(self.item.may_load(store)?.map(|stamped| stamped.value))
+    }
+
+    /// Returns the value along with the block it was saved at, None if no value is set
+    pub fn may_load_stamped(&self, store: &dyn Storage) -> StdResult<Option<Stamped<T>>> {
+        Ok(self.item.may_load(store)?)
+    }
+
+    /// load_fresh returns the value if it was saved at most `max_age` ago, returns
+    /// `StorageError::Stale` if it is older, or an error if no value is set
+    pub fn load_fresh(&self, store: &dyn Storage, env: &Env, max_age: Window) -> StorageResult<T> {
+        let stamped = self.item.load(store)?;
+        let age = stamped.age(env, max_age);
+        if age > max_age.len() {
+            return Err(StorageError::Stale {
+                age,
+                max_age: max_age.len(),
+            });
+        }
+        Ok(stamped.value)
+    }
+
+    /// Returns true if a value is set and was saved at most `max_age` ago
+    pub fn is_fresh(&self, store: &dyn Storage, env: &Env, max_age: Window) -> StdResult<bool> {
+        Ok(self
+            .item
+            .may_load(store)?
+            .is_some_and(|stamped| stamped.age(env, max_age) <= max_age.len()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::{mock_env, MockStorage};
+
+    const RATE: Fresh<u64> = Fresh::new("rate");
+
+    fn env_at(height: u64, time: u64) -> Env {
+        let mut env = mock_env("oracle", &[]);
+        env.block.height = height;
+        env.block.time = time;
+        env
+    }
+
+    #[test]
+    fn refuses_stale_values() {
+        let mut store = MockStorage::new();
+        assert!(RATE
+            .load_fresh(&store, &env_at(1, 100), Window::Blocks(5))
+            .is_err());
+        assert!(!RATE
+            .is_fresh(&store, &env_at(1, 100), Window::Blocks(5))
+            .unwrap());
+
+        RATE.save(&mut store, &env_at(10, 1000), &42).unwrap();
+        let stamped = RATE.may_load_stamped(&store).unwrap().unwrap();
+        assert_eq!((stamped.height, stamped.time), (10, 1000));
+
+        let later = env_at(15, 1100);
+        assert_eq!(
+            RATE.load_fresh(&store, &later, Window::Blocks(5)).unwrap(),
+            42
+        );
+        assert!(RATE.is_fresh(&store, &later, Window::Blocks(5)).unwrap());
+        assert_eq!(
+            RATE.load_fresh(&store, &later, Window::Seconds(60))
+                .unwrap_err(),
+            StorageError::Stale {
+                age: 100,
+                max_age: 60
+            }
+        );
+        assert!(!RATE.is_fresh(&store, &later, Window::Blocks(4)).unwrap());
+        // the value can still be read without checking its age
+        assert_eq!(RATE.load(&store).unwrap(), 42);
+
+        RATE.save(&mut store, &later, &43).unwrap();
+        assert_eq!(
+            RATE.load_fresh(&store, &later, Window::Seconds(60))
+                .unwrap(),
+            43
+        );
+    }
+}
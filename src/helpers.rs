@@ -7,39 +7,131 @@
 use serde::{de::DeserializeOwned, Serialize};
 use std::any::type_name;
 
+use crate::error::{to_hex, StorageError, StorageResult};
 use crate::keys::Key;
+use crate::serialization::Serde;
 
-use cosmwasm_std::{StdError, StdResult};
+use cosmwasm_std::{
+    Empty, HumanAddr as Addr, Querier, QueryRequest, StdError, StdResult, WasmQuery,
+};
 
-/// may_deserialize parses json bytes from storage (Option), returning Ok(None) if no data present
+/// may_deserialize parses bytes from storage (Option), returning Ok(None) if no data present
 ///
 /// value is an odd type, but this is meant to be easy to use with output from storage.get (Option<Vec<u8>>)
-/// and value.map(|s| s.as_slice()) seems trickier than &value
-pub(crate) fn may_deserialize<T: DeserializeOwned>(
+/// and value.map(|s| s.as_slice()) seems trickier than &value.
+/// `namespace` and `key` tell where the value was loaded from, and are added to parse errors
+pub(crate) fn may_deserialize<T, Ser: Serde<T>>(
     value: &Option<Vec<u8>>,
-) -> StdResult<Option<T>> {
+    namespace: &[u8],
+    key: &[u8],
+) -> StorageResult<Option<T>> {
     match value {
-        Some(vec) => Ok(Some(deserialize_slice(vec)?)),
+        Some(vec) => {
+            Ok(Some(Ser::deserialize(vec).map_err(|err| {
+                with_key_context::<T>(err, namespace, key)
+            })?))
+        }
         None => Ok(None),
     }
 }
 
-/// must_deserialize parses json bytes from storage (Option), returning NotFound error if no data present
-pub(crate) fn must_deserialize<T: DeserializeOwned>(value: &Option<Vec<u8>>) -> StdResult<T> {
+/// must_deserialize parses bytes from storage (Option), returning a NotFound error naming
+/// `namespace` and `key` if no data present
+pub(crate) fn must_deserialize<T, Ser: Serde<T>>(
+    value: &Option<Vec<u8>>,
+    namespace: &[u8],
+    key: &[u8],
+) -> StorageResult<T> {
     match value {
-        Some(vec) => deserialize_slice(vec),
-        None => Err(StdError::not_found(type_name::<T>())),
+        Some(vec) => {
+            Ser::deserialize(vec).map_err(|err| with_key_context::<T>(err, namespace, key))
+        }
+        None => Err(StorageError::NotFound {
+            type_name: type_name::<T>(),
+            namespace: String::from_utf8_lossy(namespace).into_owned(),
+            key: key.to_vec(),
+        }),
+    }
+}
+
+/// Turns parse errors into `StorageError::Deserialize` with the location of the value added,
+/// so failed migrations can be traced to the data
+fn with_key_context<T>(err: StdError, namespace: &[u8], key: &[u8]) -> StorageError {
+    match err {
+        StdError::ParseErr { msg, .. } => StorageError::Deserialize {
+            type_name: type_name::<T>(),
+            source: format!(
+                "{} (in namespace {:?} at key 0x{})",
+                msg,
+                String::from_utf8_lossy(namespace),
+                to_hex(key)
+            ),
+        },
+        err => err.into(),
     }
 }
 
-fn deserialize_slice<T: DeserializeOwned>(slice: &[u8]) -> StdResult<T> {
-    bincode2::deserialize(slice)
-        .map_err(|err| StdError::generic_err(format!("bincode2 deserialization failed: {err}")))
+/// Splits a key built by `namespaces_with_key` into its top level namespace and the rest of the key
+pub(crate) fn split_namespace(storage_key: &[u8]) -> (&[u8], &[u8]) {
+    match storage_key {
+        [len_hi, len_lo, rest @ ..] => {
+            let len = u16::from_be_bytes([*len_hi, *len_lo]) as usize;
+            if len <= rest.len() {
+                rest.split_at(len)
+            } else {
+                (&[], storage_key)
+            }
+        }
+        _ => (&[], storage_key),
+    }
+}
+
+pub(crate) fn deserialize_slice<T: DeserializeOwned>(slice: &[u8]) -> StdResult<T> {
+    bincode2::deserialize(slice).map_err(|err| {
+        StorageError::Deserialize {
+            type_name: type_name::<T>(),
+            source: err.to_string(),
+        }
+        .into()
+    })
 }
 
 pub(crate) fn serialize<T: Serialize>(value: &T) -> StdResult<Vec<u8>> {
-    bincode2::serialize(&value)
-        .map_err(|err| StdError::serialize_err(err.to_string(), "bincode2 serialization failed"))
+    bincode2::serialize(&value).map_err(|err| {
+        StorageError::Serialize {
+            type_name: type_name::<T>(),
+            source: err.to_string(),
+        }
+        .into()
+    })
+}
+
+/// query_raw reads the raw value a remote contract stores under `key`,
+/// returning None if nothing is stored there
+pub(crate) fn query_raw<Q: Querier>(
+    querier: &Q,
+    contract_addr: Addr,
+    callback_code_hash: String,
+    key: &[u8],
+) -> StdResult<Option<Vec<u8>>> {
+    let request: QueryRequest<Empty> = WasmQuery::Raw {
+        contract_addr,
+        key: key.into(),
+        callback_code_hash,
+    }
+    .into();
+    let raw = cosmwasm_std::to_vec(&request)?;
+    match querier.raw_query(&raw) {
+        Err(sys) => Err(StdError::generic_err(format!(
+            "Querier system error: {}",
+            sys
+        ))),
+        Ok(Err(StdError::NotFound { .. })) => Ok(None),
+        Ok(Err(err)) => Err(err),
+        // missing keys are returned as empty values
+        Ok(Ok(value)) if value.is_empty() => Ok(None),
+        Ok(Ok(value)) => Ok(Some(value.0)),
+    }
 }
 
 /// This is equivalent concat(to_length_prefixed_nested(namespaces), key)
@@ -96,10 +188,46 @@ pub(crate) fn encode_length(namespace: &[u8]) -> [u8; 2] {
     [length_bytes[2], length_bytes[3]]
 }
 
+#[cfg(test)]
+pub(crate) mod testing {
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::{
+        from_slice, Binary, Empty, HumanAddr as Addr, Querier, QuerierResult, QueryRequest,
+        ReadonlyStorage, SystemError, WasmQuery,
+    };
+
+    /// A querier answering raw queries from the storage of a single remote contract
+    pub(crate) struct RemoteContract {
+        pub addr: Addr,
+        pub storage: MockStorage,
+    }
+
+    impl Querier for RemoteContract {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let request: QueryRequest<Empty> = from_slice(bin_request).unwrap();
+            match request {
+                QueryRequest::Wasm(WasmQuery::Raw {
+                    contract_addr, key, ..
+                }) if contract_addr == self.addr => Ok(Ok(Binary(
+                    self.storage.get(key.as_slice()).unwrap_or_default(),
+                ))),
+                QueryRequest::Wasm(WasmQuery::Raw { contract_addr, .. }) => {
+                    Err(SystemError::NoSuchContract {
+                        addr: contract_addr,
+                    })
+                }
+                _ => Err(SystemError::UnsupportedRequest {
+                    kind: "non-raw".to_string(),
+                }),
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use cosmwasm_std::StdError;
+    use crate::serialization::Bincode2;
     use serde::{Deserialize, Serialize};
 
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -134,13 +262,14 @@ mod test {
         };
         let value = serialize(&person).unwrap();
 
-        let may_parse: Option<Person> = may_deserialize(&Some(value)).unwrap();
+        let may_parse: Option<Person> =
+            may_deserialize::<_, Bincode2>(&Some(value), b"people", b"").unwrap();
         assert_eq!(may_parse, Some(person));
     }
 
     #[test]
     fn may_deserialize_handles_none() {
-        let may_parse = may_deserialize::<Person>(&None).unwrap();
+        let may_parse = may_deserialize::<Person, Bincode2>(&None, b"people", b"").unwrap();
         assert_eq!(may_parse, None);
     }
 
@@ -153,18 +282,16 @@ mod test {
         let value = serialize(&person).unwrap();
         let loaded = Some(value);
 
-        let parsed: Person = must_deserialize(&loaded).unwrap();
+        let parsed: Person = must_deserialize::<_, Bincode2>(&loaded, b"people", b"").unwrap();
         assert_eq!(parsed, person);
     }
 
     #[test]
     fn must_deserialize_handles_none() {
-        let parsed = must_deserialize::<Person>(&None);
-        match parsed.unwrap_err() {
-            StdError::NotFound { kind, .. } => {
-                assert_eq!(kind, "secret_storage_lite::helpers::test::Person")
-            }
-            e => panic!("Unexpected error {}", e),
-        }
+        let parsed = must_deserialize::<Person, Bincode2>(&None, b"people", b"maria");
+        assert_eq!(
+            parsed.unwrap_err().to_string(),
+            r#"secret_storage_lite::helpers::test::Person in namespace "people" at key 0x6d61726961 not found"#
+        );
     }
 }
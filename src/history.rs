@@ -0,0 +1,110 @@
+use std::iter::Rev;
+
+use cosmwasm_std::{StdResult, Storage};
+
+use crate::error::StorageResult;
+use crate::queue::{Queue, QueueIter};
+use crate::serialization::{Bincode2, Serde};
+
+/// The last `max_len` values saved to an item, e.g. the last 100 oracle prices. Saving a value
+/// when the history is full evicts the oldest one. Stored as a `Queue` with capacity `max_len`
+pub struct History<'a, T, Ser = Bincode2> {
+    queue: Queue<'a, T, Ser>,
+}
+
+impl<'a, T, Ser> History<'a, T, Ser> {
+    /// Creates a history keeping the last `max_len` values,
+    /// panics if `max_len` is zero or `u32::MAX` like `Queue::with_capacity`
+    pub const fn new(namespace: &'a str, max_len: u32) -> Self {
+        Self {
+            queue: Queue::with_capacity(namespace, max_len),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.queue.namespace()
+    }
+
+    pub fn max_len(&self) -> u32 {
+        self.queue.max_capacity()
+    }
+
+    /// Returns the number of values kept, at most `max_len`
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.queue.len(store)
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+}
+
+impl<'a, T, Ser> History<'a, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    /// Saves `t` as the latest value, returns the oldest value if it was evicted to make room
+    pub fn save(&self, store: &mut dyn Storage, t: &T) -> StorageResult<Option<T>> {
+        self.queue.push_back_overwrite(store, t)
+    }
+
+    /// Returns the latest value, None if nothing was saved yet
+    pub fn latest(&self, store: &dyn Storage) -> StdResult<Option<T>> {
+        Ok(self.queue.peek_back(store)?)
+    }
+
+    /// Returns the value saved `n` saves before the latest one, so `nth_back(store, 0)` is the
+    /// latest value. Returns None if fewer values are kept
+    pub fn nth_back(&self, store: &dyn Storage, n: u32) -> StdResult<Option<T>> {
+        let len = self.len(store);
+        if n >= len {
+            return Ok(None);
+        }
+        Ok(self.queue.get(store, len - 1 - n)?)
+    }
+
+    /// Returns an iterator over the values kept, from the latest to the oldest
+    pub fn iter<'c>(&'c self, store: &'c dyn Storage) -> Rev<QueueIter<'a, 'c, T, Ser>> {
+        self.queue.iter(store).rev()
+    }
+
+    /// Removes all values, touching every value kept
+    pub fn clear(&self, store: &mut dyn Storage) {
+        self.queue.clear(store)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cosmwasm_std::testing::MockStorage;
+
+    use super::*;
+
+    const PRICES: History<u64> = History::new("prices", 3);
+
+    #[test]
+    fn keeps_the_last_values() {
+        let mut store = MockStorage::new();
+        assert!(PRICES.is_empty(&store));
+        assert_eq!(PRICES.latest(&store).unwrap(), None);
+        assert_eq!(PRICES.nth_back(&store, 0).unwrap(), None);
+
+        for price in 1..=3 {
+            assert_eq!(PRICES.save(&mut store, &price).unwrap(), None);
+        }
+        assert_eq!(PRICES.save(&mut store, &4).unwrap(), Some(1));
+        assert_eq!(PRICES.save(&mut store, &5).unwrap(), Some(2));
+        assert_eq!(PRICES.len(&store), PRICES.max_len());
+
+        assert_eq!(PRICES.latest(&store).unwrap(), Some(5));
+        assert_eq!(PRICES.nth_back(&store, 0).unwrap(), Some(5));
+        assert_eq!(PRICES.nth_back(&store, 2).unwrap(), Some(3));
+        assert_eq!(PRICES.nth_back(&store, 3).unwrap(), None);
+        let recent: StdResult<Vec<_>> = PRICES.iter(&store).collect();
+        assert_eq!(recent.unwrap(), vec![5, 4, 3]);
+
+        PRICES.clear(&mut store);
+        assert!(PRICES.is_empty(&store));
+        assert_eq!(PRICES.iter(&store).count(), 0);
+    }
+}
@@ -0,0 +1,227 @@
+use cosmwasm_std::{HumanAddr as Addr, Storage};
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, StdResult};
+
+use crate::error::{StorageError, StorageResult};
+#[cfg(feature = "iterator")]
+use crate::pagination::{page, Page};
+#[cfg(feature = "iterator")]
+use crate::prefix::Bound;
+use crate::queue::{load_u32, save_u32};
+use crate::Map;
+
+/// Hooks holds the contracts to notify of an event, with the code hash needed to call them.
+///
+/// At most `max_hooks` contracts can be registered, so notifying all of them stays within the
+/// gas limit. Messages are built a page at a time with `prepare_msgs`.
+pub struct Hooks<'a> {
+    map: Map<'a, Addr, String>,
+    max_hooks: u32,
+}
+
+impl<'a> Hooks<'a> {
+    const LEN: &'static [u8] = b"_len";
+
+    pub const fn new(namespace: &'a str, max_hooks: u32) -> Self {
+        Hooks {
+            map: Map::new(namespace),
+            max_hooks,
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+
+    /// Returns the number of registered hooks
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.with_namespace_suffix(Self::LEN, |ns| load_u32(store, ns))
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    fn set_len(&self, store: &mut dyn Storage, len: u32) {
+        self.with_namespace_suffix(Self::LEN, |ns| save_u32(store, ns, len))
+    }
+
+    fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
+        let namespace = &[self.namespace(), namespace].concat();
+        f(namespace)
+    }
+
+    /// Registers `addr`, or updates its code hash if it is registered already.
+    /// Returns whether it was newly registered, or an error if `max_hooks` are registered
+    pub fn add_hook(
+        &self,
+        store: &mut dyn Storage,
+        addr: Addr,
+        code_hash: String,
+    ) -> StorageResult<bool> {
+        let registered = self.map.has(store, addr.clone());
+        let len = self.len(store);
+        if !registered && len >= self.max_hooks {
+            return Err(StorageError::CapacityExceeded {
+                collection: "Hooks",
+            });
+        }
+        self.map.save(store, addr, &code_hash)?;
+        if !registered {
+            self.set_len(store, len + 1);
+        }
+        Ok(!registered)
+    }
+
+    /// Unregisters `addr`, returns whether it was registered
+    pub fn remove_hook(&self, store: &mut dyn Storage, addr: Addr) -> bool {
+        if !self.map.has(store, addr.clone()) {
+            return false;
+        }
+        self.map.remove(store, addr);
+        let len = self.len(store);
+        self.set_len(store, len - 1);
+        true
+    }
+
+    pub fn is_hook(&self, store: &dyn Storage, addr: Addr) -> bool {
+        self.map.has(store, addr)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a> Hooks<'a> {
+    /// Returns up to `limit` hooks with their code hash in address order, starting after
+    /// `start_after`
+    pub fn hooks(
+        &self,
+        store: &dyn Storage,
+        start_after: Option<Addr>,
+        limit: u32,
+    ) -> StdResult<Page<Addr, String>> {
+        let mut items = self
+            .map
+            .range(
+                store,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit as usize + 1)
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok(page(&mut items, limit))
+    }
+
+    /// Builds a message, usually a `CosmosMsg`, for each of up to `limit` hooks in address
+    /// order, starting after `start_after`, by calling `build` with its address and code hash
+    pub fn prepare_msgs<F, M>(
+        &self,
+        store: &dyn Storage,
+        start_after: Option<Addr>,
+        limit: u32,
+        mut build: F,
+    ) -> StdResult<Page<Addr, M>>
+    where
+        F: FnMut(Addr, String) -> StdResult<M>,
+    {
+        let hooks = self.hooks(store, start_after, limit)?;
+        let items = hooks
+            .items
+            .into_iter()
+            .map(|(addr, code_hash)| Ok((addr.clone(), build(addr, code_hash)?)))
+            .collect::<StdResult<_>>()?;
+        Ok(Page {
+            items,
+            next_key: hooks.next_key,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const HOOKS: Hooks = Hooks::new("hooks", 3);
+
+    fn addr(s: &str) -> Addr {
+        Addr::from(s)
+    }
+
+    #[test]
+    fn add_and_remove() {
+        let mut store = MockStorage::new();
+        assert!(HOOKS
+            .add_hook(&mut store, addr("a"), "hash_a".to_string())
+            .unwrap());
+        assert!(!HOOKS
+            .add_hook(&mut store, addr("a"), "new_hash_a".to_string())
+            .unwrap());
+        HOOKS
+            .add_hook(&mut store, addr("b"), "hash_b".to_string())
+            .unwrap();
+        HOOKS
+            .add_hook(&mut store, addr("c"), "hash_c".to_string())
+            .unwrap();
+        assert_eq!(HOOKS.len(&store), 3);
+
+        assert_eq!(
+            HOOKS
+                .add_hook(&mut store, addr("d"), "hash_d".to_string())
+                .unwrap_err(),
+            StorageError::CapacityExceeded {
+                collection: "Hooks"
+            }
+        );
+
+        assert!(HOOKS.remove_hook(&mut store, addr("b")));
+        assert!(!HOOKS.remove_hook(&mut store, addr("b")));
+        assert!(!HOOKS.is_hook(&store, addr("b")));
+        HOOKS
+            .add_hook(&mut store, addr("d"), "hash_d".to_string())
+            .unwrap();
+        assert_eq!(HOOKS.len(&store), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn prepares_messages_a_page_at_a_time() {
+        use cosmwasm_std::{to_binary, CosmosMsg, WasmMsg};
+
+        let mut store = MockStorage::new();
+        for name in ["c", "a", "b"] {
+            HOOKS
+                .add_hook(&mut store, addr(name), format!("hash_{}", name))
+                .unwrap();
+        }
+
+        let build = |contract_addr, callback_code_hash| -> StdResult<CosmosMsg> {
+            Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr,
+                callback_code_hash,
+                msg: to_binary("event")?,
+                send: vec![],
+            }))
+        };
+        let first = HOOKS.prepare_msgs(&store, None, 2, build).unwrap();
+        assert_eq!(first.next_key, Some(addr("b")));
+        match &first.items[1] {
+            (
+                hook,
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    callback_code_hash, ..
+                }),
+            ) => {
+                assert_eq!(hook, &addr("b"));
+                assert_eq!(callback_code_hash, "hash_b");
+            }
+            item => panic!("Unexpected item: {:?}", item),
+        }
+
+        let last = HOOKS
+            .prepare_msgs(&store, first.next_key, 2, build)
+            .unwrap();
+        assert_eq!(last.items.len(), 1);
+        assert_eq!(last.next_key, None);
+    }
+}
@@ -0,0 +1,456 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, KV};
+use cosmwasm_std::{StdError, StdResult, Storage};
+
+use crate::error::StorageResult;
+use crate::indexes::Index;
+#[cfg(feature = "iterator")]
+use crate::keys::KeyDeserialize;
+use crate::keys::PrimaryKey;
+use crate::map::Map;
+use crate::path::Path;
+#[cfg(feature = "iterator")]
+use crate::prefix::Bound;
+use crate::prefix::Prefix;
+
+pub trait IndexList<T> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<T>> + '_>;
+}
+
+/// `IndexedMap` works like a `Map` but has a secondary index
+pub struct IndexedMap<'a, K, T, I>
+where
+    K: PrimaryKey<'a>,
+    T: Serialize + DeserializeOwned + Clone,
+    I: IndexList<T>,
+{
+    primary: Map<'a, K, T>,
+    /// This is meant to be read directly to get the proper types, like:
+    /// map.idx.owner.range(...)
+    pub idx: I,
+}
+
+impl<'a, K, T, I> IndexedMap<'a, K, T, I>
+where
+    K: PrimaryKey<'a>,
+    T: Serialize + DeserializeOwned + Clone,
+    I: IndexList<T>,
+{
+    /// Creates a new `IndexedMap`. The indexes must have been created with `pk_namespace`
+    /// as their primary key namespace where applicable.
+    pub fn new(pk_namespace: &'a str, indexes: I) -> Self {
+        IndexedMap {
+            primary: Map::new(pk_namespace),
+            idx: indexes,
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.primary.namespace()
+    }
+
+    pub fn key(&self, k: K) -> Path<T> {
+        self.primary.key(k)
+    }
+
+    /// save will serialize the model and store, returns an error on serialization issues.
+    /// this must load the old value to update the indexes properly
+    /// if you loaded the old value earlier in the same function, use replace to avoid needless db reads
+    pub fn save(&self, store: &mut dyn Storage, key: K, data: &T) -> StorageResult<()> {
+        let old_data = self.may_load(store, key.clone())?;
+        self.replace(store, key, Some(data), old_data.as_ref())
+    }
+
+    pub fn remove(&self, store: &mut dyn Storage, key: K) -> StorageResult<()> {
+        let old_data = self.may_load(store, key.clone())?;
+        self.replace(store, key, None, old_data.as_ref())
+    }
+
+    /// replace writes data to key. old_data must be the current stored value (from a previous load)
+    /// and is used to properly update the index. This is used by save, replace, and update
+    /// and can be called directly if you want to optimize
+    pub fn replace(
+        &self,
+        store: &mut dyn Storage,
+        key: K,
+        data: Option<&T>,
+        old_data: Option<&T>,
+    ) -> StorageResult<()> {
+        // this is the key *relative* to the primary map namespace
+        let pk = key.joined_key();
+        if let Some(old) = old_data {
+            for index in self.idx.get_indexes() {
+                index.remove(store, &pk, old)?;
+            }
+        }
+        if let Some(updated) = data {
+            for index in self.idx.get_indexes() {
+                index.save(store, &pk, updated)?;
+            }
+            self.primary.save(store, key, updated)?;
+        } else {
+            self.primary.remove(store, key);
+        }
+        Ok(())
+    }
+
+    /// Loads the data, perform the specified action, and store the result
+    /// in the database. This is shorthand for some common sequences, which may be useful.
+    ///
+    /// If the data exists, `action(Some(value))` is called. Otherwise `action(None)` is called.
+    pub fn update<A, E>(&self, store: &mut dyn Storage, key: K, action: A) -> Result<T, E>
+    where
+        A: FnOnce(Option<T>) -> Result<T, E>,
+        E: From<StdError>,
+    {
+        let input = self.may_load(store, key.clone())?;
+        let old_val = input.clone();
+        let output = action(input)?;
+        self.replace(store, key, Some(&output), old_val.as_ref())
+            .map_err(StdError::from)?;
+        Ok(output)
+    }
+
+    // Everything else, that doesn't touch indexers, is just pass-through from self.primary,
+    // thus can be used from while iterating over indexes
+
+    /// load will return an error if no data is set at the given key, or on parse error
+    pub fn load(&self, store: &dyn Storage, key: K) -> StdResult<T> {
+        Ok(self.primary.load(store, key)?)
+    }
+
+    /// may_load will parse the data stored at the key if present, returns Ok(None) if no data there.
+    /// returns an error on issues parsing
+    pub fn may_load(&self, store: &dyn Storage, key: K) -> StdResult<Option<T>> {
+        Ok(self.primary.may_load(store, key)?)
+    }
+
+    /// has returns true or false if any data is at this key, without parsing or interpreting the
+    /// contents.
+    pub fn has(&self, store: &dyn Storage, key: K) -> bool {
+        self.primary.has(store, key)
+    }
+
+    pub fn prefix(&self, p: K::Prefix) -> Prefix<K::Suffix, T, K::Suffix>
+    where
+        K::Suffix: PrimaryKey<'a>,
+    {
+        self.primary.prefix(p)
+    }
+
+    pub fn sub_prefix(&self, p: K::SubPrefix) -> Prefix<K::SuperSuffix, T, K::SuperSuffix>
+    where
+        K::SuperSuffix: PrimaryKey<'a>,
+    {
+        self.primary.sub_prefix(p)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, K, T, I> IndexedMap<'a, K, T, I>
+where
+    K: PrimaryKey<'a>,
+    T: Serialize + DeserializeOwned + Clone,
+    I: IndexList<T>,
+{
+    pub fn range_raw<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<KV<T>>> + 'c>
+    where
+        T: 'c,
+    {
+        self.primary.range_raw(store, min, max, order)
+    }
+
+    pub fn keys_raw<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + 'c>
+    where
+        T: 'c,
+    {
+        self.primary.keys_raw(store, min, max, order)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, K, T, I> IndexedMap<'a, K, T, I>
+where
+    K: PrimaryKey<'a> + KeyDeserialize,
+    T: Serialize + DeserializeOwned + Clone,
+    I: IndexList<T>,
+{
+    pub fn range<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'c>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.primary.range(store, min, max, order)
+    }
+
+    pub fn keys<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<K::Output>> + 'c>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.primary.keys(store, min, max, order)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::indexes::{MultiIndex, UniqueIndex};
+    use cosmwasm_std::testing::MockStorage;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    struct Data {
+        pub name: String,
+        pub last_name: String,
+        pub age: u32,
+    }
+
+    struct DataIndexes<'a> {
+        // Last type parameters are for signaling pk deserialization
+        pub name: MultiIndex<'a, String, Data, String>,
+        pub age: UniqueIndex<'a, u32, Data, String>,
+    }
+
+    // Future Note: this can likely be macro-derived
+    impl<'a> IndexList<Data> for DataIndexes<'a> {
+        fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Data>> + '_> {
+            let v: Vec<&dyn Index<Data>> = vec![&self.name, &self.age];
+            Box::new(v.into_iter())
+        }
+    }
+
+    // Can we make it easier to define this? (less wordy generic)
+    fn build_map<'a>() -> IndexedMap<'a, &'a str, Data, DataIndexes<'a>> {
+        let indexes = DataIndexes {
+            name: MultiIndex::new(|d| d.name.clone(), "data", "data__name"),
+            age: UniqueIndex::new(|d| d.age, "data__age"),
+        };
+        IndexedMap::new("data", indexes)
+    }
+
+    fn save_data<'a>(
+        store: &mut MockStorage,
+        map: &IndexedMap<'a, &'a str, Data, DataIndexes<'a>>,
+    ) -> (Vec<&'a str>, Vec<Data>) {
+        let mut pks = vec![];
+        let mut datas = vec![];
+        let data = Data {
+            name: "Maria".to_string(),
+            last_name: "Doe".to_string(),
+            age: 42,
+        };
+        let pk = "1";
+        map.save(store, pk, &data).unwrap();
+        pks.push(pk);
+        datas.push(data);
+
+        // same name (multi-index), different last name, different age => ok
+        let data = Data {
+            name: "Maria".to_string(),
+            last_name: "Williams".to_string(),
+            age: 23,
+        };
+        let pk = "2";
+        map.save(store, pk, &data).unwrap();
+        pks.push(pk);
+        datas.push(data);
+
+        // different name, different last name, different age => ok
+        let data = Data {
+            name: "John".to_string(),
+            last_name: "Wayne".to_string(),
+            age: 32,
+        };
+        let pk = "3";
+        map.save(store, pk, &data).unwrap();
+        pks.push(pk);
+        datas.push(data);
+
+        (pks, datas)
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn store_and_load_by_index() {
+        let mut store = MockStorage::new();
+        let map = build_map();
+
+        // save data
+        let (pks, datas) = save_data(&mut store, &map);
+        let pk = pks[0];
+        let data = &datas[0];
+
+        // load it properly
+        let loaded = map.load(&store, pk).unwrap();
+        assert_eq!(*data, loaded);
+
+        // load it by multi index
+        let marias: Vec<_> = map
+            .idx
+            .name
+            .range(&store, "Maria".to_string(), None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(
+            marias,
+            vec![
+                ("1".to_string(), datas[0].clone()),
+                ("2".to_string(), datas[1].clone())
+            ]
+        );
+
+        // other names are not found
+        let count = map
+            .idx
+            .name
+            .keys_raw(&store, "Mar".to_string(), None, None, Order::Ascending)
+            .count();
+        assert_eq!(0, count);
+
+        // load it by unique index
+        let (k, v) = map.idx.age.item(&store, 42).unwrap().unwrap();
+        assert_eq!(k, pk.as_bytes());
+        assert_eq!(v, *data);
+
+        // the unique index is ordered by index value
+        let pks: Vec<_> = map
+            .idx
+            .age
+            .keys(&store, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(pks, vec!["2".to_string(), "3".to_string(), "1".to_string()]);
+
+        // other ages are not found
+        assert_eq!(None, map.idx.age.item(&store, 41).unwrap());
+    }
+
+    #[test]
+    fn unique_index_enforced() {
+        let mut store = MockStorage::new();
+        let map = build_map();
+
+        // save data
+        let (pks, datas) = save_data(&mut store, &map);
+
+        // different name, different last name, same age => error
+        let data = Data {
+            name: "Marta".to_string(),
+            last_name: "Laurens".to_string(),
+            age: 42,
+        };
+        let pk = "4";
+
+        // enforce this returns some error
+        map.save(&mut store, pk, &data).unwrap_err();
+
+        // query by unique key
+        // match on proper age
+        let (k, v) = map.idx.age.item(&store, 42).unwrap().unwrap();
+        assert_eq!(k, pks[0].as_bytes());
+        assert_eq!(v, datas[0]);
+
+        // update original data
+        map.remove(&mut store, pks[0]).unwrap();
+
+        // now we can save it
+        map.save(&mut store, pk, &data).unwrap();
+        let (k, v) = map.idx.age.item(&store, 42).unwrap().unwrap();
+        assert_eq!(k, pk.as_bytes());
+        assert_eq!(v, data);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn update_moves_indexes() {
+        let mut store = MockStorage::new();
+        let map = build_map();
+
+        // save data
+        let (pks, datas) = save_data(&mut store, &map);
+        let pk = pks[0];
+
+        // rename and age
+        let updated = map
+            .update(&mut store, pk, |d| -> StdResult<_> {
+                let mut d = d.unwrap();
+                d.name = "Mary".to_string();
+                d.age = 43;
+                Ok(d)
+            })
+            .unwrap();
+
+        // old index entries are gone
+        assert_eq!(None, map.idx.age.item(&store, 42).unwrap());
+        let marias: Vec<_> = map
+            .idx
+            .name
+            .keys(&store, "Maria".to_string(), None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(marias, vec!["2".to_string()]);
+
+        // new index entries are present
+        let (_, v) = map.idx.age.item(&store, 43).unwrap().unwrap();
+        assert_eq!(v, updated);
+        let marys: Vec<_> = map
+            .idx
+            .name
+            .range(&store, "Mary".to_string(), None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(marys, vec![("1".to_string(), updated)]);
+
+        // removing clears all the indexes
+        map.remove(&mut store, pk).unwrap();
+        assert_eq!(None, map.idx.age.item(&store, 43).unwrap());
+        let count = map
+            .idx
+            .name
+            .keys_raw(&store, "Mary".to_string(), None, None, Order::Ascending)
+            .count();
+        assert_eq!(0, count);
+
+        // and the rest is untouched
+        let all: Vec<_> = map
+            .range(&store, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(
+            all,
+            vec![
+                ("2".to_string(), datas[1].clone()),
+                ("3".to_string(), datas[2].clone())
+            ]
+        );
+    }
+}
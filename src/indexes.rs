@@ -0,0 +1,179 @@
+mod multi;
+mod unique;
+
+pub use multi::MultiIndex;
+pub use unique::UniqueIndex;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use cosmwasm_std::Storage;
+
+use crate::error::StorageResult;
+
+/// Index is a secondary index over the values of an `IndexedMap`.
+/// It is kept up to date by the `IndexedMap` on every `save`, `remove` and `update`.
+/// Implement it to add a custom indexing scheme to an `IndexList`.
+pub trait Index<T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    fn save(&self, store: &mut dyn Storage, pk: &[u8], data: &T) -> StorageResult<()>;
+    fn remove(&self, store: &mut dyn Storage, pk: &[u8], old_data: &T) -> StorageResult<()>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::StdResult;
+    use serde::Deserialize;
+
+    use crate::{IndexList, IndexedMap, Map};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    struct Token {
+        pub owner: String,
+        pub uri: String,
+    }
+
+    /// A custom index, counting the tokens held by every owner
+    struct OwnerCount<'a> {
+        counts: Map<'a, &'a str, u32>,
+    }
+
+    impl<'a> Index<Token> for OwnerCount<'a> {
+        fn save(&self, store: &mut dyn Storage, _pk: &[u8], data: &Token) -> StorageResult<()> {
+            self.counts
+                .update_or_default(store, &data.owner, |c| -> StdResult<_> { Ok(c + 1) })?;
+            Ok(())
+        }
+
+        fn remove(
+            &self,
+            store: &mut dyn Storage,
+            _pk: &[u8],
+            old_data: &Token,
+        ) -> StorageResult<()> {
+            self.counts
+                .update_or_default(store, &old_data.owner, |c| -> StdResult<_> { Ok(c - 1) })?;
+            Ok(())
+        }
+    }
+
+    struct TokenIndexes<'a> {
+        pub owner: MultiIndex<'a, String, Token, String>,
+        pub uri: UniqueIndex<'a, String, Token, String>,
+        pub count: OwnerCount<'a>,
+    }
+
+    impl<'a> IndexList<Token> for TokenIndexes<'a> {
+        fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Token>> + '_> {
+            let v: Vec<&dyn Index<Token>> = vec![&self.owner, &self.uri, &self.count];
+            Box::new(v.into_iter())
+        }
+    }
+
+    fn tokens<'a>() -> IndexedMap<'a, &'a str, Token, TokenIndexes<'a>> {
+        let indexes = TokenIndexes {
+            // indexes are derived from the values, e.g. from a single field
+            owner: MultiIndex::new(|t| t.owner.clone(), "tokens", "tokens__owner"),
+            uri: UniqueIndex::new(|t| t.uri.to_lowercase(), "tokens__uri"),
+            count: OwnerCount {
+                counts: Map::new("tokens__count"),
+            },
+        };
+        IndexedMap::new("tokens", indexes)
+    }
+
+    fn token(owner: &str, uri: &str) -> Token {
+        Token {
+            owner: owner.to_string(),
+            uri: uri.to_string(),
+        }
+    }
+
+    #[test]
+    fn custom_indexes_are_maintained() {
+        let mut store = MockStorage::new();
+        let map = tokens();
+
+        map.save(&mut store, "1", &token("alice", "ipfs://one"))
+            .unwrap();
+        map.save(&mut store, "2", &token("alice", "ipfs://two"))
+            .unwrap();
+        map.save(&mut store, "3", &token("bob", "ipfs://three"))
+            .unwrap();
+
+        let counts = &map.idx.count.counts;
+        assert_eq!(counts.load(&store, "alice").unwrap(), 2);
+        assert_eq!(counts.load(&store, "bob").unwrap(), 1);
+
+        // transfer a token
+        map.update(&mut store, "2", |t| -> StdResult<_> {
+            let mut t = t.unwrap();
+            t.owner = "bob".to_string();
+            Ok(t)
+        })
+        .unwrap();
+        assert_eq!(counts.load(&store, "alice").unwrap(), 1);
+        assert_eq!(counts.load(&store, "bob").unwrap(), 2);
+
+        // the derived unique index is case insensitive
+        map.save(&mut store, "4", &token("carol", "IPFS://ONE"))
+            .unwrap_err();
+        let (pk, t) = map
+            .idx
+            .uri
+            .item(&store, "ipfs://one".to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(pk, b"1");
+        assert_eq!(t.owner, "alice");
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn query_by_derived_index() {
+        use cosmwasm_std::Order;
+
+        let mut store = MockStorage::new();
+        let map = tokens();
+
+        map.save(&mut store, "1", &token("alice", "ipfs://one"))
+            .unwrap();
+        map.save(&mut store, "2", &token("bob", "ipfs://two"))
+            .unwrap();
+        map.save(&mut store, "3", &token("alice", "ipfs://three"))
+            .unwrap();
+
+        let owned: StdResult<Vec<_>> = map
+            .idx
+            .owner
+            .keys(&store, "alice".to_string(), None, None, Order::Ascending)
+            .collect();
+        assert_eq!(owned.unwrap(), vec!["1".to_string(), "3".to_string()]);
+    }
+
+    #[test]
+    fn standalone_unique_index() {
+        // an index can be maintained by hand next to a plain map
+        const TOKENS: Map<&str, Token> = Map::new("tokens");
+        const BY_URI: UniqueIndex<String, Token> = UniqueIndex::new(|t| t.uri.clone(), "by_uri");
+
+        let mut store = MockStorage::new();
+        let t = token("alice", "ipfs://one");
+
+        TOKENS.save(&mut store, "1", &t).unwrap();
+        BY_URI.save(&mut store, b"1", &t).unwrap();
+
+        let (pk, loaded) = BY_URI.item(&store, t.uri.clone()).unwrap().unwrap();
+        assert_eq!(pk, b"1");
+        assert_eq!(loaded, t);
+
+        TOKENS.remove(&mut store, "1");
+        BY_URI.remove(&mut store, b"1", &t).unwrap();
+        assert_eq!(BY_URI.item(&store, t.uri.clone()).unwrap(), None);
+    }
+}
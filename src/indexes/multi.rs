@@ -0,0 +1,176 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+
+use cosmwasm_std::Storage;
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, StdResult, KV};
+
+use crate::error::StorageResult;
+#[cfg(feature = "iterator")]
+use crate::helpers::{must_deserialize, namespaces_with_key};
+use crate::indexes::Index;
+#[cfg(feature = "iterator")]
+use crate::keys::KeyDeserialize;
+use crate::keys::PrimaryKey;
+use crate::map::Map;
+#[cfg(feature = "iterator")]
+use crate::namespace::storage_namespace;
+#[cfg(feature = "iterator")]
+use crate::prefix::{Bound, RawBound};
+#[cfg(feature = "iterator")]
+use crate::serialization::Bincode2;
+
+/// MultiIndex stores (namespace, index_name, idx_value, pk) -> ()
+/// Allows many values per index, and references the pk, so the value is loaded from the
+/// primary map on each lookup.
+/// The optional PK type defines the type of Primary Key deserialization.
+pub struct MultiIndex<'a, IK, T, PK = ()> {
+    index: fn(&T) -> IK,
+    idx_map: Map<'a, (Vec<u8>, Vec<u8>), ()>,
+    // only needed to load the values when querying, which requires the `iterator` feature
+    #[cfg_attr(not(feature = "iterator"), allow(dead_code))]
+    pk_namespace: &'a [u8],
+    phantom: PhantomData<PK>,
+}
+
+impl<'a, IK, T, PK> MultiIndex<'a, IK, T, PK> {
+    /// Creates a new multi index. `idx_fn` derives the index key from a value,
+    /// `pk_namespace` must match the namespace of the `IndexedMap` this index is attached to,
+    /// and `idx_namespace` must not clash with any other namespace in the contract.
+    pub const fn new(idx_fn: fn(&T) -> IK, pk_namespace: &'a str, idx_namespace: &'a str) -> Self {
+        MultiIndex {
+            index: idx_fn,
+            idx_map: Map::new(idx_namespace),
+            pk_namespace: pk_namespace.as_bytes(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, IK, T, PK> Index<T> for MultiIndex<'a, IK, T, PK>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    IK: PrimaryKey<'a>,
+{
+    fn save(&self, store: &mut dyn Storage, pk: &[u8], data: &T) -> StorageResult<()> {
+        let idx = (self.index)(data).joined_key();
+        self.idx_map.save(store, (idx, pk.to_vec()), &())
+    }
+
+    fn remove(&self, store: &mut dyn Storage, pk: &[u8], old_data: &T) -> StorageResult<()> {
+        let idx = (self.index)(old_data).joined_key();
+        self.idx_map.remove(store, (idx, pk.to_vec()));
+        Ok(())
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, IK, T, PK> MultiIndex<'a, IK, T, PK>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    IK: PrimaryKey<'a>,
+    PK: PrimaryKey<'a>,
+{
+    /// keys_raw returns the raw primary keys of all values matching `idx`,
+    /// with the bounds applied to the primary keys
+    pub fn keys_raw<'c>(
+        &self,
+        store: &'c dyn Storage,
+        idx: IK,
+        min: Option<Bound<'a, PK>>,
+        max: Option<Bound<'a, PK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + 'c>
+    where
+        T: 'c,
+    {
+        self.idx_map.prefix(idx.joined_key()).keys_raw(
+            store,
+            min.map(to_raw_pk_bound),
+            max.map(to_raw_pk_bound),
+            order,
+        )
+    }
+
+    /// range_raw returns the raw primary keys of all values matching `idx`, along with the
+    /// values loaded from the primary map
+    pub fn range_raw<'c>(
+        &self,
+        store: &'c dyn Storage,
+        idx: IK,
+        min: Option<Bound<'a, PK>>,
+        max: Option<Bound<'a, PK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<KV<T>>> + 'c>
+    where
+        T: 'c,
+    {
+        // make a copy for the closure to handle lifetimes safely
+        let pk_name = self.pk_namespace.to_vec();
+        let pk_namespace = storage_namespace(self.pk_namespace).into_owned();
+        let mapped = self.keys_raw(store, idx, min, max, order).map(move |pk| {
+            let value = store.get(&namespaces_with_key(&[&pk_namespace], &pk));
+            let t = must_deserialize::<_, Bincode2>(&value, &pk_name, &pk)?;
+            Ok((pk, t))
+        });
+        Box::new(mapped)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, IK, T, PK> MultiIndex<'a, IK, T, PK>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    IK: PrimaryKey<'a>,
+    PK: PrimaryKey<'a> + KeyDeserialize,
+{
+    /// range returns the deserialized primary keys of all values matching `idx`, along with the
+    /// values loaded from the primary map
+    pub fn range<'c>(
+        &self,
+        store: &'c dyn Storage,
+        idx: IK,
+        min: Option<Bound<'a, PK>>,
+        max: Option<Bound<'a, PK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(PK::Output, T)>> + 'c>
+    where
+        T: 'c,
+        PK::Output: 'static,
+    {
+        let de_fn: fn(Vec<u8>) -> StdResult<PK::Output> = PK::from_vec;
+        let mapped = self
+            .range_raw(store, idx, min, max, order)
+            .map(move |res| res.and_then(|(pk, value)| Ok((de_fn(pk)?, value))));
+        Box::new(mapped)
+    }
+
+    /// keys returns the deserialized primary keys of all values matching `idx`
+    pub fn keys<'c>(
+        &self,
+        store: &'c dyn Storage,
+        idx: IK,
+        min: Option<Bound<'a, PK>>,
+        max: Option<Bound<'a, PK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<PK::Output>> + 'c>
+    where
+        T: 'c,
+        PK::Output: 'static,
+    {
+        let de_fn: fn(Vec<u8>) -> StdResult<PK::Output> = PK::from_vec;
+        let mapped = self.keys_raw(store, idx, min, max, order).map(de_fn);
+        Box::new(mapped)
+    }
+}
+
+/// The index entries are keyed by the joined primary key bytes, so typed primary key bounds
+/// are converted to raw bounds over those bytes
+#[cfg(feature = "iterator")]
+fn to_raw_pk_bound<'a, 'b, PK: PrimaryKey<'a>>(bound: Bound<'a, PK>) -> Bound<'b, Vec<u8>> {
+    match bound.to_raw_bound() {
+        RawBound::Inclusive(limit) => Bound::InclusiveRaw(limit),
+        RawBound::Exclusive(limit) => Bound::ExclusiveRaw(limit),
+    }
+}
@@ -0,0 +1,175 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, KV};
+use cosmwasm_std::{StdResult, Storage};
+
+use crate::error::{StorageError, StorageResult};
+use crate::indexes::Index;
+#[cfg(feature = "iterator")]
+use crate::keys::KeyDeserialize;
+use crate::keys::PrimaryKey;
+use crate::map::Map;
+#[cfg(feature = "iterator")]
+use crate::prefix::Bound;
+
+/// UniqueRef stores a copy of the value alongside the primary key it belongs to,
+/// so an index lookup doesn't need a second read of the primary map.
+#[derive(Deserialize, Serialize)]
+pub(crate) struct UniqueRef<T> {
+    pk: Vec<u8>,
+    value: T,
+}
+
+/// UniqueIndex stores (namespace, index_name, idx_value) -> {key, value}
+/// Allows one value per index (i.e. unique) and copies pk and data
+/// The optional PK type defines the type of Primary Key deserialization.
+pub struct UniqueIndex<'a, IK, T, PK = ()> {
+    index: fn(&T) -> IK,
+    idx_map: Map<'a, IK, UniqueRef<T>>,
+    phantom: PhantomData<PK>,
+}
+
+impl<'a, IK, T, PK> UniqueIndex<'a, IK, T, PK> {
+    /// Creates a new unique index. `idx_fn` derives the index key from a value,
+    /// and `idx_namespace` must not clash with any other namespace in the contract.
+    pub const fn new(idx_fn: fn(&T) -> IK, idx_namespace: &'a str) -> Self {
+        UniqueIndex {
+            index: idx_fn,
+            idx_map: Map::new(idx_namespace),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, IK, T, PK> Index<T> for UniqueIndex<'a, IK, T, PK>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    IK: PrimaryKey<'a>,
+{
+    fn save(&self, store: &mut dyn Storage, pk: &[u8], data: &T) -> StorageResult<()> {
+        let idx = (self.index)(data);
+        // error if this is already set
+        if self.idx_map.has(store, idx.clone()) {
+            return Err(StorageError::UniqueConstraint {
+                index: String::from_utf8_lossy(self.idx_map.name()).into_owned(),
+            });
+        }
+        let unique_ref = UniqueRef {
+            pk: pk.to_vec(),
+            value: data.clone(),
+        };
+        self.idx_map.save(store, idx, &unique_ref)
+    }
+
+    fn remove(&self, store: &mut dyn Storage, _pk: &[u8], old_data: &T) -> StorageResult<()> {
+        let idx = (self.index)(old_data);
+        self.idx_map.remove(store, idx);
+        Ok(())
+    }
+}
+
+impl<'a, IK, T, PK> UniqueIndex<'a, IK, T, PK>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    IK: PrimaryKey<'a>,
+{
+    /// item returns the primary key and value stored under `idx`, if any
+    pub fn item(&self, store: &dyn Storage, idx: IK) -> StdResult<Option<(Vec<u8>, T)>> {
+        let data = self
+            .idx_map
+            .may_load(store, idx)?
+            .map(|UniqueRef { pk, value }| (pk, value));
+        Ok(data)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, IK, T, PK> UniqueIndex<'a, IK, T, PK>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    IK: PrimaryKey<'a>,
+{
+    /// range_raw iterates over the index, returning the raw primary keys along with the values
+    pub fn range_raw<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, IK>>,
+        max: Option<Bound<'a, IK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<KV<T>>> + 'c>
+    where
+        T: 'c,
+    {
+        let mapped = self
+            .idx_map
+            .range_raw(store, min, max, order)
+            .map(|res| res.map(|(_, UniqueRef { pk, value })| (pk, value)));
+        Box::new(mapped)
+    }
+
+    /// keys_raw iterates over the index, returning the raw primary keys only
+    pub fn keys_raw<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, IK>>,
+        max: Option<Bound<'a, IK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<Vec<u8>>> + 'c>
+    where
+        T: 'c,
+    {
+        let mapped = self
+            .range_raw(store, min, max, order)
+            .map(|res| res.map(|(pk, _)| pk));
+        Box::new(mapped)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, IK, T, PK> UniqueIndex<'a, IK, T, PK>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    IK: PrimaryKey<'a>,
+    PK: KeyDeserialize,
+{
+    /// range iterates over the index, returning the deserialized primary keys along with the values
+    pub fn range<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, IK>>,
+        max: Option<Bound<'a, IK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(PK::Output, T)>> + 'c>
+    where
+        T: 'c,
+        PK::Output: 'static,
+    {
+        let de_fn: fn(Vec<u8>) -> StdResult<PK::Output> = PK::from_vec;
+        let mapped = self
+            .range_raw(store, min, max, order)
+            .map(move |res| res.and_then(|(pk, value)| Ok((de_fn(pk)?, value))));
+        Box::new(mapped)
+    }
+
+    /// keys iterates over the index, returning the deserialized primary keys only
+    pub fn keys<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, IK>>,
+        max: Option<Bound<'a, IK>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<PK::Output>> + 'c>
+    where
+        T: 'c,
+        PK::Output: 'static,
+    {
+        let de_fn: fn(Vec<u8>) -> StdResult<PK::Output> = PK::from_vec;
+        let mapped = self
+            .range_raw(store, min, max, order)
+            .map(move |res| res.and_then(|(pk, _)| de_fn(pk)));
+        Box::new(mapped)
+    }
+}
@@ -1,66 +1,149 @@
-use serde::de::DeserializeOwned;
-use serde::Serialize;
+use std::any::type_name;
+use std::borrow::Cow;
 use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
 
-use cosmwasm_std::{StdError, StdResult, Storage};
+use cosmwasm_std::{HumanAddr as Addr, Querier, StdError, Storage};
 
-use crate::helpers::{may_deserialize, must_deserialize, serialize};
+use crate::error::{StorageError, StorageResult};
+use crate::helpers::{may_deserialize, must_deserialize, query_raw};
+use crate::serialization::{Bincode2, Serde};
 
 /// Item stores one typed item at the given key.
 /// This is an analog of Singleton.
 /// It functions just as Path but doesn't ue a Vec and thus has a const fn constructor.
-pub struct Item<'a, T> {
+pub struct Item<'a, T, Ser = Bincode2> {
     // this is full key - no need to length-prefix it, we only store one item
-    storage_key: &'a [u8],
+    storage_key: Cow<'a, [u8]>,
     // see https://doc.rust-lang.org/std/marker/struct.PhantomData.html#unused-type-parameters for why this is needed
     data_type: PhantomData<T>,
+    serialization_type: PhantomData<Ser>,
 }
 
-impl<'a, T> Item<'a, T> {
+impl<'a, T, Ser> Item<'a, T, Ser> {
     pub const fn new(storage_key: &'a str) -> Self {
         Item {
-            storage_key: storage_key.as_bytes(),
+            storage_key: Cow::Borrowed(storage_key.as_bytes()),
             data_type: PhantomData,
+            serialization_type: PhantomData,
+        }
+    }
+
+    /// Creates an item under a key only known at runtime, e.g. one built with `Namespace`
+    pub fn new_owned(storage_key: impl Into<Vec<u8>>) -> Self {
+        Item {
+            storage_key: Cow::Owned(storage_key.into()),
+            data_type: PhantomData,
+            serialization_type: PhantomData,
         }
     }
 }
 
-impl<'a, T> Item<'a, T>
+impl<'a, T, Ser> Item<'a, T, Ser>
 where
-    T: Serialize + DeserializeOwned,
+    Ser: Serde<T>,
 {
     // this gets the path of the data to use elsewhere
     pub fn as_slice(&self) -> &[u8] {
-        self.storage_key
+        &self.storage_key
+    }
+
+    /// Returns the storage key, registering it first with the `namespace-check` feature
+    fn storage_key(&self) -> &[u8] {
+        #[cfg(feature = "namespace-check")]
+        crate::namespace_check::register::<T>(
+            crate::namespace_check::Usage::Key,
+            &self.storage_key,
+        );
+        &self.storage_key
     }
 
     /// save will serialize the model and store, returns an error on serialization issues
-    pub fn save(&self, store: &mut dyn Storage, data: &T) -> StdResult<()> {
-        store.set(self.storage_key, &serialize(data)?);
+    pub fn save(&self, store: &mut dyn Storage, data: &T) -> StorageResult<()> {
+        store.set(self.storage_key(), &Ser::serialize(data)?);
         Ok(())
     }
 
+    /// init will serialize the model and store it only if no data is set yet,
+    /// returns an error if the item was already initialized or on serialization issues
+    pub fn init(&self, store: &mut dyn Storage, data: &T) -> StorageResult<()> {
+        if store.get(self.storage_key()).is_some() {
+            return Err(StorageError::AlreadyInitialized {
+                type_name: type_name::<T>(),
+            });
+        }
+        self.save(store, data)
+    }
+
     pub fn remove(&self, store: &mut dyn Storage) {
-        store.remove(self.storage_key);
+        store.remove(self.storage_key());
     }
 
-    pub fn take(&self, store: &mut dyn Storage) -> StdResult<T> {
+    /// take removes the data and returns it, returns an error if no data is set, or on parse error
+    pub fn take(&self, store: &mut dyn Storage) -> StorageResult<T> {
         let t = self.load(store)?;
         self.remove(store);
         Ok(t)
     }
 
     /// load will return an error if no data is set at the given key, or on parse error
-    pub fn load(&self, store: &dyn Storage) -> StdResult<T> {
-        let value = store.get(self.storage_key);
-        must_deserialize(&value)
+    pub fn load(&self, store: &dyn Storage) -> StorageResult<T> {
+        let value = store.get(self.storage_key());
+        must_deserialize::<T, Ser>(&value, &self.storage_key, b"")
     }
 
     /// may_load will parse the data stored at the key if present, returns `Ok(None)` if no data there.
     /// returns an error on issues parsing
-    pub fn may_load(&self, store: &dyn Storage) -> StdResult<Option<T>> {
-        let value = store.get(self.storage_key);
-        may_deserialize(&value)
+    pub fn may_load(&self, store: &dyn Storage) -> StorageResult<Option<T>> {
+        let value = store.get(self.storage_key());
+        may_deserialize::<T, Ser>(&value, &self.storage_key, b"")
+    }
+
+    /// save_raw stores `data` as is, without serializing it
+    pub fn save_raw(&self, store: &mut dyn Storage, data: &[u8]) {
+        store.set(self.storage_key(), data);
+    }
+
+    /// load_raw returns the stored bytes without parsing them, returns an error if no data is set
+    pub fn load_raw(&self, store: &dyn Storage) -> StorageResult<Vec<u8>> {
+        self.may_load_raw(store)
+            .ok_or_else(|| StorageError::NotFound {
+                type_name: type_name::<T>(),
+                namespace: String::from_utf8_lossy(&self.storage_key).into_owned(),
+                key: vec![],
+            })
+    }
+
+    /// may_load_raw returns the stored bytes without parsing them, None if no data is set
+    pub fn may_load_raw(&self, store: &dyn Storage) -> Option<Vec<u8>> {
+        store.get(self.storage_key())
+    }
+
+    /// load_or returns the stored data, or `default` if no data is set.
+    /// returns an error on issues parsing
+    pub fn load_or(&self, store: &dyn Storage, default: T) -> StorageResult<T> {
+        Ok(self.may_load(store)?.unwrap_or(default))
+    }
+
+    /// load_or_default returns the stored data, or `T::default()` if no data is set.
+    /// returns an error on issues parsing
+    pub fn load_or_default(&self, store: &dyn Storage) -> StorageResult<T>
+    where
+        T: Default,
+    {
+        Ok(self.may_load(store)?.unwrap_or_default())
+    }
+
+    /// query loads the item from the storage of the remote contract at `remote_contract`
+    /// with a raw query, returns an error if no data is set there or on parse error
+    pub fn query<Q: Querier>(
+        &self,
+        querier: &Q,
+        remote_contract: Addr,
+        code_hash: String,
+    ) -> StorageResult<T> {
+        let value = query_raw(querier, remote_contract, code_hash, &self.storage_key)?;
+        must_deserialize::<T, Ser>(&value, &self.storage_key, b"")
     }
 
     /// Loads the data, perform the specified action, and store the result
@@ -73,23 +156,123 @@ where
         A: FnOnce(T) -> Result<T, E>,
         E: From<StdError>,
     {
-        let input = self.load(store)?;
+        let input = self.load(store).map_err(StdError::from)?;
         let output = action(input)?;
-        self.save(store, &output)?;
+        self.save(store, &output).map_err(StdError::from)?;
         Ok(output)
     }
 
+    /// Loads the data, lets the specified action change it in place, and stores the result.
+    /// Like `update`, but without moving large data in and out of the action.
+    ///
+    /// If no data is set, `Err(StdError::NotFound)` is returned. On error nothing is stored.
+    pub fn update_mut<A, E>(&self, store: &mut dyn Storage, action: A) -> Result<T, E>
+    where
+        A: FnOnce(&mut T) -> Result<(), E>,
+        E: From<StdError>,
+    {
+        let mut data = self.load(store).map_err(StdError::from)?;
+        action(&mut data)?;
+        self.save(store, &data).map_err(StdError::from)?;
+        Ok(data)
+    }
+
+    /// Loads the data if it exists or creates a default, performs the specified action, and store the result
+    /// in the database. This is shorthand for some common sequences, which may be useful.
     pub fn update_or_default<A, E>(&self, store: &mut dyn Storage, action: A) -> Result<T, E>
     where
         T: Default,
         A: FnOnce(T) -> Result<T, E>,
         E: From<StdError>,
     {
-        let input = self.may_load(store)?.unwrap_or_default();
+        let input = self
+            .may_load(store)
+            .map_err(StdError::from)?
+            .unwrap_or_default();
         let output = action(input)?;
-        self.save(store, &output)?;
+        self.save(store, &output).map_err(StdError::from)?;
         Ok(output)
     }
+
+    /// cached loads the data once and returns a handle to it, which is saved back to the store
+    /// on `flush` or when the handle is dropped, if it was borrowed mutably.
+    /// returns an error if no data is set, or on parse error
+    pub fn cached<'s>(&self, store: &'s mut dyn Storage) -> StorageResult<CachedItem<'s, T, Ser>> {
+        let value = self.load(store)?;
+        Ok(CachedItem {
+            storage_key: self.storage_key.to_vec(),
+            store,
+            value,
+            dirty: false,
+            serialization_type: PhantomData,
+        })
+    }
+}
+
+/// CachedItem is a handle to the deserialized data of an `Item`, see `Item::cached`.
+///
+/// Dereferencing it mutably marks the data as changed, it is then saved when the handle is
+/// dropped. Errors can't be returned from a drop, so call `flush` to handle them, or `discard`
+/// to drop the changes.
+pub struct CachedItem<'s, T, Ser = Bincode2>
+where
+    Ser: Serde<T>,
+{
+    storage_key: Vec<u8>,
+    store: &'s mut dyn Storage,
+    value: T,
+    dirty: bool,
+    serialization_type: PhantomData<Ser>,
+}
+
+impl<'s, T, Ser> CachedItem<'s, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    /// flush saves the data if it was changed, returns an error on serialization issues
+    pub fn flush(&mut self) -> StorageResult<()> {
+        if self.dirty {
+            self.store
+                .set(&self.storage_key, &Ser::serialize(&self.value)?);
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// discard drops the handle without saving the changes
+    pub fn discard(mut self) {
+        self.dirty = false;
+    }
+}
+
+impl<'s, T, Ser> Deref for CachedItem<'s, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<'s, T, Ser> DerefMut for CachedItem<'s, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        self.dirty = true;
+        &mut self.value
+    }
+}
+
+impl<'s, T, Ser> Drop for CachedItem<'s, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
 }
 
 #[cfg(test)]
@@ -98,7 +281,9 @@ mod test {
     use cosmwasm_std::testing::MockStorage;
     use serde::{Deserialize, Serialize};
 
-    use cosmwasm_std::StdError;
+    use cosmwasm_std::{StdError, StdResult};
+
+    use crate::helpers::serialize;
 
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
     struct Config {
@@ -146,6 +331,229 @@ mod test {
         assert_eq!(None, CONFIG.may_load(&store).unwrap());
     }
 
+    #[test]
+    fn json_backend_stores_json() {
+        use crate::serialization::Json;
+        use cosmwasm_std::ReadonlyStorage;
+
+        const JSON_CONFIG: Item<Config, Json> = Item::new("json_config");
+        let mut store = MockStorage::new();
+
+        let cfg = Config {
+            owner: "admin".to_string(),
+            max_tokens: 1234,
+        };
+        JSON_CONFIG.save(&mut store, &cfg).unwrap();
+        assert_eq!(
+            store.get(b"json_config").unwrap(),
+            br#"{"owner":"admin","max_tokens":1234}"#
+        );
+        assert_eq!(cfg, JSON_CONFIG.load(&store).unwrap());
+
+        // the backends don't understand each other's data
+        const BINCODE_CONFIG: Item<Config> = Item::new("json_config");
+        BINCODE_CONFIG.load(&store).unwrap_err();
+    }
+
+    #[test]
+    fn query_remote_item() {
+        use crate::helpers::testing::RemoteContract;
+        use cosmwasm_std::HumanAddr as Addr;
+
+        let mut remote = RemoteContract {
+            addr: Addr::from("remote"),
+            storage: MockStorage::new(),
+        };
+        let code_hash = "hash".to_string();
+
+        match CONFIG.query(&remote, remote.addr.clone(), code_hash.clone()) {
+            Err(StorageError::NotFound { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let cfg = Config {
+            owner: "admin".to_string(),
+            max_tokens: 1234,
+        };
+        CONFIG.save(&mut remote.storage, &cfg).unwrap();
+        assert_eq!(
+            CONFIG
+                .query(&remote, remote.addr.clone(), code_hash.clone())
+                .unwrap(),
+            cfg
+        );
+
+        CONFIG
+            .query(&remote, Addr::from("other"), code_hash)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn load_errors_name_the_item() {
+        // the item is read as another type on purpose
+        #[cfg(feature = "namespace-check")]
+        crate::namespace_check::set_enabled(false);
+        let mut store = MockStorage::new();
+
+        assert_eq!(
+            CONFIG.load(&store).unwrap_err(),
+            StorageError::NotFound {
+                type_name: "secret_storage_lite::item::test::Config",
+                namespace: "config".to_string(),
+                key: vec![],
+            }
+        );
+
+        const OTHER: Item<u8> = Item::new("config");
+        OTHER.save(&mut store, &7).unwrap();
+        match CONFIG.load(&store).unwrap_err() {
+            StorageError::Deserialize { source, .. } => {
+                assert!(source.contains(r#"in namespace "config""#), "{}", source)
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn raw_access() {
+        use cosmwasm_std::ReadonlyStorage;
+
+        let mut store = MockStorage::new();
+        const BLOB: Item<Vec<u8>> = Item::new("blob");
+
+        assert_eq!(BLOB.may_load_raw(&store), None);
+        match BLOB.load_raw(&store).unwrap_err() {
+            StorageError::NotFound { namespace, .. } => assert_eq!(namespace, "blob"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        // stored as is, not as a JSON array
+        BLOB.save_raw(&mut store, b"\x08\x96\x01");
+        assert_eq!(BLOB.load_raw(&store).unwrap(), b"\x08\x96\x01");
+        assert_eq!(store.get(b"blob").unwrap(), b"\x08\x96\x01");
+        BLOB.remove(&mut store);
+        assert_eq!(BLOB.may_load_raw(&store), None);
+    }
+
+    #[test]
+    fn init_only_saves_once() {
+        let mut store = MockStorage::new();
+
+        let cfg = Config {
+            owner: "admin".to_string(),
+            max_tokens: 1234,
+        };
+        CONFIG.init(&mut store, &cfg).unwrap();
+        assert_eq!(cfg, CONFIG.load(&store).unwrap());
+
+        // a second init fails and leaves the data untouched
+        let other = Config {
+            owner: "other".to_string(),
+            max_tokens: 1,
+        };
+        assert_eq!(
+            CONFIG.init(&mut store, &other).unwrap_err(),
+            StorageError::AlreadyInitialized {
+                type_name: "secret_storage_lite::item::test::Config"
+            }
+        );
+        assert_eq!(cfg, CONFIG.load(&store).unwrap());
+
+        // once removed it can be initialized again
+        CONFIG.remove(&mut store);
+        CONFIG.init(&mut store, &other).unwrap();
+        assert_eq!(other, CONFIG.load(&store).unwrap());
+    }
+
+    #[test]
+    fn update_or_default_works() {
+        let mut store = MockStorage::new();
+
+        const COUNTER: Item<u64> = Item::new("counter");
+
+        let add_ten = |c| -> StdResult<_> { Ok(c + 10) };
+
+        // starts from the default when empty
+        assert_eq!(COUNTER.update_or_default(&mut store, add_ten).unwrap(), 10);
+        assert_eq!(COUNTER.update_or_default(&mut store, add_ten).unwrap(), 20);
+        assert_eq!(COUNTER.load(&store).unwrap(), 20);
+    }
+
+    #[test]
+    fn load_or_default_works() {
+        let mut store = MockStorage::new();
+
+        const FEE: Item<u64> = Item::new("fee");
+
+        assert_eq!(FEE.load_or(&store, 3).unwrap(), 3);
+        assert_eq!(FEE.load_or_default(&store).unwrap(), 0);
+        // nothing is saved
+        assert_eq!(FEE.may_load(&store).unwrap(), None);
+
+        FEE.save(&mut store, &7).unwrap();
+        assert_eq!(FEE.load_or(&store, 3).unwrap(), 7);
+        assert_eq!(FEE.load_or_default(&store).unwrap(), 7);
+    }
+
+    #[test]
+    fn cached_writes_back_changes() {
+        let mut store = MockStorage::new();
+        assert!(CONFIG.cached(&mut store).is_err());
+
+        let cfg = Config {
+            owner: "admin".to_string(),
+            max_tokens: 1234,
+        };
+        CONFIG.save(&mut store, &cfg).unwrap();
+
+        {
+            let mut config = CONFIG.cached(&mut store).unwrap();
+            assert_eq!(config.max_tokens, 1234);
+            config.max_tokens += 1;
+            config.flush().unwrap();
+            config.owner = "other".to_string();
+        }
+        let loaded = CONFIG.load(&store).unwrap();
+        assert_eq!(loaded.max_tokens, 1235);
+        assert_eq!(loaded.owner, "other");
+
+        // unchanged data is not written
+        let read = CONFIG.cached(&mut store).unwrap();
+        assert_eq!(read.owner, "other");
+        drop(read);
+        store.set(b"config", b"garbage");
+        assert!(CONFIG.cached(&mut store).is_err());
+    }
+
+    #[test]
+    fn cached_changes_can_be_discarded() {
+        let mut store = MockStorage::new();
+        let cfg = Config {
+            owner: "admin".to_string(),
+            max_tokens: 1234,
+        };
+        CONFIG.save(&mut store, &cfg).unwrap();
+
+        let mut config = CONFIG.cached(&mut store).unwrap();
+        config.max_tokens += 1;
+        config.discard();
+        assert_eq!(CONFIG.load(&store).unwrap(), cfg);
+    }
+
+    #[test]
+    fn take_works() {
+        let mut store = MockStorage::new();
+        assert!(CONFIG.take(&mut store).is_err());
+
+        let cfg = Config {
+            owner: "admin".to_string(),
+            max_tokens: 1234,
+        };
+        CONFIG.save(&mut store, &cfg).unwrap();
+        assert_eq!(CONFIG.take(&mut store).unwrap(), cfg);
+        assert_eq!(CONFIG.may_load(&store).unwrap(), None);
+    }
+
     #[test]
     fn isolated_reads() {
         let mut store = MockStorage::new();
@@ -205,6 +613,34 @@ mod test {
         assert_eq!(old_max_tokens, 1234);
     }
 
+    #[test]
+    fn update_mut_works() {
+        let mut store = MockStorage::new();
+        let add_one = |c: &mut Config| -> StdResult<()> {
+            c.max_tokens += 1;
+            Ok(())
+        };
+        assert!(CONFIG.update_mut(&mut store, add_one).is_err());
+
+        let cfg = Config {
+            owner: "admin".to_string(),
+            max_tokens: 1234,
+        };
+        CONFIG.save(&mut store, &cfg).unwrap();
+
+        let output = CONFIG.update_mut(&mut store, add_one).unwrap();
+        assert_eq!(output.max_tokens, 1235);
+        assert_eq!(CONFIG.load(&store).unwrap(), output);
+
+        // changes made before an error are not stored
+        let output = CONFIG.update_mut(&mut store, |c| {
+            c.max_tokens = 0;
+            Err(StdError::generic_err("boom"))
+        });
+        assert!(output.is_err());
+        assert_eq!(CONFIG.load(&store).unwrap().max_tokens, 1235);
+    }
+
     #[test]
     fn update_does_not_change_data_on_error() {
         let mut store = MockStorage::new();
@@ -215,7 +651,7 @@ mod test {
         };
         CONFIG.save(&mut store, &cfg).unwrap();
 
-        let output = CONFIG.update(&mut store, &|_c| Err(StdError::generic_err("err")));
+        let output = CONFIG.update(&mut store, |_c| Err(StdError::generic_err("err")));
         match output.unwrap_err() {
             StdError::GenericErr { .. } => {}
             err => panic!("Unexpected error: {:?}", err),
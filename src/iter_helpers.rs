@@ -0,0 +1,161 @@
+use cosmwasm_std::{Order, StdResult, Storage, KV};
+
+use crate::keys::KeyDeserialize;
+use crate::prefix::RawBound;
+use crate::serialization::Serde;
+
+pub(crate) fn deserialize_v<T, Ser: Serde<T>>(kv: KV) -> StdResult<KV<T>> {
+    let (k, v) = kv;
+    let t = Ser::deserialize(&v)?;
+    Ok((k, t))
+}
+
+pub(crate) fn deserialize_kv<K: KeyDeserialize, T, Ser: Serde<T>>(
+    kv: KV,
+) -> StdResult<(K::Output, T)> {
+    let (k, v) = kv;
+    let kt = K::from_vec(k)?;
+    let vt = Ser::deserialize(&v)?;
+    Ok((kt, vt))
+}
+
+/// Returns all the key/value pairs in `[start, end)` stored under `namespace`,
+/// with the namespace trimmed from the returned keys.
+pub(crate) fn range_with_prefix<'a>(
+    storage: &'a dyn Storage,
+    namespace: &[u8],
+    start: Option<RawBound>,
+    end: Option<RawBound>,
+    order: Order,
+) -> Box<dyn Iterator<Item = KV> + 'a> {
+    let start = calc_start_bound(namespace, start);
+    let end = calc_end_bound(namespace, end);
+
+    // get iterator from storage
+    let base_iterator = storage.range(Some(&start), Some(&end), order);
+
+    // make a copy for the closure to handle lifetimes safely
+    let prefix = namespace.to_vec();
+    let mapped = base_iterator.map(move |(k, v)| (trim(&prefix, &k), v));
+    Box::new(mapped)
+}
+
+fn calc_start_bound(namespace: &[u8], bound: Option<RawBound>) -> Vec<u8> {
+    match bound {
+        None => namespace.to_vec(),
+        // this is the natural limits of the underlying Storage
+        Some(RawBound::Inclusive(limit)) => concat(namespace, &limit),
+        Some(RawBound::Exclusive(limit)) => concat(namespace, &extend_one_byte(&limit)),
+    }
+}
+
+fn calc_end_bound(namespace: &[u8], bound: Option<RawBound>) -> Vec<u8> {
+    match bound {
+        None => increment_last_byte(namespace),
+        // this is the natural limits of the underlying Storage
+        Some(RawBound::Exclusive(limit)) => concat(namespace, &limit),
+        Some(RawBound::Inclusive(limit)) => concat(namespace, &extend_one_byte(&limit)),
+    }
+}
+
+#[inline]
+fn trim(namespace: &[u8], key: &[u8]) -> Vec<u8> {
+    key[namespace.len()..].to_vec()
+}
+
+#[inline]
+fn concat(namespace: &[u8], key: &[u8]) -> Vec<u8> {
+    let mut k = namespace.to_vec();
+    k.extend_from_slice(key);
+    k
+}
+
+/// Returns a new vec of same length and last byte incremented by one
+/// If last bytes are 255, we handle overflow up the chain.
+/// If all bytes are 255, this returns wrong data - but that is never possible as a namespace
+fn increment_last_byte(input: &[u8]) -> Vec<u8> {
+    let mut copy = input.to_vec();
+    // zero out all trailing 255, increment first that is not such
+    for i in (0..input.len()).rev() {
+        if copy[i] == 255 {
+            copy[i] = 0;
+        } else {
+            copy[i] += 1;
+            break;
+        }
+    }
+    copy
+}
+
+/// Returns a new vec of same length plus one zero byte, the smallest key greater than `input`
+fn extend_one_byte(input: &[u8]) -> Vec<u8> {
+    let mut copy = Vec::with_capacity(input.len() + 1);
+    copy.extend_from_slice(input);
+    copy.push(0);
+    copy
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::helpers::namespaces_with_key;
+    use cosmwasm_std::testing::MockStorage;
+
+    #[test]
+    fn increment_last_byte_works() {
+        assert_eq!(increment_last_byte(b"bar"), b"bas".to_vec());
+        assert_eq!(increment_last_byte(b"\x00\x03foo"), b"\x00\x03fop".to_vec());
+        assert_eq!(increment_last_byte(b"\x01\xff"), b"\x02\x00".to_vec());
+    }
+
+    #[test]
+    fn range_with_prefix_stays_in_namespace() {
+        let mut store = MockStorage::new();
+        let prefix = namespaces_with_key(&[b"foo"], b"");
+        let other_prefix = namespaces_with_key(&[b"food"], b"");
+
+        // set some values in this range
+        store.set(&namespaces_with_key(&[b"foo"], b"bar"), b"none");
+        store.set(&namespaces_with_key(&[b"foo"], b"snowy"), b"day");
+
+        // set some values outside this range
+        store.set(&namespaces_with_key(&[b"food"], b"bar"), b"other");
+
+        // ensure we get proper result from prefixed_range iterator
+        let iter = range_with_prefix(&store, &prefix, None, None, Order::Descending);
+        let elements: Vec<KV> = iter.collect();
+        assert_eq!(
+            elements,
+            vec![
+                (b"snowy".to_vec(), b"day".to_vec()),
+                (b"bar".to_vec(), b"none".to_vec()),
+            ]
+        );
+
+        // make sure start and end are applied properly
+        let res: Vec<KV> = range_with_prefix(
+            &store,
+            &prefix,
+            Some(RawBound::Inclusive(b"bas".to_vec())),
+            Some(RawBound::Exclusive(b"sno".to_vec())),
+            Order::Ascending,
+        )
+        .collect();
+        assert_eq!(res.len(), 0);
+
+        let res: Vec<KV> = range_with_prefix(
+            &store,
+            &prefix,
+            Some(RawBound::Exclusive(b"bar".to_vec())),
+            Some(RawBound::Inclusive(b"snowy".to_vec())),
+            Order::Ascending,
+        )
+        .collect();
+        assert_eq!(res, vec![(b"snowy".to_vec(), b"day".to_vec())]);
+
+        // other namespace is untouched
+        let res: Vec<KV> =
+            range_with_prefix(&store, &other_prefix, None, None, Order::Ascending).collect();
+        assert_eq!(res, vec![(b"bar".to_vec(), b"other".to_vec())]);
+    }
+}
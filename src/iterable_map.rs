@@ -0,0 +1,596 @@
+use std::any::type_name;
+
+use cosmwasm_std::{StdError, StdResult, Storage};
+
+use crate::error::{StorageError, StorageResult};
+use crate::helpers::must_deserialize;
+use crate::keys::{KeyDeserialize, PrimaryKey};
+use crate::path::Path;
+use crate::queue::{load_u32, save_u32};
+use crate::serialization::{Bincode2, Serde};
+use crate::Map;
+
+/// A map that keeps an index of its keys so that it can be iterated, paged and counted
+/// using only `get` and `set`, i.e. without host-side range support.
+///
+/// Keys are iterated in insertion order, except that removing a key moves the most recently
+/// indexed key into its place.
+///
+/// Every entry is stored as the position of its key in the index, 4 bytes little-endian,
+/// followed by the value serialized with `Ser`.
+pub struct IterableMap<'a, K, T, Ser = Bincode2> {
+    // the entries are stored under the keys of `map`, but with their index prefixed
+    map: Map<'a, K, T, Ser>,
+}
+
+impl<'a, K, T, Ser> IterableMap<'a, K, T, Ser> {
+    const LEN: &'static [u8] = b"_len";
+    const KEYS: &'static [u8] = b"_keys";
+
+    pub const fn new(namespace: &'a str) -> Self {
+        IterableMap {
+            map: Map::new(namespace),
+        }
+    }
+
+    /// Creates a map under a namespace only known at runtime, e.g. one built with `Namespace`
+    pub fn new_owned(namespace: impl Into<Vec<u8>>) -> Self {
+        IterableMap {
+            map: Map::new_owned(namespace),
+        }
+    }
+
+    /// See `Map::namespace`
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+
+    pub fn name(&self) -> &[u8] {
+        self.map.name()
+    }
+
+    /// The map the entries are stored in, to range over their keys. Its values can't be loaded
+    #[cfg(feature = "iterator")]
+    pub(crate) fn map(&self) -> &Map<'a, K, T, Ser> {
+        &self.map
+    }
+
+    /// Returns the number of entries in the map
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.with_namespace_suffix(Self::LEN, |ns| load_u32(store, ns))
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    pub(crate) fn set_len(&self, store: &mut dyn Storage, len: u32) {
+        self.with_namespace_suffix(Self::LEN, |ns| save_u32(store, ns, len))
+    }
+
+    fn key_at(&self, index: u32) -> Path<Vec<u8>> {
+        self.with_namespace_suffix(Self::KEYS, |ns| Path::new(ns, &[&index.to_be_bytes()]))
+    }
+
+    fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
+        let namespace = &[self.namespace(), namespace].concat();
+        f(namespace)
+    }
+}
+
+impl<'a, K, T, Ser> IterableMap<'a, K, T, Ser>
+where
+    K: PrimaryKey<'a>,
+    Ser: Serde<T>,
+{
+    /// The path of the entry stored under the raw joined key, the same path a `Map` would use
+    fn entry(&self, raw_key: &[u8]) -> Path<T, Ser> {
+        Path::new(self.namespace(), &[raw_key])
+    }
+
+    /// Loads the index and the serialized value stored under the raw joined key
+    fn may_load_stored(
+        &self,
+        store: &dyn Storage,
+        raw_key: &[u8],
+    ) -> StorageResult<Option<(u32, Vec<u8>)>> {
+        let mut stored = match store.get(&self.entry(raw_key)) {
+            Some(stored) => stored,
+            None => return Ok(None),
+        };
+        if stored.len() < 4 {
+            return Err(StorageError::Deserialize {
+                type_name: type_name::<T>(),
+                source: format!(
+                    "missing index (in namespace {:?})",
+                    String::from_utf8_lossy(self.name())
+                ),
+            });
+        }
+        let value = stored.split_off(4);
+        let index = u32::from_le_bytes([stored[0], stored[1], stored[2], stored[3]]);
+        Ok(Some((index, value)))
+    }
+
+    /// Loads the index and the value stored under the raw joined key
+    fn may_load_entry(
+        &self,
+        store: &dyn Storage,
+        raw_key: &[u8],
+    ) -> StorageResult<Option<(u32, T)>> {
+        match self.may_load_stored(store, raw_key)? {
+            Some((index, value)) => {
+                let value = must_deserialize::<T, Ser>(&Some(value), self.name(), raw_key)?;
+                Ok(Some((index, value)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn load_entry(&self, store: &dyn Storage, raw_key: &[u8]) -> StorageResult<(u32, T)> {
+        self.may_load_entry(store, raw_key)?
+            .ok_or_else(|| self.not_found(raw_key))
+    }
+
+    fn not_found(&self, raw_key: &[u8]) -> StorageError {
+        StorageError::NotFound {
+            type_name: type_name::<T>(),
+            namespace: String::from_utf8_lossy(self.name()).into_owned(),
+            key: raw_key.to_vec(),
+        }
+    }
+
+    /// Saves `data` under `k`, adding `k` to the end of the index if it is new
+    pub fn save(&self, store: &mut dyn Storage, k: K, data: &T) -> StorageResult<()> {
+        self.save_raw(store, &k.joined_key(), &Ser::serialize(data)?)
+    }
+
+    /// Removes `k` from the map, returning the removed value if there was one
+    pub fn remove(&self, store: &mut dyn Storage, k: K) -> StdResult<Option<T>> {
+        Ok(self.remove_raw(store, &k.joined_key())?)
+    }
+
+    pub(crate) fn remove_raw(
+        &self,
+        store: &mut dyn Storage,
+        raw_key: &[u8],
+    ) -> StorageResult<Option<T>> {
+        let (index, removed) = match self.may_load_entry(store, raw_key)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let last = self.len(store) - 1;
+        if index != last {
+            // move the last key into the freed slot of the index
+            let last_key = self.key_at(last).load(store)?;
+            let (_, moved) = self
+                .may_load_stored(store, &last_key)?
+                .ok_or_else(|| self.not_found(&last_key))?;
+            self.save_stored(store, &last_key, index, &moved);
+            self.key_at(index).save(store, &last_key)?;
+        }
+        self.key_at(last).remove(store);
+        self.set_len(store, last);
+        store.remove(&self.entry(raw_key));
+
+        Ok(Some(removed))
+    }
+
+    /// Removes up to `limit` entries, the last indexed first, and returns how many are left,
+    /// so a large map can be wiped over several transactions
+    pub fn clear(&self, store: &mut dyn Storage, limit: u32) -> StdResult<u32> {
+        let len = self.len(store);
+        let left = len.saturating_sub(limit);
+        for index in (left..len).rev() {
+            let key_at = self.key_at(index);
+            let raw_key = key_at.load(store)?;
+            store.remove(&self.entry(&raw_key));
+            key_at.remove(store);
+        }
+        self.set_len(store, left);
+        Ok(left)
+    }
+
+    /// Loads the raw joined key and the value at position `index` of the key index
+    pub(crate) fn load_raw_at(&self, store: &dyn Storage, index: u32) -> StdResult<(Vec<u8>, T)> {
+        let raw_key = self.key_at(index).load(store)?;
+        let (_, value) = self.load_entry(store, &raw_key)?;
+        Ok((raw_key, value))
+    }
+
+    /// Returns the position of `k` in the key index, or an error if `k` is not in the map
+    pub(crate) fn index_of(&self, store: &dyn Storage, k: K) -> StdResult<u32> {
+        Ok(self.load_entry(store, &k.joined_key())?.0)
+    }
+
+    /// load will return an error if no data is set at the given key, or on parse error
+    pub fn load(&self, store: &dyn Storage, k: K) -> StdResult<T> {
+        Ok(self.load_entry(store, &k.joined_key())?.1)
+    }
+
+    /// may_load will parse the data stored at the key if present, returns Ok(None) if no data there.
+    /// returns an error on issues parsing
+    pub fn may_load(&self, store: &dyn Storage, k: K) -> StdResult<Option<T>> {
+        Ok(self
+            .may_load_entry(store, &k.joined_key())?
+            .map(|(_, value)| value))
+    }
+
+    /// has returns true or false if any data is at this key, without parsing or interpreting the
+    /// contents.
+    pub fn has(&self, store: &dyn Storage, k: K) -> bool {
+        self.has_raw(store, &k.joined_key())
+    }
+
+    pub(crate) fn has_raw(&self, store: &dyn Storage, raw_key: &[u8]) -> bool {
+        self.entry(raw_key).has(store)
+    }
+
+    /// Loads the data, perform the specified action, and store the result
+    /// in the database. This is shorthand for some common sequences, which may be useful.
+    ///
+    /// If the data exists, `action(Some(value))` is called. Otherwise `action(None)` is called.
+    pub fn update<A, E>(&self, store: &mut dyn Storage, k: K, action: A) -> Result<T, E>
+    where
+        A: FnOnce(Option<T>) -> Result<T, E>,
+        E: From<StdError>,
+    {
+        let raw_key = k.joined_key();
+        let input = self
+            .may_load_entry(store, &raw_key)
+            .map_err(StdError::from)?
+            .map(|(_, value)| value);
+        let output = action(input)?;
+        let data = Ser::serialize(&output)?;
+        self.save_raw(store, &raw_key, &data)
+            .map_err(StdError::from)?;
+        Ok(output)
+    }
+
+    /// Saves the serialized `data` under the raw joined key. `data` may be any value serialized
+    /// just like a `T`
+    pub(crate) fn save_raw(
+        &self,
+        store: &mut dyn Storage,
+        raw_key: &[u8],
+        data: &[u8],
+    ) -> StorageResult<()> {
+        match self.may_load_stored(store, raw_key)? {
+            Some((index, _)) => self.save_stored(store, raw_key, index, data),
+            None => self.push_raw(store, raw_key, data)?,
+        }
+        Ok(())
+    }
+
+    /// Saves the serialized `data` under the raw joined key and adds the key to the end of the
+    /// index, whatever was stored under it, e.g. to index entries saved without one
+    pub(crate) fn push_raw(
+        &self,
+        store: &mut dyn Storage,
+        raw_key: &[u8],
+        data: &[u8],
+    ) -> StorageResult<()> {
+        let len = self.len(store);
+        if len == u32::MAX {
+            return Err(StorageError::CapacityExceeded {
+                collection: "IterableMap",
+            });
+        }
+        self.key_at(len).save(store, &raw_key.to_vec())?;
+        self.set_len(store, len + 1);
+        self.save_stored(store, raw_key, len, data);
+        Ok(())
+    }
+
+    fn save_stored(&self, store: &mut dyn Storage, raw_key: &[u8], index: u32, data: &[u8]) {
+        store.set(
+            &self.entry(raw_key),
+            &[&index.to_le_bytes()[..], data].concat(),
+        );
+    }
+}
+
+impl<'a, K, T, Ser> IterableMap<'a, K, T, Ser>
+where
+    K: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<T>,
+{
+    /// Returns the entry at position `index` of the key index, or None if `index` is out of bounds
+    pub fn get_at(&self, store: &dyn Storage, index: u32) -> StdResult<Option<(K::Output, T)>> {
+        if index >= self.len(store) {
+            return Ok(None);
+        }
+        self.load_at(store, index).map(Some)
+    }
+
+    fn load_at(&self, store: &dyn Storage, index: u32) -> StdResult<(K::Output, T)> {
+        let (raw_key, value) = self.load_raw_at(store, index)?;
+        Ok((K::from_vec(raw_key)?, value))
+    }
+
+    /// Returns an iterator over all the entries of the map, in index order
+    pub fn iter<'c>(&'c self, store: &'c dyn Storage) -> IterableMapIter<'a, 'c, K, T, Ser> {
+        IterableMapIter {
+            map: self,
+            store,
+            start: 0,
+            end: self.len(store),
+        }
+    }
+
+    /// Returns the entries of page `page`, where every page holds `page_size` entries
+    pub fn paging(
+        &self,
+        store: &dyn Storage,
+        page: u32,
+        page_size: u32,
+    ) -> StdResult<Vec<(K::Output, T)>> {
+        let start = page.saturating_mul(page_size);
+        self.iter(store)
+            .skip(start as usize)
+            .take(page_size as usize)
+            .collect()
+    }
+}
+
+/// An iterator over the entries of an `IterableMap`, created by `IterableMap::iter`
+pub struct IterableMapIter<'a, 'c, K, T, Ser = Bincode2> {
+    map: &'c IterableMap<'a, K, T, Ser>,
+    store: &'c dyn Storage,
+    start: u32,
+    end: u32,
+}
+
+impl<'a, 'c, K, T, Ser> Iterator for IterableMapIter<'a, 'c, K, T, Ser>
+where
+    K: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<T>,
+{
+    type Item = StdResult<(K::Output, T)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start >= self.end {
+            return None;
+        }
+        let index = self.start;
+        self.start += 1;
+        Some(self.map.load_at(self.store, index))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.end - self.start) as usize;
+        (len, Some(len))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.start = self
+            .start
+            .saturating_add(n.try_into().unwrap_or(u32::MAX))
+            .min(self.end);
+        self.next()
+    }
+}
+
+impl<'a, 'c, K, T, Ser> DoubleEndedIterator for IterableMapIter<'a, 'c, K, T, Ser>
+where
+    K: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<T>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.start >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(self.map.load_at(self.store, self.end))
+    }
+}
+
+impl<'a, 'c, K, T, Ser> ExactSizeIterator for IterableMapIter<'a, 'c, K, T, Ser>
+where
+    K: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<T>,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use cosmwasm_std::testing::MockStorage;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::serialization::Json;
+
+    #[derive(Debug, Clone, Copy, proptest_derive::Arbitrary)]
+    enum Op {
+        Save(u8, u8),
+        Remove(u8),
+    }
+
+    const MAP: IterableMap<u8, u8> = IterableMap::new("map");
+
+    proptest! {
+        #[test]
+        fn behaves_like_a_map(ops: Vec<Op>) {
+            let mut store = MockStorage::new();
+            let mut model = HashMap::new();
+
+            for op in ops {
+                match op {
+                    Op::Save(k, v) => {
+                        MAP.save(&mut store, k, &v).unwrap();
+                        model.insert(k, v);
+                    }
+                    Op::Remove(k) => {
+                        prop_assert_eq!(MAP.remove(&mut store, k).unwrap(), model.remove(&k));
+                    }
+                }
+
+                prop_assert_eq!(MAP.len(&store) as usize, model.len());
+                let all: HashMap<u8, u8> = MAP.iter(&store).map(Result::unwrap).collect();
+                prop_assert_eq!(&all, &model);
+            }
+        }
+    }
+
+    #[test]
+    fn iterates_in_insertion_order() {
+        let mut store = MockStorage::new();
+        let map: IterableMap<&str, u32> = IterableMap::new("people");
+
+        map.save(&mut store, "john", &32).unwrap();
+        map.save(&mut store, "jim", &44).unwrap();
+        map.save(&mut store, "ada", &28).unwrap();
+        // overwriting keeps the position in the index
+        map.save(&mut store, "john", &33).unwrap();
+
+        let all: StdResult<Vec<_>> = map.iter(&store).collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![
+                ("john".to_string(), 33),
+                ("jim".to_string(), 44),
+                ("ada".to_string(), 28)
+            ]
+        );
+
+        let all: StdResult<Vec<_>> = map.iter(&store).rev().collect();
+        assert_eq!(all.unwrap()[0], ("ada".to_string(), 28));
+        assert_eq!(map.iter(&store).len(), 3);
+    }
+
+    #[test]
+    fn remove_moves_last_key() {
+        let mut store = MockStorage::new();
+        let map: IterableMap<&str, u32> = IterableMap::new("people");
+
+        map.save(&mut store, "john", &32).unwrap();
+        map.save(&mut store, "jim", &44).unwrap();
+        map.save(&mut store, "ada", &28).unwrap();
+
+        assert_eq!(map.remove(&mut store, "john").unwrap(), Some(32));
+        assert_eq!(map.remove(&mut store, "john").unwrap(), None);
+        assert!(!map.has(&store, "john"));
+        assert_eq!(map.len(&store), 2);
+
+        assert_eq!(
+            map.get_at(&store, 0).unwrap(),
+            Some(("ada".to_string(), 28))
+        );
+        assert_eq!(
+            map.get_at(&store, 1).unwrap(),
+            Some(("jim".to_string(), 44))
+        );
+        assert_eq!(map.get_at(&store, 2).unwrap(), None);
+    }
+
+    #[test]
+    fn paging_works() {
+        let mut store = MockStorage::new();
+        let map: IterableMap<u32, u32> = IterableMap::new("nums");
+
+        for i in 0..7 {
+            map.save(&mut store, i, &(i * 10)).unwrap();
+        }
+
+        assert_eq!(
+            map.paging(&store, 0, 3).unwrap(),
+            vec![(0, 0), (1, 10), (2, 20)]
+        );
+        assert_eq!(map.paging(&store, 2, 3).unwrap(), vec![(6, 60)]);
+        assert_eq!(map.paging(&store, 3, 3).unwrap(), vec![]);
+        assert_eq!(map.paging(&store, u32::MAX, u32::MAX).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn clear_in_batches() {
+        let mut store = MockStorage::new();
+        let map: IterableMap<u32, u32> = IterableMap::new("nums");
+        for i in 0..5 {
+            map.save(&mut store, i, &(i * 10)).unwrap();
+        }
+
+        assert_eq!(map.clear(&mut store, 3).unwrap(), 2);
+        assert_eq!(map.paging(&store, 0, 5).unwrap(), vec![(0, 0), (1, 10)]);
+        assert_eq!(map.may_load(&store, 4).unwrap(), None);
+
+        // the freed positions are reused
+        map.save(&mut store, 7, &70).unwrap();
+        assert_eq!(map.get_at(&store, 2).unwrap(), Some((7, 70)));
+
+        assert_eq!(map.clear(&mut store, 10).unwrap(), 0);
+        assert!(map.is_empty(&store));
+        assert_eq!(map.may_load(&store, 0).unwrap(), None);
+    }
+
+    #[test]
+    fn update_works() {
+        let mut store = MockStorage::new();
+        let map: IterableMap<(&str, u8), u32> = IterableMap::new("composite");
+
+        let v = map
+            .update(&mut store, ("a", 1), |v| -> StdResult<_> {
+                Ok(v.unwrap_or_default() + 5)
+            })
+            .unwrap();
+        assert_eq!(v, 5);
+        map.update(&mut store, ("a", 1), |v| -> StdResult<_> {
+            Ok(v.unwrap_or_default() + 5)
+        })
+        .unwrap();
+
+        assert_eq!(map.load(&store, ("a", 1)).unwrap(), 10);
+        assert_eq!(map.len(&store), 1);
+        assert_eq!(
+            map.get_at(&store, 0).unwrap(),
+            Some((("a".to_string(), 1), 10))
+        );
+    }
+
+    #[test]
+    fn entries_keep_their_bincode_layout() {
+        #[derive(serde::Serialize)]
+        struct IndexedValue {
+            index: u32,
+            value: String,
+        }
+
+        let mut store = MockStorage::new();
+        let map: IterableMap<&str, String> = IterableMap::new("people");
+        map.save(&mut store, "john", &"smith".to_string()).unwrap();
+        map.save(&mut store, "jim", &"jones".to_string()).unwrap();
+
+        let stored = map.entry(b"jim").may_load_raw(&store).unwrap();
+        let expected = crate::helpers::serialize(&IndexedValue {
+            index: 1,
+            value: "jones".to_string(),
+        })
+        .unwrap();
+        assert_eq!(stored, expected);
+    }
+
+    #[test]
+    fn works_with_other_serializations() {
+        let mut store = MockStorage::new();
+        let map: IterableMap<&str, u32, Json> = IterableMap::new_owned("people");
+
+        map.save(&mut store, "john", &32).unwrap();
+        map.save(&mut store, "jim", &44).unwrap();
+        assert_eq!(
+            map.entry(b"jim").may_load_raw(&store).unwrap(),
+            b"\x01\x00\x00\x0044"
+        );
+
+        assert_eq!(map.remove(&mut store, "john").unwrap(), Some(32));
+        assert_eq!(
+            map.get_at(&store, 0).unwrap(),
+            Some(("jim".to_string(), 44))
+        );
+        assert_eq!(
+            map.entry(b"jim").may_load_raw(&store).unwrap(),
+            b"\x00\x00\x00\x0044"
+        );
+    }
+}
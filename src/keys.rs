@@ -1,11 +1,29 @@
 mod de;
 pub mod int_key;
+mod time;
 
-use cosmwasm_std::HumanAddr as Addr;
+use cosmwasm_std::{CanonicalAddr, HumanAddr as Addr, Uint128};
 
+use crate::error::{StorageError, StorageResult};
 use crate::helpers::namespaces_with_key;
-use de::KeyDeserialize;
+pub use de::KeyDeserialize;
 use int_key::CwIntKey;
+pub use time::{FromBlock, Height, Timestamp};
+
+/// Returns `StorageError::EmptyKey` if `key` is empty, or `StorageError::KeyTooLong` if it is
+/// longer than `max_len` bytes, e.g. to check a key taken from a message before writing under it
+pub fn assert_valid_key(key: &[u8], max_len: usize) -> StorageResult<()> {
+    if key.is_empty() {
+        return Err(StorageError::EmptyKey);
+    }
+    if key.len() > max_len {
+        return Err(StorageError::KeyTooLong {
+            len: key.len(),
+            max_len,
+        });
+    }
+    Ok(())
+}
 
 #[derive(Debug)]
 pub enum Key<'a> {
@@ -55,6 +73,10 @@ impl<'a> PartialEq<&[u8]> for Key<'a> {
 ///
 /// `SubPrefix` and `SuperSuffix` only make real sense in the case of triples. Still, they need to be
 /// consistently defined for all types.
+///
+/// The parts of a tuple can mix borrowed and owned types, e.g. `(&Addr, u64, String)`. They share
+/// the lifetime `'a`, but as maps and keys are covariant in it, parts borrowed for different
+/// lifetimes, or a const map used with keys borrowed at runtime, all shorten to the same one.
 pub trait PrimaryKey<'a>: Clone {
     /// These associated types need to implement `Prefixer`, so that they can be useful arguments
     /// for `prefix()`, `sub_prefix()`, and their key-deserializable variants.
@@ -67,7 +89,7 @@ pub trait PrimaryKey<'a>: Clone {
     type SuperSuffix: KeyDeserialize;
 
     /// returns a slice of key steps, which can be optionally combined
-    fn key(&self) -> Vec<Key>;
+    fn key(&self) -> Vec<Key<'_>>;
 
     fn joined_key(&self) -> Vec<u8> {
         let keys = self.key();
@@ -91,7 +113,7 @@ impl<'a> PrimaryKey<'a> for () {
     type Suffix = Self;
     type SuperSuffix = Self;
 
-    fn key(&self) -> Vec<Key> {
+    fn key(&self) -> Vec<Key<'_>> {
         vec![]
     }
 }
@@ -102,7 +124,7 @@ impl<'a> PrimaryKey<'a> for &'a [u8] {
     type Suffix = Self;
     type SuperSuffix = Self;
 
-    fn key(&self) -> Vec<Key> {
+    fn key(&self) -> Vec<Key<'_>> {
         // this is simple, we don't add more prefixes
         vec![Key::Ref(self)]
     }
@@ -115,7 +137,7 @@ impl<'a> PrimaryKey<'a> for &'a str {
     type Suffix = Self;
     type SuperSuffix = Self;
 
-    fn key(&self) -> Vec<Key> {
+    fn key(&self) -> Vec<Key<'_>> {
         // this is simple, we don't add more prefixes
         vec![Key::Ref(self.as_bytes())]
     }
@@ -130,7 +152,7 @@ impl<'a, T: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize, U: PrimaryKey<'a> +
     type Suffix = U;
     type SuperSuffix = Self;
 
-    fn key(&self) -> Vec<Key> {
+    fn key(&self) -> Vec<Key<'_>> {
         let mut keys = self.0.key();
         keys.extend(self.1.key());
         keys
@@ -150,17 +172,65 @@ impl<
     type Suffix = V;
     type SuperSuffix = (U, V);
 
-    fn key(&self) -> Vec<Key> {
+    fn key(&self) -> Vec<Key<'_>> {
+        let mut keys = self.0.key();
+        keys.extend(self.1.key());
+        keys.extend(self.2.key());
+        keys
+    }
+}
+
+// use generics for combining there - so we can use &[u8], Vec<u8>, or IntKey
+impl<
+        'a,
+        T: PrimaryKey<'a> + Prefixer<'a>,
+        U: PrimaryKey<'a> + Prefixer<'a>,
+        V: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+        W: PrimaryKey<'a> + KeyDeserialize,
+    > PrimaryKey<'a> for (T, U, V, W)
+{
+    type Prefix = (T, U, V);
+    type SubPrefix = (T, U);
+    type Suffix = W;
+    type SuperSuffix = (V, W);
+
+    fn key(&self) -> Vec<Key<'_>> {
         let mut keys = self.0.key();
         keys.extend(self.1.key());
         keys.extend(self.2.key());
+        keys.extend(self.3.key());
+        keys
+    }
+}
+
+// use generics for combining there - so we can use &[u8], Vec<u8>, or IntKey
+impl<
+        'a,
+        T: PrimaryKey<'a> + Prefixer<'a>,
+        U: PrimaryKey<'a> + Prefixer<'a>,
+        V: PrimaryKey<'a> + Prefixer<'a>,
+        W: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+        X: PrimaryKey<'a> + KeyDeserialize,
+    > PrimaryKey<'a> for (T, U, V, W, X)
+{
+    type Prefix = (T, U, V, W);
+    type SubPrefix = (T, U, V);
+    type Suffix = X;
+    type SuperSuffix = (W, X);
+
+    fn key(&self) -> Vec<Key<'_>> {
+        let mut keys = self.0.key();
+        keys.extend(self.1.key());
+        keys.extend(self.2.key());
+        keys.extend(self.3.key());
+        keys.extend(self.4.key());
         keys
     }
 }
 
 pub trait Prefixer<'a> {
     /// returns 0 or more namespaces that should be length-prefixed and concatenated for range searches
-    fn prefix(&self) -> Vec<Key>;
+    fn prefix(&self) -> Vec<Key<'_>>;
 
     fn joined_prefix(&self) -> Vec<u8> {
         let prefixes = self.prefix();
@@ -169,37 +239,62 @@ pub trait Prefixer<'a> {
 }
 
 impl<'a> Prefixer<'a> for () {
-    fn prefix(&self) -> Vec<Key> {
+    fn prefix(&self) -> Vec<Key<'_>> {
         vec![]
     }
 }
 
 impl<'a> Prefixer<'a> for &'a [u8] {
-    fn prefix(&self) -> Vec<Key> {
+    fn prefix(&self) -> Vec<Key<'_>> {
         vec![Key::Ref(self)]
     }
 }
 
 impl<'a, T: Prefixer<'a>, U: Prefixer<'a>> Prefixer<'a> for (T, U) {
-    fn prefix(&self) -> Vec<Key> {
+    fn prefix(&self) -> Vec<Key<'_>> {
         let mut res = self.0.prefix();
-        res.extend(self.1.prefix().into_iter());
+        res.extend(self.1.prefix());
         res
     }
 }
 
 impl<'a, T: Prefixer<'a>, U: Prefixer<'a>, V: Prefixer<'a>> Prefixer<'a> for (T, U, V) {
-    fn prefix(&self) -> Vec<Key> {
+    fn prefix(&self) -> Vec<Key<'_>> {
         let mut res = self.0.prefix();
-        res.extend(self.1.prefix().into_iter());
-        res.extend(self.2.prefix().into_iter());
+        res.extend(self.1.prefix());
+        res.extend(self.2.prefix());
+        res
+    }
+}
+
+impl<'a, T: Prefixer<'a>, U: Prefixer<'a>, V: Prefixer<'a>, W: Prefixer<'a>> Prefixer<'a>
+    for (T, U, V, W)
+{
+    fn prefix(&self) -> Vec<Key<'_>> {
+        let mut res = self.0.prefix();
+        res.extend(self.1.prefix());
+        res.extend(self.2.prefix());
+        res.extend(self.3.prefix());
+        res
+    }
+}
+
+impl<'a, T: Prefixer<'a>, U: Prefixer<'a>, V: Prefixer<'a>, W: Prefixer<'a>, X: Prefixer<'a>>
+    Prefixer<'a> for (T, U, V, W, X)
+{
+    fn prefix(&self) -> Vec<Key<'_>> {
+        let mut res = self.0.prefix();
+        res.extend(self.1.prefix());
+        res.extend(self.2.prefix());
+        res.extend(self.3.prefix());
+        res.extend(self.4.prefix());
         res
     }
 }
 
 // Provide a string version of this to raw encode strings
 impl<'a> Prefixer<'a> for &'a str {
-    fn prefix(&self) -> Vec<Key> {
+    fn prefix(&self) -> Vec<Key<'_>> {
         vec![Key::Ref(self.as_bytes())]
     }
 }
@@ -210,13 +305,13 @@ impl<'a> PrimaryKey<'a> for Vec<u8> {
     type Suffix = Self;
     type SuperSuffix = Self;
 
-    fn key(&self) -> Vec<Key> {
+    fn key(&self) -> Vec<Key<'_>> {
         vec![Key::Ref(self)]
     }
 }
 
 impl<'a> Prefixer<'a> for Vec<u8> {
-    fn prefix(&self) -> Vec<Key> {
+    fn prefix(&self) -> Vec<Key<'_>> {
         vec![Key::Ref(self.as_ref())]
     }
 }
@@ -227,13 +322,47 @@ impl<'a> PrimaryKey<'a> for String {
     type Suffix = Self;
     type SuperSuffix = Self;
 
-    fn key(&self) -> Vec<Key> {
+    fn key(&self) -> Vec<Key<'_>> {
         vec![Key::Ref(self.as_bytes())]
     }
 }
 
 impl<'a> Prefixer<'a> for String {
-    fn prefix(&self) -> Vec<Key> {
+    fn prefix(&self) -> Vec<Key<'_>> {
+        vec![Key::Ref(self.as_bytes())]
+    }
+}
+
+impl<'a> PrimaryKey<'a> for &'a Vec<u8> {
+    type Prefix = ();
+    type SubPrefix = ();
+    type Suffix = Self;
+    type SuperSuffix = Self;
+
+    fn key(&self) -> Vec<Key<'_>> {
+        vec![Key::Ref(self)]
+    }
+}
+
+impl<'a> Prefixer<'a> for &'a Vec<u8> {
+    fn prefix(&self) -> Vec<Key<'_>> {
+        vec![Key::Ref(self)]
+    }
+}
+
+impl<'a> PrimaryKey<'a> for &'a String {
+    type Prefix = ();
+    type SubPrefix = ();
+    type Suffix = Self;
+    type SuperSuffix = Self;
+
+    fn key(&self) -> Vec<Key<'_>> {
+        vec![Key::Ref(self.as_bytes())]
+    }
+}
+
+impl<'a> Prefixer<'a> for &'a String {
+    fn prefix(&self) -> Vec<Key<'_>> {
         vec![Key::Ref(self.as_bytes())]
     }
 }
@@ -245,14 +374,14 @@ impl<'a> PrimaryKey<'a> for &'a Addr {
     type Suffix = Self;
     type SuperSuffix = Self;
 
-    fn key(&self) -> Vec<Key> {
+    fn key(&self) -> Vec<Key<'_>> {
         // this is simple, we don't add more prefixes
         vec![Key::Ref(self.as_str().as_bytes())]
     }
 }
 
 impl<'a> Prefixer<'a> for &'a Addr {
-    fn prefix(&self) -> Vec<Key> {
+    fn prefix(&self) -> Vec<Key<'_>> {
         vec![Key::Ref(self.as_str().as_bytes())]
     }
 }
@@ -264,18 +393,54 @@ impl<'a> PrimaryKey<'a> for Addr {
     type Suffix = Self;
     type SuperSuffix = Self;
 
-    fn key(&self) -> Vec<Key> {
+    fn key(&self) -> Vec<Key<'_>> {
         // this is simple, we don't add more prefixes
         vec![Key::Ref(self.as_str().as_bytes())]
     }
 }
 
 impl<'a> Prefixer<'a> for Addr {
-    fn prefix(&self) -> Vec<Key> {
+    fn prefix(&self) -> Vec<Key<'_>> {
         vec![Key::Ref(self.as_str().as_bytes())]
     }
 }
 
+/// canonical addresses are stored as their raw bytes.
+impl<'a> PrimaryKey<'a> for &'a CanonicalAddr {
+    type Prefix = ();
+    type SubPrefix = ();
+    type Suffix = Self;
+    type SuperSuffix = Self;
+
+    fn key(&self) -> Vec<Key<'_>> {
+        vec![Key::Ref(self.as_slice())]
+    }
+}
+
+impl<'a> Prefixer<'a> for &'a CanonicalAddr {
+    fn prefix(&self) -> Vec<Key<'_>> {
+        vec![Key::Ref(self.as_slice())]
+    }
+}
+
+/// owned variant.
+impl<'a> PrimaryKey<'a> for CanonicalAddr {
+    type Prefix = ();
+    type SubPrefix = ();
+    type Suffix = Self;
+    type SuperSuffix = Self;
+
+    fn key(&self) -> Vec<Key<'_>> {
+        vec![Key::Ref(self.as_slice())]
+    }
+}
+
+impl<'a> Prefixer<'a> for CanonicalAddr {
+    fn prefix(&self) -> Vec<Key<'_>> {
+        vec![Key::Ref(self.as_slice())]
+    }
+}
+
 macro_rules! integer_key {
     (for $($t:ty, $v:tt),+) => {
         $(impl<'a> PrimaryKey<'a> for $t {
@@ -284,31 +449,100 @@ macro_rules! integer_key {
             type Suffix = Self;
             type SuperSuffix = Self;
 
-            fn key(&self) -> Vec<Key> {
+            fn key(&self) -> Vec<Key<'_>> {
                 vec![Key::$v(self.to_cw_bytes())]
             }
         })*
     }
 }
 
-integer_key!(for i8, Val8, u8, Val8, i16, Val16, u16, Val16, i32, Val32, u32, Val32, i64, Val64, u64, Val64, i128, Val128, u128, Val128);
+integer_key!(for i8, Val8, u8, Val8, i16, Val16, u16, Val16, i32, Val32, u32, Val32, i64, Val64, u64, Val64, i128, Val128, u128, Val128, Uint128, Val128, Timestamp, Val64, Height, Val64);
 
 macro_rules! integer_prefix {
     (for $($t:ty, $v:tt),+) => {
         $(impl<'a> Prefixer<'a> for $t {
-            fn prefix(&self) -> Vec<Key> {
+            fn prefix(&self) -> Vec<Key<'_>> {
                 vec![Key::$v(self.to_cw_bytes())]
             }
         })*
     }
 }
 
-integer_prefix!(for i8, Val8, u8, Val8, i16, Val16, u16, Val16, i32, Val32, u32, Val32, i64, Val64, u64, Val64, i128, Val128, u128, Val128);
+integer_prefix!(for i8, Val8, u8, Val8, i16, Val16, u16, Val16, i32, Val32, u32, Val32, i64, Val64, u64, Val64, i128, Val128, u128, Val128, Uint128, Val128, Timestamp, Val64, Height, Val64);
+
+/// Implements `PrimaryKey`, `Prefixer` and `KeyDeserialize` for tuple structs wrapping an
+/// unsigned integer, e.g. domain ids, so they can key a map directly.
+///
+/// The keys are encoded like the integer they wrap, big-endian, so the map sorts by id and the
+/// state written with the plain integer as key stays readable.
+///
+/// ```
+/// # use cosmwasm_std::testing::MockStorage;
+/// # use secret_storage_lite::{impl_uint_key, Map};
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// pub struct ProposalId(pub u64);
+///
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// pub struct TokenId(u32);
+///
+/// impl_uint_key!(ProposalId: u64, TokenId: u32);
+///
+/// const PROPOSALS: Map<ProposalId, String> = Map::new("proposals");
+/// # let mut store = MockStorage::new();
+/// PROPOSALS.save(&mut store, ProposalId(1), &"upgrade".to_string())?;
+/// # Ok::<(), cosmwasm_std::StdError>(())
+/// ```
+#[macro_export]
+macro_rules! impl_uint_key {
+    ($($t:ty: $int:ty),+ $(,)?) => {
+        $(
+            impl<'a> $crate::keys::PrimaryKey<'a> for $t {
+                type Prefix = ();
+                type SubPrefix = ();
+                type Suffix = Self;
+                type SuperSuffix = Self;
+
+                fn key(&self) -> Vec<$crate::keys::Key<'_>> {
+                    <$int as $crate::keys::PrimaryKey>::key(&self.0)
+                }
+            }
+
+            impl<'a> $crate::keys::Prefixer<'a> for $t {
+                fn prefix(&self) -> Vec<$crate::keys::Key<'_>> {
+                    <$int as $crate::keys::Prefixer>::prefix(&self.0)
+                }
+            }
+
+            impl $crate::keys::KeyDeserialize for $t {
+                type Output = $t;
+
+                const KEY_ELEMS: u16 = 1;
+
+                fn from_vec(value: Vec<u8>) -> $crate::__private::StdResult<Self::Output> {
+                    <$int as $crate::keys::KeyDeserialize>::from_vec(value).map(|id| Self(id))
+                }
+            }
+        )+
+    };
+}
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn valid_keys() {
+        assert!(assert_valid_key(b"alice", 5).is_ok());
+        assert_eq!(
+            assert_valid_key(b"", 5).unwrap_err(),
+            StorageError::EmptyKey
+        );
+        assert_eq!(
+            assert_valid_key(b"albert", 5).unwrap_err(),
+            StorageError::KeyTooLong { len: 6, max_len: 5 }
+        );
+    }
+
     #[test]
     fn naked_8key_works() {
         let k: u8 = 42u8;
@@ -372,6 +606,35 @@ mod test {
         let path = k.key();
         assert_eq!(1, path.len());
         assert_eq!(4242i128.to_cw_bytes(), path[0].as_ref());
+
+        let k = Uint128(4242);
+        let path = k.key();
+        assert_eq!(1, path.len());
+        assert_eq!(4242u128.to_cw_bytes(), path[0].as_ref());
+    }
+
+    #[test]
+    fn time_keys_work() {
+        let k = Timestamp(1_650_000_000);
+        let path = k.key();
+        assert_eq!(1, path.len());
+        assert_eq!(1_650_000_000u64.to_cw_bytes(), path[0].as_ref());
+
+        let k = Height(12345);
+        let path = k.key();
+        assert_eq!(1, path.len());
+        assert_eq!(12345u64.to_cw_bytes(), path[0].as_ref());
+
+        // later times sort after earlier ones
+        assert!(Timestamp(255).joined_key() < Timestamp(256).joined_key());
+        assert_eq!(
+            Timestamp::from_vec(Timestamp(256).joined_key()).unwrap(),
+            Timestamp(256)
+        );
+        assert_eq!(
+            <(Height, u32)>::from_vec((Height(7), 3u32).joined_key()).unwrap(),
+            (Height(7), 3)
+        );
     }
 
     #[test]
@@ -400,6 +663,143 @@ mod test {
         assert_eq!(joined, b"hello")
     }
 
+    #[test]
+    fn owned_key_refs_work() {
+        let k = "hello".to_string();
+        let k = &k;
+        let path = k.key();
+        assert_eq!(1, path.len());
+        assert_eq!(b"hello", path[0].as_ref());
+        assert_eq!(k.joined_key(), "hello".joined_key());
+
+        let k = b"hello".to_vec();
+        let k = &k;
+        let path = k.key();
+        assert_eq!(1, path.len());
+        assert_eq!(b"hello", path[0].as_ref());
+        assert_eq!(k.joined_key(), b"hello".as_slice().joined_key());
+    }
+
+    #[test]
+    fn owned_keys_in_maps() {
+        use crate::Map;
+        use cosmwasm_std::testing::MockStorage;
+
+        let mut store = MockStorage::new();
+        let supply: Map<String, u64> = Map::new("supply");
+        let by_denom: Map<&str, u64> = Map::new("supply");
+
+        // e.g. parsed from a message at runtime
+        let denom = format!("u{}", "scrt");
+        supply.save(&mut store, denom.clone(), &10).unwrap();
+        assert_eq!(supply.load(&store, denom.clone()).unwrap(), 10);
+        // stored just like under the borrowed key
+        assert_eq!(by_denom.load(&store, "uscrt").unwrap(), 10);
+
+        let refs: Map<(&String, &Vec<u8>), u64> = Map::new("refs");
+        let raw = vec![1, 2, 3];
+        refs.save(&mut store, (&denom, &raw), &7).unwrap();
+        assert_eq!(refs.load(&store, (&denom, &raw)).unwrap(), 7);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn mixed_borrowed_and_owned_tuple_keys() {
+        use crate::{Bound, Map};
+        use cosmwasm_std::testing::MockStorage;
+        use cosmwasm_std::{Order, StdResult};
+
+        const LISTINGS: Map<(&Addr, u64, String), u64> = Map::new("listings");
+
+        // a map held by a struct, used with keys borrowed for shorter than the struct
+        struct Registry<'a> {
+            entries: Map<'a, (&'a Addr, u64), u64>,
+        }
+
+        impl<'a> Registry<'a> {
+            fn after(&self, store: &MockStorage, owner: &Addr, start_after: u64) -> Vec<u64> {
+                self.entries
+                    .prefix(owner)
+                    .keys(
+                        store,
+                        Some(Bound::exclusive(start_after)),
+                        None,
+                        Order::Ascending,
+                    )
+                    .collect::<StdResult<_>>()
+                    .unwrap()
+            }
+        }
+
+        let mut store = MockStorage::new();
+        // borrowed from values only known at runtime
+        let owner = Addr::from(format!("al{}", "ice"));
+        let name = format!("item-{}", 1);
+        LISTINGS
+            .save(&mut store, (&owner, 1, name.clone()), &10)
+            .unwrap();
+        LISTINGS
+            .save(&mut store, (&owner, 2, "item-2".to_string()), &20)
+            .unwrap();
+        assert_eq!(LISTINGS.load(&store, (&owner, 1, name)).unwrap(), 10);
+
+        let names = LISTINGS
+            .prefix((&owner, 2))
+            .keys(&store, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(names, vec!["item-2".to_string()]);
+        let all = LISTINGS
+            .sub_prefix(&owner)
+            .range(
+                &store,
+                Some(Bound::exclusive((1, "item-1".to_string()))),
+                None,
+                Order::Ascending,
+            )
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(all, vec![((2, "item-2".to_string()), 20)]);
+
+        let namespace = String::from("registry");
+        let registry = Registry {
+            entries: Map::new(&namespace),
+        };
+        for id in [1, 2, 3] {
+            registry
+                .entries
+                .save(&mut store, (&owner, id), &id)
+                .unwrap();
+        }
+        assert_eq!(registry.after(&store, &owner, 1), vec![2, 3]);
+    }
+
+    #[test]
+    fn addr_key_works() {
+        let k = Addr::from("hello");
+        let path = k.key();
+        assert_eq!(1, path.len());
+        assert_eq!(b"hello", path[0].as_ref());
+
+        let k = &k;
+        let path = k.key();
+        assert_eq!(1, path.len());
+        assert_eq!(b"hello", path[0].as_ref());
+    }
+
+    #[test]
+    fn canonical_addr_key_works() {
+        let k = CanonicalAddr::from(&b"hello"[..]);
+        let path = k.key();
+        assert_eq!(1, path.len());
+        assert_eq!(b"hello", path[0].as_ref());
+
+        let k = &k;
+        let path = k.key();
+        assert_eq!(1, path.len());
+        assert_eq!(b"hello", path[0].as_ref());
+    }
+
     #[test]
     fn nested_str_key_works() {
         type K<'a> = (&'a str, &'a [u8]);
@@ -512,4 +912,181 @@ mod test {
             vec![one.as_slice(), two.as_slice(), three.as_slice()]
         );
     }
+
+    #[test]
+    fn larger_tuple_keys_work() {
+        let quad: (&str, u8, u64, u32) = ("market", 1, 100, 7);
+        let path = quad.key();
+        assert_eq!(4, path.len());
+        assert_eq!(
+            quad.joined_key(),
+            namespaces_with_key(
+                &[b"market", &[1], &100u64.to_be_bytes()],
+                &7u32.to_be_bytes()
+            )
+        );
+        assert_eq!(
+            <(String, u8, u64, u32)>::from_vec(quad.joined_key()).unwrap(),
+            ("market".to_string(), 1, 100, 7)
+        );
+
+        let quint: (&str, u8, u64, u32, &[u8]) = ("market", 1, 100, 7, b"end");
+        assert_eq!(5, quint.key().len());
+        assert_eq!(quint.prefix().len(), 5);
+        assert_eq!(
+            <(String, u8, u64, u32, Vec<u8>)>::from_vec(quint.joined_key()).unwrap(),
+            ("market".to_string(), 1, 100, 7, b"end".to_vec())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn uint_newtype_keys_work() {
+        use cosmwasm_std::testing::MockStorage;
+        use cosmwasm_std::{Order, StdResult};
+
+        use crate::Map;
+
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        struct ProposalId(u64);
+
+        crate::impl_uint_key!(ProposalId: u64);
+
+        let id = ProposalId(0x0102);
+        assert_eq!(id.joined_key(), 0x0102u64.to_be_bytes());
+        assert_eq!((id, "yes").joined_key(), (0x0102u64, "yes").joined_key());
+        assert_eq!(ProposalId::from_vec(id.joined_key()).unwrap(), id);
+
+        let mut store = MockStorage::new();
+        let proposals: Map<ProposalId, u8> = Map::new("proposals");
+        proposals.save(&mut store, ProposalId(256), &2).unwrap();
+        proposals.save(&mut store, ProposalId(1), &1).unwrap();
+        let ids = proposals
+            .keys(&store, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(ids, vec![ProposalId(1), ProposalId(256)]);
+
+        // written with the plain integer as key
+        let plain: Map<u64, u8> = Map::new("proposals");
+        assert_eq!(plain.load(&store, 256).unwrap(), 2);
+    }
+
+    #[cfg(feature = "derive")]
+    mod derive {
+        use cosmwasm_std::testing::MockStorage;
+        use cosmwasm_std::{Order, StdResult};
+
+        use super::*;
+        use crate::Map;
+
+        #[derive(crate::PrimaryKey, Clone, Debug, PartialEq)]
+        struct PairKey {
+            base: Addr,
+            quote: Addr,
+        }
+
+        #[derive(crate::PrimaryKey, Clone, Debug, PartialEq)]
+        struct OrderKey(String, u8, u64);
+
+        #[derive(crate::PrimaryKey, Clone, Debug, PartialEq)]
+        struct Id {
+            id: u32,
+        }
+
+        #[derive(crate::PrimaryKey, Clone, Copy, Debug, PartialEq)]
+        enum Side {
+            Buy = 1,
+            Sell = 2,
+            Cancel,
+        }
+
+        #[test]
+        fn derived_keys_encode_like_tuples() {
+            let pair = PairKey {
+                base: Addr::from("uscrt"),
+                quote: Addr::from("usdc"),
+            };
+            let tuple = (Addr::from("uscrt"), Addr::from("usdc"));
+            assert_eq!(pair.joined_key(), tuple.joined_key());
+            assert_eq!(pair.joined_prefix(), tuple.joined_prefix());
+            assert_eq!(PairKey::from_vec(pair.joined_key()).unwrap(), pair);
+
+            let order = OrderKey("market".to_string(), 1, 42);
+            let tuple = ("market".to_string(), 1u8, 42u64);
+            assert_eq!(order.joined_key(), tuple.joined_key());
+            assert_eq!(OrderKey::from_vec(order.joined_key()).unwrap(), order);
+
+            assert_eq!(Id { id: 7 }.joined_key(), 7u32.joined_key());
+            assert_eq!(Id::from_vec(7u32.joined_key()).unwrap(), Id { id: 7 });
+        }
+
+        #[test]
+        fn derived_keys_in_maps() {
+            let mut store = MockStorage::new();
+            let prices: Map<PairKey, u64> = Map::new("prices");
+            let pair = |base: &str, quote: &str| PairKey {
+                base: Addr::from(base),
+                quote: Addr::from(quote),
+            };
+
+            prices.save(&mut store, pair("uscrt", "usdc"), &3).unwrap();
+            prices.save(&mut store, pair("uscrt", "usdt"), &4).unwrap();
+            prices.save(&mut store, pair("uatom", "usdc"), &9).unwrap();
+            assert_eq!(prices.load(&store, pair("uscrt", "usdt")).unwrap(), 4);
+
+            let quotes = prices
+                .prefix(Addr::from("uscrt"))
+                .range(&store, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap();
+            assert_eq!(
+                quotes,
+                vec![(Addr::from("usdc"), 3), (Addr::from("usdt"), 4)]
+            );
+
+            let all = prices
+                .range(&store, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap();
+            assert_eq!(all[0], (pair("uatom", "usdc"), 9));
+
+            // as part of a composite key
+            let orders: Map<(PairKey, u64), u64> = Map::new("orders");
+            orders
+                .save(&mut store, (pair("uscrt", "usdc"), 1), &10)
+                .unwrap();
+            let ids = orders
+                .prefix(pair("uscrt", "usdc"))
+                .keys(&store, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap();
+            assert_eq!(ids, vec![1]);
+        }
+
+        #[test]
+        fn derived_enum_keys() {
+            assert_eq!(Side::Buy.joined_key(), vec![1]);
+            assert_eq!(Side::Sell.joined_key(), vec![2]);
+            assert_eq!(Side::Cancel.joined_key(), vec![3]);
+            assert_eq!(Side::from_vec(vec![2]).unwrap(), Side::Sell);
+            assert!(Side::from_vec(vec![0]).is_err());
+            assert!(Side::from_vec(vec![1, 1]).is_err());
+
+            let mut store = MockStorage::new();
+            let orders: Map<(Addr, Side), u64> = Map::new("orders");
+            orders
+                .save(&mut store, (Addr::from("alice"), Side::Sell), &5)
+                .unwrap();
+            orders
+                .save(&mut store, (Addr::from("alice"), Side::Buy), &3)
+                .unwrap();
+            let sides = orders
+                .prefix(Addr::from("alice"))
+                .range(&store, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap();
+            assert_eq!(sides, vec![(Side::Buy, 3), (Side::Sell, 5)]);
+        }
+    }
 }
@@ -1,13 +1,20 @@
 use std::array::TryFromSliceError;
 use std::convert::TryInto;
 
-use cosmwasm_std::{HumanAddr as Addr, StdError, StdResult};
+use cosmwasm_std::{CanonicalAddr, HumanAddr as Addr, StdError, StdResult, Uint128};
 
 use super::int_key::CwIntKey;
+use super::time::{Height, Timestamp};
 
+/// `KeyDeserialize` is the inverse of `PrimaryKey::joined_key`, it turns the raw key bytes
+/// returned when iterating back into a typed key.
 pub trait KeyDeserialize {
     type Output: Sized;
 
+    /// The number of key elements (length-prefixed segments) this key is made of,
+    /// needed to find where a nested composite key ends.
+    const KEY_ELEMS: u16;
+
     fn from_vec(value: Vec<u8>) -> StdResult<Self::Output>;
 
     fn from_slice(value: &[u8]) -> StdResult<Self::Output> {
@@ -18,6 +25,8 @@ pub trait KeyDeserialize {
 impl KeyDeserialize for () {
     type Output = ();
 
+    const KEY_ELEMS: u16 = 0;
+
     #[inline(always)]
     fn from_vec(_value: Vec<u8>) -> StdResult<Self::Output> {
         Ok(())
@@ -27,6 +36,8 @@ impl KeyDeserialize for () {
 impl KeyDeserialize for Vec<u8> {
     type Output = Vec<u8>;
 
+    const KEY_ELEMS: u16 = 1;
+
     #[inline(always)]
     fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
         Ok(value)
@@ -36,6 +47,8 @@ impl KeyDeserialize for Vec<u8> {
 impl KeyDeserialize for &Vec<u8> {
     type Output = Vec<u8>;
 
+    const KEY_ELEMS: u16 = 1;
+
     #[inline(always)]
     fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
         Ok(value)
@@ -45,6 +58,8 @@ impl KeyDeserialize for &Vec<u8> {
 impl KeyDeserialize for &[u8] {
     type Output = Vec<u8>;
 
+    const KEY_ELEMS: u16 = 1;
+
     #[inline(always)]
     fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
         Ok(value)
@@ -54,6 +69,8 @@ impl KeyDeserialize for &[u8] {
 impl KeyDeserialize for String {
     type Output = String;
 
+    const KEY_ELEMS: u16 = 1;
+
     #[inline(always)]
     fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
         String::from_utf8(value).map_err(StdError::invalid_utf8)
@@ -63,6 +80,8 @@ impl KeyDeserialize for String {
 impl KeyDeserialize for &String {
     type Output = String;
 
+    const KEY_ELEMS: u16 = 1;
+
     #[inline(always)]
     fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
         Self::Output::from_vec(value)
@@ -72,6 +91,8 @@ impl KeyDeserialize for &String {
 impl KeyDeserialize for &str {
     type Output = String;
 
+    const KEY_ELEMS: u16 = 1;
+
     #[inline(always)]
     fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
         Self::Output::from_vec(value)
@@ -81,6 +102,8 @@ impl KeyDeserialize for &str {
 impl KeyDeserialize for Addr {
     type Output = Addr;
 
+    const KEY_ELEMS: u16 = 1;
+
     #[inline(always)]
     fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
         Ok(Addr::from(String::from_vec(value)?))
@@ -90,6 +113,30 @@ impl KeyDeserialize for Addr {
 impl KeyDeserialize for &Addr {
     type Output = Addr;
 
+    const KEY_ELEMS: u16 = 1;
+
+    #[inline(always)]
+    fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
+        Self::Output::from_vec(value)
+    }
+}
+
+impl KeyDeserialize for CanonicalAddr {
+    type Output = CanonicalAddr;
+
+    const KEY_ELEMS: u16 = 1;
+
+    #[inline(always)]
+    fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
+        Ok(CanonicalAddr::from(value))
+    }
+}
+
+impl KeyDeserialize for &CanonicalAddr {
+    type Output = CanonicalAddr;
+
+    const KEY_ELEMS: u16 = 1;
+
     #[inline(always)]
     fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
         Self::Output::from_vec(value)
@@ -101,6 +148,8 @@ macro_rules! integer_de {
         $(impl KeyDeserialize for $t {
             type Output = $t;
 
+            const KEY_ELEMS: u16 = 1;
+
             #[inline(always)]
             fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
                 Ok(<$t>::from_cw_bytes(value.as_slice().try_into()
@@ -110,7 +159,7 @@ macro_rules! integer_de {
     }
 }
 
-integer_de!(for i8, u8, i16, u16, i32, u32, i64, u64, i128, u128);
+integer_de!(for i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, Uint128, Timestamp, Height);
 
 fn parse_length(value: &[u8]) -> StdResult<usize> {
     Ok(u16::from_be_bytes(
@@ -121,33 +170,108 @@ fn parse_length(value: &[u8]) -> StdResult<usize> {
     .into())
 }
 
+/// Splits the first key off `value`, which may itself be made of `key_elems` sub keys.
+/// The first key is returned in joined form, i.e. all of its sub keys length-prefixed
+/// except for the last one.
+fn split_first_key(key_elems: u16, value: &[u8]) -> StdResult<(Vec<u8>, &[u8])> {
+    let mut index = 0;
+    let mut first_key = Vec::new();
+
+    // Iterate over the sub keys
+    for i in 0..key_elems {
+        let len_slice = value
+            .get(index..index + 2)
+            .ok_or_else(|| StdError::generic_err("Could not read 2 byte length"))?;
+        index += 2;
+        let is_last_key = i == key_elems - 1;
+
+        if !is_last_key {
+            first_key.extend_from_slice(len_slice);
+        }
+
+        let subkey_len = parse_length(len_slice)?;
+        let subkey = value
+            .get(index..index + subkey_len)
+            .ok_or_else(|| StdError::generic_err("Key is shorter than its length prefix"))?;
+        first_key.extend_from_slice(subkey);
+        index += subkey_len;
+    }
+
+    let remainder = &value[index..];
+    Ok((first_key, remainder))
+}
+
 impl<T: KeyDeserialize, U: KeyDeserialize> KeyDeserialize for (T, U) {
     type Output = (T::Output, U::Output);
 
-    #[inline(always)]
-    fn from_vec(mut value: Vec<u8>) -> StdResult<Self::Output> {
-        let mut tu = value.split_off(2);
-        let t_len = parse_length(&value)?;
-        let u = tu.split_off(t_len);
+    const KEY_ELEMS: u16 = T::KEY_ELEMS + U::KEY_ELEMS;
 
-        Ok((T::from_vec(tu)?, U::from_vec(u)?))
+    #[inline(always)]
+    fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
+        let (t, u) = split_first_key(T::KEY_ELEMS, value.as_ref())?;
+        Ok((T::from_vec(t)?, U::from_vec(u.to_vec())?))
     }
 }
 
 impl<T: KeyDeserialize, U: KeyDeserialize, V: KeyDeserialize> KeyDeserialize for (T, U, V) {
     type Output = (T::Output, U::Output, V::Output);
 
+    const KEY_ELEMS: u16 = T::KEY_ELEMS + U::KEY_ELEMS + V::KEY_ELEMS;
+
     #[inline(always)]
-    fn from_vec(mut value: Vec<u8>) -> StdResult<Self::Output> {
-        let mut tuv = value.split_off(2);
-        let t_len = parse_length(&value)?;
-        let mut len_uv = tuv.split_off(t_len);
+    fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
+        let (t, vu) = split_first_key(T::KEY_ELEMS, value.as_ref())?;
+        let (u, v) = split_first_key(U::KEY_ELEMS, vu)?;
+        Ok((T::from_vec(t)?, U::from_vec(u)?, V::from_vec(v.to_vec())?))
+    }
+}
+
+impl<T: KeyDeserialize, U: KeyDeserialize, V: KeyDeserialize, W: KeyDeserialize> KeyDeserialize
+    for (T, U, V, W)
+{
+    type Output = (T::Output, U::Output, V::Output, W::Output);
 
-        let mut uv = len_uv.split_off(2);
-        let u_len = parse_length(&len_uv)?;
-        let v = uv.split_off(u_len);
+    const KEY_ELEMS: u16 = T::KEY_ELEMS + U::KEY_ELEMS + V::KEY_ELEMS + W::KEY_ELEMS;
 
-        Ok((T::from_vec(tuv)?, U::from_vec(uv)?, V::from_vec(v)?))
+    #[inline(always)]
+    fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
+        let (t, uvw) = split_first_key(T::KEY_ELEMS, value.as_ref())?;
+        let (u, vw) = split_first_key(U::KEY_ELEMS, uvw)?;
+        let (v, w) = split_first_key(V::KEY_ELEMS, vw)?;
+        Ok((
+            T::from_vec(t)?,
+            U::from_vec(u)?,
+            V::from_vec(v)?,
+            W::from_vec(w.to_vec())?,
+        ))
+    }
+}
+
+impl<
+        T: KeyDeserialize,
+        U: KeyDeserialize,
+        V: KeyDeserialize,
+        W: KeyDeserialize,
+        X: KeyDeserialize,
+    > KeyDeserialize for (T, U, V, W, X)
+{
+    type Output = (T::Output, U::Output, V::Output, W::Output, X::Output);
+
+    const KEY_ELEMS: u16 = T::KEY_ELEMS + U::KEY_ELEMS + V::KEY_ELEMS + W::KEY_ELEMS + X::KEY_ELEMS;
+
+    #[inline(always)]
+    fn from_vec(value: Vec<u8>) -> StdResult<Self::Output> {
+        let (t, uvwx) = split_first_key(T::KEY_ELEMS, value.as_ref())?;
+        let (u, vwx) = split_first_key(U::KEY_ELEMS, uvwx)?;
+        let (v, wx) = split_first_key(V::KEY_ELEMS, vwx)?;
+        let (w, x) = split_first_key(W::KEY_ELEMS, wx)?;
+        Ok((
+            T::from_vec(t)?,
+            U::from_vec(u)?,
+            V::from_vec(v)?,
+            W::from_vec(w)?,
+            X::from_vec(x.to_vec())?,
+        ))
     }
 }
 
@@ -193,6 +317,18 @@ mod test {
         assert_eq!(<&Addr>::from_slice(BYTES).unwrap(), Addr::from(STRING));
     }
 
+    #[test]
+    fn deserialize_canonical_addr_works() {
+        assert_eq!(
+            <CanonicalAddr>::from_slice(BYTES).unwrap(),
+            CanonicalAddr::from(BYTES)
+        );
+        assert_eq!(
+            <&CanonicalAddr>::from_slice(BYTES).unwrap(),
+            CanonicalAddr::from(BYTES)
+        );
+    }
+
     #[test]
     fn deserialize_broken_addr_errs() {
         assert!(matches!(
@@ -229,6 +365,10 @@ mod test {
             u128::from_slice(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap(),
             1329227995784915872903807060280344576u128
         );
+        assert_eq!(
+            Uint128::from_slice(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap(),
+            Uint128(1329227995784915872903807060280344576u128)
+        );
         assert_eq!(
             i128::from_slice(&[128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap(),
             0i128
@@ -265,4 +405,43 @@ mod test {
             (BYTES.to_vec(), 1234, STRING.to_string())
         );
     }
+
+    #[test]
+    fn key_elems_works() {
+        assert_eq!(<()>::KEY_ELEMS, 0);
+        assert_eq!(<&str>::KEY_ELEMS, 1);
+        assert_eq!(<u64>::KEY_ELEMS, 1);
+        assert_eq!(<(&str, u64)>::KEY_ELEMS, 2);
+        assert_eq!(<((&str, u64), &[u8])>::KEY_ELEMS, 3);
+        assert_eq!(<(&str, (u8, u16), u32)>::KEY_ELEMS, 4);
+    }
+
+    #[test]
+    fn deserialize_nested_tuple_works() {
+        assert_eq!(
+            <((&[u8], u32), &str)>::from_slice(((BYTES, 1234u32), STRING).joined_key().as_slice())
+                .unwrap(),
+            ((BYTES.to_vec(), 1234), STRING.to_string())
+        );
+        assert_eq!(
+            <(&str, (u8, &[u8]), u64)>::from_slice(
+                (STRING, (7u8, BYTES), 42u64).joined_key().as_slice()
+            )
+            .unwrap(),
+            (STRING.to_string(), (7, BYTES.to_vec()), 42)
+        );
+    }
+
+    #[test]
+    fn deserialize_truncated_tuple_errs() {
+        let key = (BYTES, STRING).joined_key();
+        assert!(matches!(
+            <(&[u8], &str)>::from_slice(&key[..1]).err(),
+            Some(StdError::GenericErr { .. })
+        ));
+        assert!(matches!(
+            <(&[u8], &str)>::from_slice(&key[..4]).err(),
+            Some(StdError::GenericErr { .. })
+        ));
+    }
 }
@@ -1,5 +1,9 @@
 use std::mem;
 
+use cosmwasm_std::Uint128;
+
+use super::time::{Height, Timestamp};
+
 /// Our int keys are simply the big-endian representation bytes for unsigned ints,
 /// but "sign-flipped" (xored msb) big-endian bytes for signed ints.
 ///
@@ -52,6 +56,42 @@ macro_rules! cw_int_keys {
 
 cw_int_keys!(for i8, u8, i16, u16, i32, u32, i64, u64, i128, u128);
 
+/// `Uint128` is encoded just like the `u128` it wraps.
+impl CwIntKey for Uint128 {
+    type Buf = [u8; mem::size_of::<u128>()];
+
+    #[inline]
+    fn to_cw_bytes(&self) -> Self::Buf {
+        self.u128().to_cw_bytes()
+    }
+
+    #[inline]
+    fn from_cw_bytes(bytes: Self::Buf) -> Self {
+        Uint128(u128::from_cw_bytes(bytes))
+    }
+}
+
+macro_rules! cw_u64_newtype_keys {
+    (for $($t:ident),+) => {
+        $(impl CwIntKey for $t {
+            type Buf = [u8; mem::size_of::<u64>()];
+
+            #[inline]
+            fn to_cw_bytes(&self) -> Self::Buf {
+                self.0.to_cw_bytes()
+            }
+
+            #[inline]
+            fn from_cw_bytes(bytes: Self::Buf) -> Self {
+                $t(u64::from_cw_bytes(bytes))
+            }
+        })*
+    }
+}
+
+// `Timestamp` and `Height` are encoded just like the `u64` they wrap.
+cw_u64_newtype_keys!(for Timestamp, Height);
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -118,9 +158,22 @@ mod test {
         );
     }
 
+    #[test]
+    fn uint128_key_works() {
+        assert_eq!(
+            Uint128(0x4243444546).to_cw_bytes(),
+            0x4243444546u128.to_cw_bytes()
+        );
+        assert_eq!(
+            Uint128::from_cw_bytes(Uint128(u128::MAX).to_cw_bytes()),
+            Uint128(u128::MAX)
+        );
+    }
+
     #[test]
     fn unsigned_int_key_order() {
         assert!(0u32.to_cw_bytes() < 652u32.to_cw_bytes());
+        assert!(Uint128(255).to_cw_bytes() < Uint128(256).to_cw_bytes());
     }
 
     #[test]
@@ -128,4 +181,23 @@ mod test {
         assert!((-321i32).to_cw_bytes() < 0i32.to_cw_bytes());
         assert!(0i32.to_cw_bytes() < 652i32.to_cw_bytes());
     }
+
+    macro_rules! assert_signed_order {
+        ($($t:ty),+) => {
+            $({
+                let ordered = [<$t>::MIN, <$t>::MIN + 1, -1, 0, 1, <$t>::MAX - 1, <$t>::MAX];
+                for pair in ordered.windows(2) {
+                    assert!(pair[0].to_cw_bytes() < pair[1].to_cw_bytes());
+                }
+                for v in ordered {
+                    assert_eq!(<$t>::from_cw_bytes(v.to_cw_bytes()), v);
+                }
+            })*
+        };
+    }
+
+    #[test]
+    fn signed_int_key_order_all_widths() {
+        assert_signed_order!(i8, i16, i32, i64, i128);
+    }
 }
@@ -0,0 +1,78 @@
+use std::fmt;
+
+use cosmwasm_std::BlockInfo;
+use serde::{Deserialize, Serialize};
+
+/// A block time in seconds since epoch, as found in `BlockInfo::time`.
+///
+/// As a key it is encoded like the `u64` it wraps, so maps keyed by time sort chronologically.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub struct Timestamp(pub u64);
+
+impl Timestamp {
+    pub const fn from_seconds(seconds: u64) -> Self {
+        Timestamp(seconds)
+    }
+
+    pub fn from_block(block: &BlockInfo) -> Self {
+        Timestamp(block.time)
+    }
+
+    pub const fn seconds(&self) -> u64 {
+        self.0
+    }
+
+    pub const fn plus_seconds(&self, seconds: u64) -> Self {
+        Timestamp(self.0 + seconds)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A block height, as found in `BlockInfo::height`.
+///
+/// As a key it is encoded like the `u64` it wraps, so maps keyed by height sort by height.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub struct Height(pub u64);
+
+impl Height {
+    pub fn from_block(block: &BlockInfo) -> Self {
+        Height(block.height)
+    }
+
+    pub const fn plus_blocks(&self, blocks: u64) -> Self {
+        Height(self.0 + blocks)
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A point in time read from the current block, implemented by `Timestamp` and `Height`
+/// so collections can be generic over the one they are keyed by
+pub trait FromBlock {
+    fn from_block(block: &BlockInfo) -> Self;
+}
+
+impl FromBlock for Timestamp {
+    fn from_block(block: &BlockInfo) -> Self {
+        Timestamp::from_block(block)
+    }
+}
+
+impl FromBlock for Height {
+    fn from_block(block: &BlockInfo) -> Self {
+        Height::from_block(block)
+    }
+}
@@ -1,11 +1,140 @@
+pub mod append_store;
+pub mod audit;
+pub mod bimap;
+pub mod blob;
+pub mod bloom;
+pub mod cached_storage;
+pub mod commit_store;
+pub mod compat;
+pub mod counter;
+pub mod counting_storage;
+pub mod cursor;
+pub mod deque;
+pub mod error;
+pub mod expiring_map;
+pub mod fresh;
 pub(crate) mod helpers;
+pub mod history;
+pub mod hooks;
+pub mod indexed_map;
+pub mod indexes;
 pub mod item;
-pub(crate) mod keys;
+#[cfg(feature = "iterator")]
+pub(crate) mod iter_helpers;
+pub mod iterable_map;
+pub mod keys;
+pub mod linked_list;
+pub mod lock;
 pub mod map;
-pub(crate) mod path;
+pub mod merkle;
+pub mod migrator;
+pub mod multimap;
+pub mod namespace;
+#[cfg(feature = "namespace-check")]
+pub mod namespace_check;
+pub mod nested_map;
+pub mod numeric;
+pub mod ownership;
+pub mod pagination;
+pub mod path;
+pub mod payload_queue;
+pub mod prefix;
+pub mod priority_queue;
 pub mod queue;
+pub mod rate_limiter;
+pub mod readonly;
+pub mod reference;
+pub mod reward_index;
+#[cfg(feature = "iterator")]
+pub mod scheduler;
+pub mod serialization;
 pub mod set;
+#[cfg(feature = "iterator")]
+pub mod shadow_map;
+#[cfg(feature = "iterator")]
+pub mod snapshot;
+pub mod stack;
+pub mod tally;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "iterator")]
+pub mod time_queue;
+pub mod transaction;
+pub mod versioned_map;
 
-pub use item::Item;
-pub use map::Map;
+pub use append_store::AppendStore;
+pub use audit::{AuditedItem, AuditedMap};
+pub use bimap::BiMap;
+pub use blob::Blob;
+pub use bloom::BloomFilter;
+pub use cached_storage::CachedStorage;
+pub use commit_store::{CommitStore, Commitment};
+pub use counter::Counter;
+pub use counting_storage::{CountingStorage, StorageStats};
+pub use cursor::{Cursor, ScanBatch};
+pub use deque::Deque;
+pub use error::{StorageError, StorageResult};
+pub use expiring_map::{Expiration, ExpiringMap};
+pub use fresh::{Fresh, Stamped};
+pub use history::History;
+pub use hooks::Hooks;
+pub use indexed_map::{IndexList, IndexedMap};
+pub use indexes::{Index, MultiIndex, UniqueIndex};
+pub use item::{CachedItem, Item};
+pub use iterable_map::IterableMap;
+pub use keys::{FromBlock, Height, Key, KeyDeserialize, Prefixer, PrimaryKey, Timestamp};
+pub use linked_list::LinkedList;
+pub use lock::{Lock, LockGuard};
+pub use map::{CountedMap, Map};
+pub use merkle::{verify_proof, MerkleTree, ProofStep};
+pub use migrator::Migrator;
+pub use multimap::MultiMap;
+pub use namespace::{hash_namespace, Namespace};
+pub use nested_map::NestedMap;
+pub use numeric::Numeric;
+pub use ownership::{Ownership, OwnershipState};
+pub use pagination::Page;
+pub use path::Path;
+pub use payload_queue::PayloadQueue;
+pub use prefix::Prefix;
+#[cfg(feature = "iterator")]
+pub use prefix::{Bound, RawBound};
+pub use priority_queue::PriorityQueue;
+pub use rate_limiter::{RateLimiter, Window};
+pub use readonly::{ReadonlyItem, ReadonlyMap};
+pub use reference::Ref;
+pub use reward_index::RewardIndex;
+#[cfg(feature = "iterator")]
+pub use scheduler::Scheduler;
+#[cfg(feature = "borsh")]
+pub use serialization::Borsh;
+#[cfg(feature = "compression")]
+pub use serialization::Compressed;
+#[cfg(feature = "protobuf")]
+pub use serialization::Proto;
+pub use serialization::{Bincode2, Json, Serde};
 pub use set::Set;
+#[cfg(feature = "iterator")]
+pub use shadow_map::{ShadowMap, WriteMode};
+#[cfg(feature = "iterator")]
+pub use snapshot::{CheckpointMap, SnapshotItem, SnapshotMap, Strategy};
+pub use stack::Stack;
+pub use tally::Tally;
+#[cfg(feature = "iterator")]
+pub use time_queue::{HeightQueue, TimeQueue};
+pub use transaction::{transactional, StorageTransaction};
+pub use versioned_map::VersionedMap;
+
+#[cfg(feature = "derive")]
+pub use secret_storage_lite_derive::PrimaryKey;
+
+// lets the derived code name this crate from within its own tests
+#[cfg(all(test, feature = "derive"))]
+extern crate self as secret_storage_lite;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use cosmwasm_std::{StdError, StdResult};
+
+    pub use crate::namespace::namespace_conflicts;
+}
@@ -0,0 +1,335 @@
+use cosmwasm_std::{StdResult, Storage};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::error::{StorageError, StorageResult};
+use crate::path::Path;
+use crate::queue::{load_u32, save_u32};
+use crate::Map;
+
+/// The neighbours of a node, stored apart from its item so relinking doesn't touch the items
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct Links {
+    prev: Option<u32>,
+    next: Option<u32>,
+}
+
+/// A doubly linked list, whose items keep the node id returned when they were pushed.
+///
+/// Removing a node by its id reads and writes O(1) entries, wherever it is in the list.
+/// Iteration follows the links, so it needs no host-side range support.
+pub struct LinkedList<'a, T> {
+    map: Map<'a, u32, T>,
+}
+
+impl<'a, T> LinkedList<'a, T> {
+    const LEN: &'static [u8] = b"_len";
+    const HEAD: &'static [u8] = b"_head";
+    const TAIL: &'static [u8] = b"_tail";
+    const NEXT_ID: &'static [u8] = b"_next_id";
+    const LINKS: &'static [u8] = b"_links";
+
+    pub const fn new(namespace: &'a str) -> Self {
+        Self {
+            map: Map::new(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+
+    /// Returns the number of items in the list
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.with_namespace_suffix(Self::LEN, |ns| load_u32(store, ns))
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    /// Returns the id of the first node, None if the list is empty
+    pub fn front_id(&self, store: &dyn Storage) -> Option<u32> {
+        self.with_namespace_suffix(Self::HEAD, |ns| load_end(store, ns))
+    }
+
+    /// Returns the id of the last node, None if the list is empty
+    pub fn back_id(&self, store: &dyn Storage) -> Option<u32> {
+        self.with_namespace_suffix(Self::TAIL, |ns| load_end(store, ns))
+    }
+
+    fn set_len(&self, store: &mut dyn Storage, len: u32) {
+        self.with_namespace_suffix(Self::LEN, |ns| save_u32(store, ns, len))
+    }
+
+    fn set_front_id(&self, store: &mut dyn Storage, id: Option<u32>) {
+        self.with_namespace_suffix(Self::HEAD, |ns| save_end(store, ns, id))
+    }
+
+    fn set_back_id(&self, store: &mut dyn Storage, id: Option<u32>) {
+        self.with_namespace_suffix(Self::TAIL, |ns| save_end(store, ns, id))
+    }
+
+    fn links(&self, id: u32) -> Path<Links> {
+        self.with_namespace_suffix(Self::LINKS, |ns| Path::new(ns, &[&id.to_be_bytes()]))
+    }
+
+    fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
+        let namespace = &[self.namespace(), namespace].concat();
+        f(namespace)
+    }
+}
+
+impl<'a, T> LinkedList<'a, T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Adds an item to the back of the list, returns the id of its node
+    pub fn push_back(&self, store: &mut dyn Storage, t: &T) -> StorageResult<u32> {
+        let id = self.new_node(store, t)?;
+        let prev = self.back_id(store);
+        self.links(id).save(store, &Links { prev, next: None })?;
+        match prev {
+            Some(prev) => self.relink(store, prev, |l| l.next = Some(id))?,
+            None => self.set_front_id(store, Some(id)),
+        }
+        self.set_back_id(store, Some(id));
+        Ok(id)
+    }
+
+    /// Adds an item to the front of the list, returns the id of its node
+    pub fn push_front(&self, store: &mut dyn Storage, t: &T) -> StorageResult<u32> {
+        let id = self.new_node(store, t)?;
+        let next = self.front_id(store);
+        self.links(id).save(store, &Links { prev: None, next })?;
+        match next {
+            Some(next) => self.relink(store, next, |l| l.prev = Some(id))?,
+            None => self.set_back_id(store, Some(id)),
+        }
+        self.set_front_id(store, Some(id));
+        Ok(id)
+    }
+
+    /// Saves the item of a new, still unlinked node
+    fn new_node(&self, store: &mut dyn Storage, t: &T) -> StorageResult<u32> {
+        let id = self.with_namespace_suffix(Self::NEXT_ID, |ns| load_u32(store, ns));
+        if id == u32::MAX {
+            return Err(StorageError::CapacityExceeded {
+                collection: "LinkedList",
+            });
+        }
+        self.map.save(store, id, t)?;
+        self.with_namespace_suffix(Self::NEXT_ID, |ns| save_u32(store, ns, id + 1));
+        let len = self.len(store);
+        self.set_len(store, len + 1);
+        Ok(id)
+    }
+
+    /// Returns the item of node `id`, None if there is no such node
+    pub fn get(&self, store: &dyn Storage, id: u32) -> StdResult<Option<T>> {
+        Ok(self.map.may_load(store, id)?)
+    }
+
+    /// Replaces the item of node `id`, keeping its place in the list.
+    /// Returns an error if there is no such node
+    pub fn set(&self, store: &mut dyn Storage, id: u32, t: &T) -> StorageResult<()> {
+        // the links are loaded for the not found error
+        self.links(id).load(store)?;
+        self.map.save(store, id, t)
+    }
+
+    /// Unlinks node `id` and returns its item, returns None if there is no such node
+    pub fn remove(&self, store: &mut dyn Storage, id: u32) -> StdResult<Option<T>> {
+        let links = match self.links(id).may_load(store)? {
+            Some(links) => links,
+            None => return Ok(None),
+        };
+        let t = self.map.load(store, id)?;
+
+        match links.prev {
+            Some(prev) => self.relink(store, prev, |l| l.next = links.next)?,
+            None => self.set_front_id(store, links.next),
+        }
+        match links.next {
+            Some(next) => self.relink(store, next, |l| l.prev = links.prev)?,
+            None => self.set_back_id(store, links.prev),
+        }
+        self.links(id).remove(store);
+        self.map.remove(store, id);
+        let len = self.len(store);
+        self.set_len(store, len - 1);
+
+        Ok(Some(t))
+    }
+
+    fn relink<F: FnOnce(&mut Links)>(
+        &self,
+        store: &mut dyn Storage,
+        id: u32,
+        f: F,
+    ) -> StdResult<()> {
+        let path = self.links(id);
+        let mut links = path.load(store)?;
+        f(&mut links);
+        path.save(store, &links)
+    }
+
+    /// Removes the first item and returns it with its node id, returns None if the list is empty
+    pub fn pop_front(&self, store: &mut dyn Storage) -> StdResult<Option<(u32, T)>> {
+        match self.front_id(store) {
+            Some(id) => Ok(self.remove(store, id)?.map(|t| (id, t))),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes the last item and returns it with its node id, returns None if the list is empty
+    pub fn pop_back(&self, store: &mut dyn Storage) -> StdResult<Option<(u32, T)>> {
+        match self.back_id(store) {
+            Some(id) => Ok(self.remove(store, id)?.map(|t| (id, t))),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns an iterator over the node ids and items, from front to back
+    pub fn iter<'c>(&'c self, store: &'c dyn Storage) -> LinkedListIter<'a, 'c, T> {
+        LinkedListIter {
+            list: self,
+            store,
+            next: self.front_id(store),
+        }
+    }
+}
+
+/// An iterator over the nodes of a `LinkedList`, created by `LinkedList::iter`
+pub struct LinkedListIter<'a, 'c, T> {
+    list: &'c LinkedList<'a, T>,
+    store: &'c dyn Storage,
+    next: Option<u32>,
+}
+
+impl<'a, 'c, T> Iterator for LinkedListIter<'a, 'c, T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    type Item = StdResult<(u32, T)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.next?;
+        let node = self
+            .list
+            .links(id)
+            .load(self.store)
+            .and_then(|links| Ok((links, self.list.map.load(self.store, id)?)));
+        match node {
+            Ok((links, t)) => {
+                self.next = links.next;
+                Some(Ok((id, t)))
+            }
+            Err(err) => {
+                self.next = None;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+fn load_end(store: &dyn Storage, namespace: &[u8]) -> Option<u32> {
+    store
+        .get(namespace)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_be_bytes)
+}
+
+fn save_end(store: &mut dyn Storage, namespace: &[u8], id: Option<u32>) {
+    match id {
+        Some(id) => save_u32(store, namespace, id),
+        None => store.remove(namespace),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cosmwasm_std::testing::MockStorage;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, proptest_derive::Arbitrary)]
+    enum Op {
+        PushBack(u8),
+        PushFront(u8),
+        // removes the node at this position modulo the length
+        Remove(u8),
+        PopFront,
+        PopBack,
+    }
+
+    const LIST: LinkedList<u8> = LinkedList::new("list");
+
+    proptest! {
+        #[test]
+        fn impl_matches_model(ops: Vec<Op>) {
+            let mut model: Vec<(u32, u8)> = vec![];
+            let mut store = MockStorage::new();
+            for op in ops {
+                match op {
+                    Op::PushBack(u) => {
+                        let id = LIST.push_back(&mut store, &u).unwrap();
+                        model.push((id, u));
+                    }
+                    Op::PushFront(u) => {
+                        let id = LIST.push_front(&mut store, &u).unwrap();
+                        model.insert(0, (id, u));
+                    }
+                    Op::Remove(i) => {
+                        if !model.is_empty() {
+                            let (id, u) = model.remove(i as usize % model.len());
+                            prop_assert_eq!(LIST.remove(&mut store, id).unwrap(), Some(u));
+                            prop_assert_eq!(LIST.remove(&mut store, id).unwrap(), None);
+                        }
+                    }
+                    Op::PopFront => {
+                        let expected = if model.is_empty() { None } else { Some(model.remove(0)) };
+                        prop_assert_eq!(LIST.pop_front(&mut store).unwrap(), expected);
+                    }
+                    Op::PopBack => {
+                        prop_assert_eq!(LIST.pop_back(&mut store).unwrap(), model.pop());
+                    }
+                }
+
+                prop_assert_eq!(LIST.len(&store), model.len() as u32, "len results differ");
+                let items = LIST.iter(&store).collect::<StdResult<Vec<_>>>().unwrap();
+                prop_assert_eq!(items, model.clone(), "items differ");
+            }
+        }
+    }
+
+    #[test]
+    fn ids_are_stable() {
+        let mut store = MockStorage::new();
+        let jobs: LinkedList<String> = LinkedList::new("jobs");
+
+        let a = jobs.push_back(&mut store, &"a".to_string()).unwrap();
+        let b = jobs.push_back(&mut store, &"b".to_string()).unwrap();
+        let c = jobs.push_back(&mut store, &"c".to_string()).unwrap();
+
+        assert_eq!(jobs.remove(&mut store, b).unwrap(), Some("b".to_string()));
+        assert_eq!(jobs.get(&store, b).unwrap(), None);
+        assert_eq!(jobs.get(&store, c).unwrap(), Some("c".to_string()));
+        jobs.set(&mut store, a, &"a2".to_string()).unwrap();
+        assert!(jobs.set(&mut store, b, &"b2".to_string()).is_err());
+
+        // ids are never reused
+        let d = jobs.push_back(&mut store, &"d".to_string()).unwrap();
+        assert!(d > c);
+        assert_eq!(
+            jobs.iter(&store).collect::<StdResult<Vec<_>>>().unwrap(),
+            vec![
+                (a, "a2".to_string()),
+                (c, "c".to_string()),
+                (d, "d".to_string())
+            ]
+        );
+        assert_eq!(jobs.front_id(&store), Some(a));
+        assert_eq!(jobs.back_id(&store), Some(d));
+    }
+}
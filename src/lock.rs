@@ -0,0 +1,169 @@
+use std::ops::{Deref, DerefMut};
+
+use cosmwasm_std::{StdError, Storage};
+
+use crate::error::{StorageError, StorageResult};
+use crate::Item;
+
+/// Lock guards against reentrancy, e.g. a contract being called back while it waits for the
+/// reply of a submessage.
+///
+/// The lock is held while its item is set. It can be acquired in an execute call and released
+/// in the reply to it, or held for one scope with `guard` or `with`.
+pub struct Lock<'a> {
+    item: Item<'a, bool>,
+}
+
+impl<'a> Lock<'a> {
+    pub const fn new(storage_key: &'a str) -> Self {
+        Lock {
+            item: Item::new(storage_key),
+        }
+    }
+
+    pub fn is_held(&self, store: &dyn Storage) -> bool {
+        store.get(self.item.as_slice()).is_some()
+    }
+
+    /// Acquires the lock, returns `StorageError::LockHeld` if it is held already
+    pub fn acquire(&self, store: &mut dyn Storage) -> StorageResult<()> {
+        if self.is_held(store) {
+            return Err(StorageError::LockHeld {
+                lock: String::from_utf8_lossy(self.item.as_slice()).into_owned(),
+            });
+        }
+        self.item.save(store, &true)
+    }
+
+    /// Releases the lock, whether it was held or not
+    pub fn release(&self, store: &mut dyn Storage) {
+        self.item.remove(store)
+    }
+
+    /// Acquires the lock and returns a guard releasing it when dropped.
+    /// The store can still be used through the guard
+    pub fn guard<'s>(&self, store: &'s mut dyn Storage) -> StorageResult<LockGuard<'s>> {
+        self.acquire(store)?;
+        Ok(LockGuard {
+            storage_key: self.item.as_slice().to_vec(),
+            store,
+            keep: false,
+        })
+    }
+
+    /// Runs `action` while holding the lock, returns an error without running it if the lock
+    /// is held already
+    pub fn with<A, R, E>(&self, store: &mut dyn Storage, action: A) -> Result<R, E>
+    where
+        A: FnOnce(&mut dyn Storage) -> Result<R, E>,
+        E: From<StdError>,
+    {
+        self.acquire(store).map_err(StdError::from)?;
+        let result = action(store);
+        self.release(store);
+        result
+    }
+}
+
+/// LockGuard holds a `Lock` until it is dropped, see `Lock::guard`
+pub struct LockGuard<'s> {
+    storage_key: Vec<u8>,
+    store: &'s mut dyn Storage,
+    keep: bool,
+}
+
+impl<'s> LockGuard<'s> {
+    /// Keeps the lock held after the guard is dropped, e.g. until the reply to a submessage
+    /// releases it
+    pub fn keep(mut self) {
+        self.keep = true;
+    }
+}
+
+impl<'s> Deref for LockGuard<'s> {
+    type Target = dyn Storage + 's;
+
+    fn deref(&self) -> &Self::Target {
+        self.store
+    }
+}
+
+impl<'s> DerefMut for LockGuard<'s> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.store
+    }
+}
+
+impl<'s> Drop for LockGuard<'s> {
+    fn drop(&mut self) {
+        if !self.keep {
+            self.store.remove(&self.storage_key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cosmwasm_std::testing::MockStorage;
+
+    const LOCK: Lock = Lock::new("lock");
+    const COUNT: Item<u32> = Item::new("count");
+
+    #[test]
+    fn acquire_and_release() {
+        let mut store = MockStorage::new();
+        assert!(!LOCK.is_held(&store));
+
+        LOCK.acquire(&mut store).unwrap();
+        assert!(LOCK.is_held(&store));
+        assert_eq!(
+            LOCK.acquire(&mut store).unwrap_err(),
+            StorageError::LockHeld {
+                lock: "lock".to_string()
+            }
+        );
+
+        LOCK.release(&mut store);
+        assert!(!LOCK.is_held(&store));
+        LOCK.acquire(&mut store).unwrap();
+    }
+
+    #[test]
+    fn guard_releases_on_drop() {
+        let mut store = MockStorage::new();
+        {
+            let mut guard = LOCK.guard(&mut store).unwrap();
+            COUNT.save(&mut *guard, &1).unwrap();
+            assert!(LOCK.is_held(&*guard));
+        }
+        assert!(!LOCK.is_held(&store));
+        assert_eq!(COUNT.load(&store).unwrap(), 1);
+
+        // kept for the reply
+        LOCK.guard(&mut store).unwrap().keep();
+        assert!(LOCK.is_held(&store));
+        assert!(LOCK.guard(&mut store).is_err());
+    }
+
+    #[test]
+    fn with_holds_the_lock() {
+        let mut store = MockStorage::new();
+        let count = LOCK
+            .with(&mut store, |store| {
+                // a reentrant call fails
+                assert!(LOCK.acquire(store).is_err());
+                COUNT.save(store, &2)?;
+                COUNT.load(store)
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+        assert!(!LOCK.is_held(&store));
+
+        let result: Result<(), StdError> =
+            LOCK.with(&mut store, |_| Err(StdError::generic_err("oops")));
+        assert!(result.is_err());
+        assert!(!LOCK.is_held(&store));
+    }
+}
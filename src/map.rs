@@ -1,62 +1,249 @@
-use serde::de::DeserializeOwned;
-use serde::Serialize;
+mod counted;
+mod entry;
+
+pub use counted::CountedMap;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+
+use std::any::type_name;
+use std::borrow::Cow;
 use std::marker::PhantomData;
 
-use crate::keys::{Key, PrimaryKey};
+#[cfg(feature = "iterator")]
+use crate::cursor::{Cursor, ScanBatch};
+use crate::error::{StorageError, StorageResult};
+use crate::helpers::{
+    may_deserialize, must_deserialize, namespaces_with_key, query_raw, split_namespace,
+};
+#[cfg(feature = "migration")]
+use crate::iter_helpers::range_with_prefix;
+#[cfg(feature = "iterator")]
+use crate::keys::KeyDeserialize;
+use crate::keys::Prefixer;
+use crate::keys::{assert_valid_key, Key, PrimaryKey};
+use crate::namespace::hashed;
+use crate::numeric::Numeric;
 use crate::path::Path;
-use cosmwasm_std::{StdError, StdResult, Storage};
+#[cfg(feature = "iterator")]
+use crate::prefix::Bound;
+use crate::prefix::Prefix;
+use crate::queue::{load_u32, save_u32};
+use crate::serialization::{Bincode2, Serde};
+use cosmwasm_std::{HumanAddr as Addr, Querier, StdError, Storage};
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, StdResult, KV};
 
 #[derive(Debug, Clone)]
-pub struct Map<'a, K, T> {
-    namespace: &'a [u8],
+pub struct Map<'a, K, T, Ser = Bincode2> {
+    namespace: Cow<'a, [u8]>,
     // see https://doc.rust-lang.org/std/marker/struct.PhantomData.html#unused-type-parameters for why this is needed
     key_type: PhantomData<K>,
     data_type: PhantomData<T>,
+    serialization_type: PhantomData<Ser>,
+    counted: bool,
+    // the namespace the map is stored under instead, with the `hashed-namespaces` feature
+    hash: Option<[u8; 4]>,
+    max_key_len: Option<usize>,
 }
 
-impl<'a, K, T> Map<'a, K, T> {
+impl<'a, K, T, Ser> Map<'a, K, T, Ser> {
+    const LEN: &'static [u8] = b"_len";
+
     pub const fn new(namespace: &'a str) -> Self {
         Map {
-            namespace: namespace.as_bytes(),
+            namespace: Cow::Borrowed(namespace.as_bytes()),
+            data_type: PhantomData,
+            key_type: PhantomData,
+            serialization_type: PhantomData,
+            counted: false,
+            hash: hashed(namespace.as_bytes()),
+            max_key_len: None,
+        }
+    }
+
+    /// Creates a map stored under `namespace` as is, also with the `hashed-namespaces` feature,
+    /// e.g. to read state written before the feature was enabled
+    pub const fn new_unhashed(namespace: &'a str) -> Self {
+        Map {
+            namespace: Cow::Borrowed(namespace.as_bytes()),
+            data_type: PhantomData,
+            key_type: PhantomData,
+            serialization_type: PhantomData,
+            counted: false,
+            hash: None,
+            max_key_len: None,
+        }
+    }
+
+    /// Creates a map under a namespace only known at runtime, e.g. one built with `Namespace`
+    pub fn new_owned(namespace: impl Into<Vec<u8>>) -> Self {
+        let namespace = namespace.into();
+        Map {
+            hash: hashed(&namespace),
+            namespace: Cow::Owned(namespace),
             data_type: PhantomData,
             key_type: PhantomData,
+            serialization_type: PhantomData,
+            counted: false,
+            max_key_len: None,
+        }
+    }
+
+    /// Makes the methods of the map keep count of its entries, see `CountedMap`
+    const fn counted(mut self) -> Self {
+        self.counted = true;
+        self
+    }
+
+    /// Rejects writes under an empty key or a key longer than `max_len` bytes with
+    /// `StorageError::EmptyKey` or `StorageError::KeyTooLong`, e.g. for a backend capping the key
+    /// size. The length is that of the joined key, the namespace adds its length and 2 bytes
+    pub const fn with_max_key_len(mut self, max_len: usize) -> Self {
+        self.max_key_len = Some(max_len);
+        self
+    }
+
+    /// Returns the namespace the map is stored under, the hash of its name with the
+    /// `hashed-namespaces` feature
+    pub fn namespace(&self) -> &[u8] {
+        match &self.hash {
+            Some(hash) => hash,
+            None => &self.namespace,
         }
     }
 
-    pub fn namespace(&self) -> &'a [u8] {
-        self.namespace
+    /// Returns the namespace the map was created with, which errors name
+    pub fn name(&self) -> &[u8] {
+        &self.namespace
+    }
+
+    fn stored_len(&self, store: &dyn Storage) -> u32 {
+        load_u32(store, &self.len_key())
+    }
+
+    fn len_key(&self) -> Vec<u8> {
+        [self.namespace(), Self::LEN].concat()
+    }
+
+    /// The counter of a counted map, None otherwise
+    fn counter(&self) -> Option<Vec<u8>> {
+        self.counted.then(|| self.len_key())
+    }
+
+    /// Returns whether saving at the full storage key `key` adds an entry to a counted map,
+    /// or an error if it has no room for one
+    fn is_new_entry(&self, store: &dyn Storage, key: &[u8]) -> StorageResult<bool> {
+        if !self.counted || store.get(key).is_some() {
+            return Ok(false);
+        }
+        if self.stored_len(store) == u32::MAX {
+            return Err(StorageError::CapacityExceeded { collection: "Map" });
+        }
+        Ok(true)
+    }
+
+    fn add_to_len(&self, store: &mut dyn Storage, added: u32) {
+        let len = self.stored_len(store);
+        save_u32(store, &self.len_key(), len + added)
+    }
+
+    /// Removes the entry at the full storage key `key`, keeping the count of a counted map
+    fn remove_at(&self, store: &mut dyn Storage, key: &[u8]) {
+        if self.counted && store.get(key).is_some() {
+            // entries saved under the namespace by a plain `Map` were never counted
+            let len = self.stored_len(store);
+            save_u32(store, &self.len_key(), len.saturating_sub(1));
+        }
+        store.remove(key);
+    }
+
+    /// Checks the joined `key` against the limit set with `with_max_key_len`, if any
+    fn validate_key(&self, key: &[u8]) -> StorageResult<()> {
+        match self.max_key_len {
+            Some(max_len) => assert_valid_key(key, max_len),
+            None => Ok(()),
+        }
     }
 }
 
-impl<'a, K, T> Map<'a, K, T>
+impl<'a, K, T, Ser> Map<'a, K, T, Ser>
 where
-    T: Serialize + DeserializeOwned,
     K: PrimaryKey<'a>,
+    Ser: Serde<T>,
 {
-    pub fn key(&self, k: K) -> Path<T> {
+    pub fn key(&self, k: K) -> Path<T, Ser> {
         Path::new(
-            self.namespace,
+            self.namespace(),
             &k.key().iter().map(Key::as_ref).collect::<Vec<_>>(),
         )
     }
 
-    pub fn save(&self, store: &mut dyn Storage, k: K, data: &T) -> StdResult<()> {
-        self.key(k).save(store, data)
+    pub fn save(&self, store: &mut dyn Storage, k: K, data: &T) -> StorageResult<()> {
+        self.save_at(store, &self.key(k), data)
+    }
+
+    /// Saves `data` at `path`, counting the entry if it is new to a counted map
+    fn save_at(&self, store: &mut dyn Storage, path: &Path<T, Ser>, data: &T) -> StorageResult<()> {
+        self.validate_key(split_namespace(path).1)?;
+        let new_entry = self.is_new_entry(store, path)?;
+        path.save(store, data)?;
+        if new_entry {
+            self.add_to_len(store, 1);
+        }
+        Ok(())
     }
 
     pub fn remove(&self, store: &mut dyn Storage, k: K) {
-        self.key(k).remove(store)
+        self.remove_at(store, &self.key(k))
+    }
+
+    /// take removes the data stored at the key and returns it, returns Ok(None) if no data there.
+    /// returns an error on issues parsing, in which case the data is not removed
+    pub fn take(&self, store: &mut dyn Storage, k: K) -> StorageResult<Option<T>> {
+        let path = self.key(k);
+        let t = path.may_load(store)?;
+        if t.is_some() {
+            self.remove_at(store, &path);
+        }
+        Ok(t)
+    }
+
+    /// replace saves the data at the key and returns the data stored there before, if any.
+    /// returns an error on issues parsing or serializing, in which case nothing is saved
+    pub fn replace(&self, store: &mut dyn Storage, k: K, data: &T) -> StorageResult<Option<T>> {
+        let path = self.key(k);
+        let old = path.may_load(store)?;
+        self.save_at(store, &path, data)?;
+        Ok(old)
     }
 
     /// load will return an error if no data is set at the given key, or on parse error
-    pub fn load(&self, store: &dyn Storage, k: K) -> StdResult<T> {
-        self.key(k).load(store)
+    pub fn load(&self, store: &dyn Storage, k: K) -> StorageResult<T> {
+        let path = self.key(k);
+        must_deserialize::<T, Ser>(
+            &path.may_load_raw(store),
+            self.name(),
+            split_namespace(&path).1,
+        )
     }
 
     /// may_load will parse the data stored at the key if present, returns Ok(None) if no data there.
     /// returns an error on issues parsing
-    pub fn may_load(&self, store: &dyn Storage, k: K) -> StdResult<Option<T>> {
-        self.key(k).may_load(store)
+    pub fn may_load(&self, store: &dyn Storage, k: K) -> StorageResult<Option<T>> {
+        let path = self.key(k);
+        may_deserialize::<T, Ser>(
+            &path.may_load_raw(store),
+            self.name(),
+            split_namespace(&path).1,
+        )
+    }
+
+    /// load_or_default returns the data stored at the key, or `T::default()` if no data there.
+    /// returns an error on issues parsing
+    pub fn load_or_default(&self, store: &dyn Storage, k: K) -> StorageResult<T>
+    where
+        T: Default,
+    {
+        Ok(self.may_load(store, k)?.unwrap_or_default())
     }
 
     /// has returns true or false if any data is at this key, without parsing or interpreting the
@@ -65,6 +252,59 @@ where
         self.key(k).has(store)
     }
 
+    /// save_raw stores `data` at the key as is, without serializing it,
+    /// returns an error only if a `CountedMap` has no room for another entry
+    pub fn save_raw(&self, store: &mut dyn Storage, k: K, data: &[u8]) -> StorageResult<()> {
+        let path = self.key(k);
+        self.validate_key(split_namespace(&path).1)?;
+        let new_entry = self.is_new_entry(store, &path)?;
+        path.save_raw(store, data);
+        if new_entry {
+            self.add_to_len(store, 1);
+        }
+        Ok(())
+    }
+
+    /// load_raw returns the bytes stored at the key without parsing them,
+    /// returns an error if no data is set there
+    pub fn load_raw(&self, store: &dyn Storage, k: K) -> StorageResult<Vec<u8>> {
+        let path = self.key(k);
+        path.may_load_raw(store)
+            .ok_or_else(|| StorageError::NotFound {
+                type_name: type_name::<T>(),
+                namespace: String::from_utf8_lossy(self.name()).into_owned(),
+                key: split_namespace(&path).1.to_vec(),
+            })
+    }
+
+    /// may_load_raw returns the bytes stored at the key without parsing them,
+    /// None if no data is set there
+    pub fn may_load_raw(&self, store: &dyn Storage, k: K) -> Option<Vec<u8>> {
+        self.key(k).may_load_raw(store)
+    }
+
+    /// entry loads the entry at the given key for in-place manipulation,
+    /// returns an error on issues parsing an existing value
+    pub fn entry<'s>(&self, store: &'s mut dyn Storage, k: K) -> StorageResult<Entry<'s, T, Ser>> {
+        let path = self.key(k);
+        self.validate_key(split_namespace(&path).1)?;
+        Entry::new(store, path, self.counter())
+    }
+
+    /// query loads the entry at the given key from the storage of the remote contract at
+    /// `remote_contract` with a raw query, returns Ok(None) if no data is set there
+    pub fn query<Q: Querier>(
+        &self,
+        querier: &Q,
+        remote_contract: Addr,
+        code_hash: String,
+        k: K,
+    ) -> StorageResult<Option<T>> {
+        let path = self.key(k);
+        let value = query_raw(querier, remote_contract, code_hash, &path)?;
+        may_deserialize::<T, Ser>(&value, self.name(), split_namespace(&path).1)
+    }
+
     /// Loads the data, perform the specified action, and store the result
     /// in the database. This is shorthand for some common sequences, which may be useful.
     ///
@@ -74,7 +314,12 @@ where
         A: FnOnce(Option<T>) -> Result<T, E>,
         E: From<StdError>,
     {
-        self.key(k).update(store, action)
+        let path = self.key(k);
+        let input = path.may_load(store)?;
+        let output = action(input)?;
+        self.save_at(store, &path, &output)
+            .map_err(StdError::from)?;
+        Ok(output)
     }
 
     /// Loads the data if it exists or creates a default, performs the specified action, and store the result
@@ -85,7 +330,345 @@ where
         A: FnOnce(T) -> Result<T, E>,
         E: From<StdError>,
     {
-        self.key(k).update_or_default(store, action)
+        let path = self.key(k);
+        let input = path.may_load(store)?.unwrap_or_default();
+        let output = action(input)?;
+        self.save_at(store, &path, &output)
+            .map_err(StdError::from)?;
+        Ok(output)
+    }
+
+    /// Returns a `Prefix` over all the entries whose composite key starts with `p`.
+    /// For a `Map<(A, B), T>` this accesses all `B` stored under a given `A`.
+    pub fn prefix(&self, p: K::Prefix) -> Prefix<K::Suffix, T, K::Suffix, Ser>
+    where
+        K::Suffix: PrimaryKey<'a>,
+    {
+        Prefix::new(self.namespace(), &p.prefix())
+    }
+
+    /// Returns a `Prefix` over all the entries whose composite key starts with `p`.
+    /// For a `Map<(A, B, C), T>` this accesses all `(B, C)` stored under a given `A`.
+    pub fn sub_prefix(&self, p: K::SubPrefix) -> Prefix<K::SuperSuffix, T, K::SuperSuffix, Ser>
+    where
+        K::SuperSuffix: PrimaryKey<'a>,
+    {
+        Prefix::new(self.namespace(), &p.prefix())
+    }
+
+    /// save_many saves all the given entries, returns an error on serialization issues.
+    ///
+    /// All values are serialized before the first one is written, so on error nothing is saved.
+    pub fn save_many<'t, I>(&self, store: &mut dyn Storage, entries: I) -> StorageResult<()>
+    where
+        I: IntoIterator<Item = (K, &'t T)>,
+        T: 't,
+    {
+        let prefix = namespaces_with_key(&[self.namespace()], &[]);
+        let serialized = entries
+            .into_iter()
+            .map(|(k, data)| {
+                let key = k.joined_key();
+                self.validate_key(&key)?;
+                Ok(([prefix.as_slice(), &key].concat(), Ser::serialize(data)?))
+            })
+            .collect::<StorageResult<Vec<_>>>()?;
+
+        let mut new_keys = vec![];
+        if self.counted {
+            new_keys = serialized
+                .iter()
+                .map(|(key, _)| key.as_slice())
+                .filter(|key| store.get(key).is_none())
+                .collect();
+            new_keys.sort();
+            new_keys.dedup();
+            let room = u32::MAX - self.stored_len(store);
+            if new_keys.len() > room as usize {
+                return Err(StorageError::CapacityExceeded { collection: "Map" });
+            }
+        }
+        let added = new_keys.len() as u32;
+
+        for (key, value) in &serialized {
+            store.set(key, value);
+        }
+        if added > 0 {
+            self.add_to_len(store, added);
+        }
+        Ok(())
+    }
+
+    /// remove_many removes all the given keys
+    pub fn remove_many<I>(&self, store: &mut dyn Storage, keys: I)
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let prefix = namespaces_with_key(&[self.namespace()], &[]);
+        for k in keys {
+            self.remove_at(store, &[prefix.as_slice(), &k.joined_key()].concat());
+        }
+    }
+}
+
+impl<'a, K, T, Ser> Map<'a, K, T, Ser>
+where
+    K: PrimaryKey<'a>,
+    T: Numeric,
+    Ser: Serde<T>,
+{
+    /// add adds `amount` to the value at the given key, starting from zero if no data is set,
+    /// and returns the new value. Returns an error and leaves the value untouched on overflow
+    pub fn add(&self, store: &mut dyn Storage, k: K, amount: T) -> StorageResult<T> {
+        let path = self.key(k);
+        let value = path
+            .may_load(store)?
+            .unwrap_or_default()
+            .checked_add(amount)
+            .ok_or(StorageError::Overflow { collection: "Map" })?;
+        self.save_at(store, &path, &value)?;
+        Ok(value)
+    }
+
+    /// checked_sub subtracts `amount` from the value at the given key, treated as zero if no
+    /// data is set, and returns the new value. Returns `StorageError::InsufficientFunds` and
+    /// leaves the value untouched if the value is less than `amount`
+    pub fn checked_sub(&self, store: &mut dyn Storage, k: K, amount: T) -> StorageResult<T> {
+        let path = self.key(k);
+        let available = path.may_load(store)?.unwrap_or_default();
+        let value =
+            available
+                .checked_sub(amount)
+                .ok_or_else(|| StorageError::InsufficientFunds {
+                    available: available.to_string(),
+                    required: amount.to_string(),
+                })?;
+        self.save_at(store, &path, &value)?;
+        Ok(value)
+    }
+
+    /// sub_saturating subtracts `amount` from the value at the given key, stopping at zero,
+    /// and returns the new value
+    pub fn sub_saturating(&self, store: &mut dyn Storage, k: K, amount: T) -> StorageResult<T> {
+        let path = self.key(k);
+        let value = path
+            .may_load(store)?
+            .unwrap_or_default()
+            .saturating_sub(amount);
+        self.save_at(store, &path, &value)?;
+        Ok(value)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, K, T, Ser> Map<'a, K, T, Ser>
+where
+    K: PrimaryKey<'a>,
+    Ser: Serde<T>,
+{
+    fn no_prefix_raw(&self) -> Prefix<Vec<u8>, T, K, Ser> {
+        Prefix::new(self.namespace(), &[])
+    }
+
+    /// count returns the number of entries in the map without parsing the values, see
+    /// `Prefix::count` to count the entries under a prefix.
+    /// Returns `StorageError::TooManyEntries` if there are more than `max_entries`
+    pub fn count(&self, store: &dyn Storage, max_entries: u32) -> StorageResult<u32> {
+        self.no_prefix_raw().count(store, max_entries)
+    }
+
+    /// fold calls `f` with the accumulator and every value in the map, in key order.
+    /// Returns `StorageError::TooManyEntries` if there are more than `max_entries`
+    pub fn fold<A, F>(
+        &self,
+        store: &dyn Storage,
+        max_entries: u32,
+        init: A,
+        f: F,
+    ) -> StorageResult<A>
+    where
+        F: FnMut(A, T) -> StdResult<A>,
+    {
+        self.no_prefix_raw().fold(store, max_entries, init, f)
+    }
+
+    /// sum adds up the values in the map, e.g. `map.prefix(owner).sum(store, 100)` sums those
+    /// of one owner. Returns `StorageError::TooManyEntries` if there are more than
+    /// `max_entries`, and `StorageError::Overflow` if the sum overflows
+    pub fn sum(&self, store: &dyn Storage, max_entries: u32) -> StorageResult<T>
+    where
+        T: Numeric,
+    {
+        self.no_prefix_raw().sum(store, max_entries)
+    }
+
+    /// has_prefix returns true if any entry's key starts with `p`,
+    /// e.g. whether an owner has any allowances in a `Map<(&Addr, &Addr), T>`
+    pub fn has_prefix(&self, store: &dyn Storage, p: K::Prefix) -> bool
+    where
+        K::Suffix: PrimaryKey<'a>,
+    {
+        !self.prefix(p).is_empty(store)
+    }
+
+    /// has_sub_prefix returns true if any entry's key starts with `p`,
+    /// the sub prefix of a triple key
+    pub fn has_sub_prefix(&self, store: &dyn Storage, p: K::SubPrefix) -> bool
+    where
+        K::SuperSuffix: PrimaryKey<'a>,
+    {
+        !self.sub_prefix(p).is_empty(store)
+    }
+
+    /// range_raw iterates over all entries in the map, returning the raw joined keys
+    pub fn range_raw<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<KV<T>>> + 'c>
+    where
+        T: 'c,
+    {
+        self.no_prefix_raw().range_raw(store, min, max, order)
+    }
+
+    /// keys_raw iterates over all keys in the map, returning them raw and joined
+    pub fn keys_raw<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + 'c>
+    where
+        T: 'c,
+    {
+        self.no_prefix_raw().keys_raw(store, min, max, order)
+    }
+
+    /// clear removes up to `limit` entries in key order and returns how many are left,
+    /// so a large map can be wiped over several transactions: it is empty once this returns 0.
+    /// The entries left are only counted up to `limit`, at least 1
+    pub fn clear(&self, store: &mut dyn Storage, limit: u32) -> StorageResult<u32> {
+        let keys: Vec<_> = self
+            .keys_raw(store, None, None, Order::Ascending)
+            .take(limit as usize)
+            .collect();
+        let prefix = namespaces_with_key(&[self.namespace()], &[]);
+        for key in keys {
+            self.remove_at(store, &[prefix.as_slice(), &key].concat());
+        }
+        if self.counted {
+            return Ok(self.stored_len(store));
+        }
+        // counting every entry left would read all their keys on every call
+        Ok(self
+            .keys_raw(store, None, None, Order::Ascending)
+            .take(limit.max(1) as usize)
+            .count() as u32)
+    }
+}
+
+#[cfg(feature = "migration")]
+impl<'a, K, T, Ser> Map<'a, K, T, Ser> {
+    /// export returns all entries as raw joined keys and serialized values, e.g. to answer a state
+    /// export query. The keys don't include the namespace, so they can be imported anywhere
+    pub fn export(&self, store: &dyn Storage) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let prefix = namespaces_with_key(&[self.namespace()], &[]);
+        range_with_prefix(store, &prefix, None, None, Order::Ascending).collect()
+    }
+
+    /// import saves entries as returned by `export`, overwriting existing ones
+    pub fn import<I>(&self, store: &mut dyn Storage, entries: I) -> StorageResult<()>
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        let prefix = namespaces_with_key(&[self.namespace()], &[]);
+        for (key, value) in entries {
+            self.validate_key(&key)?;
+            let key = [prefix.as_slice(), &key].concat();
+            let new_entry = self.is_new_entry(store, &key)?;
+            store.set(&key, &value);
+            if new_entry {
+                self.add_to_len(store, 1);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, K, T, Ser> Map<'a, K, T, Ser>
+where
+    K: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<T>,
+{
+    fn no_prefix(&self) -> Prefix<K, T, K, Ser> {
+        Prefix::new(self.namespace(), &[])
+    }
+
+    /// range iterates over all entries in the map, deserializing both the keys and the values
+    pub fn range<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'c>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.no_prefix().range(store, min, max, order)
+    }
+
+    /// keys iterates over all keys in the map, deserializing them without parsing the values
+    pub fn keys<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<K::Output>> + 'c>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.no_prefix().keys(store, min, max, order)
+    }
+
+    /// range_from_cursor returns up to `limit` entries in key order after the position of
+    /// `cursor`, and the cursor to continue from, which is finished once fewer than `limit`
+    /// entries are returned. Returns `StorageError::CursorMismatch` if `cursor` was created for
+    /// another map
+    pub fn range_from_cursor(
+        &self,
+        store: &dyn Storage,
+        cursor: &Cursor,
+        limit: u32,
+    ) -> StorageResult<ScanBatch<K::Output, T>> {
+        if cursor.validate(self.namespace()).is_err() {
+            return Err(StorageError::CursorMismatch {
+                namespace: String::from_utf8_lossy(self.name()).into_owned(),
+            });
+        }
+        if cursor.is_finished() {
+            return Ok((vec![], cursor.clone()));
+        }
+        let min = cursor.last_key().map(|k| Bound::ExclusiveRaw(k.to_vec()));
+        let entries = self
+            .range_raw(store, min, None, Order::Ascending)
+            .take(limit as usize)
+            .collect::<StdResult<Vec<_>>>()?;
+        let last_key = entries.last().map(|(k, _)| k.clone());
+        let next = cursor.advance(last_key, entries.len(), limit);
+        let items = entries
+            .into_iter()
+            .map(|(k, v)| Ok((K::from_vec(k)?, v)))
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok((items, next))
     }
 }
 
@@ -93,11 +676,9 @@ where
 mod test {
     use super::*;
     use serde::{Deserialize, Serialize};
-    use std::ops::Deref;
-
-    use crate::keys::int_key::CwIntKey;
 
     use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::{HumanAddr as Addr, StdResult};
 
     #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
     struct Data {
@@ -111,8 +692,15 @@ mod test {
 
     const TRIPLE: Map<(&[u8], u8, &str), u64> = Map::new("triple");
 
+    #[cfg(feature = "iterator")]
+    const NESTED: Map<((&[u8], u8), &str), u64> = Map::new("nested");
+
     #[test]
+    #[cfg(not(feature = "hashed-namespaces"))]
     fn create_path() {
+        use crate::keys::int_key::CwIntKey;
+        use std::ops::Deref;
+
         let path = PEOPLE.key(b"john");
         let key = path.deref();
         // this should be prefixed(people) || john
@@ -144,6 +732,45 @@ mod test {
         assert_eq!(b"pedro".to_vec().as_slice(), &key[17..]);
     }
 
+    #[test]
+    #[cfg(feature = "hashed-namespaces")]
+    fn hashed_namespaces_shorten_keys() {
+        let path = PEOPLE.key(b"john");
+        // prefixed(hash(people)) || john
+        assert_eq!(&path[..2], b"\x00\x04");
+        assert_eq!(&path[2..6], crate::hash_namespace(b"people"));
+        assert_eq!(&path[6..], b"john");
+        assert_eq!(PEOPLE.name(), b"people");
+
+        // errors name the namespace the map was created with
+        let store = MockStorage::new();
+        match PEOPLE.load(&store, b"john").unwrap_err() {
+            StorageError::NotFound { namespace, .. } => assert_eq!(namespace, "people"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        let unhashed: Map<&[u8], Data> = Map::new_unhashed("people");
+        assert_eq!(unhashed.namespace(), b"people");
+    }
+
+    #[test]
+    fn raw_access() {
+        use cosmwasm_std::ReadonlyStorage;
+
+        let mut store = MockStorage::new();
+        const BLOBS: Map<&str, Vec<u8>> = Map::new("blobs");
+
+        BLOBS.save_raw(&mut store, "john", b"\x08\x96\x01").unwrap();
+        assert_eq!(BLOBS.load_raw(&store, "john").unwrap(), b"\x08\x96\x01");
+        assert_eq!(
+            store.get(&BLOBS.key("john")).unwrap(),
+            b"\x08\x96\x01".to_vec()
+        );
+        assert_eq!(BLOBS.may_load_raw(&store, "jim"), None);
+        assert!(BLOBS.load_raw(&store, "jim").is_err());
+        assert!(BLOBS.has(&store, "john"));
+    }
+
     #[test]
     fn save_and_load() {
         let mut store = MockStorage::new();
@@ -236,6 +863,772 @@ mod test {
         assert_eq!(1234, same);
     }
 
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_raw_simple_key() {
+        let mut store = MockStorage::new();
+
+        // save and load on two keys
+        let data = Data {
+            name: "John".to_string(),
+            age: 32,
+        };
+        PEOPLE.save(&mut store, b"john", &data).unwrap();
+
+        let data2 = Data {
+            name: "Jim".to_string(),
+            age: 44,
+        };
+        PEOPLE.save(&mut store, b"jim", &data2).unwrap();
+
+        // let's try to iterate!
+        let all: StdResult<Vec<_>> = PEOPLE
+            .range_raw(&store, None, None, Order::Ascending)
+            .collect();
+        let all = all.unwrap();
+        assert_eq!(2, all.len());
+        assert_eq!(
+            all,
+            vec![
+                (b"jim".to_vec(), data2.clone()),
+                (b"john".to_vec(), data.clone())
+            ]
+        );
+
+        // let's try to iterate over a range
+        let all: StdResult<Vec<_>> = PEOPLE
+            .range_raw(
+                &store,
+                Some(Bound::inclusive(b"j" as &[u8])),
+                None,
+                Order::Ascending,
+            )
+            .collect();
+        let all = all.unwrap();
+        assert_eq!(2, all.len());
+        assert_eq!(
+            all,
+            vec![(b"jim".to_vec(), data2), (b"john".to_vec(), data.clone())]
+        );
+
+        // let's try to iterate over a more restrictive range
+        let all: StdResult<Vec<_>> = PEOPLE
+            .range_raw(
+                &store,
+                Some(Bound::InclusiveRaw(b"jo".to_vec())),
+                None,
+                Order::Ascending,
+            )
+            .collect();
+        let all = all.unwrap();
+        assert_eq!(1, all.len());
+        assert_eq!(all, vec![(b"john".to_vec(), data)]);
+
+        // keys_raw only returns the keys
+        let keys: Vec<_> = PEOPLE
+            .keys_raw(&store, None, None, Order::Descending)
+            .collect();
+        assert_eq!(keys, vec![b"john".to_vec(), b"jim".to_vec()]);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_simple_int_key() {
+        let mut store = MockStorage::new();
+
+        const AGES: Map<u32, String> = Map::new("ages");
+
+        AGES.save(&mut store, 1234, &"John".to_string()).unwrap();
+        AGES.save(&mut store, 56, &"Jim".to_string()).unwrap();
+        AGES.save(&mut store, 7, &"Jo".to_string()).unwrap();
+
+        // ints are ordered numerically, not lexicographically
+        let all: StdResult<Vec<_>> = AGES.range(&store, None, None, Order::Ascending).collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![
+                (7, "Jo".to_string()),
+                (56, "Jim".to_string()),
+                (1234, "John".to_string())
+            ]
+        );
+
+        let all: StdResult<Vec<_>> = AGES
+            .range(
+                &store,
+                Some(Bound::exclusive(7u32)),
+                Some(Bound::inclusive(1234u32)),
+                Order::Descending,
+            )
+            .collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![(1234, "John".to_string()), (56, "Jim".to_string())]
+        );
+
+        let keys: StdResult<Vec<_>> = AGES.keys(&store, None, None, Order::Ascending).collect();
+        assert_eq!(keys.unwrap(), vec![7, 56, 1234]);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_composite_key() {
+        let mut store = MockStorage::new();
+
+        // save and load on three keys, one under different owner
+        ALLOWANCE
+            .save(&mut store, (b"owner", b"spender"), &1000)
+            .unwrap();
+        ALLOWANCE
+            .save(&mut store, (b"owner", b"spender2"), &3000)
+            .unwrap();
+        ALLOWANCE
+            .save(&mut store, (b"owner2", b"spender"), &5000)
+            .unwrap();
+
+        // let's try to iterate!
+        let all: StdResult<Vec<_>> = ALLOWANCE
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        let all = all.unwrap();
+        assert_eq!(3, all.len());
+        assert_eq!(
+            all,
+            vec![
+                ((b"owner".to_vec(), b"spender".to_vec()), 1000),
+                ((b"owner".to_vec(), b"spender2".to_vec()), 3000),
+                ((b"owner2".to_vec(), b"spender".to_vec()), 5000),
+            ]
+        );
+
+        // let's try to iterate over a prefix
+        let all: StdResult<Vec<_>> = ALLOWANCE
+            .prefix(b"owner")
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        let all = all.unwrap();
+        assert_eq!(2, all.len());
+        assert_eq!(
+            all,
+            vec![(b"spender".to_vec(), 1000), (b"spender2".to_vec(), 3000)]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn paginate_with_typed_bounds() {
+        let mut store = MockStorage::new();
+
+        const BALANCES: Map<(&str, u64), u64> = Map::new("balances");
+
+        for id in 1..=5u64 {
+            BALANCES
+                .save(&mut store, ("alice", id), &(id * 10))
+                .unwrap();
+        }
+        BALANCES.save(&mut store, ("carol", 3), &300).unwrap();
+
+        // page through alice's entries, two at a time, using the last key as exclusive start
+        let mut pages = vec![];
+        let mut start_after = None;
+        loop {
+            let page: Vec<_> = BALANCES
+                .prefix("alice")
+                .range(
+                    &store,
+                    start_after.map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(2)
+                .collect::<StdResult<_>>()
+                .unwrap();
+            if page.is_empty() {
+                break;
+            }
+            start_after = page.last().map(|(id, _)| *id);
+            pages.push(page);
+        }
+        assert_eq!(
+            pages,
+            vec![
+                vec![(1, 10), (2, 20)],
+                vec![(3, 30), (4, 40)],
+                vec![(5, 50)]
+            ]
+        );
+
+        // full composite keys bound the whole map
+        // (note keys are length-prefixed, so only equal length prefixes sort lexicographically)
+        let all: Vec<_> = BALANCES
+            .keys(
+                &store,
+                Some(Bound::exclusive(("alice", 4))),
+                Some(Bound::inclusive(("carol", 3))),
+                Order::Ascending,
+            )
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(
+            all,
+            vec![("alice".to_string(), 5), ("carol".to_string(), 3)]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_quad_key() {
+        let mut store = MockStorage::new();
+        // market, side, price, order id
+        const ORDERS: Map<(&str, u8, u64, u32), u64> = Map::new("orders");
+
+        ORDERS.save(&mut store, ("scrt", 0, 100, 1), &10).unwrap();
+        ORDERS.save(&mut store, ("scrt", 0, 100, 2), &20).unwrap();
+        ORDERS.save(&mut store, ("scrt", 0, 101, 3), &30).unwrap();
+        ORDERS.save(&mut store, ("scrt", 1, 99, 4), &40).unwrap();
+        assert_eq!(ORDERS.load(&store, ("scrt", 0, 101, 3)).unwrap(), 30);
+
+        // orders at a price
+        let all: StdResult<Vec<_>> = ORDERS
+            .prefix(("scrt", 0, 100))
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(all.unwrap(), vec![(1, 10), (2, 20)]);
+
+        // orders on a side
+        let all: StdResult<Vec<_>> = ORDERS
+            .sub_prefix(("scrt", 0))
+            .range(&store, None, None, Order::Descending)
+            .collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![((101, 3), 30), ((100, 2), 20), ((100, 1), 10)]
+        );
+
+        let all: StdResult<Vec<_>> = ORDERS.range(&store, None, None, Order::Ascending).collect();
+        assert_eq!(
+            all.unwrap().last().unwrap(),
+            &(("scrt".to_string(), 1, 99, 4), 40)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_triple_key() {
+        let mut store = MockStorage::new();
+
+        // save and load on three keys, one under different owner
+        TRIPLE
+            .save(&mut store, (b"owner", 9, "recipient"), &1000)
+            .unwrap();
+        TRIPLE
+            .save(&mut store, (b"owner", 9, "recipient2"), &3000)
+            .unwrap();
+        TRIPLE
+            .save(&mut store, (b"owner", 10, "recipient3"), &3000)
+            .unwrap();
+        TRIPLE
+            .save(&mut store, (b"owner2", 9, "recipient"), &5000)
+            .unwrap();
+
+        // let's iterate over a prefix
+        let all: StdResult<Vec<_>> = TRIPLE
+            .prefix((b"owner", 9))
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        let all = all.unwrap();
+        assert_eq!(
+            all,
+            vec![
+                ("recipient".to_string(), 1000),
+                ("recipient2".to_string(), 3000)
+            ]
+        );
+
+        // let's iterate over a sub prefix
+        let all: StdResult<Vec<_>> = TRIPLE
+            .sub_prefix(b"owner")
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        let all = all.unwrap();
+        assert_eq!(
+            all,
+            vec![
+                ((9, "recipient".to_string()), 1000),
+                ((9, "recipient2".to_string()), 3000),
+                ((10, "recipient3".to_string()), 3000),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_signed_int_key() {
+        let mut store = MockStorage::new();
+
+        const TEMPS: Map<i64, String> = Map::new("temps");
+
+        TEMPS.save(&mut store, 12, &"warm".to_string()).unwrap();
+        TEMPS
+            .save(&mut store, -40, &"freezing".to_string())
+            .unwrap();
+        TEMPS.save(&mut store, 0, &"cold".to_string()).unwrap();
+        TEMPS.save(&mut store, -1, &"chilly".to_string()).unwrap();
+
+        // negative keys sort before positive ones, not after them as two's complement would
+        let all: StdResult<Vec<_>> = TEMPS.keys(&store, None, None, Order::Ascending).collect();
+        assert_eq!(all.unwrap(), vec![-40, -1, 0, 12]);
+
+        let all: StdResult<Vec<_>> = TEMPS
+            .keys(
+                &store,
+                Some(Bound::inclusive(-1i64)),
+                Some(Bound::exclusive(12i64)),
+                Order::Descending,
+            )
+            .collect();
+        assert_eq!(all.unwrap(), vec![0, -1]);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_uint128_key() {
+        use cosmwasm_std::Uint128;
+
+        let mut store = MockStorage::new();
+
+        const BOOK: Map<(Uint128, &str), u64> = Map::new("book");
+
+        BOOK.save(&mut store, (Uint128(1_000), "carol"), &3)
+            .unwrap();
+        BOOK.save(&mut store, (Uint128(25), "alice"), &1).unwrap();
+        BOOK.save(&mut store, (Uint128(300), "bob"), &2).unwrap();
+
+        let all: StdResult<Vec<_>> = BOOK.keys(&store, None, None, Order::Descending).collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![
+                (Uint128(1_000), "carol".to_string()),
+                (Uint128(300), "bob".to_string()),
+                (Uint128(25), "alice".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn addr_keys_work() {
+        let mut store = MockStorage::new();
+
+        const BALANCES: Map<&Addr, u64> = Map::new("balances");
+        const ALLOWANCES: Map<(&Addr, &Addr), u64> = Map::new("allowances");
+
+        let owner = Addr::from("owner");
+        let spender = Addr::from("spender");
+
+        BALANCES.save(&mut store, &owner, &1000).unwrap();
+        assert_eq!(BALANCES.load(&store, &owner).unwrap(), 1000);
+        assert_eq!(BALANCES.may_load(&store, &spender).unwrap(), None);
+
+        ALLOWANCES
+            .save(&mut store, (&owner, &spender), &50)
+            .unwrap();
+        assert_eq!(ALLOWANCES.load(&store, (&owner, &spender)).unwrap(), 50);
+        assert!(!ALLOWANCES.has(&store, (&spender, &owner)));
+    }
+
+    #[test]
+    fn prefix_accesses_entries_by_suffix() {
+        let mut store = MockStorage::new();
+
+        const ALLOWANCES: Map<(&Addr, &str), u64> = Map::new("allowances");
+
+        let owner = Addr::from("owner");
+        ALLOWANCES
+            .save(&mut store, (&owner, "spender1"), &50)
+            .unwrap();
+
+        // the owner only has to be supplied once
+        let owned = ALLOWANCES.prefix(&owner);
+        assert_eq!(owned.load(&store, "spender1").unwrap(), 50);
+        owned.save(&mut store, "spender2", &70).unwrap();
+        owned.remove(&mut store, "spender1");
+
+        // and the entries are the same as when accessed with the full key
+        assert!(!ALLOWANCES.has(&store, (&owner, "spender1")));
+        assert_eq!(ALLOWANCES.load(&store, (&owner, "spender2")).unwrap(), 70);
+
+        // a sub prefix accesses the remaining composite key
+        TRIPLE
+            .save(&mut store, (b"owner", 9, "recipient"), &1000)
+            .unwrap();
+        let sub = TRIPLE.sub_prefix(b"owner");
+        assert_eq!(sub.load(&store, (9, "recipient")).unwrap(), 1000);
+        assert_eq!(sub.may_load(&store, (10, "recipient")).unwrap(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_addr_prefix() {
+        let mut store = MockStorage::new();
+
+        const ALLOWANCES: Map<(&Addr, &Addr), u64> = Map::new("allowances");
+
+        let owner = Addr::from("owner");
+        let (spender1, spender2) = (Addr::from("spender1"), Addr::from("spender2"));
+
+        ALLOWANCES
+            .save(&mut store, (&owner, &spender1), &50)
+            .unwrap();
+        ALLOWANCES
+            .save(&mut store, (&owner, &spender2), &70)
+            .unwrap();
+        ALLOWANCES
+            .save(&mut store, (&spender1, &owner), &10)
+            .unwrap();
+
+        let all: StdResult<Vec<_>> = ALLOWANCES
+            .prefix(&owner)
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(all.unwrap(), vec![(spender1, 50), (spender2, 70)]);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_nested_composite_key() {
+        let mut store = MockStorage::new();
+
+        NESTED
+            .save(&mut store, ((b"owner", 9), "recipient"), &1000)
+            .unwrap();
+        NESTED
+            .save(&mut store, ((b"owner", 10), "recipient2"), &3000)
+            .unwrap();
+
+        // the nested first key is deserialized back as a whole
+        let all: StdResult<Vec<_>> = NESTED.range(&store, None, None, Order::Ascending).collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![
+                (((b"owner".to_vec(), 9), "recipient".to_string()), 1000),
+                (((b"owner".to_vec(), 10), "recipient2".to_string()), 3000),
+            ]
+        );
+
+        // and so is it when iterating under the nested prefix
+        let all: StdResult<Vec<_>> = NESTED
+            .prefix((b"owner", 9))
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(all.unwrap(), vec![("recipient".to_string(), 1000)]);
+    }
+
+    #[test]
+    fn save_and_remove_many() {
+        let mut store = MockStorage::new();
+
+        let john = Data {
+            name: "John".to_string(),
+            age: 32,
+        };
+        let jim = Data {
+            name: "Jim".to_string(),
+            age: 44,
+        };
+
+        PEOPLE
+            .save_many(&mut store, [(&b"john"[..], &john), (b"jim", &jim)])
+            .unwrap();
+        // the entries are stored just like when saved one by one
+        assert_eq!(PEOPLE.load(&store, b"john").unwrap(), john);
+        assert_eq!(PEOPLE.load(&store, b"jim").unwrap(), jim);
+
+        ALLOWANCE
+            .save_many(&mut store, vec![((&b"owner"[..], &b"spender"[..]), &5)])
+            .unwrap();
+        assert_eq!(ALLOWANCE.load(&store, (b"owner", b"spender")).unwrap(), 5);
+
+        PEOPLE.remove_many(&mut store, [&b"john"[..], b"nobody"]);
+        assert!(!PEOPLE.has(&store, b"john"));
+        assert_eq!(PEOPLE.load(&store, b"jim").unwrap(), jim);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn clear_in_batches() {
+        let mut store = MockStorage::new();
+        for (owner, amount) in [(b"a", 1), (b"b", 2), (b"c", 3)] {
+            ALLOWANCE
+                .save(&mut store, (owner, b"spender"), &amount)
+                .unwrap();
+        }
+        PEOPLE
+            .save(
+                &mut store,
+                b"john",
+                &Data {
+                    name: "John".to_string(),
+                    age: 32,
+                },
+            )
+            .unwrap();
+
+        // the entries left are counted up to the limit, at least 1
+        assert_eq!(ALLOWANCE.clear(&mut store, 0).unwrap(), 1);
+        assert_eq!(ALLOWANCE.clear(&mut store, 2).unwrap(), 1);
+        assert!(!ALLOWANCE.has(&store, (b"a", b"spender")));
+        assert!(!ALLOWANCE.has(&store, (b"b", b"spender")));
+        assert_eq!(ALLOWANCE.clear(&mut store, 2).unwrap(), 0);
+        assert!(!ALLOWANCE.has(&store, (b"c", b"spender")));
+        // other maps are left alone
+        assert!(PEOPLE.has(&store, b"john"));
+    }
+
+    #[test]
+    fn take_removes_the_entry() {
+        let mut store = MockStorage::new();
+
+        let john = Data {
+            name: "John".to_string(),
+            age: 32,
+        };
+        PEOPLE.save(&mut store, b"john", &john).unwrap();
+
+        assert_eq!(PEOPLE.take(&mut store, b"john").unwrap(), Some(john));
+        assert!(!PEOPLE.has(&store, b"john"));
+        assert_eq!(PEOPLE.take(&mut store, b"john").unwrap(), None);
+
+        // unparsable data is kept
+        store.set(&PEOPLE.key(b"jim"), b"\xff");
+        assert!(PEOPLE.take(&mut store, b"jim").is_err());
+        assert!(PEOPLE.has(&store, b"jim"));
+    }
+
+    #[test]
+    fn replace_returns_the_previous_value() {
+        let mut store = MockStorage::new();
+
+        assert_eq!(
+            ALLOWANCE
+                .replace(&mut store, (b"owner", b"spender"), &5)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            ALLOWANCE
+                .replace(&mut store, (b"owner", b"spender"), &7)
+                .unwrap(),
+            Some(5)
+        );
+        assert_eq!(ALLOWANCE.load(&store, (b"owner", b"spender")).unwrap(), 7);
+    }
+
+    #[test]
+    fn save_many_is_all_or_nothing() {
+        // fails to serialize when negative
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Picky(i32);
+
+        impl Serialize for Picky {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                if self.0 < 0 {
+                    return Err(serde::ser::Error::custom("negative"));
+                }
+                serializer.serialize_i32(self.0)
+            }
+        }
+
+        const PICKY: Map<&str, Picky> = Map::new("picky");
+        let mut store = MockStorage::new();
+
+        let res = PICKY.save_many(&mut store, [("a", &Picky(1)), ("b", &Picky(-1))]);
+        assert!(matches!(
+            res,
+            Err(StorageError::Std(StdError::SerializeErr { .. }))
+        ));
+        assert!(!PICKY.has(&store, "a"));
+        assert!(!PICKY.has(&store, "b"));
+    }
+
+    #[test]
+    fn json_backend_works() {
+        use crate::serialization::Json;
+        use cosmwasm_std::ReadonlyStorage;
+
+        const JSON_PEOPLE: Map<&[u8], Data, Json> = Map::new("people");
+        let mut store = MockStorage::new();
+
+        let john = Data {
+            name: "John".to_string(),
+            age: 32,
+        };
+        JSON_PEOPLE.save(&mut store, b"john", &john).unwrap();
+        assert_eq!(
+            store.get(&JSON_PEOPLE.key(b"john")).unwrap(),
+            br#"{"name":"John","age":32}"#
+        );
+        assert_eq!(JSON_PEOPLE.load(&store, b"john").unwrap(), john);
+        assert_eq!(JSON_PEOPLE.may_load(&store, b"jim").unwrap(), None);
+
+        JSON_PEOPLE
+            .update(&mut store, b"john", |d| -> StdResult<_> {
+                let mut d = d.unwrap();
+                d.age += 1;
+                Ok(d)
+            })
+            .unwrap();
+        assert_eq!(JSON_PEOPLE.load(&store, b"john").unwrap().age, 33);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn json_backend_range_works() {
+        use crate::serialization::Json;
+
+        const JSON_ALLOWANCE: Map<(&[u8], &[u8]), u64, Json> = Map::new("allow");
+        let mut store = MockStorage::new();
+
+        JSON_ALLOWANCE
+            .save(&mut store, (b"owner", b"spender"), &1000)
+            .unwrap();
+        JSON_ALLOWANCE
+            .save(&mut store, (b"owner", b"spender2"), &3000)
+            .unwrap();
+        JSON_ALLOWANCE
+            .save(&mut store, (b"owner2", b"spender"), &5000)
+            .unwrap();
+
+        let all: StdResult<Vec<_>> = JSON_ALLOWANCE
+            .prefix(b"owner")
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(
+            all.unwrap(),
+            vec![(b"spender".to_vec(), 1000), (b"spender2".to_vec(), 3000)]
+        );
+        assert_eq!(
+            JSON_ALLOWANCE
+                .prefix(b"owner2")
+                .load(&store, b"spender")
+                .unwrap(),
+            5000
+        );
+    }
+
+    #[test]
+    fn query_remote_map() {
+        use crate::helpers::testing::RemoteContract;
+
+        let mut remote = RemoteContract {
+            addr: Addr::from("remote"),
+            storage: MockStorage::new(),
+        };
+        let code_hash = "hash".to_string();
+
+        ALLOWANCE
+            .save(&mut remote.storage, (b"owner", b"spender"), &1000)
+            .unwrap();
+        TRIPLE
+            .save(&mut remote.storage, (b"owner", 9, "recipient"), &30)
+            .unwrap();
+
+        let allowance = ALLOWANCE
+            .query(
+                &remote,
+                remote.addr.clone(),
+                code_hash.clone(),
+                (b"owner", b"spender"),
+            )
+            .unwrap();
+        assert_eq!(allowance, Some(1000));
+        let missing = ALLOWANCE
+            .query(
+                &remote,
+                remote.addr.clone(),
+                code_hash.clone(),
+                (b"owner", b"other"),
+            )
+            .unwrap();
+        assert_eq!(missing, None);
+        let triple = TRIPLE
+            .query(
+                &remote,
+                remote.addr.clone(),
+                code_hash,
+                (b"owner", 9, "recipient"),
+            )
+            .unwrap();
+        assert_eq!(triple, Some(30));
+    }
+
+    #[test]
+    fn load_errors_name_namespace_and_key() {
+        let mut store = MockStorage::new();
+
+        let err = ALLOWANCE.load(&store, (b"owner", b"spender")).unwrap_err();
+        assert_eq!(
+            err,
+            StorageError::NotFound {
+                type_name: "u64",
+                namespace: "allow".to_string(),
+                key: b"\x00\x05ownerspender".to_vec(),
+            }
+        );
+        // the composite key is rendered joined, with the length prefix of "owner"
+        assert_eq!(
+            err.to_string(),
+            r#"u64 in namespace "allow" at key 0x00056f776e65727370656e646572 not found"#
+        );
+
+        // data written with another layout
+        store.set(&ALLOWANCE.key((b"owner", b"spender")), b"\x01");
+        match ALLOWANCE
+            .may_load(&store, (b"owner", b"spender"))
+            .unwrap_err()
+        {
+            StorageError::Deserialize { type_name, source } => {
+                assert_eq!(type_name, "u64");
+                assert!(
+                    source.ends_with(
+                        r#"(in namespace "allow" at key 0x00056f776e65727370656e646572)"#
+                    ),
+                    "{}",
+                    source
+                );
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn has_prefix_works() {
+        let mut store = MockStorage::new();
+        assert!(!ALLOWANCE.has_prefix(&store, b"owner"));
+
+        ALLOWANCE
+            .save(&mut store, (b"owner2", b"spender"), &1000)
+            .unwrap();
+        TRIPLE
+            .save(&mut store, (b"owner", 9, "recipient"), &30)
+            .unwrap();
+        // neither a longer first key nor another map with the same first key count
+        assert!(!ALLOWANCE.has_prefix(&store, b"owner"));
+        assert!(ALLOWANCE.has_prefix(&store, b"owner2"));
+
+        ALLOWANCE
+            .save(&mut store, (b"owner", b"spender"), &500)
+            .unwrap();
+        assert!(ALLOWANCE.has_prefix(&store, b"owner"));
+        ALLOWANCE.remove(&mut store, (b"owner", b"spender"));
+        assert!(!ALLOWANCE.has_prefix(&store, b"owner"));
+
+        assert!(TRIPLE.has_sub_prefix(&store, b"owner"));
+        assert!(TRIPLE.has_prefix(&store, (b"owner", 9)));
+        assert!(!TRIPLE.has_prefix(&store, (b"owner", 10)));
+        assert!(!TRIPLE.has_sub_prefix(&store, b"owner2"));
+    }
+
     #[test]
     fn basic_update() {
         let mut store = MockStorage::new();
@@ -270,6 +1663,92 @@ mod test {
         assert_eq!(20, loaded);
     }
 
+    #[test]
+    fn load_or_default() {
+        let mut store = MockStorage::new();
+
+        let key: (&[u8], &[u8]) = (b"owner", b"spender");
+        assert_eq!(0, ALLOWANCE.load_or_default(&store, key).unwrap());
+        assert!(!ALLOWANCE.has(&store, key));
+
+        ALLOWANCE.save(&mut store, key, &5).unwrap();
+        assert_eq!(5, ALLOWANCE.load_or_default(&store, key).unwrap());
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        use cosmwasm_std::Uint128;
+
+        let mut store = MockStorage::new();
+        const BALANCES: Map<&str, Uint128> = Map::new("balances");
+
+        assert_eq!(
+            BALANCES.add(&mut store, "alice", Uint128(100)).unwrap(),
+            Uint128(100)
+        );
+        assert_eq!(
+            BALANCES
+                .checked_sub(&mut store, "alice", Uint128(30))
+                .unwrap(),
+            Uint128(70)
+        );
+        assert_eq!(
+            BALANCES
+                .checked_sub(&mut store, "alice", Uint128(80))
+                .unwrap_err(),
+            StorageError::InsufficientFunds {
+                available: "70".to_string(),
+                required: "80".to_string(),
+            }
+        );
+        assert_eq!(BALANCES.load(&store, "alice").unwrap(), Uint128(70));
+        assert_eq!(
+            BALANCES
+                .sub_saturating(&mut store, "alice", Uint128(80))
+                .unwrap(),
+            Uint128(0)
+        );
+
+        // missing entries count as zero
+        assert!(BALANCES.checked_sub(&mut store, "bob", Uint128(1)).is_err());
+        assert!(!BALANCES.has(&store, "bob"));
+
+        const SUPPLY: Map<u8, u64> = Map::new("supply");
+        SUPPLY.add(&mut store, 0, u64::MAX).unwrap();
+        assert_eq!(
+            SUPPLY.add(&mut store, 0, 1).unwrap_err(),
+            StorageError::Overflow { collection: "Map" }
+        );
+        assert_eq!(SUPPLY.load(&store, 0).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    #[cfg(feature = "migration")]
+    fn export_and_import() {
+        let mut store = MockStorage::new();
+        ALLOWANCE
+            .save(&mut store, (b"owner", b"spender"), &5)
+            .unwrap();
+        ALLOWANCE
+            .save(&mut store, (b"owner", b"other"), &7)
+            .unwrap();
+
+        let exported = ALLOWANCE.export(&store);
+        assert_eq!(exported.len(), 2);
+
+        let mut other_store = MockStorage::new();
+        const IMPORTED: Map<(&[u8], &[u8]), u64> = Map::new("imported");
+        IMPORTED.import(&mut other_store, exported).unwrap();
+        assert_eq!(
+            IMPORTED.load(&other_store, (b"owner", b"spender")).unwrap(),
+            5
+        );
+        assert_eq!(
+            IMPORTED.load(&other_store, (b"owner", b"other")).unwrap(),
+            7
+        );
+    }
+
     #[test]
     fn readme_works() -> StdResult<()> {
         let mut store = MockStorage::new();
@@ -382,4 +1861,58 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn aggregates_with_a_cap() {
+        let mut store = MockStorage::new();
+        const STAKES: Map<(&str, &str), u64> = Map::new("stakes");
+        STAKES.save(&mut store, ("alice", "val1"), &10).unwrap();
+        STAKES.save(&mut store, ("alice", "val2"), &20).unwrap();
+        STAKES.save(&mut store, ("bob", "val1"), &5).unwrap();
+
+        assert_eq!(STAKES.sum(&store, 3).unwrap(), 35);
+        assert_eq!(STAKES.count(&store, 3).unwrap(), 3);
+        assert_eq!(STAKES.prefix("alice").sum(&store, 2).unwrap(), 30);
+        assert_eq!(STAKES.prefix("carol").count(&store, 0).unwrap(), 0);
+        let max = STAKES
+            .prefix("alice")
+            .fold(&store, 10, 0, |max, stake| Ok(stake.max(max)))
+            .unwrap();
+        assert_eq!(max, 20);
+
+        assert_eq!(
+            STAKES.sum(&store, 2).unwrap_err(),
+            StorageError::TooManyEntries { max_entries: 2 }
+        );
+        assert!(STAKES.prefix("alice").count(&store, 1).is_err());
+    }
+
+    #[test]
+    fn long_keys_are_rejected() {
+        let mut store = MockStorage::new();
+        let names: Map<&str, u64> = Map::new("names").with_max_key_len(5);
+
+        names.save(&mut store, "alice", &1).unwrap();
+        assert_eq!(
+            names.save(&mut store, "albert", &2).unwrap_err(),
+            StorageError::KeyTooLong { len: 6, max_len: 5 }
+        );
+        assert_eq!(
+            names.save_raw(&mut store, "", b"3").unwrap_err(),
+            StorageError::EmptyKey
+        );
+        assert!(names.entry(&mut store, "albert").is_err());
+        assert!(names
+            .save_many(&mut store, vec![("bob", &4), ("albert", &5)])
+            .is_err());
+        assert!(!names.has(&store, "albert"));
+        assert!(!names.has(&store, "bob"));
+
+        // reads are not checked
+        assert_eq!(names.may_load(&store, "albert").unwrap(), None);
+        // nor are maps without a limit
+        let unchecked: Map<&str, u64> = Map::new("names");
+        unchecked.save(&mut store, "", &6).unwrap();
+    }
 }
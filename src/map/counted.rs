@@ -0,0 +1,254 @@
+use cosmwasm_std::{StdError, Storage};
+
+use super::{Entry, Map};
+use crate::error::StorageResult;
+use crate::keys::PrimaryKey;
+use crate::numeric::Numeric;
+use crate::readonly::ReadonlyMap;
+use crate::serialization::{Bincode2, Serde};
+
+/// A `Map` that keeps count of its entries, so `len` needs no iteration.
+///
+/// The count costs an extra read per write. Every write goes through the methods below, there is
+/// no `key` or `prefix` to write through, so the count can't drift from the entries. Use a
+/// namespace of its own: entries saved under it by a plain `Map` are not counted.
+#[derive(Debug, Clone)]
+pub struct CountedMap<'a, K, T, Ser = Bincode2> {
+    map: Map<'a, K, T, Ser>,
+}
+
+impl<'a, K, T, Ser> CountedMap<'a, K, T, Ser> {
+    pub const fn new(namespace: &'a str) -> Self {
+        Self {
+            map: Map::new(namespace).counted(),
+        }
+    }
+
+    /// Creates a map under a namespace only known at runtime, e.g. one built with `Namespace`
+    pub fn new_owned(namespace: impl Into<Vec<u8>>) -> Self {
+        Self {
+            map: Map::new_owned(namespace).counted(),
+        }
+    }
+
+    /// See `Map::with_max_key_len`
+    pub const fn with_max_key_len(mut self, max_len: usize) -> Self {
+        self.map.max_key_len = Some(max_len);
+        self
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+
+    pub fn name(&self) -> &[u8] {
+        self.map.name()
+    }
+
+    /// Returns the number of entries
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.map.stored_len(store)
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    /// Returns a view of the map that can only read it, e.g. to iterate over its entries
+    pub fn readonly(&self) -> ReadonlyMap<'_, 'a, K, T, Ser> {
+        self.map.readonly()
+    }
+}
+
+impl<'a, K, T, Ser> CountedMap<'a, K, T, Ser>
+where
+    K: PrimaryKey<'a>,
+    Ser: Serde<T>,
+{
+    pub fn save(&self, store: &mut dyn Storage, k: K, data: &T) -> StorageResult<()> {
+        self.map.save(store, k, data)
+    }
+
+    pub fn remove(&self, store: &mut dyn Storage, k: K) {
+        self.map.remove(store, k)
+    }
+
+    /// See `Map::take`
+    pub fn take(&self, store: &mut dyn Storage, k: K) -> StorageResult<Option<T>> {
+        self.map.take(store, k)
+    }
+
+    /// See `Map::replace`
+    pub fn replace(&self, store: &mut dyn Storage, k: K, data: &T) -> StorageResult<Option<T>> {
+        self.map.replace(store, k, data)
+    }
+
+    pub fn load(&self, store: &dyn Storage, k: K) -> StorageResult<T> {
+        self.map.load(store, k)
+    }
+
+    pub fn may_load(&self, store: &dyn Storage, k: K) -> StorageResult<Option<T>> {
+        self.map.may_load(store, k)
+    }
+
+    pub fn has(&self, store: &dyn Storage, k: K) -> bool {
+        self.map.has(store, k)
+    }
+
+    /// See `Map::save_raw`
+    pub fn save_raw(&self, store: &mut dyn Storage, k: K, data: &[u8]) -> StorageResult<()> {
+        self.map.save_raw(store, k, data)
+    }
+
+    /// See `Map::entry`
+    pub fn entry<'s>(&self, store: &'s mut dyn Storage, k: K) -> StorageResult<Entry<'s, T, Ser>> {
+        self.map.entry(store, k)
+    }
+
+    /// See `Map::update`
+    pub fn update<A, E>(&self, store: &mut dyn Storage, k: K, action: A) -> Result<T, E>
+    where
+        A: FnOnce(Option<T>) -> Result<T, E>,
+        E: From<StdError>,
+    {
+        self.map.update(store, k, action)
+    }
+
+    /// See `Map::save_many`
+    pub fn save_many<'t, I>(&self, store: &mut dyn Storage, entries: I) -> StorageResult<()>
+    where
+        I: IntoIterator<Item = (K, &'t T)>,
+        T: 't,
+    {
+        self.map.save_many(store, entries)
+    }
+
+    pub fn remove_many<I>(&self, store: &mut dyn Storage, keys: I)
+    where
+        I: IntoIterator<Item = K>,
+    {
+        self.map.remove_many(store, keys)
+    }
+}
+
+impl<'a, K, T, Ser> CountedMap<'a, K, T, Ser>
+where
+    K: PrimaryKey<'a>,
+    T: Numeric,
+    Ser: Serde<T>,
+{
+    /// See `Map::add`
+    pub fn add(&self, store: &mut dyn Storage, k: K, amount: T) -> StorageResult<T> {
+        self.map.add(store, k, amount)
+    }
+
+    /// See `Map::checked_sub`
+    pub fn checked_sub(&self, store: &mut dyn Storage, k: K, amount: T) -> StorageResult<T> {
+        self.map.checked_sub(store, k, amount)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, K, T, Ser> CountedMap<'a, K, T, Ser>
+where
+    K: PrimaryKey<'a>,
+    Ser: Serde<T>,
+{
+    /// clear removes up to `limit` entries in key order and returns how many are left,
+    /// so a large map can be wiped over several transactions
+    pub fn clear(&self, store: &mut dyn Storage, limit: u32) -> StorageResult<u32> {
+        self.map.clear(store, limit)
+    }
+}
+
+#[cfg(feature = "migration")]
+impl<'a, K, T, Ser> CountedMap<'a, K, T, Ser> {
+    /// See `Map::export`
+    pub fn export(&self, store: &dyn Storage) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.map.export(store)
+    }
+
+    /// import saves entries as returned by `export`, overwriting existing ones and counting
+    /// the new ones
+    pub fn import<I>(&self, store: &mut dyn Storage, entries: I) -> StorageResult<()>
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        self.map.import(store, entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::StdResult;
+
+    const MEMBERS: CountedMap<&str, u64> = CountedMap::new("members");
+
+    #[test]
+    fn keeps_len() {
+        let mut store = MockStorage::new();
+        assert!(MEMBERS.is_empty(&store));
+
+        MEMBERS.save(&mut store, "alice", &1).unwrap();
+        MEMBERS.save(&mut store, "alice", &2).unwrap();
+        MEMBERS
+            .update(&mut store, "bob", |_| -> StdResult<_> { Ok(3) })
+            .unwrap();
+        MEMBERS.add(&mut store, "carol", 4).unwrap();
+        MEMBERS
+            .save_raw(&mut store, "dave", &5u64.to_be_bytes())
+            .unwrap();
+        assert_eq!(MEMBERS.replace(&mut store, "bob", &6).unwrap(), Some(3));
+        assert_eq!(MEMBERS.len(&store), 4);
+
+        MEMBERS
+            .save_many(&mut store, [("erin", &7), ("alice", &8), ("erin", &9)])
+            .unwrap();
+        assert_eq!(MEMBERS.len(&store), 5);
+
+        MEMBERS.remove(&mut store, "alice");
+        MEMBERS.remove(&mut store, "alice");
+        assert_eq!(MEMBERS.take(&mut store, "bob").unwrap(), Some(6));
+        assert_eq!(MEMBERS.take(&mut store, "bob").unwrap(), None);
+        MEMBERS.remove_many(&mut store, ["carol", "nobody"]);
+        assert_eq!(MEMBERS.len(&store), 2);
+
+        MEMBERS
+            .entry(&mut store, "frank")
+            .unwrap()
+            .or_insert(10)
+            .unwrap();
+        assert_eq!(MEMBERS.len(&store), 3);
+        match MEMBERS.entry(&mut store, "frank").unwrap() {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 10),
+            Entry::Vacant(_) => panic!("frank was not saved"),
+        }
+        assert_eq!(MEMBERS.len(&store), 2);
+
+        #[cfg(feature = "iterator")]
+        {
+            assert_eq!(MEMBERS.readonly().count(&store, 10).unwrap(), 2);
+            assert_eq!(MEMBERS.clear(&mut store, 1).unwrap(), 1);
+            assert_eq!(MEMBERS.clear(&mut store, 1).unwrap(), 0);
+            assert!(MEMBERS.is_empty(&store));
+        }
+    }
+
+    #[test]
+    fn entries_of_a_plain_map_are_not_counted() {
+        let mut store = MockStorage::new();
+        let plain: Map<&str, u64> = Map::new("members");
+
+        plain.save(&mut store, "alice", &1).unwrap();
+        assert_eq!(MEMBERS.load(&store, "alice").unwrap(), 1);
+        assert_eq!(MEMBERS.len(&store), 0);
+        // removing it never takes the count below zero
+        MEMBERS.remove(&mut store, "alice");
+        assert_eq!(MEMBERS.len(&store), 0);
+
+        MEMBERS.save(&mut store, "bob", &2).unwrap();
+        assert_eq!(MEMBERS.len(&store), 1);
+    }
+}
@@ -0,0 +1,230 @@
+use cosmwasm_std::Storage;
+
+use crate::error::{StorageError, StorageResult};
+use crate::path::Path;
+use crate::queue::{load_u32, save_u32};
+use crate::serialization::{Bincode2, Serde};
+
+/// Entry is a view into a single entry of a `Map`, which is either occupied or vacant.
+/// It is created by `Map::entry`, and writes through to the storage it was created with.
+pub enum Entry<'s, T, Ser = Bincode2>
+where
+    Ser: Serde<T>,
+{
+    Occupied(OccupiedEntry<'s, T, Ser>),
+    Vacant(VacantEntry<'s, T, Ser>),
+}
+
+/// An entry holding a value, the value is loaded when the entry is created
+pub struct OccupiedEntry<'s, T, Ser = Bincode2>
+where
+    Ser: Serde<T>,
+{
+    store: &'s mut dyn Storage,
+    path: Path<T, Ser>,
+    value: T,
+    /// the counter of a counted map
+    counter: Option<Vec<u8>>,
+}
+
+/// An entry without a value
+pub struct VacantEntry<'s, T, Ser = Bincode2>
+where
+    Ser: Serde<T>,
+{
+    store: &'s mut dyn Storage,
+    path: Path<T, Ser>,
+    /// the counter of a counted map
+    counter: Option<Vec<u8>>,
+}
+
+impl<'s, T, Ser> Entry<'s, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    pub(crate) fn new(
+        store: &'s mut dyn Storage,
+        path: Path<T, Ser>,
+        counter: Option<Vec<u8>>,
+    ) -> StorageResult<Self> {
+        Ok(match path.may_load(store)? {
+            Some(value) => Entry::Occupied(OccupiedEntry {
+                store,
+                path,
+                value,
+                counter,
+            }),
+            None => Entry::Vacant(VacantEntry {
+                store,
+                path,
+                counter,
+            }),
+        })
+    }
+
+    /// Returns the value of an occupied entry, or saves and returns `default` for a vacant one
+    pub fn or_insert(self, default: T) -> StorageResult<T> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Returns the value of an occupied entry, or saves and returns the result of `default`
+    /// for a vacant one
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> StorageResult<T> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_value()),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Returns the value of an occupied entry, or saves and returns `T::default()` for a vacant one
+    pub fn or_default(self) -> StorageResult<T>
+    where
+        T: Default,
+    {
+        self.or_insert_with(T::default)
+    }
+
+    /// Modifies and saves the value of an occupied entry, leaves a vacant one untouched
+    pub fn and_modify<F: FnOnce(&mut T)>(self, f: F) -> StorageResult<Self> {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(&mut entry.value);
+                entry.path.save(entry.store, &entry.value)?;
+                Ok(Entry::Occupied(entry))
+            }
+            vacant => Ok(vacant),
+        }
+    }
+}
+
+impl<'s, T, Ser> OccupiedEntry<'s, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    /// Saves `value` and returns the previous one
+    pub fn insert(&mut self, value: T) -> StorageResult<T> {
+        self.path.save(self.store, &value)?;
+        Ok(std::mem::replace(&mut self.value, value))
+    }
+
+    /// Removes the entry from storage and returns its value
+    pub fn remove(self) -> T {
+        self.path.remove(self.store);
+        if let Some(counter) = &self.counter {
+            // entries saved through a `Path` or `Prefix` were never counted
+            let len = load_u32(self.store, counter);
+            save_u32(self.store, counter, len.saturating_sub(1));
+        }
+        self.value
+    }
+}
+
+impl<'s, T, Ser> VacantEntry<'s, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    /// Saves `value` and returns it
+    pub fn insert(self, value: T) -> StorageResult<T> {
+        let len = self
+            .counter
+            .as_ref()
+            .map(|counter| load_u32(self.store, counter));
+        if len == Some(u32::MAX) {
+            return Err(StorageError::CapacityExceeded { collection: "Map" });
+        }
+        self.path.save(self.store, &value)?;
+        if let (Some(counter), Some(len)) = (&self.counter, len) {
+            save_u32(self.store, counter, len + 1);
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cosmwasm_std::testing::MockStorage;
+
+    use super::*;
+    use crate::Map;
+
+    const BALANCES: Map<&str, u64> = Map::new("balances");
+
+    #[test]
+    fn or_insert_and_modify() {
+        let mut store = MockStorage::new();
+
+        let balance = BALANCES
+            .entry(&mut store, "alice")
+            .unwrap()
+            .and_modify(|b| *b += 10)
+            .unwrap()
+            .or_insert(10)
+            .unwrap();
+        assert_eq!(balance, 10);
+        assert_eq!(BALANCES.load(&store, "alice").unwrap(), 10);
+
+        let balance = BALANCES
+            .entry(&mut store, "alice")
+            .unwrap()
+            .and_modify(|b| *b += 10)
+            .unwrap()
+            .or_insert(10)
+            .unwrap();
+        assert_eq!(balance, 20);
+        assert_eq!(BALANCES.load(&store, "alice").unwrap(), 20);
+
+        assert_eq!(
+            BALANCES
+                .entry(&mut store, "bob")
+                .unwrap()
+                .or_default()
+                .unwrap(),
+            0
+        );
+        assert!(BALANCES.has(&store, "bob"));
+        assert_eq!(
+            BALANCES
+                .entry(&mut store, "bob")
+                .unwrap()
+                .or_insert_with(|| panic!("bob is already stored"))
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn match_on_entries() {
+        let mut store = MockStorage::new();
+        BALANCES.save(&mut store, "alice", &5).unwrap();
+
+        match BALANCES.entry(&mut store, "alice").unwrap() {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(*entry.get(), 5);
+                assert_eq!(entry.insert(7).unwrap(), 5);
+                assert_eq!(*entry.get(), 7);
+            }
+            Entry::Vacant(_) => panic!("alice is stored"),
+        }
+        assert_eq!(BALANCES.load(&store, "alice").unwrap(), 7);
+
+        match BALANCES.entry(&mut store, "alice").unwrap() {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 7),
+            Entry::Vacant(_) => panic!("alice is stored"),
+        }
+        assert!(!BALANCES.has(&store, "alice"));
+
+        match BALANCES.entry(&mut store, "alice").unwrap() {
+            Entry::Occupied(_) => panic!("alice was removed"),
+            Entry::Vacant(entry) => assert_eq!(entry.insert(3).unwrap(), 3),
+        }
+        assert_eq!(BALANCES.load(&store, "alice").unwrap(), 3);
+    }
+}
@@ -0,0 +1,225 @@
+use cosmwasm_std::{StdResult, Storage};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{StorageError, StorageResult};
+use crate::queue::{load_u32, save_u32};
+use crate::serialization::{Bincode2, Serde};
+use crate::{Item, Map};
+
+/// A sha256 digest
+pub type Hash = [u8; 32];
+
+/// One level of an inclusion proof: the sibling to hash with and which side it is on
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofStep {
+    pub sibling: Hash,
+    pub sibling_is_left: bool,
+}
+
+/// Returns the hash of a leaf holding `data`.
+/// Leaves and inner nodes are hashed with different prefixes, so neither passes for the other
+pub fn hash_leaf(data: &[u8]) -> Hash {
+    Sha256::new()
+        .chain_update([0])
+        .chain_update(data)
+        .finalize()
+        .into()
+}
+
+fn hash_nodes(left: &Hash, right: &Hash) -> Hash {
+    Sha256::new()
+        .chain_update([1])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/// Returns true if `proof` shows that a leaf holding `leaf` is part of the tree with `root`.
+///
+/// A `MerkleTree` leaf holds its item serialized with the tree's serialization.
+pub fn verify_proof(root: &Hash, leaf: &[u8], proof: &[ProofStep]) -> bool {
+    let computed = proof.iter().fold(hash_leaf(leaf), |node, step| {
+        if step.sibling_is_left {
+            hash_nodes(&step.sibling, &node)
+        } else {
+            hash_nodes(&node, &step.sibling)
+        }
+    });
+    &computed == root
+}
+
+/// MerkleTree is an append-only list whose root hash is kept up to date, and which proves that
+/// an item is part of it, e.g. to check airdrop claims or verify data from another contract.
+///
+/// Every node of the tree is stored, so pushing an item and proving one both touch one node per
+/// level. A node without a sibling, at the end of an odd level, is moved up unchanged.
+pub struct MerkleTree<'a, T, Ser = Bincode2> {
+    leaves: Map<'a, u32, T, Ser>,
+}
+
+impl<'a, T, Ser> MerkleTree<'a, T, Ser> {
+    const LEN: &'static [u8] = b"_len";
+    const NODES: &'static [u8] = b"_nodes";
+    const ROOT: &'static [u8] = b"_root";
+
+    pub const fn new(namespace: &'a str) -> Self {
+        MerkleTree {
+            leaves: Map::new(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.leaves.namespace()
+    }
+
+    /// Returns the number of items in the tree
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.with_namespace_suffix(Self::LEN, |ns| load_u32(store, ns))
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    /// Returns the root hash, None if the tree is empty
+    pub fn root(&self, store: &dyn Storage) -> StdResult<Option<Hash>> {
+        Ok(self.root_item().may_load(store)?)
+    }
+
+    /// Returns an inclusion proof for the item at `index`, to check with `verify_proof`.
+    /// Returns an error if there is no such item
+    pub fn prove(&self, store: &dyn Storage, index: u32) -> StdResult<Vec<ProofStep>> {
+        let nodes = self.nodes();
+        // loaded for the not found error
+        nodes.load(store, (0, index))?;
+
+        let mut proof = vec![];
+        let (mut level, mut index, mut count) = (0, index, self.len(store));
+        while count > 1 {
+            if index % 2 == 1 {
+                proof.push(ProofStep {
+                    sibling: nodes.load(store, (level, index - 1))?,
+                    sibling_is_left: true,
+                });
+            } else if index + 1 < count {
+                proof.push(ProofStep {
+                    sibling: nodes.load(store, (level, index + 1))?,
+                    sibling_is_left: false,
+                });
+            }
+            level += 1;
+            index /= 2;
+            count = count / 2 + count % 2;
+        }
+        Ok(proof)
+    }
+
+    fn nodes(&self) -> Map<'static, (u8, u32), Hash> {
+        Map::new_owned([self.namespace(), Self::NODES].concat())
+    }
+
+    fn root_item(&self) -> Item<'static, Hash> {
+        Item::new_owned([self.namespace(), Self::ROOT].concat())
+    }
+
+    fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
+        let namespace = &[self.namespace(), namespace].concat();
+        f(namespace)
+    }
+}
+
+impl<'a, T, Ser> MerkleTree<'a, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    /// Adds an item to the tree and updates the root hash, returns the index of the item
+    pub fn push(&self, store: &mut dyn Storage, t: &T) -> StorageResult<u32> {
+        let index = self.len(store);
+        if index == u32::MAX {
+            return Err(StorageError::CapacityExceeded {
+                collection: "MerkleTree",
+            });
+        }
+        let data = Ser::serialize(t)?;
+        self.leaves.save(store, index, t)?;
+        let len = index + 1;
+        self.with_namespace_suffix(Self::LEN, |ns| save_u32(store, ns, len));
+
+        // only the nodes on the path of the new leaf change, and it is the last one of its level
+        let nodes = self.nodes();
+        let mut node = hash_leaf(&data);
+        let (mut level, mut index, mut count) = (0, index, len);
+        nodes.save(store, (level, index), &node)?;
+        while count > 1 {
+            if index % 2 == 1 {
+                node = hash_nodes(&nodes.load(store, (level, index - 1))?, &node);
+            }
+            level += 1;
+            index /= 2;
+            count = count / 2 + count % 2;
+            nodes.save(store, (level, index), &node)?;
+        }
+        self.root_item().save(store, &node)?;
+        Ok(len - 1)
+    }
+
+    /// Returns the item at `index`, None if there is no such item
+    pub fn get(&self, store: &dyn Storage, index: u32) -> StdResult<Option<T>> {
+        Ok(self.leaves.may_load(store, index)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::HumanAddr as Addr;
+
+    const AIRDROP: MerkleTree<(Addr, u64)> = MerkleTree::new("airdrop");
+
+    fn claim(i: u64) -> (Addr, u64) {
+        (Addr::from(format!("secret1{}", i)), i * 100)
+    }
+
+    #[test]
+    fn proves_every_item() {
+        for len in 1..=9 {
+            let mut store = MockStorage::new();
+            for i in 0..len {
+                assert_eq!(AIRDROP.push(&mut store, &claim(i)).unwrap(), i as u32);
+            }
+            let root = AIRDROP.root(&store).unwrap().unwrap();
+
+            for i in 0..len {
+                let proof = AIRDROP.prove(&store, i as u32).unwrap();
+                let leaf = Bincode2::serialize(&claim(i)).unwrap();
+                assert!(verify_proof(&root, &leaf, &proof), "{} of {}", i, len);
+
+                let other = Bincode2::serialize(&claim(i + 1)).unwrap();
+                assert!(!verify_proof(&root, &other, &proof));
+            }
+            assert!(AIRDROP.prove(&store, len as u32).is_err());
+        }
+    }
+
+    #[test]
+    fn root_changes_with_every_push() {
+        let mut store = MockStorage::new();
+        assert_eq!(AIRDROP.root(&store).unwrap(), None);
+
+        AIRDROP.push(&mut store, &claim(0)).unwrap();
+        let first = AIRDROP.root(&store).unwrap().unwrap();
+        assert_eq!(first, hash_leaf(&Bincode2::serialize(&claim(0)).unwrap()));
+
+        AIRDROP.push(&mut store, &claim(1)).unwrap();
+        let second = AIRDROP.root(&store).unwrap().unwrap();
+        assert_ne!(first, second);
+        // an old proof doesn't verify against the new root
+        let leaf = Bincode2::serialize(&claim(0)).unwrap();
+        assert!(!verify_proof(&second, &leaf, &[]));
+        assert_eq!(AIRDROP.get(&store, 1).unwrap(), Some(claim(1)));
+        assert_eq!(AIRDROP.len(&store), 2);
+    }
+}
@@ -0,0 +1,193 @@
+use cosmwasm_std::{StdResult, Storage};
+
+use crate::error::{StorageError, StorageResult};
+use crate::Item;
+
+/// A migration step, moving the state from one schema version to the next
+pub type MigrationStep = fn(&mut dyn Storage) -> StdResult<()>;
+
+/// Migrator upgrades the state of a contract through a sequence of schema versions.
+///
+/// The current schema version is stored in an `Item`, starting at 0 for contracts that never
+/// recorded one. Every step moves the state from one version to the next, and the version is
+/// saved after each step, so a migration too large for one transaction can be continued by
+/// `migrate_steps` in the next one.
+///
+/// ```
+/// # use cosmwasm_std::{testing::MockStorage, StdResult, Storage};
+/// # use secret_storage_lite::migrator::Migrator;
+/// fn add_fees(store: &mut dyn Storage) -> StdResult<()> {
+///     // rewrite the config with a fee field
+///     Ok(())
+/// }
+///
+/// let migrator = Migrator::new("schema_version").add_step(1, add_fees);
+/// # let mut store = MockStorage::new();
+/// # migrator.set_version(&mut store, 1).unwrap();
+/// migrator.migrate(&mut store, migrator.latest())?;
+/// # assert_eq!(migrator.version(&store)?, 2);
+/// # Ok::<(), cosmwasm_std::StdError>(())
+/// ```
+pub struct Migrator<'a> {
+    version: Item<'a, u32>,
+    steps: Vec<(u32, MigrationStep)>,
+}
+
+impl<'a> Migrator<'a> {
+    pub const fn new(namespace: &'a str) -> Self {
+        Migrator {
+            version: Item::new(namespace),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Registers the step migrating from version `from` to `from + 1`,
+    /// replacing any step registered for the same version before
+    pub fn add_step(mut self, from: u32, step: MigrationStep) -> Self {
+        match self.steps.binary_search_by_key(&from, |(v, _)| *v) {
+            Ok(i) => self.steps[i].1 = step,
+            Err(i) => self.steps.insert(i, (from, step)),
+        }
+        self
+    }
+
+    /// Returns the version reached by running every registered step
+    pub fn latest(&self) -> u32 {
+        self.steps.last().map_or(0, |(from, _)| from + 1)
+    }
+
+    /// Returns the stored schema version, 0 if none was stored yet
+    pub fn version(&self, store: &dyn Storage) -> StdResult<u32> {
+        Ok(self.version.may_load(store)?.unwrap_or_default())
+    }
+
+    /// Stores the schema version without running any step, e.g. at instantiation,
+    /// where the state is created at the latest version
+    pub fn set_version(&self, store: &mut dyn Storage, version: u32) -> StdResult<()> {
+        Ok(self.version.save(store, &version)?)
+    }
+
+    /// migrate runs all steps from the stored version up to `target`, returns the version reached
+    pub fn migrate(&self, store: &mut dyn Storage, target: u32) -> StorageResult<u32> {
+        self.migrate_steps(store, target, u32::MAX)
+    }
+
+    /// migrate_steps runs at most `max_steps` steps from the stored version towards `target`,
+    /// returns the version reached. Calling it again continues where it stopped.
+    ///
+    /// Returns an error if `target` is below the stored version, or a step on the way is missing.
+    pub fn migrate_steps(
+        &self,
+        store: &mut dyn Storage,
+        target: u32,
+        max_steps: u32,
+    ) -> StorageResult<u32> {
+        let mut version = self.version(store)?;
+        if target < version {
+            return Err(StorageError::UnsupportedMigration {
+                from: version,
+                to: target,
+            });
+        }
+
+        let mut steps = 0;
+        while version < target && steps < max_steps {
+            let step = match self.steps.binary_search_by_key(&version, |(v, _)| *v) {
+                Ok(i) => self.steps[i].1,
+                Err(_) => {
+                    return Err(StorageError::UnsupportedMigration {
+                        from: version,
+                        to: version + 1,
+                    })
+                }
+            };
+            step(store)?;
+            version += 1;
+            steps += 1;
+            self.set_version(store, version)?;
+        }
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::StdError;
+
+    const LOG: Item<Vec<u32>> = Item::new("log");
+
+    fn log(store: &mut dyn Storage, from: u32) -> StdResult<()> {
+        LOG.update(store, |mut log| -> StdResult<_> {
+            log.push(from);
+            Ok(log)
+        })?;
+        Ok(())
+    }
+
+    fn migrator() -> Migrator<'static> {
+        Migrator::new("version")
+            .add_step(1, |store| log(store, 1))
+            .add_step(0, |store| log(store, 0))
+            .add_step(2, |store| log(store, 2))
+    }
+
+    #[test]
+    fn runs_pending_steps_in_order() {
+        let mut store = MockStorage::new();
+        LOG.save(&mut store, &vec![]).unwrap();
+        let migrator = migrator();
+        assert_eq!(migrator.latest(), 3);
+        assert_eq!(migrator.version(&store).unwrap(), 0);
+
+        assert_eq!(migrator.migrate(&mut store, 2).unwrap(), 2);
+        assert_eq!(LOG.load(&store).unwrap(), vec![0, 1]);
+
+        assert_eq!(migrator.migrate(&mut store, 3).unwrap(), 3);
+        assert_eq!(migrator.migrate(&mut store, 3).unwrap(), 3);
+        assert_eq!(LOG.load(&store).unwrap(), vec![0, 1, 2]);
+        assert_eq!(migrator.version(&store).unwrap(), 3);
+    }
+
+    #[test]
+    fn resumes_partial_migrations() {
+        let mut store = MockStorage::new();
+        LOG.save(&mut store, &vec![]).unwrap();
+        let migrator = migrator();
+
+        assert_eq!(migrator.migrate_steps(&mut store, 3, 2).unwrap(), 2);
+        assert_eq!(migrator.version(&store).unwrap(), 2);
+        assert_eq!(migrator.migrate_steps(&mut store, 3, 2).unwrap(), 3);
+        assert_eq!(LOG.load(&store).unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn failing_steps_keep_the_version() {
+        let mut store = MockStorage::new();
+        let migrator = Migrator::new("version")
+            .add_step(0, |_| Ok(()))
+            .add_step(1, |_| Err(StdError::generic_err("boom")));
+
+        assert!(migrator.migrate(&mut store, 2).is_err());
+        assert_eq!(migrator.version(&store).unwrap(), 1);
+    }
+
+    #[test]
+    fn unsupported_migrations() {
+        let mut store = MockStorage::new();
+        let migrator = Migrator::new("version").add_step(1, |_| Ok(()));
+
+        // no step from 0
+        assert_eq!(
+            migrator.migrate(&mut store, 2).unwrap_err(),
+            StorageError::UnsupportedMigration { from: 0, to: 1 }
+        );
+
+        migrator.set_version(&mut store, 2).unwrap();
+        assert_eq!(
+            migrator.migrate(&mut store, 1).unwrap_err(),
+            StorageError::UnsupportedMigration { from: 2, to: 1 }
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use cosmwasm_std::Storage;
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, StdResult};
+
+use crate::error::{StorageError, StorageResult};
+use crate::helpers::namespaces_with_key;
+use crate::keys::{KeyDeserialize, Prefixer, PrimaryKey};
+#[cfg(feature = "iterator")]
+use crate::prefix::Bound;
+use crate::queue::{load_u32, save_u32};
+use crate::NestedMap;
+
+/// MultiMap maps every key to a set of values, e.g. the tokens each operator is approved for.
+///
+/// The values are stored as the keys of a `NestedMap` with empty values, so they are kept in
+/// key order and must be usable as keys themselves. The number of values of every key is kept
+/// in a separate counter.
+pub struct MultiMap<'a, K, V> {
+    map: NestedMap<'a, K, V, ()>,
+}
+
+impl<'a, K, V> MultiMap<'a, K, V> {
+    const COUNT: &'static [u8] = b"_count";
+
+    pub const fn new(namespace: &'a str) -> Self {
+        MultiMap {
+            map: NestedMap::new(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+
+    fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
+        let namespace = &[self.namespace(), namespace].concat();
+        f(namespace)
+    }
+}
+
+impl<'a, K, V> MultiMap<'a, K, V>
+where
+    K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+    V: PrimaryKey<'a> + KeyDeserialize,
+{
+    fn count_key(&self, k: &K) -> Vec<u8> {
+        self.with_namespace_suffix(Self::COUNT, |ns| {
+            namespaces_with_key(&[ns], &k.joined_key())
+        })
+    }
+
+    /// Returns the number of values of `k`
+    pub fn count(&self, store: &dyn Storage, k: K) -> u32 {
+        load_u32(store, &self.count_key(&k))
+    }
+
+    fn set_count(&self, store: &mut dyn Storage, k: &K, count: u32) {
+        let key = self.count_key(k);
+        match count {
+            0 => store.remove(&key),
+            _ => save_u32(store, &key, count),
+        }
+    }
+
+    /// Adds `v` to the values of `k`, returns whether it was newly added
+    pub fn add(&self, store: &mut dyn Storage, k: K, v: V) -> StorageResult<bool> {
+        if self.map.has(store, k.clone(), v.clone()) {
+            return Ok(false);
+        }
+        let count = self.count(store, k.clone());
+        if count == u32::MAX {
+            return Err(StorageError::CapacityExceeded {
+                collection: "MultiMap",
+            });
+        }
+        self.map.save(store, k.clone(), v, &())?;
+        self.set_count(store, &k, count + 1);
+        Ok(true)
+    }
+
+    /// Removes `v` from the values of `k`, returns whether it was one of them
+    pub fn remove(&self, store: &mut dyn Storage, k: K, v: V) -> bool {
+        if !self.map.has(store, k.clone(), v.clone()) {
+            return false;
+        }
+        self.map.remove(store, k.clone(), v);
+        let count = self.count(store, k.clone());
+        self.set_count(store, &k, count - 1);
+        true
+    }
+
+    /// Returns true if `v` is one of the values of `k`
+    pub fn contains(&self, store: &dyn Storage, k: K, v: V) -> bool {
+        self.map.has(store, k, v)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, K, V> MultiMap<'a, K, V>
+where
+    K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+    V: PrimaryKey<'a> + KeyDeserialize,
+    V::Output: 'static,
+{
+    /// Returns up to `limit` values of `k` in key order, starting after `start_after`
+    pub fn values(
+        &self,
+        store: &dyn Storage,
+        k: K,
+        start_after: Option<V>,
+        limit: u32,
+    ) -> StdResult<Vec<V::Output>> {
+        self.map
+            .sub_map(k)
+            .keys(
+                store,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Removes all values of `k`, returns how many were removed
+    pub fn remove_key(&self, store: &mut dyn Storage, k: K) -> u32 {
+        let removed = self.map.remove_all(store, k.clone());
+        self.set_count(store, &k, 0);
+        removed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const APPROVALS: MultiMap<&str, u64> = MultiMap::new("approvals");
+
+    #[test]
+    fn add_and_remove() {
+        let mut store = MockStorage::new();
+
+        assert!(APPROVALS.add(&mut store, "alice", 3).unwrap());
+        assert!(APPROVALS.add(&mut store, "alice", 1).unwrap());
+        assert!(!APPROVALS.add(&mut store, "alice", 3).unwrap());
+        assert!(APPROVALS.add(&mut store, "bob", 3).unwrap());
+
+        assert!(APPROVALS.contains(&store, "alice", 1));
+        assert!(!APPROVALS.contains(&store, "bob", 1));
+        assert_eq!(APPROVALS.count(&store, "alice"), 2);
+        assert_eq!(APPROVALS.count(&store, "carol"), 0);
+
+        assert!(APPROVALS.remove(&mut store, "alice", 3));
+        assert!(!APPROVALS.remove(&mut store, "alice", 3));
+        assert_eq!(APPROVALS.count(&store, "alice"), 1);
+        assert!(APPROVALS.contains(&store, "bob", 3));
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn pages_through_values() {
+        let mut store = MockStorage::new();
+        for token in [5, 1, 4, 2, 3] {
+            APPROVALS.add(&mut store, "alice", token).unwrap();
+        }
+        APPROVALS.add(&mut store, "bob", 9).unwrap();
+
+        assert_eq!(
+            APPROVALS.values(&store, "alice", None, 2).unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            APPROVALS.values(&store, "alice", Some(2), 10).unwrap(),
+            vec![3, 4, 5]
+        );
+
+        assert_eq!(APPROVALS.remove_key(&mut store, "alice"), 5);
+        assert_eq!(APPROVALS.count(&store, "alice"), 0);
+        assert_eq!(
+            APPROVALS.values(&store, "alice", None, 10).unwrap(),
+            Vec::<u64>::new()
+        );
+        assert_eq!(APPROVALS.values(&store, "bob", None, 10).unwrap(), vec![9]);
+    }
+}
@@ -0,0 +1,245 @@
+#[cfg(feature = "iterator")]
+use std::borrow::Cow;
+
+use crate::helpers::encode_length;
+
+/// Namespace builds namespaces at runtime, for collections created with `new_owned`,
+/// e.g. one map of balances per denom:
+///
+/// ```
+/// # use secret_storage_lite::{Map, Namespace};
+/// let denom = "uscrt";
+/// let balances: Map<&str, u128> = Map::new_owned(Namespace::new("balances").push(denom));
+/// ```
+///
+/// The root segment is used as is, so `Namespace::new("config")` is the same namespace as
+/// `Item::new("config")`. Every further segment is appended with its length, so the segments
+/// `("ab", "c")` and `("a", "bc")` never share a namespace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Namespace(Vec<u8>);
+
+impl Namespace {
+    pub fn new(root: impl AsRef<[u8]>) -> Self {
+        Namespace(root.as_ref().to_vec())
+    }
+
+    /// Appends another segment to the namespace
+    pub fn push(mut self, segment: impl AsRef<[u8]>) -> Self {
+        let segment = segment.as_ref();
+        self.0.extend_from_slice(&encode_length(segment));
+        self.0.extend_from_slice(segment);
+        self
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Namespace> for Vec<u8> {
+    fn from(namespace: Namespace) -> Self {
+        namespace.0
+    }
+}
+
+/// Declares collections as constants, and fails to compile if two of them conflict: if they
+/// have the same namespace, or one namespace is another followed by `_`, like the keys
+/// collections keep their counters under.
+///
+/// Every collection is created with a constructor taking its namespace as a string literal
+/// first, the namespace of a `BiMap` checked is its left one.
+///
+/// ```
+/// # use secret_storage_lite::{collections, queue::Queue, Item, Map};
+/// collections! {
+///     pub const CONFIG: Item<u32> = Item::new("config");
+///     const BALANCES: Map<&str, u128> = Map::new("balances");
+///     const PENDING: Queue<u64> = Queue::new("pending");
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use secret_storage_lite::{collections, queue::Queue, Item};
+/// collections! {
+///     const PENDING: Queue<u64> = Queue::new("pending");
+///     // the queue keeps its tail under this key
+///     const TAIL: Item<u32> = Item::new("pending_tail");
+/// }
+/// ```
+#[macro_export]
+macro_rules! collections {
+    ($(
+        $(#[$meta:meta])*
+        $vis:vis const $name:ident: $ty:ty = $($ctor:ident)::+($ns:literal $(, $arg:expr)* $(,)?);
+    )*) => {
+        $(
+            $(#[$meta])*
+            $vis const $name: $ty = $($ctor)::+($ns $(, $arg)*);
+        )*
+        const _: () = {
+            const NAMESPACES: &[&str] = &[$($ns),*];
+            $(
+                assert!(
+                    $crate::__private::namespace_conflicts(NAMESPACES, $ns) == 1,
+                    concat!("the namespace of ", stringify!($name), " conflicts with another collection")
+                );
+            )*
+        };
+    };
+}
+
+/// Returns how many of `namespaces` conflict with `namespace`, counting itself. With the
+/// `hashed-namespaces` feature, namespaces sharing a hash conflict too
+#[doc(hidden)]
+pub const fn namespace_conflicts(namespaces: &[&str], namespace: &str) -> usize {
+    let mut conflicts = 0;
+    let mut i = 0;
+    while i < namespaces.len() {
+        if conflict(namespaces[i].as_bytes(), namespace.as_bytes())
+            || conflict(namespace.as_bytes(), namespaces[i].as_bytes())
+            || (cfg!(feature = "hashed-namespaces")
+                && fnv1a(namespaces[i].as_bytes()) == fnv1a(namespace.as_bytes()))
+        {
+            conflicts += 1;
+        }
+        i += 1;
+    }
+    conflicts
+}
+
+/// Returns true if `b` is `a`, or `a` followed by `_`
+const fn conflict(a: &[u8], b: &[u8]) -> bool {
+    if b.len() < a.len() || (b.len() > a.len() && b[a.len()] != b'_') {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Hashes `namespace` to the 4 bytes a `Map` created with it is stored under with the
+/// `hashed-namespaces` feature, e.g. to build the keys of a raw query.
+///
+/// Two namespaces can share a hash, the `collections!` macro fails to compile if two of its
+/// collections do.
+pub const fn hash_namespace(namespace: &[u8]) -> [u8; 4] {
+    fnv1a(namespace).to_be_bytes()
+}
+
+/// The hash a map created with `namespace` is stored under, None unless the `hashed-namespaces`
+/// feature is enabled
+pub(crate) const fn hashed(namespace: &[u8]) -> Option<[u8; 4]> {
+    if cfg!(feature = "hashed-namespaces") {
+        Some(hash_namespace(namespace))
+    } else {
+        None
+    }
+}
+
+/// The namespace a map created with `namespace` is stored under
+#[cfg(feature = "iterator")]
+pub(crate) fn storage_namespace(namespace: &[u8]) -> Cow<'_, [u8]> {
+    match hashed(namespace) {
+        Some(hash) => Cow::Owned(hash.to_vec()),
+        None => Cow::Borrowed(namespace),
+    }
+}
+
+/// The 32 bit FNV-1a hash, simple enough for a const fn
+const fn fnv1a(bytes: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    hash
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cosmwasm_std::testing::MockStorage;
+
+    use crate::{Item, Map};
+
+    #[test]
+    fn segments_are_length_prefixed() {
+        assert_eq!(Namespace::new("config").as_slice(), b"config");
+        assert_eq!(
+            Namespace::new("balances").push("uscrt").as_slice(),
+            b"balances\x00\x05uscrt"
+        );
+        assert_ne!(
+            Namespace::new("x").push("ab").push("c"),
+            Namespace::new("x").push("a").push("bc")
+        );
+    }
+
+    #[test]
+    fn owned_collections_match_const_ones() {
+        const CONFIG: Item<u32> = Item::new("config");
+        const BALANCES: Map<&str, u64> = Map::new("balances");
+        let mut store = MockStorage::new();
+
+        CONFIG.save(&mut store, &7).unwrap();
+        let config: Item<u32> = Item::new_owned(Namespace::new("config"));
+        assert_eq!(config.load(&store).unwrap(), 7);
+
+        BALANCES.save(&mut store, "alice", &100).unwrap();
+        let balances: Map<&str, u64> = Map::new_owned(String::from("balances"));
+        assert_eq!(balances.load(&store, "alice").unwrap(), 100);
+    }
+
+    #[test]
+    fn runtime_namespaces_are_separate() {
+        let mut store = MockStorage::new();
+        let balances = |denom: &str| -> Map<&str, u64> {
+            Map::new_owned(Namespace::new("balances").push(denom))
+        };
+
+        balances("uscrt").save(&mut store, "alice", &100).unwrap();
+        balances("uatom").save(&mut store, "alice", &5).unwrap();
+        assert_eq!(balances("uscrt").load(&store, "alice").unwrap(), 100);
+        assert_eq!(balances("uatom").load(&store, "alice").unwrap(), 5);
+        assert_eq!(balances("ujuno").may_load(&store, "alice").unwrap(), None);
+    }
+
+    #[test]
+    fn finds_conflicting_namespaces() {
+        let namespaces = ["queue", "queue_tail", "queues", "config", "config"];
+        assert_eq!(namespace_conflicts(&namespaces, "queue"), 2);
+        assert_eq!(namespace_conflicts(&namespaces, "queue_tail"), 2);
+        assert_eq!(namespace_conflicts(&namespaces, "queues"), 1);
+        assert_eq!(namespace_conflicts(&namespaces, "config"), 2);
+    }
+
+    #[test]
+    fn hashes_are_fnv1a() {
+        assert_eq!(hash_namespace(b""), 0x811c_9dc5u32.to_be_bytes());
+        assert_eq!(hash_namespace(b"a"), 0xe40c_292cu32.to_be_bytes());
+        assert_ne!(hash_namespace(b"balances"), hash_namespace(b"balance"));
+    }
+
+    crate::collections! {
+        const DECLARED_CONFIG: Item<u32> = Item::new("declared_config");
+        /// collections take their other arguments after the namespace
+        pub(crate) const DECLARED_FILTER: crate::BloomFilter<&str> =
+            crate::BloomFilter::new("declared_filter", 64, 2);
+    }
+
+    #[test]
+    fn declared_collections_work() {
+        let mut store = MockStorage::new();
+        DECLARED_CONFIG.save(&mut store, &1).unwrap();
+        DECLARED_FILTER.insert(&mut store, "a");
+        assert!(DECLARED_FILTER.maybe_contains(&store, "a"));
+    }
+}
@@ -0,0 +1,181 @@
+//! Detects collections sharing storage by mistake, e.g. two `Map`s created with the same
+//! namespace for different types, or an `Item` whose key is also a counter of a `Queue`.
+//!
+//! Enabled by the `namespace-check` feature, which is meant for dev-dependencies only. Every key
+//! of an `Item` or counter, and every namespace of a `Map` or other path-based collection, is
+//! registered with its type when it is used. Using one for two types, or an `Item` key that lies
+//! inside a namespace, panics.
+//!
+//! The registry is kept per thread, so every test starts with an empty one. A migration reading a
+//! namespace as its old type and writing it as the new one can `forget` it in between, a test
+//! reading data as another type on purpose can turn the check off with `set_enabled`.
+
+use std::any::type_name;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use crate::error::to_hex;
+use crate::helpers::namespaces_with_key;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Usage {
+    /// a full storage key
+    Key,
+    /// a namespace, under which the keys are length-prefixed
+    Namespace,
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(true) };
+    static REGISTRY: RefCell<BTreeMap<(Usage, Vec<u8>), &'static str>> =
+        const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Turns the check on or off for the current thread
+pub fn set_enabled(enabled: bool) {
+    ENABLED.with(|e| e.set(enabled))
+}
+
+/// Forgets every key and namespace registered on the current thread
+pub fn clear() {
+    REGISTRY.with(|r| r.borrow_mut().clear())
+}
+
+/// Forgets `namespace`, used as a key or namespace, so it can be used for another type
+pub fn forget(namespace: &[u8]) {
+    REGISTRY.with(|r| {
+        let mut registry = r.borrow_mut();
+        registry.remove(&(Usage::Key, namespace.to_vec()));
+        registry.remove(&(Usage::Namespace, namespace.to_vec()));
+    })
+}
+
+/// Registers `bytes` as used for `T`, panics if it collides with another registered usage
+pub(crate) fn register<T: ?Sized>(usage: Usage, bytes: &[u8]) {
+    register_as(usage, bytes, type_name::<T>())
+}
+
+pub(crate) fn register_as(usage: Usage, bytes: &[u8], type_name: &'static str) {
+    if !ENABLED.with(Cell::get) {
+        return;
+    }
+    REGISTRY.with(|r| {
+        let mut registry = r.borrow_mut();
+        if let Some(registered) = registry.get(&(usage, bytes.to_vec())) {
+            if *registered != type_name {
+                collision(bytes, registered, type_name);
+            }
+            return;
+        }
+
+        // a key collides with a namespace if it starts like the keys stored under it
+        let colliding = match usage {
+            Usage::Key => registry
+                .iter()
+                .filter(|((u, _), _)| *u == Usage::Namespace)
+                .find(|((_, namespace), _)| {
+                    bytes.starts_with(&namespaces_with_key(&[namespace], b""))
+                }),
+            Usage::Namespace => {
+                let prefix = namespaces_with_key(&[bytes], b"");
+                registry
+                    .iter()
+                    .filter(|((u, _), _)| *u == Usage::Key)
+                    .find(|((_, key), _)| key.starts_with(&prefix))
+            }
+        };
+        if let Some(((_, other), registered)) = colliding {
+            panic!(
+                "{} at 0x{} collides with {} at 0x{}",
+                type_name,
+                to_hex(bytes),
+                registered,
+                to_hex(other)
+            );
+        }
+        registry.insert((usage, bytes.to_vec()), type_name);
+    })
+}
+
+fn collision(bytes: &[u8], registered: &str, type_name: &str) -> ! {
+    panic!(
+        "{:?} is used for both {} and {}",
+        String::from_utf8_lossy(bytes),
+        registered,
+        type_name
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    use crate::queue::Queue;
+    use crate::{Item, Map};
+
+    #[test]
+    fn same_collection_can_be_used_again() {
+        let mut store = MockStorage::new();
+        let balances: Map<&str, u64> = Map::new_unhashed("balances");
+        balances.save(&mut store, "alice", &1).unwrap();
+        balances.save(&mut store, "bob", &2).unwrap();
+        // the same namespace and type, e.g. a const used in two places
+        let again: Map<&str, u64> = Map::new_unhashed("balances");
+        assert_eq!(again.load(&store, "alice").unwrap(), 1);
+
+        let config: Item<u64> = Item::new("balances");
+        // keys of an item and namespaces of a map never overlap
+        config.save(&mut store, &3).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "\"balances\" is used for both")]
+    fn namespace_reused_for_another_type() {
+        let mut store = MockStorage::new();
+        let balances: Map<&str, u64> = Map::new_unhashed("balances");
+        let names: Map<&str, String> = Map::new_unhashed("balances");
+        balances.save(&mut store, "alice", &1).unwrap();
+        names
+            .save(&mut store, "alice", &"Alice".to_string())
+            .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "is used for both u32 counter and u32")]
+    fn item_on_a_counter() {
+        let mut store = MockStorage::new();
+        let queue: Queue<u64> = Queue::new("queue");
+        queue.push_back(&mut store, &1).unwrap();
+        let tail: Item<u32> = Item::new_owned([queue.namespace(), b"_tail"].concat());
+        tail.save(&mut store, &1).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "collides with")]
+    fn item_inside_a_namespace() {
+        let mut store = MockStorage::new();
+        let balances: Map<&str, u64> = Map::new_unhashed("balances");
+        balances.save(&mut store, "alice", &1).unwrap();
+        let item: Item<u64> = Item::new_owned(namespaces_with_key(&[b"balances"], b"alice"));
+        item.save(&mut store, &2).unwrap();
+    }
+
+    #[test]
+    fn forgotten_namespaces_can_change_type() {
+        let mut store = MockStorage::new();
+        Map::<&str, u32>::new_unhashed("v")
+            .save(&mut store, "a", &1)
+            .unwrap();
+        forget(b"v");
+        Map::<&str, u64>::new_unhashed("v")
+            .save(&mut store, "a", &1)
+            .unwrap();
+
+        clear();
+        set_enabled(false);
+        Map::<&str, u32>::new_unhashed("v")
+            .save(&mut store, "a", &1)
+            .unwrap();
+    }
+}
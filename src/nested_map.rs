@@ -0,0 +1,170 @@
+#[cfg(feature = "iterator")]
+use cosmwasm_std::Order;
+use cosmwasm_std::{StdError, StdResult, Storage};
+
+use crate::error::StorageResult;
+#[cfg(feature = "iterator")]
+use crate::helpers::nested_namespaces_with_key;
+use crate::keys::{KeyDeserialize, Prefixer, PrimaryKey};
+use crate::prefix::Prefix;
+use crate::serialization::{Bincode2, Serde};
+use crate::Map;
+
+/// NestedMap is a map of maps: the outer key `K1` selects a sub-map keyed by `K2`,
+/// e.g. the allowances of each owner by spender.
+///
+/// The entries are stored exactly like a `Map<(K1, K2), T>` under the same namespace,
+/// so an existing map with a pair key can be switched to a `NestedMap` without migrating.
+pub struct NestedMap<'a, K1, K2, T, Ser = Bincode2> {
+    map: Map<'a, (K1, K2), T, Ser>,
+}
+
+impl<'a, K1, K2, T, Ser> NestedMap<'a, K1, K2, T, Ser> {
+    pub const fn new(namespace: &'a str) -> Self {
+        NestedMap {
+            map: Map::new(namespace),
+        }
+    }
+
+    /// Creates a nested map under a namespace only known at runtime, e.g. one built with `Namespace`
+    pub fn new_owned(namespace: impl Into<Vec<u8>>) -> Self {
+        NestedMap {
+            map: Map::new_owned(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+}
+
+impl<'a, K1, K2, T, Ser> NestedMap<'a, K1, K2, T, Ser>
+where
+    K1: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+    K2: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<T>,
+{
+    /// Returns the sub-map stored under the outer key `k1`
+    pub fn sub_map(&self, k1: K1) -> Prefix<K2, T, K2, Ser> {
+        self.map.prefix(k1)
+    }
+
+    pub fn save(&self, store: &mut dyn Storage, k1: K1, k2: K2, data: &T) -> StorageResult<()> {
+        self.map.save(store, (k1, k2), data)
+    }
+
+    pub fn remove(&self, store: &mut dyn Storage, k1: K1, k2: K2) {
+        self.map.remove(store, (k1, k2))
+    }
+
+    /// load will return an error if no data is set at the given keys, or on parse error
+    pub fn load(&self, store: &dyn Storage, k1: K1, k2: K2) -> StdResult<T> {
+        Ok(self.map.load(store, (k1, k2))?)
+    }
+
+    /// may_load will parse the data stored at the keys if present, returns Ok(None) if no data there.
+    /// returns an error on issues parsing
+    pub fn may_load(&self, store: &dyn Storage, k1: K1, k2: K2) -> StdResult<Option<T>> {
+        Ok(self.map.may_load(store, (k1, k2))?)
+    }
+
+    /// has returns true or false if any data is at these keys, without parsing or interpreting the
+    /// contents.
+    pub fn has(&self, store: &dyn Storage, k1: K1, k2: K2) -> bool {
+        self.map.has(store, (k1, k2))
+    }
+
+    /// Loads the data, perform the specified action, and store the result
+    /// in the database. This is shorthand for some common sequences, which may be useful.
+    ///
+    /// If the data exists, `action(Some(value))` is called. Otherwise `action(None)` is called.
+    pub fn update<A, E>(&self, store: &mut dyn Storage, k1: K1, k2: K2, action: A) -> Result<T, E>
+    where
+        A: FnOnce(Option<T>) -> Result<T, E>,
+        E: From<StdError>,
+    {
+        self.map.update(store, (k1, k2), action)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, K1, K2, T, Ser> NestedMap<'a, K1, K2, T, Ser>
+where
+    K1: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+    K2: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<T>,
+{
+    /// Returns true if the sub-map under `k1` holds any entries
+    pub fn contains(&self, store: &dyn Storage, k1: K1) -> bool {
+        !self.sub_map(k1).is_empty(store)
+    }
+
+    /// remove_all removes every entry of the sub-map under `k1`, returns how many were removed
+    pub fn remove_all(&self, store: &mut dyn Storage, k1: K1) -> u32 {
+        let keys: Vec<Vec<u8>> = self
+            .sub_map(k1.clone())
+            .keys_raw(store, None, None, Order::Ascending)
+            .collect();
+        let prefix = nested_namespaces_with_key(&[self.namespace()], &k1.prefix(), b"");
+        for key in &keys {
+            store.remove(&[prefix.as_slice(), key].concat());
+        }
+        keys.len() as u32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const ALLOWANCES: NestedMap<&str, &str, u64> = NestedMap::new("allowances");
+
+    #[test]
+    fn save_and_load() {
+        let mut store = MockStorage::new();
+
+        ALLOWANCES.save(&mut store, "alice", "bob", &10).unwrap();
+        ALLOWANCES.save(&mut store, "alice", "carol", &20).unwrap();
+        ALLOWANCES.save(&mut store, "bob", "alice", &30).unwrap();
+
+        assert_eq!(ALLOWANCES.load(&store, "alice", "bob").unwrap(), 10);
+        assert_eq!(ALLOWANCES.may_load(&store, "bob", "carol").unwrap(), None);
+        assert_eq!(
+            ALLOWANCES.sub_map("alice").load(&store, "carol").unwrap(),
+            20
+        );
+
+        ALLOWANCES
+            .update(&mut store, "bob", "alice", |a| -> StdResult<u64> {
+                Ok(a.unwrap_or_default() + 1)
+            })
+            .unwrap();
+        assert_eq!(ALLOWANCES.load(&store, "bob", "alice").unwrap(), 31);
+
+        ALLOWANCES.remove(&mut store, "alice", "bob");
+        assert!(!ALLOWANCES.has(&store, "alice", "bob"));
+
+        // stored like a map with a pair key
+        let pairs: Map<(&str, &str), u64> = Map::new("allowances");
+        assert_eq!(pairs.load(&store, ("alice", "carol")).unwrap(), 20);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn remove_all_clears_one_sub_map() {
+        let mut store = MockStorage::new();
+
+        ALLOWANCES.save(&mut store, "alice", "bob", &10).unwrap();
+        ALLOWANCES.save(&mut store, "alice", "carol", &20).unwrap();
+        // shares a prefix with "alice", but is a different sub-map
+        ALLOWANCES.save(&mut store, "alicee", "bob", &30).unwrap();
+
+        assert!(ALLOWANCES.contains(&store, "alice"));
+        assert_eq!(ALLOWANCES.remove_all(&mut store, "alice"), 2);
+        assert!(!ALLOWANCES.contains(&store, "alice"));
+        assert_eq!(ALLOWANCES.remove_all(&mut store, "alice"), 0);
+
+        assert_eq!(ALLOWANCES.load(&store, "alicee", "bob").unwrap(), 30);
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt::Display;
+
+use cosmwasm_std::Uint128;
+
+/// Numeric is implemented by the value types `Map::add`, `Map::checked_sub` and
+/// `Map::sub_saturating` can do arithmetic on, typically token balances.
+pub trait Numeric: Copy + Default + Display {
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn saturating_sub(self, rhs: Self) -> Self;
+}
+
+macro_rules! numeric_primitive {
+    (for $($t:ty),+) => {
+        $(impl Numeric for $t {
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_sub(self, rhs)
+            }
+
+            fn saturating_sub(self, rhs: Self) -> Self {
+                <$t>::saturating_sub(self, rhs)
+            }
+        })*
+    }
+}
+
+numeric_primitive!(for u8, u16, u32, u64, u128);
+
+impl Numeric for Uint128 {
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Uint128)
+    }
+
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Uint128)
+    }
+
+    fn saturating_sub(self, rhs: Self) -> Self {
+        Uint128(self.0.saturating_sub(rhs.0))
+    }
+}
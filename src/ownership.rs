@@ -0,0 +1,232 @@
+use cosmwasm_std::{BlockInfo, HumanAddr as Addr, StdResult, Storage};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{StorageError, StorageResult};
+use crate::{Expiration, Item};
+
+/// The owner of a contract and the transfer of ownership waiting to be accepted, if any
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OwnershipState {
+    /// None once ownership is renounced
+    pub owner: Option<Addr>,
+    pub pending_owner: Option<Addr>,
+    /// When the pending transfer can no longer be accepted
+    pub pending_expiry: Option<Expiration>,
+}
+
+/// Ownership stores the owner of a contract, which is transferred in two steps: the owner
+/// proposes a new owner, which then has to accept. A typo in the new address can't lock the
+/// contract that way.
+pub struct Ownership<'a> {
+    item: Item<'a, OwnershipState>,
+}
+
+impl<'a> Ownership<'a> {
+    pub const fn new(storage_key: &'a str) -> Self {
+        Ownership {
+            item: Item::new(storage_key),
+        }
+    }
+
+    /// Sets the first owner, returns an error if ownership was initialized already
+    pub fn initialize(&self, store: &mut dyn Storage, owner: Addr) -> StorageResult<()> {
+        let state = OwnershipState {
+            owner: Some(owner),
+            ..OwnershipState::default()
+        };
+        self.item.init(store, &state)
+    }
+
+    /// Returns the ownership state, with no owner if it was never initialized
+    pub fn load(&self, store: &dyn Storage) -> StdResult<OwnershipState> {
+        Ok(self.item.load_or_default(store)?)
+    }
+
+    /// Returns the owner, None if there is none
+    pub fn owner(&self, store: &dyn Storage) -> StdResult<Option<Addr>> {
+        Ok(self.load(store)?.owner)
+    }
+
+    /// Returns `StorageError::NotOwner` unless `sender` is the owner
+    pub fn assert_owner(&self, store: &dyn Storage, sender: &Addr) -> StorageResult<()> {
+        self.load(store)?.assert_owner(sender)
+    }
+
+    /// Proposes `new_owner` as the owner, replacing any pending transfer.
+    /// Only the owner can transfer ownership
+    pub fn transfer(
+        &self,
+        store: &mut dyn Storage,
+        block: &BlockInfo,
+        sender: &Addr,
+        new_owner: Addr,
+        expiry: Option<Expiration>,
+    ) -> StorageResult<OwnershipState> {
+        let mut state = self.load(store)?;
+        state.assert_owner(sender)?;
+        if expiry.is_some_and(|expiry| expiry.is_expired(block)) {
+            return Err(StorageError::TransferExpired);
+        }
+        state.pending_owner = Some(new_owner);
+        state.pending_expiry = expiry;
+        self.item.save(store, &state)?;
+        Ok(state)
+    }
+
+    /// Makes `sender` the owner, if it is the pending owner and the transfer hasn't expired
+    pub fn accept(
+        &self,
+        store: &mut dyn Storage,
+        block: &BlockInfo,
+        sender: &Addr,
+    ) -> StorageResult<OwnershipState> {
+        let mut state = self.load(store)?;
+        if state.pending_owner.as_ref() != Some(sender) {
+            return Err(StorageError::NoPendingTransfer);
+        }
+        if state
+            .pending_expiry
+            .is_some_and(|expiry| expiry.is_expired(block))
+        {
+            return Err(StorageError::TransferExpired);
+        }
+        state.owner = state.pending_owner.take();
+        state.pending_expiry = None;
+        self.item.save(store, &state)?;
+        Ok(state)
+    }
+
+    /// Leaves the contract without an owner, for good. Only the owner can renounce ownership
+    pub fn renounce(
+        &self,
+        store: &mut dyn Storage,
+        sender: &Addr,
+    ) -> StorageResult<OwnershipState> {
+        let state = self.load(store)?;
+        state.assert_owner(sender)?;
+        let state = OwnershipState::default();
+        self.item.save(store, &state)?;
+        Ok(state)
+    }
+}
+
+impl OwnershipState {
+    /// Returns `StorageError::NotOwner` unless `sender` is the owner
+    pub fn assert_owner(&self, sender: &Addr) -> StorageResult<()> {
+        match &self.owner {
+            Some(owner) if owner == sender => Ok(()),
+            _ => Err(StorageError::NotOwner),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::{mock_env, MockStorage};
+
+    const OWNERSHIP: Ownership = Ownership::new("ownership");
+
+    fn block(height: u64) -> BlockInfo {
+        let mut block = mock_env("creator", &[]).block;
+        block.height = height;
+        block
+    }
+
+    fn addr(s: &str) -> Addr {
+        Addr::from(s)
+    }
+
+    #[test]
+    fn transfers_in_two_steps() {
+        let mut store = MockStorage::new();
+        OWNERSHIP.initialize(&mut store, addr("alice")).unwrap();
+        assert!(OWNERSHIP.initialize(&mut store, addr("bob")).is_err());
+        OWNERSHIP.assert_owner(&store, &addr("alice")).unwrap();
+        assert_eq!(
+            OWNERSHIP.assert_owner(&store, &addr("bob")).unwrap_err(),
+            StorageError::NotOwner
+        );
+
+        assert!(OWNERSHIP
+            .transfer(&mut store, &block(1), &addr("bob"), addr("bob"), None)
+            .is_err());
+        OWNERSHIP
+            .transfer(
+                &mut store,
+                &block(1),
+                &addr("alice"),
+                addr("bob"),
+                Some(Expiration::AtHeight(10)),
+            )
+            .unwrap();
+        // alice stays the owner until bob accepts
+        OWNERSHIP.assert_owner(&store, &addr("alice")).unwrap();
+        assert!(OWNERSHIP
+            .accept(&mut store, &block(2), &addr("carol"))
+            .is_err());
+
+        let state = OWNERSHIP
+            .accept(&mut store, &block(9), &addr("bob"))
+            .unwrap();
+        assert_eq!(
+            state,
+            OwnershipState {
+                owner: Some(addr("bob")),
+                pending_owner: None,
+                pending_expiry: None,
+            }
+        );
+        assert_eq!(OWNERSHIP.load(&store).unwrap(), state);
+        assert!(OWNERSHIP.assert_owner(&store, &addr("alice")).is_err());
+    }
+
+    #[test]
+    fn transfers_expire() {
+        let mut store = MockStorage::new();
+        OWNERSHIP.initialize(&mut store, addr("alice")).unwrap();
+        assert!(OWNERSHIP
+            .transfer(
+                &mut store,
+                &block(10),
+                &addr("alice"),
+                addr("bob"),
+                Some(Expiration::AtHeight(10)),
+            )
+            .is_err());
+
+        OWNERSHIP
+            .transfer(
+                &mut store,
+                &block(5),
+                &addr("alice"),
+                addr("bob"),
+                Some(Expiration::AtHeight(10)),
+            )
+            .unwrap();
+        assert_eq!(
+            OWNERSHIP
+                .accept(&mut store, &block(10), &addr("bob"))
+                .unwrap_err(),
+            StorageError::TransferExpired
+        );
+    }
+
+    #[test]
+    fn renounces() {
+        let mut store = MockStorage::new();
+        OWNERSHIP.initialize(&mut store, addr("alice")).unwrap();
+        OWNERSHIP
+            .transfer(&mut store, &block(1), &addr("alice"), addr("bob"), None)
+            .unwrap();
+        assert!(OWNERSHIP.renounce(&mut store, &addr("bob")).is_err());
+
+        OWNERSHIP.renounce(&mut store, &addr("alice")).unwrap();
+        assert_eq!(OWNERSHIP.owner(&store).unwrap(), None);
+        // the pending transfer is cancelled too
+        assert!(OWNERSHIP
+            .accept(&mut store, &block(2), &addr("bob"))
+            .is_err());
+        assert!(OWNERSHIP.assert_owner(&store, &addr("alice")).is_err());
+    }
+}
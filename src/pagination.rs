@@ -0,0 +1,153 @@
+//! Helpers for the paginated list queries most contracts answer, returning a `Page` of entries
+//! and the key to pass as `start_after` to get the next one.
+
+#[cfg(feature = "iterator")]
+use cosmwasm_std::Order;
+use cosmwasm_std::{StdResult, Storage};
+use serde::{Deserialize, Serialize};
+
+use crate::keys::{KeyDeserialize, PrimaryKey};
+#[cfg(feature = "iterator")]
+use crate::prefix::Bound;
+use crate::serialization::Serde;
+use crate::IterableMap;
+#[cfg(feature = "iterator")]
+use crate::Map;
+
+/// A page of entries, as returned by a list query
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Page<K, T> {
+    pub items: Vec<(K, T)>,
+    /// The key to start after to get the next page, None if this is the last page
+    pub next_key: Option<K>,
+}
+
+/// Returns the limit to use for a query, `default` if none is given and at most `max`
+pub fn clamp_limit(limit: Option<u32>, default: u32, max: u32) -> u32 {
+    limit.unwrap_or(default).min(max)
+}
+
+/// Returns up to `limit` entries of `map` in key order, starting after `start_after`
+#[cfg(feature = "iterator")]
+pub fn paginate_map<'a, K, T, Ser>(
+    map: &Map<'a, K, T, Ser>,
+    store: &dyn Storage,
+    start_after: Option<K>,
+    limit: u32,
+) -> StdResult<Page<K::Output, T>>
+where
+    K: PrimaryKey<'a> + KeyDeserialize,
+    K::Output: Clone + 'static,
+    Ser: Serde<T>,
+{
+    let min = start_after.map(Bound::exclusive);
+    let mut items = map
+        .range(store, min, None, Order::Ascending)
+        .take(limit as usize + 1)
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(page(&mut items, limit))
+}
+
+/// Returns up to `limit` entries of `map` in index order, starting after `start_after`.
+///
+/// Returns an error if `start_after` is not in the map. Removing an entry moves the last one
+/// into its place, so entries removed or added while paging may be skipped or returned twice.
+pub fn paginate_iterable_map<'a, K, T, Ser>(
+    map: &IterableMap<'a, K, T, Ser>,
+    store: &dyn Storage,
+    start_after: Option<K>,
+    limit: u32,
+) -> StdResult<Page<K::Output, T>>
+where
+    K: PrimaryKey<'a> + KeyDeserialize,
+    K::Output: Clone,
+    Ser: Serde<T>,
+{
+    let start = match start_after {
+        Some(k) => map.index_of(store, k)? + 1,
+        None => 0,
+    };
+    let mut items = map
+        .iter(store)
+        .skip(start as usize)
+        .take(limit as usize + 1)
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(page(&mut items, limit))
+}
+
+/// Builds the page from up to `limit + 1` items, the extra item only tells there is a next page
+pub(crate) fn page<K: Clone, T>(items: &mut Vec<(K, T)>, limit: u32) -> Page<K, T> {
+    let next_key = if items.len() > limit as usize {
+        items.truncate(limit as usize);
+        items.last().map(|(k, _)| k.clone())
+    } else {
+        None
+    };
+    Page {
+        items: std::mem::take(items),
+        next_key,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn pages_through_a_map() {
+        let mut store = MockStorage::new();
+        let balances: Map<&str, u64> = Map::new("balances");
+        for (i, name) in ["alice", "bob", "carol", "dave", "eve"].iter().enumerate() {
+            balances.save(&mut store, name, &(i as u64)).unwrap();
+        }
+
+        let first = paginate_map(&balances, &store, None, 2).unwrap();
+        assert_eq!(
+            first.items,
+            vec![("alice".to_string(), 0), ("bob".to_string(), 1)]
+        );
+        assert_eq!(first.next_key, Some("bob".to_string()));
+
+        let second = paginate_map(&balances, &store, Some("bob"), 2).unwrap();
+        assert_eq!(second.next_key, Some("dave".to_string()));
+        let last = paginate_map(&balances, &store, Some("dave"), 2).unwrap();
+        assert_eq!(last.items, vec![("eve".to_string(), 4)]);
+        assert_eq!(last.next_key, None);
+
+        // an exactly full last page has no next page
+        let all = paginate_map(&balances, &store, None, 5).unwrap();
+        assert_eq!(all.items.len(), 5);
+        assert_eq!(all.next_key, None);
+    }
+
+    #[test]
+    fn pages_through_an_iterable_map() {
+        let mut store = MockStorage::new();
+        let members: IterableMap<&str, u32> = IterableMap::new("members");
+        for (i, name) in ["carol", "alice", "bob"].iter().enumerate() {
+            members.save(&mut store, name, &(i as u32)).unwrap();
+        }
+
+        let first = paginate_iterable_map(&members, &store, None, 2).unwrap();
+        assert_eq!(
+            first.items,
+            vec![("carol".to_string(), 0), ("alice".to_string(), 1)]
+        );
+        assert_eq!(first.next_key, Some("alice".to_string()));
+
+        let last = paginate_iterable_map(&members, &store, Some("alice"), 2).unwrap();
+        assert_eq!(last.items, vec![("bob".to_string(), 2)]);
+        assert_eq!(last.next_key, None);
+
+        assert!(paginate_iterable_map(&members, &store, Some("dave"), 2).is_err());
+    }
+
+    #[test]
+    fn clamps_limits() {
+        assert_eq!(clamp_limit(None, 10, 30), 10);
+        assert_eq!(clamp_limit(Some(5), 10, 30), 5);
+        assert_eq!(clamp_limit(Some(100), 10, 30), 30);
+    }
+}
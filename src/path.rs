@@ -1,26 +1,65 @@
-use serde::de::DeserializeOwned;
-use serde::Serialize;
+use std::any::type_name;
 use std::marker::PhantomData;
 
-use crate::helpers::{may_deserialize, must_deserialize, nested_namespaces_with_key, serialize};
+use crate::error::StorageError;
+use crate::helpers::{
+    may_deserialize, must_deserialize, nested_namespaces_with_key, split_namespace,
+};
 use crate::keys::Key;
+use crate::serialization::{Bincode2, Serde};
 use cosmwasm_std::{StdError, StdResult, Storage};
+use std::fmt;
 use std::ops::Deref;
 
-#[derive(Debug, Clone)]
-pub struct Path<T>
+/// Path is the full storage key of a single value, e.g. a `Map` entry returned by `Map::key`.
+///
+/// It can be built detached from any collection, from raw namespace and key segments with
+/// `new`, or from a full storage key with `from_storage_key`.
+pub struct Path<T, Ser = Bincode2>
 where
-    T: Serialize + DeserializeOwned,
+    Ser: Serde<T>,
 {
     /// all namespaces prefixes and concatenated with the key
     pub(crate) storage_key: Vec<u8>,
     // see https://doc.rust-lang.org/std/marker/struct.PhantomData.html#unused-type-parameters for why this is needed
     data: PhantomData<T>,
+    serialization_type: PhantomData<Ser>,
 }
 
-impl<T> Deref for Path<T>
+impl<T, Ser> Clone for Path<T, Ser>
 where
-    T: Serialize + DeserializeOwned,
+    Ser: Serde<T>,
+{
+    fn clone(&self) -> Self {
+        Self::from_storage_key(self.storage_key.clone())
+    }
+}
+
+impl<T, Ser> PartialEq for Path<T, Ser>
+where
+    Ser: Serde<T>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.storage_key == other.storage_key
+    }
+}
+
+impl<T, Ser> Eq for Path<T, Ser> where Ser: Serde<T> {}
+
+impl<T, Ser> fmt::Debug for Path<T, Ser>
+where
+    Ser: Serde<T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Path")
+            .field("storage_key", &self.storage_key)
+            .finish()
+    }
+}
+
+impl<T, Ser> Deref for Path<T, Ser>
+where
+    Ser: Serde<T>,
 {
     type Target = [u8];
 
@@ -29,11 +68,15 @@ where
     }
 }
 
-impl<T> Path<T>
+impl<T, Ser> Path<T, Ser>
 where
-    T: Serialize + DeserializeOwned,
+    Ser: Serde<T>,
 {
+    /// Builds the path of the value at `keys` under `namespace`, all keys but the last are
+    /// length-prefixed like the parts of a composite key
     pub fn new(namespace: &[u8], keys: &[&[u8]]) -> Self {
+        #[cfg(feature = "namespace-check")]
+        crate::namespace_check::register::<T>(crate::namespace_check::Usage::Namespace, namespace);
         let l = keys.len();
         // FIXME: make this more efficient
         let storage_key = nested_namespaces_with_key(
@@ -44,15 +87,26 @@ where
                 .collect::<Vec<Key>>(),
             keys[l - 1],
         );
+        Self::from_storage_key(storage_key)
+    }
+
+    /// Builds the path of the value stored at the full `storage_key`
+    pub fn from_storage_key(storage_key: impl Into<Vec<u8>>) -> Self {
         Path {
-            storage_key,
+            storage_key: storage_key.into(),
             data: PhantomData,
+            serialization_type: PhantomData,
         }
     }
 
+    /// Returns the full storage key, e.g. for a raw query
+    pub fn storage_key(&self) -> &[u8] {
+        &self.storage_key
+    }
+
     /// save will serialize the model and store, returns an error on serialization issues
     pub fn save(&self, store: &mut dyn Storage, data: &T) -> StdResult<()> {
-        store.set(&self.storage_key, &serialize(data)?);
+        store.set(&self.storage_key, &Ser::serialize(data)?);
         Ok(())
     }
 
@@ -63,14 +117,16 @@ where
     /// load will return an error if no data is set at the given key, or on parse error
     pub fn load(&self, store: &dyn Storage) -> StdResult<T> {
         let value = store.get(&self.storage_key);
-        must_deserialize(&value)
+        let (namespace, key) = split_namespace(&self.storage_key);
+        Ok(must_deserialize::<T, Ser>(&value, namespace, key)?)
     }
 
     /// may_load will parse the data stored at the key if present, returns Ok(None) if no data there.
     /// returns an error on issues parsing
     pub fn may_load(&self, store: &dyn Storage) -> StdResult<Option<T>> {
         let value = store.get(&self.storage_key);
-        may_deserialize(&value)
+        let (namespace, key) = split_namespace(&self.storage_key);
+        Ok(may_deserialize::<T, Ser>(&value, namespace, key)?)
     }
 
     /// has returns true or false if any data is at this key, without parsing or interpreting the
@@ -79,6 +135,30 @@ where
         store.get(&self.storage_key).is_some()
     }
 
+    /// save_raw stores `data` as is, e.g. an already encoded protobuf message or a ciphertext
+    pub fn save_raw(&self, store: &mut dyn Storage, data: &[u8]) {
+        store.set(&self.storage_key, data);
+    }
+
+    /// load_raw returns the stored bytes without parsing them,
+    /// returns an error if no data is set at the given key
+    pub fn load_raw(&self, store: &dyn Storage) -> StdResult<Vec<u8>> {
+        self.may_load_raw(store).ok_or_else(|| {
+            let (namespace, key) = split_namespace(&self.storage_key);
+            StorageError::NotFound {
+                type_name: type_name::<T>(),
+                namespace: String::from_utf8_lossy(namespace).into_owned(),
+                key: key.to_vec(),
+            }
+            .into()
+        })
+    }
+
+    /// may_load_raw returns the stored bytes without parsing them, None if no data is set
+    pub fn may_load_raw(&self, store: &dyn Storage) -> Option<Vec<u8>> {
+        store.get(&self.storage_key)
+    }
+
     /// Loads the data, perform the specified action, and store the result
     /// in the database. This is shorthand for some common sequences, which may be useful.
     ///
@@ -108,3 +188,31 @@ where
         Ok(output)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    use crate::Map;
+
+    const ALLOWANCE: Map<(&str, &str), u64> = Map::new_unhashed("allow");
+
+    #[test]
+    fn detached_paths() {
+        let mut store = MockStorage::new();
+        let path = ALLOWANCE.key(("owner", "spender"));
+        assert_eq!(path.storage_key(), b"\x00\x05allow\x00\x05ownerspender");
+
+        let detached: Path<u64> = Path::new(b"allow", &[b"owner", b"spender"]);
+        assert_eq!(detached, path);
+        assert_eq!(
+            Path::<u64>::from_storage_key(path.storage_key()),
+            path.clone()
+        );
+        assert_ne!(detached, ALLOWANCE.key(("owner", "other")));
+
+        detached.save(&mut store, &5).unwrap();
+        assert_eq!(ALLOWANCE.load(&store, ("owner", "spender")).unwrap(), 5);
+    }
+}
@@ -0,0 +1,188 @@
+use std::marker::PhantomData;
+
+use cosmwasm_std::{StdResult, Storage};
+
+use crate::error::{StorageError, StorageResult};
+use crate::helpers::namespaces_with_key;
+use crate::path::Path;
+use crate::queue::{load_u32, save_u32, Queue};
+use crate::serialization::{Bincode2, Serde};
+
+/// PayloadQueue is a `Queue` for large items: its slots hold only the id of an item, and the
+/// item itself, the payload, is stored under the id in a separate sub-namespace.
+///
+/// `pop_front_id` pops the id without reading the payload, so a handler can take an item off the
+/// queue and process its payload later, or in parts, with `load_payload` and `delete_payload`.
+/// A payload whose id was popped stays in storage until it is deleted. Ids are never reused.
+pub struct PayloadQueue<'a, T, Ser = Bincode2> {
+    ids: Queue<'a, u32>,
+    payload_type: PhantomData<T>,
+    serialization_type: PhantomData<Ser>,
+}
+
+impl<'a, T, Ser> PayloadQueue<'a, T, Ser> {
+    const PAYLOADS: &'static [u8] = b"_payloads";
+    const NEXT_ID: &'static [u8] = b"_next_id";
+
+    /// Creates a PayloadQueue with capacity `u32::MAX - 1`
+    pub const fn new(namespace: &'a str) -> Self {
+        Self::with_capacity(namespace, u32::MAX - 1)
+    }
+
+    /// Creates a PayloadQueue holding at most `capacity` items,
+    /// panics if `capacity` is zero or `u32::MAX` like `Queue::with_capacity`
+    pub const fn with_capacity(namespace: &'a str, capacity: u32) -> Self {
+        PayloadQueue {
+            ids: Queue::with_capacity(namespace, capacity),
+            payload_type: PhantomData,
+            serialization_type: PhantomData,
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.ids.namespace()
+    }
+
+    pub fn max_capacity(&self) -> u32 {
+        self.ids.max_capacity()
+    }
+
+    /// Returns the number of items in the queue, not counting payloads whose id was popped
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.ids.len(store)
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    pub fn is_full(&self, store: &dyn Storage) -> bool {
+        self.ids.is_full(store)
+    }
+
+    /// Pops the id at the front of the queue, leaving its payload in storage,
+    /// returns None if the queue is empty
+    pub fn pop_front_id(&self, store: &mut dyn Storage) -> StorageResult<Option<u32>> {
+        self.ids.pop_front(store)
+    }
+
+    /// Returns the id at the front of the queue without removing it
+    pub fn peek_front_id(&self, store: &dyn Storage) -> StdResult<Option<u32>> {
+        Ok(self.ids.peek_front(store)?)
+    }
+
+    /// Deletes the payload stored under `id`, once it has been processed
+    pub fn delete_payload(&self, store: &mut dyn Storage, id: u32) {
+        store.remove(&self.payload_key(id));
+    }
+
+    /// The full storage key of the payload stored under `id`
+    fn payload_key(&self, id: u32) -> Vec<u8> {
+        self.with_namespace_suffix(Self::PAYLOADS, |ns| {
+            namespaces_with_key(&[ns], &id.to_be_bytes())
+        })
+    }
+
+    fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
+        let namespace = &[self.namespace(), namespace].concat();
+        f(namespace)
+    }
+}
+
+impl<'a, T, Ser> PayloadQueue<'a, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    fn payload(&self, id: u32) -> Path<T, Ser> {
+        Path::from_storage_key(self.payload_key(id))
+    }
+
+    /// Adds an item to the back of the queue, returns its id, or None if the queue is full
+    pub fn push_back(&self, store: &mut dyn Storage, t: &T) -> StorageResult<Option<u32>> {
+        if self.is_full(store) {
+            return Ok(None);
+        }
+        let id = self.with_namespace_suffix(Self::NEXT_ID, |ns| load_u32(store, ns));
+        if id == u32::MAX {
+            return Err(StorageError::CapacityExceeded {
+                collection: "PayloadQueue",
+            });
+        }
+
+        self.payload(id).save(store, t)?;
+        self.ids.push_back(store, &id)?;
+        self.with_namespace_suffix(Self::NEXT_ID, |ns| save_u32(store, ns, id + 1));
+        Ok(Some(id))
+    }
+
+    /// Pops the item at the front of the queue and deletes its payload,
+    /// returns None if the queue is empty
+    pub fn pop_front(&self, store: &mut dyn Storage) -> StdResult<Option<T>> {
+        let id = match self.pop_front_id(store)? {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        let payload = self.load_payload(store, id)?;
+        self.delete_payload(store, id);
+        Ok(Some(payload))
+    }
+
+    /// Returns the payload stored under `id`, or `StorageError::NotFound` if it was deleted
+    pub fn load_payload(&self, store: &dyn Storage, id: u32) -> StdResult<T> {
+        self.payload(id).load(store)
+    }
+
+    /// Returns the payload stored under `id`, None if it was deleted
+    pub fn may_load_payload(&self, store: &dyn Storage, id: u32) -> StdResult<Option<T>> {
+        self.payload(id).may_load(store)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const REPORTS: PayloadQueue<Vec<u8>> = PayloadQueue::with_capacity("reports", 2);
+
+    #[test]
+    fn defers_payloads() {
+        let mut store = MockStorage::new();
+        assert_eq!(
+            REPORTS.push_back(&mut store, &vec![1; 1000]).unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            REPORTS.push_back(&mut store, &vec![2; 1000]).unwrap(),
+            Some(1)
+        );
+        assert_eq!(REPORTS.push_back(&mut store, &vec![3]).unwrap(), None);
+
+        assert_eq!(REPORTS.peek_front_id(&store).unwrap(), Some(0));
+        let id = REPORTS.pop_front_id(&mut store).unwrap().unwrap();
+        assert_eq!(REPORTS.len(&store), 1);
+        // popping the id frees its slot but keeps the payload
+        assert_eq!(REPORTS.push_back(&mut store, &vec![3]).unwrap(), Some(2));
+        assert_eq!(REPORTS.load_payload(&store, id).unwrap(), vec![1; 1000]);
+
+        REPORTS.delete_payload(&mut store, id);
+        assert!(REPORTS.load_payload(&store, id).is_err());
+        assert_eq!(REPORTS.may_load_payload(&store, id).unwrap(), None);
+    }
+
+    #[test]
+    fn pop_front_deletes_the_payload() {
+        let mut store = MockStorage::new();
+        REPORTS.push_back(&mut store, &vec![1]).unwrap();
+        REPORTS.push_back(&mut store, &vec![2]).unwrap();
+
+        assert_eq!(REPORTS.pop_front(&mut store).unwrap(), Some(vec![1]));
+        assert_eq!(REPORTS.may_load_payload(&store, 0).unwrap(), None);
+        assert_eq!(REPORTS.pop_front(&mut store).unwrap(), Some(vec![2]));
+        assert_eq!(REPORTS.pop_front(&mut store).unwrap(), None);
+        assert!(REPORTS.is_empty(&store));
+
+        // ids are not reused
+        assert_eq!(REPORTS.push_back(&mut store, &vec![3]).unwrap(), Some(2));
+    }
+}
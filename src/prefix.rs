@@ -0,0 +1,470 @@
+use std::marker::PhantomData;
+
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, KV};
+use cosmwasm_std::{StdResult, Storage};
+
+#[cfg(feature = "iterator")]
+use crate::error::{StorageError, StorageResult};
+use crate::helpers::{
+    may_deserialize, must_deserialize, nested_namespaces_with_key, split_namespace,
+};
+#[cfg(feature = "iterator")]
+use crate::iter_helpers::{deserialize_kv, deserialize_v, range_with_prefix};
+use crate::keys::{Key, KeyDeserialize, PrimaryKey};
+#[cfg(feature = "iterator")]
+use crate::numeric::Numeric;
+use crate::serialization::{Bincode2, Serde};
+
+/// RawBound is used to define the two ends of a range over the raw, joined key bytes.
+/// Inclusive means we use the given bytes as a limit and *include* anything at that exact key
+/// Exclusive means we use the given bytes as a limit and *exclude* anything at that exact key
+#[cfg(feature = "iterator")]
+#[derive(Clone, Debug)]
+pub enum RawBound {
+    Inclusive(Vec<u8>),
+    Exclusive(Vec<u8>),
+}
+
+/// Bound is used to define the two ends of a range, more explicit than Option<K>.
+/// None means that we don't limit that side of the range at all.
+/// Inclusive means we use the given key as a limit and *include* anything at that exact key
+/// Exclusive means we use the given key as a limit and *exclude* anything at that exact key
+/// The Raw variants take already joined key bytes, for when the typed key isn't at hand.
+#[cfg(feature = "iterator")]
+#[derive(Clone, Debug)]
+pub enum Bound<'a, K: PrimaryKey<'a>> {
+    Inclusive((K, PhantomData<&'a bool>)),
+    Exclusive((K, PhantomData<&'a bool>)),
+    InclusiveRaw(Vec<u8>),
+    ExclusiveRaw(Vec<u8>),
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, K: PrimaryKey<'a>> Bound<'a, K> {
+    pub fn inclusive<T: Into<K>>(limit: T) -> Self {
+        Self::Inclusive((limit.into(), PhantomData))
+    }
+
+    pub fn exclusive<T: Into<K>>(limit: T) -> Self {
+        Self::Exclusive((limit.into(), PhantomData))
+    }
+
+    pub fn to_raw_bound(&self) -> RawBound {
+        match self {
+            Bound::Inclusive((k, _)) => RawBound::Inclusive(k.joined_key()),
+            Bound::Exclusive((k, _)) => RawBound::Exclusive(k.joined_key()),
+            Bound::ExclusiveRaw(raw_k) => RawBound::Exclusive(raw_k.clone()),
+            Bound::InclusiveRaw(raw_k) => RawBound::Inclusive(raw_k.clone()),
+        }
+    }
+}
+
+/// Prefix is a handle on all the entries stored under one namespace (and optionally some
+/// leading parts of a composite key). The entries can be accessed by the remaining key `B`,
+/// and with the `iterator` feature iterated over in either direction, using `B` to limit the range.
+#[derive(Debug, Clone)]
+pub struct Prefix<K, T, B = Vec<u8>, Ser = Bincode2>
+where
+    K: KeyDeserialize,
+{
+    /// all namespaces prefixes and concatenated with the key
+    storage_prefix: Vec<u8>,
+    // see https://doc.rust-lang.org/std/marker/struct.PhantomData.html#unused-type-parameters for why this is needed
+    data: PhantomData<(K, T, B, Ser)>,
+}
+
+impl<'b, K, T, B, Ser> Prefix<K, T, B, Ser>
+where
+    K: KeyDeserialize,
+    B: PrimaryKey<'b>,
+    Ser: Serde<T>,
+{
+    pub fn new(top_name: &[u8], sub_names: &[Key]) -> Self {
+        let storage_prefix = nested_namespaces_with_key(&[top_name], sub_names, b"");
+        Prefix {
+            storage_prefix,
+            data: PhantomData,
+        }
+    }
+
+    /// The full storage key of the entry under the remaining key `k`
+    fn storage_key(&self, k: B) -> Vec<u8> {
+        [self.storage_prefix.as_slice(), &k.joined_key()].concat()
+    }
+
+    /// save will serialize the model and store it under the remaining key `k`,
+    /// returns an error on serialization issues
+    pub fn save(&self, store: &mut dyn Storage, k: B, data: &T) -> StdResult<()> {
+        store.set(&self.storage_key(k), &Ser::serialize(data)?);
+        Ok(())
+    }
+
+    pub fn remove(&self, store: &mut dyn Storage, k: B) {
+        store.remove(&self.storage_key(k));
+    }
+
+    /// load will return an error if no data is set at the given key, or on parse error
+    pub fn load(&self, store: &dyn Storage, k: B) -> StdResult<T> {
+        let storage_key = self.storage_key(k);
+        let (namespace, key) = split_namespace(&storage_key);
+        Ok(must_deserialize::<T, Ser>(
+            &store.get(&storage_key),
+            namespace,
+            key,
+        )?)
+    }
+
+    /// may_load will parse the data stored at the key if present, returns Ok(None) if no data there.
+    /// returns an error on issues parsing
+    pub fn may_load(&self, store: &dyn Storage, k: B) -> StdResult<Option<T>> {
+        let storage_key = self.storage_key(k);
+        let (namespace, key) = split_namespace(&storage_key);
+        Ok(may_deserialize::<T, Ser>(
+            &store.get(&storage_key),
+            namespace,
+            key,
+        )?)
+    }
+
+    /// has returns true or false if any data is at this key, without parsing or interpreting the
+    /// contents.
+    pub fn has(&self, store: &dyn Storage, k: B) -> bool {
+        store.get(&self.storage_key(k)).is_some()
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'b, K, T, B, Ser> Prefix<K, T, B, Ser>
+where
+    K: KeyDeserialize,
+    B: PrimaryKey<'b>,
+    Ser: Serde<T>,
+{
+    /// range_raw returns the raw (remaining) keys along with the deserialized values
+    pub fn range_raw<'a>(
+        &self,
+        store: &'a dyn Storage,
+        min: Option<Bound<'b, B>>,
+        max: Option<Bound<'b, B>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<KV<T>>> + 'a>
+    where
+        T: 'a,
+    {
+        // a fn pointer doesn't capture `Ser`, so the backend doesn't need to outlive the store
+        let de_fn: fn(KV) -> StdResult<KV<T>> = deserialize_v::<T, Ser>;
+        let mapped = range_with_prefix(
+            store,
+            &self.storage_prefix,
+            min.map(|b| b.to_raw_bound()),
+            max.map(|b| b.to_raw_bound()),
+            order,
+        )
+        .map(de_fn);
+        Box::new(mapped)
+    }
+
+    /// keys_raw returns the raw (remaining) keys only, without parsing the values
+    pub fn keys_raw<'a>(
+        &self,
+        store: &'a dyn Storage,
+        min: Option<Bound<'b, B>>,
+        max: Option<Bound<'b, B>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + 'a> {
+        let mapped = range_with_prefix(
+            store,
+            &self.storage_prefix,
+            min.map(|b| b.to_raw_bound()),
+            max.map(|b| b.to_raw_bound()),
+            order,
+        )
+        .map(|(k, _)| k);
+        Box::new(mapped)
+    }
+
+    /// range returns the deserialized (remaining) keys along with the deserialized values
+    pub fn range<'a>(
+        &self,
+        store: &'a dyn Storage,
+        min: Option<Bound<'b, B>>,
+        max: Option<Bound<'b, B>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'a>
+    where
+        T: 'a,
+        K::Output: 'static,
+    {
+        // a fn pointer only captures `K::Output`, so `K` itself doesn't need to outlive the store
+        let de_fn: fn(KV) -> StdResult<(K::Output, T)> = deserialize_kv::<K, T, Ser>;
+        let mapped = range_with_prefix(
+            store,
+            &self.storage_prefix,
+            min.map(|b| b.to_raw_bound()),
+            max.map(|b| b.to_raw_bound()),
+            order,
+        )
+        .map(de_fn);
+        Box::new(mapped)
+    }
+
+    /// keys returns the deserialized (remaining) keys only, without parsing the values
+    pub fn keys<'a>(
+        &self,
+        store: &'a dyn Storage,
+        min: Option<Bound<'b, B>>,
+        max: Option<Bound<'b, B>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<K::Output>> + 'a>
+    where
+        T: 'a,
+        K::Output: 'static,
+    {
+        let de_fn: fn(Vec<u8>) -> StdResult<K::Output> = K::from_vec;
+        let mapped = range_with_prefix(
+            store,
+            &self.storage_prefix,
+            min.map(|b| b.to_raw_bound()),
+            max.map(|b| b.to_raw_bound()),
+            order,
+        )
+        .map(move |(k, _)| de_fn(k));
+        Box::new(mapped)
+    }
+
+    /// is_empty returns true if no entry is stored under this prefix,
+    /// reading at most one key and without parsing any value
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        range_with_prefix(store, &self.storage_prefix, None, None, Order::Ascending)
+            .next()
+            .is_none()
+    }
+
+    /// count returns the number of entries under this prefix without parsing the values.
+    /// Returns `StorageError::TooManyEntries` if there are more than `max_entries`
+    pub fn count(&self, store: &dyn Storage, max_entries: u32) -> StorageResult<u32> {
+        let keys = range_with_prefix(store, &self.storage_prefix, None, None, Order::Ascending);
+        let mut count = 0;
+        for entry in capped(keys, max_entries) {
+            entry?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// fold calls `f` with the accumulator and every value under this prefix, in key order.
+    /// Returns `StorageError::TooManyEntries` if there are more than `max_entries`
+    pub fn fold<A, F>(
+        &self,
+        store: &dyn Storage,
+        max_entries: u32,
+        init: A,
+        mut f: F,
+    ) -> StorageResult<A>
+    where
+        F: FnMut(A, T) -> StdResult<A>,
+    {
+        let mut acc = init;
+        for entry in capped(
+            self.range_raw(store, None, None, Order::Ascending),
+            max_entries,
+        ) {
+            let (_, value) = entry??;
+            acc = f(acc, value)?;
+        }
+        Ok(acc)
+    }
+
+    /// sum adds up the values under this prefix.
+    /// Returns `StorageError::TooManyEntries` if there are more than `max_entries`,
+    /// and `StorageError::Overflow` if the sum overflows
+    pub fn sum(&self, store: &dyn Storage, max_entries: u32) -> StorageResult<T>
+    where
+        T: Numeric,
+    {
+        let mut sum = T::default();
+        for entry in capped(
+            self.range_raw(store, None, None, Order::Ascending),
+            max_entries,
+        ) {
+            let (_, value) = entry??;
+            sum = sum.checked_add(value).ok_or(StorageError::Overflow {
+                collection: "Prefix",
+            })?;
+        }
+        Ok(sum)
+    }
+}
+
+/// Yields the first `max_entries` items of `iter`, then `StorageError::TooManyEntries` if there
+/// are more, so a scan reads at most one entry more than allowed
+#[cfg(feature = "iterator")]
+fn capped<I: Iterator>(iter: I, max_entries: u32) -> impl Iterator<Item = StorageResult<I::Item>> {
+    iter.enumerate().map(move |(i, item)| {
+        if i as u64 >= max_entries as u64 {
+            Err(StorageError::TooManyEntries { max_entries })
+        } else {
+            Ok(item)
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::ReadonlyStorage;
+
+    #[test]
+    fn access_by_remaining_key() {
+        let mut store = MockStorage::new();
+        let prefix: Prefix<Vec<u8>, u64, &[u8]> = Prefix::new(b"foo", &[Key::Ref(b"bar")]);
+
+        prefix.save(&mut store, b"baz", &7).unwrap();
+        assert_eq!(prefix.load(&store, b"baz").unwrap(), 7);
+        assert_eq!(prefix.may_load(&store, b"qux").unwrap(), None);
+        // stored under the length-prefixed namespace and prefix, followed by the remaining key
+        assert!(store.get(b"\x00\x03foo\x00\x03barbaz").is_some());
+
+        prefix.remove(&mut store, b"baz");
+        assert!(!prefix.has(&store, b"baz"));
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn ensure_proper_range_bounds() {
+        let mut store = MockStorage::new();
+        // manually create this - not testing nested prefixes here
+        let prefix: Prefix<Vec<u8>, u64> = Prefix {
+            storage_prefix: b"foo".to_vec(),
+            data: PhantomData,
+        };
+
+        // set some data, we care about "foo" prefix
+        store.set(b"foobar", b"\x01\x00\x00\x00\x00\x00\x00\x00");
+        store.set(b"foora", b"\x02\x00\x00\x00\x00\x00\x00\x00");
+        store.set(b"foozi", b"\x03\x00\x00\x00\x00\x00\x00\x00");
+        // these shouldn't match
+        store.set(b"foply", b"\x64\x00\x00\x00\x00\x00\x00\x00");
+        store.set(b"font", b"\xe7\x03\x00\x00\x00\x00\x00\x00");
+
+        let expected = vec![
+            (b"bar".to_vec(), 1u64),
+            (b"ra".to_vec(), 2u64),
+            (b"zi".to_vec(), 3u64),
+        ];
+        let expected_reversed: Vec<(Vec<u8>, u64)> = expected.iter().rev().cloned().collect();
+
+        // let's do the basic sanity check
+        let res: StdResult<Vec<_>> = prefix.range(&store, None, None, Order::Ascending).collect();
+        assert_eq!(&expected, &res.unwrap());
+        let res: StdResult<Vec<_>> = prefix
+            .range(&store, None, None, Order::Descending)
+            .collect();
+        assert_eq!(&expected_reversed, &res.unwrap());
+
+        // now let's check some ascending ranges
+        let res: StdResult<Vec<_>> = prefix
+            .range(
+                &store,
+                Some(Bound::inclusive(b"ra".to_vec())),
+                None,
+                Order::Ascending,
+            )
+            .collect();
+        assert_eq!(&expected[1..], res.unwrap().as_slice());
+        // skip excluded
+        let res: StdResult<Vec<_>> = prefix
+            .range(
+                &store,
+                Some(Bound::exclusive(b"ra".to_vec())),
+                None,
+                Order::Ascending,
+            )
+            .collect();
+        assert_eq!(&expected[2..], res.unwrap().as_slice());
+        // if we exclude something a little lower, we get matched
+        let res: StdResult<Vec<_>> = prefix
+            .range(
+                &store,
+                Some(Bound::exclusive(b"r".to_vec())),
+                None,
+                Order::Ascending,
+            )
+            .collect();
+        assert_eq!(&expected[1..], res.unwrap().as_slice());
+
+        // now let's check some descending ranges
+        let res: StdResult<Vec<_>> = prefix
+            .range(
+                &store,
+                None,
+                Some(Bound::inclusive(b"ra".to_vec())),
+                Order::Descending,
+            )
+            .collect();
+        assert_eq!(&expected_reversed[1..], res.unwrap().as_slice());
+        // skip excluded
+        let res: StdResult<Vec<_>> = prefix
+            .range(
+                &store,
+                None,
+                Some(Bound::exclusive(b"ra".to_vec())),
+                Order::Descending,
+            )
+            .collect();
+        assert_eq!(&expected_reversed[2..], res.unwrap().as_slice());
+        // if we exclude something a little higher, we get matched
+        let res: StdResult<Vec<_>> = prefix
+            .range(
+                &store,
+                None,
+                Some(Bound::exclusive(b"rb".to_vec())),
+                Order::Descending,
+            )
+            .collect();
+        assert_eq!(&expected_reversed[1..], res.unwrap().as_slice());
+
+        // now test when both sides are set
+        let res: StdResult<Vec<_>> = prefix
+            .range(
+                &store,
+                Some(Bound::inclusive(b"ra".to_vec())),
+                Some(Bound::exclusive(b"zi".to_vec())),
+                Order::Ascending,
+            )
+            .collect();
+        assert_eq!(&expected[1..2], res.unwrap().as_slice());
+        // and descending
+        let res: StdResult<Vec<_>> = prefix
+            .range(
+                &store,
+                Some(Bound::inclusive(b"ra".to_vec())),
+                Some(Bound::exclusive(b"zi".to_vec())),
+                Order::Descending,
+            )
+            .collect();
+        assert_eq!(&expected[1..2], res.unwrap().as_slice());
+        // Include both sides
+        let res: StdResult<Vec<_>> = prefix
+            .range(
+                &store,
+                Some(Bound::inclusive(b"ra".to_vec())),
+                Some(Bound::inclusive(b"zi".to_vec())),
+                Order::Descending,
+            )
+            .collect();
+        assert_eq!(&expected_reversed[..2], res.unwrap().as_slice());
+        // Exclude both sides
+        let res: StdResult<Vec<_>> = prefix
+            .range(
+                &store,
+                Some(Bound::exclusive(b"ra".to_vec())),
+                Some(Bound::exclusive(b"zi".to_vec())),
+                Order::Ascending,
+            )
+            .collect();
+        assert_eq!(res.unwrap().as_slice(), &[]);
+    }
+}
@@ -0,0 +1,196 @@
+use cosmwasm_std::{StdResult, Storage};
+
+use crate::error::{StorageError, StorageResult};
+use crate::queue::{load_u32, save_u32};
+use crate::Map;
+
+/// A priority queue, implemented as a binary max-heap over indexed slots.
+/// `pop` and `peek` return the greatest item, wrap the items in `std::cmp::Reverse`
+/// to get the smallest item first instead.
+///
+/// `push` and `pop` read and write O(log n) slots.
+pub struct PriorityQueue<'a, T> {
+    map: Map<'a, u32, T>,
+}
+
+impl<'a, T> PriorityQueue<'a, T> {
+    const LEN: &'static [u8] = b"_len";
+
+    pub const fn new(namespace: &'a str) -> Self {
+        Self {
+            map: Map::new(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+
+    /// Returns the number of items in the queue
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.with_namespace_suffix(Self::LEN, |ns| load_u32(store, ns))
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    fn set_len(&self, store: &mut dyn Storage, len: u32) {
+        self.with_namespace_suffix(Self::LEN, |ns| save_u32(store, ns, len))
+    }
+
+    fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
+        let namespace = &[self.namespace(), namespace].concat();
+        f(namespace)
+    }
+}
+
+impl<'a, T> PriorityQueue<'a, T>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned + Ord,
+{
+    /// Adds an item to the queue
+    pub fn push(&self, store: &mut dyn Storage, t: &T) -> StorageResult<()> {
+        let len = self.len(store);
+        if len == u32::MAX {
+            return Err(StorageError::CapacityExceeded {
+                collection: "PriorityQueue",
+            });
+        }
+
+        // sift up: move smaller parents down into the hole until the item fits
+        let mut hole = len;
+        while hole > 0 {
+            let parent = (hole - 1) / 2;
+            let parent_item = self.map.load(store, parent)?;
+            if parent_item >= *t {
+                break;
+            }
+            self.map.save(store, hole, &parent_item)?;
+            hole = parent;
+        }
+        self.map.save(store, hole, t)?;
+        self.set_len(store, len + 1);
+
+        Ok(())
+    }
+
+    /// Removes the greatest item from the queue and returns it, returns None if the queue is empty
+    pub fn pop(&self, store: &mut dyn Storage) -> StdResult<Option<T>> {
+        let len = self.len(store);
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let top = self.map.load(store, 0)?;
+        let last = len - 1;
+        let last_item = self.map.load(store, last)?;
+        self.map.remove(store, last);
+        self.set_len(store, last);
+
+        if last > 0 {
+            // sift down: move greater children up into the hole until the last item fits
+            let mut hole = 0;
+            loop {
+                let left = 2 * hole as u64 + 1;
+                if left >= last as u64 {
+                    break;
+                }
+                let mut child = left as u32;
+                let mut child_item = self.map.load(store, child)?;
+                if child + 1 < last {
+                    let right_item = self.map.load(store, child + 1)?;
+                    if right_item > child_item {
+                        child += 1;
+                        child_item = right_item;
+                    }
+                }
+                if child_item <= last_item {
+                    break;
+                }
+                self.map.save(store, hole, &child_item)?;
+                hole = child;
+            }
+            self.map.save(store, hole, &last_item)?;
+        }
+
+        Ok(Some(top))
+    }
+
+    /// Returns the greatest item without removing it, returns None if the queue is empty
+    pub fn peek(&self, store: &dyn Storage) -> StdResult<Option<T>> {
+        if self.is_empty(store) {
+            return Ok(None);
+        }
+        Ok(self.map.load(store, 0).map(Some)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+
+    use cosmwasm_std::testing::MockStorage;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, proptest_derive::Arbitrary)]
+    enum Op {
+        Push(u8),
+        Pop,
+    }
+
+    const QUEUE: PriorityQueue<u8> = PriorityQueue::new("pq");
+
+    proptest! {
+        #[test]
+        fn impl_matches_model(ops: Vec<Op>) {
+            let mut model = BinaryHeap::new();
+            let mut store = MockStorage::new();
+            for op in ops {
+                match op {
+                    Op::Push(u) => {
+                        model.push(u);
+                        QUEUE.push(&mut store, &u).unwrap();
+                    }
+                    Op::Pop => {
+                        let impl_res = QUEUE.pop(&mut store).unwrap();
+                        prop_assert_eq!(model.pop(), impl_res, "pop results differ");
+                    }
+                }
+
+                prop_assert_eq!(QUEUE.len(&store), model.len() as u32, "len results differ");
+                prop_assert_eq!(QUEUE.peek(&store).unwrap(), model.peek().copied(), "peek results differ");
+            }
+        }
+    }
+
+    #[test]
+    fn reverse_pops_smallest_first() {
+        let mut store = MockStorage::new();
+        let deadlines: PriorityQueue<Reverse<(u64, String)>> = PriorityQueue::new("deadlines");
+
+        for (height, job) in [(30, "c"), (10, "a"), (20, "b"), (10, "aa")] {
+            deadlines
+                .push(&mut store, &Reverse((height, job.to_string())))
+                .unwrap();
+        }
+
+        let mut popped = vec![];
+        while let Some(Reverse((height, job))) = deadlines.pop(&mut store).unwrap() {
+            popped.push((height, job));
+        }
+        assert_eq!(
+            popped,
+            vec![
+                (10, "a".to_string()),
+                (10, "aa".to_string()),
+                (20, "b".to_string()),
+                (30, "c".to_string()),
+            ]
+        );
+        assert!(deadlines.is_empty(&store));
+    }
+}
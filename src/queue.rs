@@ -1,15 +1,21 @@
-use cosmwasm_std::{StdResult, Storage};
+use cosmwasm_std::{StdError, StdResult, Storage};
 
+use crate::error::{StorageError, StorageResult};
+use crate::helpers::namespaces_with_key;
+use crate::keys::int_key::CwIntKey;
+use crate::serialization::{Bincode2, Serde};
 use crate::Map;
 
-pub struct Queue<'a, T> {
+pub struct Queue<'a, T, Ser = Bincode2> {
     capacity: u32,
-    map: Map<'a, u32, T>,
+    map: Map<'a, u32, T, Ser>,
 }
 
-impl<'a, T> Queue<'a, T> {
+impl<'a, T, Ser> Queue<'a, T, Ser> {
     const HEAD: &'static [u8] = b"_head";
     const TAIL: &'static [u8] = b"_tail";
+    const CAPACITY: &'static [u8] = b"_capacity";
+    const GC_CURSOR: &'static [u8] = b"_gc_cursor";
 
     // Creates a Queue with capacity `u32::MAX - 1'
     pub const fn new(namespace: &'a str) -> Self {
@@ -34,7 +40,7 @@ impl<'a, T> Queue<'a, T> {
         }
     }
 
-    pub fn namespace(&self) -> &'a [u8] {
+    pub fn namespace(&self) -> &[u8] {
         self.map.namespace()
     }
 
@@ -58,6 +64,88 @@ impl<'a, T> Queue<'a, T> {
         self.determine_is_full(head, tail)
     }
 
+    /// Returns the capacity saved by the first write to the queue, None if it was never written to
+    pub fn stored_capacity(&self, store: &dyn Storage) -> Option<u32> {
+        match self.with_namespace_suffix(Self::CAPACITY, |ns| load_u32(store, ns)) {
+            0 => None,
+            capacity => Some(capacity),
+        }
+    }
+
+    /// validate checks that the items in storage were written with the capacity this queue is
+    /// created with, e.g. after a contract upgrade changed the capacity passed to `with_capacity`.
+    /// Returns `StorageError::CapacityMismatch` otherwise, see `resize` to move to a new capacity.
+    ///
+    /// Queues last written to before the capacity was saved can't be checked.
+    pub fn validate(&self, store: &dyn Storage) -> StorageResult<()> {
+        match self.stored_capacity(store) {
+            Some(stored) if stored != self.max_capacity() => Err(StorageError::CapacityMismatch {
+                stored,
+                configured: self.max_capacity(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// resize rearranges the items written with the stored capacity for a queue with capacity
+    /// `new_capacity`, keeping their order. Afterwards the queue must be created with
+    /// `with_capacity(namespace, new_capacity)`.
+    ///
+    /// Returns an error if the queue holds more than `new_capacity` items, and panics if
+    /// `new_capacity` is zero or `u32::MAX`, like `with_capacity`.
+    /// This touches every item in the queue.
+    pub fn resize(&self, store: &mut dyn Storage, new_capacity: u32) -> StorageResult<()> {
+        if new_capacity == 0 {
+            panic!("zero sized queues are illegal");
+        }
+        if new_capacity == u32::MAX {
+            panic!("the maximum legal capacity is u32::MAX - 1");
+        }
+
+        let slots = self.stored_capacity(store).unwrap_or(self.max_capacity()) as u64 + 1;
+        let head = self.head(store) as u64;
+        let tail = self.tail(store) as u64;
+        let len = (tail + slots - head) % slots;
+        if len > new_capacity as u64 {
+            return Err(StorageError::CapacityExceeded {
+                collection: "Queue",
+            });
+        }
+
+        let slot = |index: u64| ((head + index) % slots) as u32;
+        let items: Vec<Option<Vec<u8>>> = (0..len)
+            .map(|index| store.get(&self.slot_key(slot(index))))
+            .collect();
+        for index in 0..len {
+            store.remove(&self.slot_key(slot(index)));
+        }
+        for (slot, item) in items.into_iter().enumerate() {
+            if let Some(item) = item {
+                store.set(&self.slot_key(slot as u32), &item);
+            }
+        }
+
+        self.with_namespace_suffix(Self::HEAD, |ns| save_u32(store, ns, 0));
+        self.with_namespace_suffix(Self::TAIL, |ns| save_u32(store, ns, len as u32));
+        self.with_namespace_suffix(Self::CAPACITY, |ns| save_u32(store, ns, new_capacity));
+        Ok(())
+    }
+
+    /// The full storage key of a slot, for raw access to the stored items
+    fn slot_key(&self, slot: u32) -> Vec<u8> {
+        namespaces_with_key(&[self.namespace()], &slot.to_cw_bytes())
+    }
+
+    /// Saves the capacity on the first write, and checks it on every later one
+    fn check_capacity(&self, store: &mut dyn Storage) -> StorageResult<()> {
+        if self.stored_capacity(store).is_none() {
+            let capacity = self.max_capacity();
+            self.with_namespace_suffix(Self::CAPACITY, |ns| save_u32(store, ns, capacity));
+            return Ok(());
+        }
+        self.validate(store)
+    }
+
     fn determine_is_full(&self, head: u32, tail: u32) -> bool {
         self.determine_len(head, tail) == self.max_capacity()
     }
@@ -70,7 +158,7 @@ impl<'a, T> Queue<'a, T> {
         }
     }
 
-    fn head(&self, store: &dyn Storage) -> u32 {
+    pub(crate) fn head(&self, store: &dyn Storage) -> u32 {
         self.with_namespace_suffix(Self::HEAD, |ns| load_u32(store, ns))
     }
 
@@ -79,7 +167,7 @@ impl<'a, T> Queue<'a, T> {
         self.with_namespace_suffix(Self::HEAD, |ns| save_u32(store, ns, head))
     }
 
-    fn tail(&self, store: &dyn Storage) -> u32 {
+    pub(crate) fn tail(&self, store: &dyn Storage) -> u32 {
         self.with_namespace_suffix(Self::TAIL, |ns| load_u32(store, ns))
     }
 
@@ -88,18 +176,29 @@ impl<'a, T> Queue<'a, T> {
         self.with_namespace_suffix(Self::TAIL, |ns| save_u32(store, ns, tail))
     }
 
+    /// Maps a logical position, counted from the head of the queue, to its storage slot
+    fn slot(&self, head: u32, index: u32) -> u32 {
+        ((head as u64 + index as u64) % self.capacity as u64) as u32
+    }
+
+    /// The slot before `slot`, wrapping around like `slot`
+    fn prev_slot(&self, slot: u32) -> u32 {
+        self.slot(slot, self.capacity - 1)
+    }
+
     fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
         let namespace = &[self.namespace(), namespace].concat();
         f(namespace)
     }
 }
 
-impl<'a, T> Queue<'a, T>
+impl<'a, T, Ser> Queue<'a, T, Ser>
 where
-    T: serde::Serialize + serde::de::DeserializeOwned,
+    Ser: Serde<T>,
 {
     /// Add an item to the back of the queue, returns true if the item is added or false if the queue is full
-    pub fn push_back(&self, store: &mut dyn Storage, t: &T) -> StdResult<bool> {
+    pub fn push_back(&self, store: &mut dyn Storage, t: &T) -> StorageResult<bool> {
+        self.check_capacity(store)?;
         let tail = self.tail(store);
         let head = self.head(store);
 
@@ -107,15 +206,28 @@ where
             return Ok(false);
         }
 
-        self.map.save(store, tail.into(), t)?;
+        self.map.save(store, tail, t)?;
 
         self.inc_tail(store, tail);
 
         Ok(true)
     }
 
-    /// Pop an item from the front of the queue, returns None if the queue is empty
-    pub fn pop_front(&self, store: &mut dyn Storage) -> StdResult<Option<T>> {
+    /// Add an item to the back of the queue, evicting the item at the front if the queue is full,
+    /// e.g. to keep the last N events. Returns the evicted item
+    pub fn push_back_overwrite(&self, store: &mut dyn Storage, t: &T) -> StorageResult<Option<T>> {
+        let evicted = if self.is_full(store) {
+            self.pop_front(store)?
+        } else {
+            None
+        };
+        self.push_back(store, t)?;
+        Ok(evicted)
+    }
+
+    /// Pop an item from the front of the queue and delete its slot, returns None if the queue is empty
+    pub fn pop_front(&self, store: &mut dyn Storage) -> StorageResult<Option<T>> {
+        self.check_capacity(store)?;
         let tail = self.tail(store);
         let head = self.head(store);
 
@@ -123,15 +235,356 @@ where
             return Ok(None);
         }
 
-        let popped = self.map.may_load(store, head.into())?;
+        let popped = self.map.may_load(store, head)?;
+        self.map.remove(store, head);
 
         self.inc_head(store, head);
 
         Ok(popped)
     }
+
+    /// Add an item to the front of the queue, returns true if the item is added or false if the
+    /// queue is full. Only `Deque` pushes to the front
+    pub(crate) fn push_front(&self, store: &mut dyn Storage, t: &T) -> StorageResult<bool> {
+        self.check_capacity(store)?;
+        let tail = self.tail(store);
+        let head = self.head(store);
+
+        if self.determine_is_full(head, tail) {
+            return Ok(false);
+        }
+
+        let head = self.prev_slot(head);
+        self.map.save(store, head, t)?;
+        self.with_namespace_suffix(Self::HEAD, |ns| save_u32(store, ns, head));
+
+        Ok(true)
+    }
+
+    /// Pop an item from the back of the queue and delete its slot, returns None if the queue is
+    /// empty. Only `Deque` pops from the back
+    pub(crate) fn pop_back(&self, store: &mut dyn Storage) -> StorageResult<Option<T>> {
+        self.check_capacity(store)?;
+        let tail = self.tail(store);
+        let head = self.head(store);
+
+        if tail == head {
+            return Ok(None);
+        }
+
+        let tail = self.prev_slot(tail);
+        let popped = self.map.may_load(store, tail)?;
+        self.map.remove(store, tail);
+        self.with_namespace_suffix(Self::TAIL, |ns| save_u32(store, ns, tail));
+
+        Ok(popped)
+    }
+
+    /// Pops up to `max_items` items from the front of the queue, returns fewer items if the queue
+    /// runs empty first
+    pub fn drain(&self, store: &mut dyn Storage, max_items: u32) -> StorageResult<Vec<T>> {
+        self.check_capacity(store)?;
+        let tail = self.tail(store);
+        let head = self.head(store);
+
+        let count = self.determine_len(head, tail).min(max_items);
+        let drained = (0..count)
+            .map(|index| self.map.load(store, self.slot(head, index)))
+            .collect::<StorageResult<Vec<_>>>()?;
+        for index in 0..count {
+            self.map.remove(store, self.slot(head, index));
+        }
+
+        if count > 0 {
+            // `inc_head` moves one slot, so step back one to move `count` slots
+            self.inc_head(store, self.slot(head, count - 1));
+        }
+
+        Ok(drained)
+    }
+
+    /// Walks up to `limit` items from the front of the queue and removes the ones `predicate`
+    /// returns false for, e.g. cancelled withdrawals. The items kept are moved back over the freed
+    /// slots so they stay in order, and the head moves forward. Returns the number of items removed
+    pub fn retain<P>(
+        &self,
+        store: &mut dyn Storage,
+        limit: u32,
+        mut predicate: P,
+    ) -> StorageResult<u32>
+    where
+        P: FnMut(&T) -> bool,
+    {
+        self.check_capacity(store)?;
+        let tail = self.tail(store);
+        let head = self.head(store);
+
+        let count = self.determine_len(head, tail).min(limit);
+        let mut kept = vec![];
+        for index in 0..count {
+            let item = self.map.load(store, self.slot(head, index))?;
+            if predicate(&item) {
+                kept.push((index, item));
+            }
+        }
+
+        let removed = count - kept.len() as u32;
+        if removed == 0 {
+            return Ok(0);
+        }
+        for (position, (index, item)) in kept.iter().enumerate() {
+            let target = removed + position as u32;
+            if target != *index {
+                self.map.save(store, self.slot(head, target), item)?;
+            }
+        }
+        for index in 0..removed {
+            self.map.remove(store, self.slot(head, index));
+        }
+        // `inc_head` moves one slot, so step back one to move `removed` slots
+        self.inc_head(store, self.slot(head, removed - 1));
+
+        Ok(removed)
+    }
+
+    /// Removes all items from the queue, deleting their slots and resetting head, tail and the
+    /// stored capacity. This touches every item in the queue, use `drain` to bound the work done per call
+    pub fn clear(&self, store: &mut dyn Storage) {
+        let tail = self.tail(store);
+        let head = self.head(store);
+
+        for index in 0..self.determine_len(head, tail) {
+            self.map.remove(store, self.slot(head, index));
+        }
+
+        self.with_namespace_suffix(Self::HEAD, |ns| store.remove(ns));
+        self.with_namespace_suffix(Self::TAIL, |ns| store.remove(ns));
+        self.with_namespace_suffix(Self::CAPACITY, |ns| store.remove(ns));
+    }
+
+    /// Deletes up to `limit` slots left behind by items popped before pops deleted their slots,
+    /// returns the number of slots deleted. Call it until it returns less than `limit` to clean
+    /// a queue written by an older version of this crate, it does nothing on newer queues.
+    ///
+    /// The stale slots are the ones before the head, it walks back from there until it finds an
+    /// empty slot or reaches the items in the queue. Where it stopped is saved for the next call.
+    pub fn gc(&self, store: &mut dyn Storage, limit: u32) -> u32 {
+        let tail = self.tail(store);
+        let head = self.head(store);
+        let len = self.determine_len(head, tail);
+        let capacity = self.capacity as u64;
+        let prev = |slot: u32| ((slot as u64 + capacity - 1) % capacity) as u32;
+
+        let cursor = self.with_namespace_suffix(Self::GC_CURSOR, |ns| store.get(ns));
+        let mut slot = match cursor.and_then(|bytes| bytes.try_into().ok()) {
+            Some(bytes) => u32::from_be_bytes(bytes),
+            None => prev(head),
+        };
+        let mut removed = 0;
+        while removed < limit {
+            let live = (slot as u64 + capacity - head as u64) % capacity < len as u64;
+            if live || store.get(&self.slot_key(slot)).is_none() {
+                self.with_namespace_suffix(Self::GC_CURSOR, |ns| store.remove(ns));
+                return removed;
+            }
+            store.remove(&self.slot_key(slot));
+            removed += 1;
+            slot = prev(slot);
+        }
+        self.with_namespace_suffix(Self::GC_CURSOR, |ns| save_u32(store, ns, slot));
+        removed
+    }
+
+    /// Returns the item at logical position `index`, counted from the front of the queue,
+    /// without removing it. Returns None if `index` is out of bounds
+    pub fn get(&self, store: &dyn Storage, index: u32) -> StorageResult<Option<T>> {
+        let tail = self.tail(store);
+        let head = self.head(store);
+
+        if index >= self.determine_len(head, tail) {
+            return Ok(None);
+        }
+
+        self.map.may_load(store, self.slot(head, index))
+    }
+
+    /// Replaces the item at logical position `index`, counted from the front of the queue, with
+    /// the result of `action`, keeping its position. Returns the new item, or None without calling
+    /// `action` if `index` is out of bounds
+    pub fn update_at<A, E>(
+        &self,
+        store: &mut dyn Storage,
+        index: u32,
+        action: A,
+    ) -> Result<Option<T>, E>
+    where
+        A: FnOnce(T) -> Result<T, E>,
+        E: From<StdError>,
+    {
+        self.check_capacity(store).map_err(StdError::from)?;
+        let tail = self.tail(store);
+        let head = self.head(store);
+
+        if index >= self.determine_len(head, tail) {
+            return Ok(None);
+        }
+
+        let slot = self.slot(head, index);
+        let updated = action(self.map.load(store, slot).map_err(StdError::from)?)?;
+        self.map
+            .save(store, slot, &updated)
+            .map_err(StdError::from)?;
+        Ok(Some(updated))
+    }
+
+    /// Replaces the item at the front of the queue with the result of `action`, e.g. to count a
+    /// failed attempt of a job without moving it to the back. Returns the new item, or None if the
+    /// queue is empty
+    pub fn update_front<A, E>(&self, store: &mut dyn Storage, action: A) -> Result<Option<T>, E>
+    where
+        A: FnOnce(T) -> Result<T, E>,
+        E: From<StdError>,
+    {
+        self.update_at(store, 0, action)
+    }
+
+    /// Returns the item at the front of the queue without removing it, returns None if the queue is empty
+    pub fn peek_front(&self, store: &dyn Storage) -> StorageResult<Option<T>> {
+        let tail = self.tail(store);
+        let head = self.head(store);
+
+        if tail == head {
+            return Ok(None);
+        }
+
+        self.map.may_load(store, head)
+    }
+
+    /// Returns the item at the back of the queue without removing it, returns None if the queue is empty
+    pub fn peek_back(&self, store: &dyn Storage) -> StorageResult<Option<T>> {
+        let tail = self.tail(store);
+        let head = self.head(store);
+
+        if tail == head {
+            return Ok(None);
+        }
+
+        let len = self.determine_len(head, tail);
+        self.map.may_load(store, self.slot(head, len - 1))
+    }
+
+    /// Returns an iterator over the items in the queue, from front to back, without removing them
+    pub fn iter<'c>(&'c self, store: &'c dyn Storage) -> QueueIter<'a, 'c, T, Ser> {
+        let tail = self.tail(store);
+        let head = self.head(store);
+        QueueIter {
+            queue: self,
+            store,
+            head,
+            start: 0,
+            end: self.determine_len(head, tail),
+        }
+    }
+}
+
+#[cfg(feature = "migration")]
+impl<'a, T, Ser> Queue<'a, T, Ser> {
+    /// export returns all items from front to back as serialized values, keyed by their
+    /// big-endian `u32` position in the queue
+    pub fn export(&self, store: &dyn Storage) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let tail = self.tail(store);
+        let head = self.head(store);
+        (0..self.determine_len(head, tail))
+            .filter_map(|index| {
+                let item = store.get(&self.slot_key(self.slot(head, index)))?;
+                Some((index.to_be_bytes().to_vec(), item))
+            })
+            .collect()
+    }
+
+    /// import pushes the items returned by `export` to the back of the queue, in the given order.
+    /// Returns `StorageError::CapacityExceeded` if the queue runs full, keeping the items
+    /// pushed before
+    pub fn import<I>(&self, store: &mut dyn Storage, entries: I) -> StorageResult<()>
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        self.check_capacity(store)?;
+        let head = self.head(store);
+        let mut tail = self.tail(store);
+        for (_, item) in entries {
+            if self.determine_is_full(head, tail) {
+                return Err(StorageError::CapacityExceeded {
+                    collection: "Queue",
+                });
+            }
+            store.set(&self.slot_key(tail), &item);
+            self.inc_tail(store, tail);
+            tail = self.tail(store);
+        }
+        Ok(())
+    }
+}
+
+/// An iterator over the items of a `Queue`, from front to back, created by `Queue::iter`
+pub struct QueueIter<'a, 'c, T, Ser = Bincode2> {
+    queue: &'c Queue<'a, T, Ser>,
+    store: &'c dyn Storage,
+    head: u32,
+    start: u32,
+    end: u32,
+}
+
+impl<'a, 'c, T, Ser> Iterator for QueueIter<'a, 'c, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    type Item = StdResult<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start >= self.end {
+            return None;
+        }
+        let slot = self.queue.slot(self.head, self.start);
+        self.start += 1;
+        Some(
+            self.queue
+                .map
+                .load(self.store, slot)
+                .map_err(StdError::from),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.end - self.start) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a, 'c, T, Ser> DoubleEndedIterator for QueueIter<'a, 'c, T, Ser>
+where
+    Ser: Serde<T>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.start >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        let slot = self.queue.slot(self.head, self.end);
+        Some(
+            self.queue
+                .map
+                .load(self.store, slot)
+                .map_err(StdError::from),
+        )
+    }
 }
 
-fn load_u32(store: &dyn Storage, namespace: &[u8]) -> u32 {
+impl<'a, 'c, T, Ser> ExactSizeIterator for QueueIter<'a, 'c, T, Ser> where Ser: Serde<T> {}
+
+pub(crate) fn load_u32(store: &dyn Storage, namespace: &[u8]) -> u32 {
+    #[cfg(feature = "namespace-check")]
+    crate::namespace_check::register_as(crate::namespace_check::Usage::Key, namespace, COUNTER);
     store
         .get(namespace)
         .and_then(|bytes| bytes.try_into().ok())
@@ -139,7 +592,13 @@ fn load_u32(store: &dyn Storage, namespace: &[u8]) -> u32 {
         .unwrap_or_default()
 }
 
-fn save_u32(store: &mut dyn Storage, namespace: &[u8], u: u32) {
+/// The type counters are registered as, so an `Item<u32>` on the same key is detected
+#[cfg(feature = "namespace-check")]
+const COUNTER: &str = "u32 counter";
+
+pub(crate) fn save_u32(store: &mut dyn Storage, namespace: &[u8], u: u32) {
+    #[cfg(feature = "namespace-check")]
+    crate::namespace_check::register_as(crate::namespace_check::Usage::Key, namespace, COUNTER);
     store.set(namespace, &u.to_be_bytes())
 }
 
@@ -148,6 +607,7 @@ mod test {
     use std::collections::VecDeque;
 
     use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::StdError;
     use proptest::prelude::*;
 
     use super::*;
@@ -155,7 +615,10 @@ mod test {
     #[derive(Debug, Clone, Copy, proptest_derive::Arbitrary)]
     enum Op {
         Push(u8),
+        PushOverwrite(u8),
         Pop,
+        Drain(u8),
+        Clear,
     }
 
     struct Model {
@@ -179,10 +642,45 @@ mod test {
             true
         }
 
+        fn push_overwrite(&mut self, i: u8) -> Option<u8> {
+            let evicted = if self.q.len() == self.max {
+                self.q.pop_front()
+            } else {
+                None
+            };
+            self.q.push_back(i);
+            evicted
+        }
+
         fn pop(&mut self) -> Option<u8> {
             self.q.pop_front()
         }
 
+        fn drain(&mut self, max: usize) -> Vec<u8> {
+            let count = max.min(self.q.len());
+            self.q.drain(..count).collect()
+        }
+
+        fn clear(&mut self) {
+            self.q.clear()
+        }
+
+        fn get(&self, index: usize) -> Option<u8> {
+            self.q.get(index).copied()
+        }
+
+        fn front(&self) -> Option<u8> {
+            self.q.front().copied()
+        }
+
+        fn back(&self) -> Option<u8> {
+            self.q.back().copied()
+        }
+
+        fn items(&self) -> Vec<u8> {
+            self.q.iter().copied().collect()
+        }
+
         fn len(&self) -> usize {
             self.q.len()
         }
@@ -214,26 +712,92 @@ mod test {
                         let impl_res = queue.push_back(&mut store, &u).unwrap();
                         prop_assert_eq!(model_res, impl_res, "push results differ");
                     }
+                    Op::PushOverwrite(u) => {
+                        let model_res = model.push_overwrite(u);
+                        let impl_res = queue.push_back_overwrite(&mut store, &u).unwrap();
+                        prop_assert_eq!(model_res, impl_res, "push_overwrite results differ");
+                    }
                     Op::Pop => {
                         let model_res = model.pop();
                         let impl_res = queue.pop_front(&mut store).unwrap();
                         prop_assert_eq!(model_res, impl_res, "pop results differ");
                     }
+                    Op::Drain(max) => {
+                        let model_res = model.drain(max as _);
+                        let impl_res = queue.drain(&mut store, max as _).unwrap();
+                        prop_assert_eq!(model_res, impl_res, "drain results differ");
+                    }
+                    Op::Clear => {
+                        model.clear();
+                        queue.clear(&mut store);
+                    }
                 }
 
                 prop_assert_eq!(queue.len(&store), model.len() as u32, "len results differ");
                 prop_assert_eq!(queue.free_capacity(&store), model.free_capacity() as u32, "free_capacity results differ");
                 prop_assert_eq!(queue.is_full(&store), model.is_full(), "is_full results differ");
+                prop_assert_eq!(queue.peek_front(&store).unwrap(), model.front(), "peek_front results differ");
+                prop_assert_eq!(queue.peek_back(&store).unwrap(), model.back(), "peek_back results differ");
+                let items = queue.iter(&store).collect::<StdResult<Vec<_>>>().unwrap();
+                prop_assert_eq!(items, model.items(), "iter results differ");
+                for index in 0..=model.len() {
+                    prop_assert_eq!(queue.get(&store, index as u32).unwrap(), model.get(index), "get results differ");
+                }
+                if !model.is_full() {
+                    let before_head = (queue.head(&store) + size) % (size + 1);
+                    prop_assert!(!queue.map.has(&store, before_head), "popped slot kept");
+                }
             }
         }
     }
 
+    #[test]
+    fn gc_deletes_stale_slots() {
+        let (queue, mut store) = setup_queue(10);
+        for i in 0..6u8 {
+            queue.push_back(&mut store, &i).unwrap();
+        }
+        queue.drain(&mut store, 4).unwrap();
+        assert_eq!(queue.gc(&mut store, 10), 0);
+
+        // popped by an older version, which kept the slots
+        for slot in 0..4u32 {
+            queue.map.save(&mut store, slot, &(slot as u8)).unwrap();
+        }
+        assert_eq!(queue.gc(&mut store, 3), 3);
+        assert!(queue.map.has(&store, 0));
+        assert!(!queue.map.has(&store, 1));
+        assert_eq!(queue.gc(&mut store, 3), 1);
+        assert_eq!(queue.gc(&mut store, 3), 0);
+        assert_eq!(
+            queue.iter(&store).collect::<StdResult<Vec<_>>>().unwrap(),
+            vec![4, 5]
+        );
+    }
+
+    #[test]
+    fn gc_stops_at_the_items_of_a_wrapped_queue() {
+        let (queue, mut store) = setup_queue(3);
+        // head at 2 and tail at 1, so slot 1 is the only one not holding an item
+        queue.with_namespace_suffix(b"_head", |ns| save_u32(&mut store, ns, 2));
+        queue.with_namespace_suffix(b"_tail", |ns| save_u32(&mut store, ns, 1));
+        for slot in 0..4u32 {
+            queue.map.save(&mut store, slot, &(slot as u8)).unwrap();
+        }
+        assert_eq!(queue.gc(&mut store, 10), 1);
+        assert!(!queue.map.has(&store, 1));
+        assert_eq!(
+            queue.iter(&store).collect::<StdResult<Vec<_>>>().unwrap(),
+            vec![2, 3, 0]
+        );
+    }
+
     #[test]
     fn invariant_max_capacity_queue_wraps_around() {
-        let queue = Queue::new("test");
+        let queue: Queue<u8> = Queue::new("test");
         let mut store = MockStorage::new();
-        save_u32(&mut store, b"test_tail", u32::MAX - 1);
-        save_u32(&mut store, b"test_head", u32::MAX - 1);
+        queue.with_namespace_suffix(b"_tail", |ns| save_u32(&mut store, ns, u32::MAX - 1));
+        queue.with_namespace_suffix(b"_head", |ns| save_u32(&mut store, ns, u32::MAX - 1));
         assert_eq!(queue.len(&store), 0);
         assert!(queue.push_back(&mut store, &0u8).unwrap());
         assert!(queue.push_back(&mut store, &0u8).unwrap());
@@ -292,4 +856,224 @@ mod test {
         assert!(queue.pop_front(&mut store).unwrap().is_some());
         assert!(queue.pop_front(&mut store).unwrap().is_none());
     }
+
+    #[test]
+    fn push_back_overwrite_keeps_the_last_items() {
+        let (queue, mut store) = setup_queue(3);
+        for i in 1..=3u8 {
+            assert_eq!(queue.push_back_overwrite(&mut store, &i).unwrap(), None);
+        }
+        assert_eq!(
+            queue.push_back_overwrite(&mut store, &4u8).unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            queue.push_back_overwrite(&mut store, &5u8).unwrap(),
+            Some(2)
+        );
+
+        let items: StdResult<Vec<_>> = queue.iter(&store).collect();
+        assert_eq!(items.unwrap(), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn iter_wraps_around_and_does_not_drain() {
+        let (queue, mut store) = setup_queue(3);
+        assert!(queue.push_back(&mut store, &1u8).unwrap());
+        assert!(queue.push_back(&mut store, &2u8).unwrap());
+        assert_eq!(queue.pop_front(&mut store).unwrap(), Some(1));
+        assert!(queue.push_back(&mut store, &3u8).unwrap());
+        assert!(queue.push_back(&mut store, &4u8).unwrap());
+
+        let items: StdResult<Vec<_>> = queue.iter(&store).collect();
+        assert_eq!(items.unwrap(), vec![2, 3, 4]);
+        let items: StdResult<Vec<_>> = queue.iter(&store).rev().collect();
+        assert_eq!(items.unwrap(), vec![4, 3, 2]);
+        assert_eq!(queue.iter(&store).len(), 3);
+        assert_eq!(queue.len(&store), 3);
+
+        assert_eq!(queue.get(&store, 0).unwrap(), Some(2));
+        assert_eq!(queue.get(&store, 2).unwrap(), Some(4));
+        assert_eq!(queue.get(&store, 3).unwrap(), None);
+    }
+
+    #[test]
+    fn clear_deletes_slots() {
+        let (queue, mut store) = setup_queue(3);
+        assert!(queue.push_back(&mut store, &1u8).unwrap());
+        assert!(queue.push_back(&mut store, &2u8).unwrap());
+        assert_eq!(queue.drain(&mut store, 1).unwrap(), vec![1]);
+
+        queue.clear(&mut store);
+        assert_eq!(queue.len(&store), 0);
+        assert!(!queue.map.has(&store, 1));
+        assert_eq!(queue.head(&store), 0);
+        assert_eq!(queue.tail(&store), 0);
+
+        // the queue is usable again
+        assert!(queue.push_back(&mut store, &3u8).unwrap());
+        assert_eq!(queue.drain(&mut store, 10).unwrap(), vec![3]);
+        assert!(queue.drain(&mut store, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn capacity_changes_are_detected() {
+        let (queue, mut store) = setup_queue(3);
+        assert_eq!(queue.stored_capacity(&store), None);
+        assert!(queue.push_back(&mut store, &1u8).unwrap());
+        assert_eq!(queue.stored_capacity(&store), Some(3));
+        queue.validate(&store).unwrap();
+
+        // an upgrade changed the capacity
+        let upgraded: Queue<u8> = Queue::with_capacity("test", 5);
+        let mismatch = StorageError::CapacityMismatch {
+            stored: 3,
+            configured: 5,
+        };
+        assert_eq!(upgraded.validate(&store).unwrap_err(), mismatch);
+        assert_eq!(upgraded.push_back(&mut store, &2u8).unwrap_err(), mismatch);
+        assert_eq!(upgraded.pop_front(&mut store).unwrap_err(), mismatch);
+    }
+
+    #[test]
+    fn resize_keeps_items_in_order() {
+        let (queue, mut store) = setup_queue(3);
+        for i in 1..=3u8 {
+            assert!(queue.push_back(&mut store, &i).unwrap());
+        }
+        assert_eq!(queue.pop_front(&mut store).unwrap(), Some(1));
+        // wraps around
+        assert!(queue.push_back(&mut store, &4u8).unwrap());
+
+        // too small
+        assert!(queue.resize(&mut store, 2).is_err());
+
+        queue.resize(&mut store, 5).unwrap();
+        let upgraded: Queue<u8> = Queue::with_capacity("test", 5);
+        upgraded.validate(&store).unwrap();
+        let items: StdResult<Vec<_>> = upgraded.iter(&store).collect();
+        assert_eq!(items.unwrap(), vec![2, 3, 4]);
+
+        for i in 5..=6u8 {
+            assert!(upgraded.push_back(&mut store, &i).unwrap());
+        }
+        assert!(!upgraded.push_back(&mut store, &7u8).unwrap());
+        assert_eq!(upgraded.drain(&mut store, 10).unwrap(), vec![2, 3, 4, 5, 6]);
+
+        // and back down
+        upgraded.resize(&mut store, 1).unwrap();
+        assert!(queue.validate(&store).is_err());
+        let small: Queue<u8> = Queue::with_capacity("test", 1);
+        assert!(small.push_back(&mut store, &8u8).unwrap());
+        assert!(!small.push_back(&mut store, &9u8).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "migration")]
+    fn export_and_import() {
+        let (queue, mut store) = setup_queue(3);
+        for i in 1..=3u8 {
+            assert!(queue.push_back(&mut store, &i).unwrap());
+        }
+        assert_eq!(queue.pop_front(&mut store).unwrap(), Some(1));
+        let exported = queue.export(&store);
+        assert_eq!(exported.len(), 2);
+
+        let imported: Queue<u8> = Queue::with_capacity("imported", 3);
+        assert!(imported.push_back(&mut store, &0u8).unwrap());
+        imported.import(&mut store, exported.clone()).unwrap();
+        let items: StdResult<Vec<_>> = imported.iter(&store).collect();
+        assert_eq!(items.unwrap(), vec![0, 2, 3]);
+
+        // no room left
+        assert!(imported.import(&mut store, exported).is_err());
+    }
+
+    #[test]
+    fn json_backend_works() {
+        use crate::serialization::Json;
+
+        let queue: Queue<String, Json> = Queue::with_capacity("json", 2);
+        let mut store = MockStorage::new();
+        assert!(queue.push_back(&mut store, &"a".to_string()).unwrap());
+        assert!(queue.push_back(&mut store, &"b".to_string()).unwrap());
+        assert!(!queue.push_back(&mut store, &"c".to_string()).unwrap());
+
+        let items: StdResult<Vec<_>> = queue.iter(&store).collect();
+        assert_eq!(items.unwrap(), vec!["a", "b"]);
+        assert_eq!(queue.pop_front(&mut store).unwrap(), Some("a".to_string()));
+        assert_eq!(queue.peek_back(&store).unwrap(), Some("b".to_string()));
+    }
+
+    #[test]
+    fn updates_items_in_place() {
+        // a full, wrapped queue
+        let (queue, mut store) = setup_queue::<(u32, u8)>(3);
+        for job in 0..3 {
+            assert!(queue.push_back(&mut store, &(job, 0)).unwrap());
+        }
+        queue.pop_front(&mut store).unwrap();
+        assert!(queue.push_back(&mut store, &(3, 0)).unwrap());
+
+        let retried = queue
+            .update_front(&mut store, |(job, retries)| -> StdResult<_> {
+                Ok((job, retries + 1))
+            })
+            .unwrap();
+        assert_eq!(retried, Some((1, 1)));
+        queue
+            .update_at(&mut store, 2, |(job, _)| -> StdResult<_> { Ok((job, 9)) })
+            .unwrap();
+        assert_eq!(
+            queue.iter(&store).collect::<StdResult<Vec<_>>>().unwrap(),
+            vec![(1, 1), (2, 0), (3, 9)]
+        );
+
+        let out_of_bounds = queue
+            .update_at(&mut store, 3, |_| -> StdResult<_> { panic!("not called") })
+            .unwrap();
+        assert_eq!(out_of_bounds, None);
+        // an error leaves the item as it was
+        assert!(queue
+            .update_front(&mut store, |_| Err(StdError::generic_err("failed")))
+            .is_err());
+        assert_eq!(queue.peek_front(&store).unwrap(), Some((1, 1)));
+    }
+
+    #[test]
+    fn retain_removes_items_in_order() {
+        // a wrapped queue
+        let (queue, mut store) = setup_queue(5);
+        for i in 0..5u8 {
+            assert!(queue.push_back(&mut store, &i).unwrap());
+        }
+        assert_eq!(queue.drain(&mut store, 2).unwrap(), vec![0, 1]);
+        for i in 5..7u8 {
+            assert!(queue.push_back(&mut store, &i).unwrap());
+        }
+
+        // only walks the first 3 items
+        assert_eq!(queue.retain(&mut store, 3, |i| i % 2 == 1).unwrap(), 2);
+        assert_eq!(
+            queue.iter(&store).collect::<StdResult<Vec<_>>>().unwrap(),
+            vec![3, 5, 6]
+        );
+        assert_eq!(queue.retain(&mut store, 10, |_| true).unwrap(), 0);
+        assert_eq!(queue.retain(&mut store, 10, |i| *i != 6).unwrap(), 1);
+        assert_eq!(
+            queue.iter(&store).collect::<StdResult<Vec<_>>>().unwrap(),
+            vec![3, 5]
+        );
+        // the freed slots are deleted
+        for slot in 2..5 {
+            assert!(!queue.map.has(&store, slot));
+        }
+
+        // and the queue keeps working
+        for i in 7..10u8 {
+            assert!(queue.push_back(&mut store, &i).unwrap());
+        }
+        assert!(!queue.push_back(&mut store, &10u8).unwrap());
+        assert_eq!(queue.drain(&mut store, 10).unwrap(), vec![3, 5, 7, 8, 9]);
+    }
 }
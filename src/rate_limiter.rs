@@ -0,0 +1,199 @@
+use cosmwasm_std::{BlockInfo, StdResult, Storage};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{StorageError, StorageResult};
+use crate::keys::PrimaryKey;
+use crate::Map;
+
+/// The length of a `RateLimiter` window
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Window {
+    /// a number of blocks
+    Blocks(u64),
+    /// a number of seconds of block time
+    Seconds(u64),
+}
+
+impl Window {
+    /// Returns the block height or time, whichever the window is measured in
+    pub(crate) fn now(&self, block: &BlockInfo) -> u64 {
+        match self {
+            Window::Blocks(_) => block.height,
+            Window::Seconds(_) => block.time,
+        }
+    }
+
+    pub(crate) fn len(&self) -> u64 {
+        match self {
+            Window::Blocks(len) | Window::Seconds(len) => *len,
+        }
+    }
+}
+
+/// The usage of a key in its current window
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+struct Usage {
+    start: u64,
+    used: u128,
+}
+
+/// RateLimiter allows every key to use up to `limit` within a window, e.g. tokens bridged or
+/// withdrawn from a faucet per address.
+///
+/// The window of a key starts with its first use after the previous one ended, so windows of
+/// different keys don't line up and there is no rush when they reset.
+pub struct RateLimiter<'a, K> {
+    usage: Map<'a, K, Usage>,
+    window: Window,
+    limit: u128,
+}
+
+impl<'a, K> RateLimiter<'a, K> {
+    /// Panics if the window is empty
+    pub const fn new(namespace: &'a str, window: Window, limit: u128) -> Self {
+        assert!(
+            !matches!(window, Window::Blocks(0) | Window::Seconds(0)),
+            "a RateLimiter window must not be empty"
+        );
+        RateLimiter {
+            usage: Map::new(namespace),
+            window,
+            limit,
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.usage.namespace()
+    }
+
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    pub fn limit(&self) -> u128 {
+        self.limit
+    }
+}
+
+impl<'a, K: PrimaryKey<'a>> RateLimiter<'a, K> {
+    /// Returns the usage of `k` in its window, or an unused window from `block` if that ended
+    fn current_usage(&self, store: &dyn Storage, block: &BlockInfo, k: K) -> StdResult<Usage> {
+        let now = self.window.now(block);
+        let usage = self.usage.may_load(store, k)?;
+        Ok(match usage {
+            Some(usage) if now < usage.start.saturating_add(self.window.len()) => usage,
+            _ => Usage {
+                start: now,
+                used: 0,
+            },
+        })
+    }
+
+    /// Returns how much `k` can still use in the window open at `block`
+    pub fn remaining(&self, store: &dyn Storage, block: &BlockInfo, k: K) -> StdResult<u128> {
+        let usage = self.current_usage(store, block, k)?;
+        Ok(self.limit.saturating_sub(usage.used))
+    }
+
+    /// Uses `amount` for `k`, returns how much it can still use in the window.
+    ///
+    /// Returns `StorageError::RateLimitExceeded` and uses nothing if that is less than `amount`
+    pub fn check_and_consume(
+        &self,
+        store: &mut dyn Storage,
+        block: &BlockInfo,
+        k: K,
+        amount: u128,
+    ) -> StorageResult<u128> {
+        let mut usage = self.current_usage(store, block, k.clone())?;
+        let remaining = self.limit.saturating_sub(usage.used);
+        if amount > remaining {
+            return Err(StorageError::RateLimitExceeded {
+                remaining,
+                resets_at: usage.start.saturating_add(self.window.len()),
+            });
+        }
+        usage.used += amount;
+        self.usage.save(store, k, &usage)?;
+        Ok(remaining - amount)
+    }
+
+    /// Forgets the usage of `k`, so its next use starts a new window
+    pub fn reset(&self, store: &mut dyn Storage, k: K) {
+        self.usage.remove(store, k)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::{mock_env, MockStorage};
+    use cosmwasm_std::HumanAddr as Addr;
+
+    const FAUCET: RateLimiter<&Addr> = RateLimiter::new("faucet", Window::Blocks(10), 100);
+
+    fn block(height: u64, time: u64) -> BlockInfo {
+        let mut block = mock_env("creator", &[]).block;
+        block.height = height;
+        block.time = time;
+        block
+    }
+
+    #[test]
+    fn limits_usage_per_window() {
+        let mut store = MockStorage::new();
+        let alice = Addr::from("alice");
+        let bob = Addr::from("bob");
+
+        assert_eq!(
+            FAUCET
+                .check_and_consume(&mut store, &block(5, 0), &alice, 60)
+                .unwrap(),
+            40
+        );
+        assert_eq!(
+            FAUCET
+                .check_and_consume(&mut store, &block(9, 0), &alice, 50)
+                .unwrap_err(),
+            StorageError::RateLimitExceeded {
+                remaining: 40,
+                resets_at: 15
+            }
+        );
+        assert_eq!(FAUCET.remaining(&store, &block(14, 0), &alice).unwrap(), 40);
+        assert_eq!(FAUCET.remaining(&store, &block(14, 0), &bob).unwrap(), 100);
+
+        // the window started at 5, so it is over at 15
+        assert_eq!(
+            FAUCET
+                .check_and_consume(&mut store, &block(15, 0), &alice, 100)
+                .unwrap(),
+            0
+        );
+        assert!(FAUCET
+            .check_and_consume(&mut store, &block(20, 0), &alice, 1)
+            .is_err());
+
+        FAUCET.reset(&mut store, &alice);
+        assert_eq!(
+            FAUCET.remaining(&store, &block(20, 0), &alice).unwrap(),
+            100
+        );
+    }
+
+    #[test]
+    fn time_windows() {
+        let mut store = MockStorage::new();
+        let bridge: RateLimiter<&str> = RateLimiter::new("bridge", Window::Seconds(3600), 1000);
+
+        bridge
+            .check_and_consume(&mut store, &block(1, 1000), "eth", 1000)
+            .unwrap();
+        assert!(bridge
+            .check_and_consume(&mut store, &block(500, 4599), "eth", 1)
+            .is_err());
+        bridge
+            .check_and_consume(&mut store, &block(501, 4600), "eth", 1)
+            .unwrap();
+    }
+}
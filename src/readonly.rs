@@ -0,0 +1,327 @@
+//! Views of an `Item` or a `Map` that only expose their read methods, so a query handler taking a
+//! `ReadonlyMap` or `ReadonlyItem` can't write to them, whatever storage it is given.
+//!
+//! A view borrows the collection it is created from with `Item::readonly` or `Map::readonly`, so
+//! it reads the same namespace with the same serialization backend, at no cost.
+
+use cosmwasm_std::{HumanAddr as Addr, Querier, Storage};
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, StdResult, KV};
+
+#[cfg(feature = "iterator")]
+use crate::cursor::{Cursor, ScanBatch};
+use crate::error::StorageResult;
+#[cfg(feature = "iterator")]
+use crate::keys::KeyDeserialize;
+use crate::keys::PrimaryKey;
+#[cfg(feature = "iterator")]
+use crate::numeric::Numeric;
+#[cfg(feature = "iterator")]
+use crate::prefix::Bound;
+use crate::serialization::{Bincode2, Serde};
+use crate::{Item, Map};
+
+/// The read methods of an `Item`, created by `Item::readonly`
+pub struct ReadonlyItem<'i, 'a, T, Ser = Bincode2> {
+    item: &'i Item<'a, T, Ser>,
+}
+
+impl<'a, T, Ser> Item<'a, T, Ser> {
+    /// Returns a view of the item that can only read it
+    pub fn readonly(&self) -> ReadonlyItem<'_, 'a, T, Ser> {
+        ReadonlyItem { item: self }
+    }
+}
+
+impl<'i, 'a, T, Ser> Clone for ReadonlyItem<'i, 'a, T, Ser> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'i, 'a, T, Ser> Copy for ReadonlyItem<'i, 'a, T, Ser> {}
+
+impl<'i, 'a, T, Ser: Serde<T>> ReadonlyItem<'i, 'a, T, Ser> {
+    pub fn as_slice(&self) -> &[u8] {
+        self.item.as_slice()
+    }
+
+    pub fn load(&self, store: &dyn Storage) -> StorageResult<T> {
+        self.item.load(store)
+    }
+
+    pub fn may_load(&self, store: &dyn Storage) -> StorageResult<Option<T>> {
+        self.item.may_load(store)
+    }
+
+    pub fn load_or(&self, store: &dyn Storage, default: T) -> StorageResult<T> {
+        self.item.load_or(store, default)
+    }
+
+    pub fn load_or_default(&self, store: &dyn Storage) -> StorageResult<T>
+    where
+        T: Default,
+    {
+        self.item.load_or_default(store)
+    }
+
+    pub fn load_raw(&self, store: &dyn Storage) -> StorageResult<Vec<u8>> {
+        self.item.load_raw(store)
+    }
+
+    pub fn may_load_raw(&self, store: &dyn Storage) -> Option<Vec<u8>> {
+        self.item.may_load_raw(store)
+    }
+
+    pub fn query<Q: Querier>(
+        &self,
+        querier: &Q,
+        remote_contract: Addr,
+        code_hash: String,
+    ) -> StorageResult<T> {
+        self.item.query(querier, remote_contract, code_hash)
+    }
+}
+
+/// The read methods of a `Map`, created by `Map::readonly`
+pub struct ReadonlyMap<'m, 'a, K, T, Ser = Bincode2> {
+    map: &'m Map<'a, K, T, Ser>,
+}
+
+impl<'a, K, T, Ser> Map<'a, K, T, Ser> {
+    /// Returns a view of the map that can only read it
+    pub fn readonly(&self) -> ReadonlyMap<'_, 'a, K, T, Ser> {
+        ReadonlyMap { map: self }
+    }
+}
+
+impl<'m, 'a, K, T, Ser> Clone for ReadonlyMap<'m, 'a, K, T, Ser> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'m, 'a, K, T, Ser> Copy for ReadonlyMap<'m, 'a, K, T, Ser> {}
+
+impl<'m, 'a, K, T, Ser> ReadonlyMap<'m, 'a, K, T, Ser> {
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+
+    pub fn name(&self) -> &[u8] {
+        self.map.name()
+    }
+}
+
+impl<'m, 'a, K, T, Ser> ReadonlyMap<'m, 'a, K, T, Ser>
+where
+    K: PrimaryKey<'a>,
+    Ser: Serde<T>,
+{
+    pub fn load(&self, store: &dyn Storage, k: K) -> StorageResult<T> {
+        self.map.load(store, k)
+    }
+
+    pub fn may_load(&self, store: &dyn Storage, k: K) -> StorageResult<Option<T>> {
+        self.map.may_load(store, k)
+    }
+
+    pub fn load_or_default(&self, store: &dyn Storage, k: K) -> StorageResult<T>
+    where
+        T: Default,
+    {
+        self.map.load_or_default(store, k)
+    }
+
+    pub fn has(&self, store: &dyn Storage, k: K) -> bool {
+        self.map.has(store, k)
+    }
+
+    pub fn load_raw(&self, store: &dyn Storage, k: K) -> StorageResult<Vec<u8>> {
+        self.map.load_raw(store, k)
+    }
+
+    pub fn may_load_raw(&self, store: &dyn Storage, k: K) -> Option<Vec<u8>> {
+        self.map.may_load_raw(store, k)
+    }
+
+    pub fn query<Q: Querier>(
+        &self,
+        querier: &Q,
+        remote_contract: Addr,
+        code_hash: String,
+        k: K,
+    ) -> StorageResult<Option<T>> {
+        self.map.query(querier, remote_contract, code_hash, k)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'m, 'a, K, T, Ser> ReadonlyMap<'m, 'a, K, T, Ser>
+where
+    K: PrimaryKey<'a>,
+    Ser: Serde<T>,
+{
+    pub fn has_prefix(&self, store: &dyn Storage, p: K::Prefix) -> bool
+    where
+        K::Suffix: PrimaryKey<'a>,
+    {
+        self.map.has_prefix(store, p)
+    }
+
+    pub fn has_sub_prefix(&self, store: &dyn Storage, p: K::SubPrefix) -> bool
+    where
+        K::SuperSuffix: PrimaryKey<'a>,
+    {
+        self.map.has_sub_prefix(store, p)
+    }
+
+    pub fn range_raw<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<KV<T>>> + 'c>
+    where
+        T: 'c,
+    {
+        self.map.range_raw(store, min, max, order)
+    }
+
+    pub fn keys_raw<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + 'c>
+    where
+        T: 'c,
+    {
+        self.map.keys_raw(store, min, max, order)
+    }
+
+    pub fn count(&self, store: &dyn Storage, max_entries: u32) -> StorageResult<u32> {
+        self.map.count(store, max_entries)
+    }
+
+    pub fn fold<A, F>(
+        &self,
+        store: &dyn Storage,
+        max_entries: u32,
+        init: A,
+        f: F,
+    ) -> StorageResult<A>
+    where
+        F: FnMut(A, T) -> StdResult<A>,
+    {
+        self.map.fold(store, max_entries, init, f)
+    }
+
+    pub fn sum(&self, store: &dyn Storage, max_entries: u32) -> StorageResult<T>
+    where
+        T: Numeric,
+    {
+        self.map.sum(store, max_entries)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'m, 'a, K, T, Ser> ReadonlyMap<'m, 'a, K, T, Ser>
+where
+    K: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<T>,
+{
+    pub fn range<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'c>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.map.range(store, min, max, order)
+    }
+
+    pub fn keys<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<K::Output>> + 'c>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.map.keys(store, min, max, order)
+    }
+
+    pub fn range_from_cursor(
+        &self,
+        store: &dyn Storage,
+        cursor: &Cursor,
+        limit: u32,
+    ) -> StorageResult<ScanBatch<K::Output, T>> {
+        self.map.range_from_cursor(store, cursor, limit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const CONFIG: Item<u64> = Item::new("config");
+    const BALANCES: Map<&str, u64> = Map::new("balances");
+
+    /// A query handler that can only read
+    fn balance(
+        config: ReadonlyItem<u64>,
+        balances: ReadonlyMap<&str, u64>,
+        store: &dyn Storage,
+        owner: &str,
+    ) -> StorageResult<u64> {
+        Ok(balances.load_or_default(store, owner)? * config.load(store)?)
+    }
+
+    #[test]
+    fn reads_through_views() {
+        let mut store = MockStorage::new();
+        CONFIG.save(&mut store, &2).unwrap();
+        BALANCES.save(&mut store, "alice", &21).unwrap();
+
+        assert_eq!(
+            balance(CONFIG.readonly(), BALANCES.readonly(), &store, "alice").unwrap(),
+            42
+        );
+        assert_eq!(
+            balance(CONFIG.readonly(), BALANCES.readonly(), &store, "bob").unwrap(),
+            0
+        );
+        assert!(BALANCES.readonly().has(&store, "alice"));
+        assert_eq!(BALANCES.readonly().name(), b"balances");
+        assert_eq!(CONFIG.readonly().as_slice(), b"config");
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn ranges_through_views() {
+        let mut store = MockStorage::new();
+        BALANCES.save(&mut store, "alice", &1).unwrap();
+        BALANCES.save(&mut store, "bob", &2).unwrap();
+
+        let map = BALANCES;
+        let balances = map.readonly();
+        let all = balances
+            .range(&store, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(all, vec![("alice".to_string(), 1), ("bob".to_string(), 2)]);
+        assert_eq!(balances.sum(&store, 10).unwrap(), 3);
+    }
+}
@@ -0,0 +1,189 @@
+use std::any::type_name;
+use std::fmt;
+use std::marker::PhantomData;
+
+use cosmwasm_std::{StdResult, Storage};
+use serde::{Deserialize, Serialize};
+
+use crate::error::StorageError;
+use crate::helpers::{may_deserialize, must_deserialize};
+use crate::keys::PrimaryKey;
+use crate::path::Path;
+use crate::serialization::Serde;
+use crate::Map;
+
+/// Ref stores the key of an entry of another `Map` holding values of type `T`, to relate the two
+/// instead of copying the value. It is stored like the key itself.
+///
+/// The map may be keyed by `K` or a borrowed form of it, e.g. a `Ref<Addr, Account>` resolves
+/// in a `Map<&Addr, Account>`. Nothing keeps the entry from being removed afterwards, so
+/// `resolve` returns a `StorageError::NotFound` for a dangling reference.
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Ref<K, T> {
+    key: K,
+    #[serde(skip)]
+    target_type: PhantomData<fn() -> T>,
+}
+
+impl<'k, K: PrimaryKey<'k>, T> Ref<K, T> {
+    pub fn new(key: K) -> Self {
+        Ref {
+            key,
+            target_type: PhantomData,
+        }
+    }
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// The path of the referenced entry in `map`, the same path the map uses for the key
+    fn path<'m, MK, Ser: Serde<T>>(&self, map: &Map<'m, MK, T, Ser>) -> Path<T, Ser> {
+        Path::new(map.namespace(), &[&self.key.joined_key()])
+    }
+
+    /// Returns the referenced value, or `StorageError::NotFound` if `map` holds none
+    pub fn resolve<'m, MK, Ser: Serde<T>>(
+        &self,
+        store: &dyn Storage,
+        map: &Map<'m, MK, T, Ser>,
+    ) -> StdResult<T> {
+        let value = self.path(map).may_load_raw(store);
+        Ok(must_deserialize::<T, Ser>(
+            &value,
+            map.name(),
+            &self.key.joined_key(),
+        )?)
+    }
+
+    /// Returns the referenced value, None if `map` holds none
+    pub fn may_resolve<'m, MK, Ser: Serde<T>>(
+        &self,
+        store: &dyn Storage,
+        map: &Map<'m, MK, T, Ser>,
+    ) -> StdResult<Option<T>> {
+        let value = self.path(map).may_load_raw(store);
+        Ok(may_deserialize::<T, Ser>(
+            &value,
+            map.name(),
+            &self.key.joined_key(),
+        )?)
+    }
+
+    /// Returns `StorageError::NotFound` unless `map` holds the referenced entry,
+    /// without parsing it
+    pub fn validate<'m, MK, Ser: Serde<T>>(
+        &self,
+        store: &dyn Storage,
+        map: &Map<'m, MK, T, Ser>,
+    ) -> StdResult<()> {
+        if self.path(map).has(store) {
+            return Ok(());
+        }
+        Err(StorageError::NotFound {
+            type_name: type_name::<T>(),
+            namespace: String::from_utf8_lossy(map.name()).into_owned(),
+            key: self.key.joined_key(),
+        }
+        .into())
+    }
+}
+
+impl<K: Clone, T> Clone for Ref<K, T> {
+    fn clone(&self) -> Self {
+        Ref {
+            key: self.key.clone(),
+            target_type: PhantomData,
+        }
+    }
+}
+
+impl<K: fmt::Debug, T> fmt::Debug for Ref<K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Ref").field(&self.key).finish()
+    }
+}
+
+impl<K: PartialEq, T> PartialEq for Ref<K, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Eq, T> Eq for Ref<K, T> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::{HumanAddr as Addr, StdError};
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    struct Account {
+        balance: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    struct Order {
+        account: Ref<Addr, Account>,
+        amount: u64,
+    }
+
+    const ACCOUNTS: Map<&Addr, Account> = Map::new("accounts");
+    const ORDERS: Map<u64, Order> = Map::new("orders");
+
+    #[test]
+    fn resolves_the_referenced_entry() {
+        let mut store = MockStorage::new();
+        let alice = Addr::from("alice");
+        let account = Account { balance: 100 };
+        ACCOUNTS.save(&mut store, &alice, &account).unwrap();
+
+        let account_ref = Ref::new(alice.clone());
+        account_ref.validate(&store, &ACCOUNTS).unwrap();
+        let order = Order {
+            account: account_ref,
+            amount: 5,
+        };
+        ORDERS.save(&mut store, 1, &order).unwrap();
+
+        let order = ORDERS.load(&store, 1).unwrap();
+        assert_eq!(order.account.key(), &alice);
+        assert_eq!(order.account.resolve(&store, &ACCOUNTS).unwrap(), account);
+        // stored like the key itself
+        assert_eq!(
+            bincode2::serialize(&order.account).unwrap(),
+            bincode2::serialize(&alice).unwrap()
+        );
+    }
+
+    #[test]
+    fn dangling_references() {
+        let mut store = MockStorage::new();
+        let bob = Addr::from("bob");
+        let account_ref: Ref<Addr, Account> = Ref::new(bob.clone());
+
+        match account_ref.validate(&store, &ACCOUNTS).unwrap_err() {
+            StdError::NotFound { kind, .. } => assert!(
+                kind.contains(r#"Account in namespace "accounts" at key 0x626f62"#),
+                "{}",
+                kind
+            ),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        assert!(account_ref.resolve(&store, &ACCOUNTS).is_err());
+        assert_eq!(account_ref.may_resolve(&store, &ACCOUNTS).unwrap(), None);
+
+        ACCOUNTS
+            .save(&mut store, &bob, &Account { balance: 1 })
+            .unwrap();
+        account_ref.validate(&store, &ACCOUNTS).unwrap();
+        ACCOUNTS.remove(&mut store, &bob);
+        assert!(account_ref.validate(&store, &ACCOUNTS).is_err());
+    }
+}
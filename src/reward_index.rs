@@ -0,0 +1,278 @@
+use cosmwasm_std::{StdResult, Storage, Uint128};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{StorageError, StorageResult};
+use crate::keys::PrimaryKey;
+use crate::{Item, Map};
+
+/// The factor the index is scaled by, so rewards smaller than the total stake still move it
+const SCALE: u128 = 1_000_000_000_000_000_000;
+
+/// The global state of a `RewardIndex`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GlobalIndex {
+    /// Rewards per unit of stake distributed so far, scaled by 10^18
+    pub index: Uint128,
+    /// Rewards added while nothing was staked, distributed by the next update with stake
+    pub undistributed: Uint128,
+}
+
+/// The state of a user of a `RewardIndex` at their last settlement
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UserCheckpoint {
+    /// The global index the user was settled at
+    pub index: Uint128,
+    /// Rewards settled but not claimed yet
+    pub pending: Uint128,
+}
+
+/// RewardIndex distributes rewards to stakers in proportion to their stake, without touching
+/// every staker when rewards come in.
+///
+/// `update_global` adds rewards to a global index of rewards per unit of stake. Every user keeps
+/// a checkpoint of the index, and `settle_user` credits them their stake times the growth of the
+/// index since then. The stakes themselves are kept by the contract, e.g. in a `Tally`.
+///
+/// A user must be settled with their old stake before it changes, and before their first stake,
+/// which checkpoints them at the current index. Rewards are rounded down, the dust stays in the
+/// contract. A stake times the growth of the index, scaled by 10^18, must fit in a `u128`, a
+/// settlement returns `StorageError::Overflow` otherwise. The global state is stored under the
+/// namespace followed by `_global`.
+pub struct RewardIndex<'a, K> {
+    users: Map<'a, K, UserCheckpoint>,
+}
+
+impl<'a, K> RewardIndex<'a, K> {
+    const GLOBAL: &'static [u8] = b"_global";
+
+    pub const fn new(namespace: &'a str) -> Self {
+        RewardIndex {
+            users: Map::new(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.users.namespace()
+    }
+
+    fn global_item(&self) -> Item<'static, GlobalIndex> {
+        Item::new_owned([self.namespace(), Self::GLOBAL].concat())
+    }
+
+    pub fn global(&self, store: &dyn Storage) -> StdResult<GlobalIndex> {
+        Ok(self.global_item().load_or_default(store)?)
+    }
+
+    /// Distributes `rewards` over `total_stake`, returns the new global index.
+    /// Without stake the rewards are kept for the next update
+    pub fn update_global(
+        &self,
+        store: &mut dyn Storage,
+        rewards: Uint128,
+        total_stake: Uint128,
+    ) -> StorageResult<Uint128> {
+        let mut global = self.global(store)?;
+        let rewards = global
+            .undistributed
+            .u128()
+            .checked_add(rewards.u128())
+            .ok_or_else(overflow)?;
+        if total_stake.is_zero() {
+            global.undistributed = Uint128(rewards);
+        } else {
+            let growth = rewards.checked_mul(SCALE).ok_or_else(overflow)? / total_stake.u128();
+            global.index = Uint128(
+                global
+                    .index
+                    .u128()
+                    .checked_add(growth)
+                    .ok_or_else(overflow)?,
+            );
+            global.undistributed = Uint128::zero();
+        }
+        self.global_item().save(store, &global)?;
+        Ok(global.index)
+    }
+}
+
+impl<'a, K: PrimaryKey<'a>> RewardIndex<'a, K> {
+    /// Returns the last checkpoint of `user`, None if they were never settled
+    pub fn checkpoint(&self, store: &dyn Storage, user: K) -> StdResult<Option<UserCheckpoint>> {
+        Ok(self.users.may_load(store, user)?)
+    }
+
+    /// Returns the checkpoint `user` would be settled to with `stake`
+    fn settled(
+        &self,
+        store: &dyn Storage,
+        user: K,
+        stake: Uint128,
+    ) -> StorageResult<UserCheckpoint> {
+        let index = self.global(store)?.index;
+        let checkpoint = match self.users.may_load(store, user)? {
+            Some(checkpoint) => checkpoint,
+            // a new user earns from the current index on
+            None => {
+                return Ok(UserCheckpoint {
+                    index,
+                    pending: Uint128::zero(),
+                })
+            }
+        };
+        let growth = index.u128() - checkpoint.index.u128();
+        let earned = stake.u128().checked_mul(growth).ok_or_else(overflow)? / SCALE;
+        Ok(UserCheckpoint {
+            index,
+            pending: Uint128(
+                checkpoint
+                    .pending
+                    .u128()
+                    .checked_add(earned)
+                    .ok_or_else(overflow)?,
+            ),
+        })
+    }
+
+    /// Returns the rewards `user` has earned with `stake` and not claimed, without settling them
+    pub fn pending(&self, store: &dyn Storage, user: K, stake: Uint128) -> StorageResult<Uint128> {
+        Ok(self.settled(store, user, stake)?.pending)
+    }
+
+    /// Credits `user` the rewards earned with `stake` since their last settlement and moves their
+    /// checkpoint to the current index, returns the rewards they have not claimed
+    pub fn settle_user(
+        &self,
+        store: &mut dyn Storage,
+        user: K,
+        stake: Uint128,
+    ) -> StorageResult<Uint128> {
+        let checkpoint = self.settled(store, user.clone(), stake)?;
+        self.users.save(store, user, &checkpoint)?;
+        Ok(checkpoint.pending)
+    }
+
+    /// Settles `user` and returns the rewards they have not claimed, which the caller pays out
+    pub fn claim(
+        &self,
+        store: &mut dyn Storage,
+        user: K,
+        stake: Uint128,
+    ) -> StorageResult<Uint128> {
+        let mut checkpoint = self.settled(store, user.clone(), stake)?;
+        let claimed = checkpoint.pending;
+        checkpoint.pending = Uint128::zero();
+        self.users.save(store, user, &checkpoint)?;
+        Ok(claimed)
+    }
+
+    /// Removes the checkpoint of `user`, e.g. after they unstaked everything and claimed
+    pub fn remove_user(&self, store: &mut dyn Storage, user: K) {
+        self.users.remove(store, user)
+    }
+}
+
+fn overflow() -> StorageError {
+    StorageError::Overflow {
+        collection: "RewardIndex",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const REWARDS: RewardIndex<&str> = RewardIndex::new("rewards");
+
+    #[test]
+    fn distributes_in_proportion_to_stake() {
+        let mut store = MockStorage::new();
+        REWARDS
+            .settle_user(&mut store, "alice", Uint128::zero())
+            .unwrap();
+        REWARDS
+            .settle_user(&mut store, "bob", Uint128::zero())
+            .unwrap();
+        // alice stakes 100, bob 300
+        REWARDS
+            .update_global(&mut store, Uint128(400), Uint128(400))
+            .unwrap();
+        assert_eq!(
+            REWARDS.pending(&store, "alice", Uint128(100)).unwrap(),
+            Uint128(100)
+        );
+        assert_eq!(
+            REWARDS
+                .settle_user(&mut store, "bob", Uint128(300))
+                .unwrap(),
+            Uint128(300)
+        );
+
+        // bob unstakes after being settled, only alice earns
+        REWARDS
+            .update_global(&mut store, Uint128(50), Uint128(100))
+            .unwrap();
+        assert_eq!(
+            REWARDS
+                .settle_user(&mut store, "bob", Uint128::zero())
+                .unwrap(),
+            Uint128(300)
+        );
+        assert_eq!(
+            REWARDS.claim(&mut store, "alice", Uint128(100)).unwrap(),
+            Uint128(150)
+        );
+        assert_eq!(
+            REWARDS.pending(&store, "alice", Uint128(100)).unwrap(),
+            Uint128::zero()
+        );
+    }
+
+    #[test]
+    fn new_users_earn_from_the_current_index() {
+        let mut store = MockStorage::new();
+        REWARDS
+            .update_global(&mut store, Uint128(1_000), Uint128(10))
+            .unwrap();
+
+        assert_eq!(
+            REWARDS
+                .settle_user(&mut store, "carol", Uint128(10))
+                .unwrap(),
+            Uint128::zero()
+        );
+        REWARDS
+            .update_global(&mut store, Uint128(30), Uint128(30))
+            .unwrap();
+        assert_eq!(
+            REWARDS.pending(&store, "carol", Uint128(10)).unwrap(),
+            Uint128(10)
+        );
+    }
+
+    #[test]
+    fn keeps_rewards_without_stake() {
+        let mut store = MockStorage::new();
+        REWARDS
+            .settle_user(&mut store, "alice", Uint128::zero())
+            .unwrap();
+        let index = REWARDS
+            .update_global(&mut store, Uint128(70), Uint128::zero())
+            .unwrap();
+        assert_eq!(index, Uint128::zero());
+        assert_eq!(REWARDS.global(&store).unwrap().undistributed, Uint128(70));
+
+        REWARDS
+            .update_global(&mut store, Uint128(30), Uint128(3))
+            .unwrap();
+        assert_eq!(
+            REWARDS.global(&store).unwrap().undistributed,
+            Uint128::zero()
+        );
+        // rounded down
+        assert_eq!(
+            REWARDS.pending(&store, "alice", Uint128(2)).unwrap(),
+            Uint128(66)
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use cosmwasm_std::{Env, StdResult, Storage};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::{StorageError, StorageResult};
+use crate::keys::{FromBlock, KeyDeserialize, Prefixer, PrimaryKey, Timestamp};
+use crate::path::Path;
+use crate::serialization::{Bincode2, Serde};
+use crate::TimeQueue;
+
+/// Scheduler holds tasks until they are due, e.g. payouts or expiries a contract runs in a later
+/// handler. It is a `TimeQueue` that remembers when each task is due, so it can be cancelled.
+///
+/// `schedule` returns an id for the task that stays valid until the task is returned by `due` or
+/// cancelled, ids are never reused. Tasks are stored under their time, a `Timestamp` by default or
+/// a `Height`, and their id, so `due` only reads the tasks it returns. Tasks due at the same time
+/// are returned in the order they were scheduled.
+pub struct Scheduler<'a, T, M = Timestamp, Ser = Bincode2> {
+    queue: TimeQueue<'a, T, M, Ser>,
+}
+
+impl<'a, T, M, Ser> Scheduler<'a, T, M, Ser> {
+    const WHEN: &'static [u8] = b"_when";
+
+    pub const fn new(namespace: &'a str) -> Self {
+        Self {
+            queue: TimeQueue::new(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.queue.namespace()
+    }
+
+    /// Returns the number of scheduled tasks, due or not
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.queue.len(store)
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.queue.is_empty(store)
+    }
+}
+
+impl<'a, T, M, Ser> Scheduler<'a, T, M, Ser>
+where
+    T: Serialize + DeserializeOwned + 'static,
+    M: PrimaryKey<'a>
+        + Prefixer<'a>
+        + KeyDeserialize<Output = M>
+        + FromBlock
+        + Serialize
+        + DeserializeOwned
+        + Copy
+        + 'static,
+    Ser: Serde<T>,
+{
+    /// The time the task `id` is scheduled at, kept to find the task when it is cancelled
+    fn when_path(&self, id: u32) -> Path<M> {
+        let namespace = [self.namespace(), Self::WHEN].concat();
+        Path::new(&namespace, &[&id.to_be_bytes()])
+    }
+
+    /// Schedules `task` at `when`, returns its id
+    pub fn schedule(&self, store: &mut dyn Storage, when: M, task: &T) -> StorageResult<u32> {
+        // the id is the sequence number of the queue, which would wrap around and reuse ids
+        if self.queue.next_seq(store) == u32::MAX {
+            return Err(StorageError::CapacityExceeded {
+                collection: "Scheduler",
+            });
+        }
+
+        let id = self.queue.push(store, when, task)?;
+        self.when_path(id).save(store, &when)?;
+        Ok(id)
+    }
+
+    /// Returns the time the task `id` is scheduled at, None if it was cancelled or returned as due
+    pub fn when(&self, store: &dyn Storage, id: u32) -> StdResult<Option<M>> {
+        self.when_path(id).may_load(store)
+    }
+
+    /// Removes the task `id` and returns it, None if it was cancelled or returned as due before
+    pub fn cancel(&self, store: &mut dyn Storage, id: u32) -> StdResult<Option<T>> {
+        let when = match self.when(store, id)? {
+            Some(when) => when,
+            None => return Ok(None),
+        };
+        self.when_path(id).remove(store);
+        self.queue.remove(store, when, id)
+    }
+
+    /// Returns the time of the task due first, None if no task is scheduled
+    pub fn next_due(&self, store: &dyn Storage) -> StdResult<Option<M>> {
+        self.queue.next_maturity(store)
+    }
+
+    /// Removes and returns up to `limit` tasks due at or before the current block, with their ids,
+    /// the earliest first
+    pub fn due(&self, store: &mut dyn Storage, env: &Env, limit: u32) -> StdResult<Vec<(u32, T)>> {
+        let entries = self
+            .queue
+            .pop_mature_entries(store, M::from_block(&env.block), limit)?;
+        for ((_, id), _) in &entries {
+            self.when_path(*id).remove(store);
+        }
+
+        Ok(entries
+            .into_iter()
+            .map(|((_, id), task)| (id, task))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::{mock_env, MockStorage};
+
+    use crate::keys::Height;
+
+    const PAYOUTS: Scheduler<String> = Scheduler::new("payouts");
+
+    fn env_at(time: u64) -> Env {
+        let mut env = mock_env("sender", &[]);
+        env.block.time = time;
+        env
+    }
+
+    #[test]
+    fn returns_due_tasks_in_order() {
+        let mut store = MockStorage::new();
+        let late = PAYOUTS
+            .schedule(&mut store, Timestamp(300), &"late".to_string())
+            .unwrap();
+        let first = PAYOUTS
+            .schedule(&mut store, Timestamp(100), &"first".to_string())
+            .unwrap();
+        let second = PAYOUTS
+            .schedule(&mut store, Timestamp(100), &"second".to_string())
+            .unwrap();
+        assert_eq!((late, first, second), (0, 1, 2));
+        assert_eq!(PAYOUTS.len(&store), 3);
+        assert_eq!(PAYOUTS.next_due(&store).unwrap(), Some(Timestamp(100)));
+
+        assert!(PAYOUTS.due(&mut store, &env_at(99), 10).unwrap().is_empty());
+        assert_eq!(
+            PAYOUTS.due(&mut store, &env_at(200), 1).unwrap(),
+            vec![(first, "first".to_string())]
+        );
+        assert_eq!(
+            PAYOUTS.due(&mut store, &env_at(200), 10).unwrap(),
+            vec![(second, "second".to_string())]
+        );
+        assert_eq!(PAYOUTS.when(&store, second).unwrap(), None);
+        assert_eq!(PAYOUTS.when(&store, late).unwrap(), Some(Timestamp(300)));
+        assert_eq!(PAYOUTS.len(&store), 1);
+    }
+
+    #[test]
+    fn cancels_tasks_by_id() {
+        let mut store = MockStorage::new();
+        let a = PAYOUTS
+            .schedule(&mut store, Timestamp(100), &"a".to_string())
+            .unwrap();
+        let b = PAYOUTS
+            .schedule(&mut store, Timestamp(100), &"b".to_string())
+            .unwrap();
+
+        assert_eq!(
+            PAYOUTS.cancel(&mut store, a).unwrap(),
+            Some("a".to_string())
+        );
+        assert_eq!(PAYOUTS.cancel(&mut store, a).unwrap(), None);
+        assert_eq!(PAYOUTS.len(&store), 1);
+        assert_eq!(
+            PAYOUTS.due(&mut store, &env_at(100), 10).unwrap(),
+            vec![(b, "b".to_string())]
+        );
+        // a task returned as due can't be cancelled
+        assert_eq!(PAYOUTS.cancel(&mut store, b).unwrap(), None);
+        assert!(PAYOUTS.is_empty(&store));
+
+        // ids are not reused
+        let c = PAYOUTS
+            .schedule(&mut store, Timestamp(100), &"c".to_string())
+            .unwrap();
+        assert_eq!(c, 2);
+    }
+
+    #[test]
+    fn schedules_by_height() {
+        let mut store = MockStorage::new();
+        let unlocks: Scheduler<u64, Height> = Scheduler::new("unlocks");
+        unlocks.schedule(&mut store, Height(12_345), &1).unwrap();
+        unlocks.schedule(&mut store, Height(12_346), &2).unwrap();
+
+        let env = mock_env("sender", &[]);
+        assert_eq!(unlocks.due(&mut store, &env, 10).unwrap(), vec![(0, 1)]);
+    }
+}
@@ -0,0 +1,324 @@
+//! The serialization backends values can be stored with.
+//!
+//! Every collection takes the backend as its last type parameter, defaulting to `Bincode2`.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use cosmwasm_std::StdResult;
+
+#[cfg(feature = "compression")]
+use std::marker::PhantomData;
+
+#[cfg(any(feature = "borsh", feature = "protobuf", feature = "compression"))]
+use crate::error::StorageError;
+use crate::helpers::{deserialize_slice, serialize};
+
+/// Serde turns values of type `T` into the bytes stored and back.
+///
+/// A backend implements it for every type it can encode, e.g. all the serde types,
+/// so the collections only require `Ser: Serde<T>` of their values.
+pub trait Serde<T> {
+    fn serialize(obj: &T) -> StdResult<Vec<u8>>;
+    fn deserialize(data: &[u8]) -> StdResult<T>;
+}
+
+/// Compact binary encoding, the default
+#[derive(Copy, Clone, Debug)]
+pub struct Bincode2;
+
+impl<T: Serialize + DeserializeOwned> Serde<T> for Bincode2 {
+    fn serialize(obj: &T) -> StdResult<Vec<u8>> {
+        serialize(obj)
+    }
+
+    fn deserialize(data: &[u8]) -> StdResult<T> {
+        deserialize_slice(data)
+    }
+}
+
+/// JSON encoding, as used by cosmwasm itself. Larger, but readable by other tooling
+#[derive(Copy, Clone, Debug)]
+pub struct Json;
+
+impl<T: Serialize + DeserializeOwned> Serde<T> for Json {
+    fn serialize(obj: &T) -> StdResult<Vec<u8>> {
+        cosmwasm_std::to_vec(obj)
+    }
+
+    fn deserialize(data: &[u8]) -> StdResult<T> {
+        cosmwasm_std::from_slice(data)
+    }
+}
+
+/// Borsh encoding, for values with a fixed layout or shared with Solana/NEAR tooling.
+/// Unlike the other backends, the values implement `BorshSerialize` and `BorshDeserialize`
+/// rather than the serde traits.
+#[cfg(feature = "borsh")]
+#[derive(Copy, Clone, Debug)]
+pub struct Borsh;
+
+#[cfg(feature = "borsh")]
+impl<T: borsh::BorshSerialize + borsh::BorshDeserialize> Serde<T> for Borsh {
+    fn serialize(obj: &T) -> StdResult<Vec<u8>> {
+        borsh::to_vec(obj).map_err(|err| {
+            StorageError::Serialize {
+                type_name: std::any::type_name::<T>(),
+                source: err.to_string(),
+            }
+            .into()
+        })
+    }
+
+    fn deserialize(data: &[u8]) -> StdResult<T> {
+        borsh::from_slice(data).map_err(|err| {
+            StorageError::Deserialize {
+                type_name: std::any::type_name::<T>(),
+                source: err.to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+/// Protobuf encoding, for `prost` messages such as IBC packet data, stored in their canonical
+/// wire format so they can be handed to other chains as is
+#[cfg(feature = "protobuf")]
+#[derive(Copy, Clone, Debug)]
+pub struct Proto;
+
+#[cfg(feature = "protobuf")]
+impl<T: prost::Message + Default> Serde<T> for Proto {
+    fn serialize(obj: &T) -> StdResult<Vec<u8>> {
+        Ok(obj.encode_to_vec())
+    }
+
+    fn deserialize(data: &[u8]) -> StdResult<T> {
+        T::decode(data).map_err(|err| {
+            StorageError::Deserialize {
+                type_name: std::any::type_name::<T>(),
+                source: err.to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+/// Compresses the bytes of another backend with LZ4 once they are longer than `THRESHOLD`,
+/// for large values such as JSON documents.
+///
+/// A leading byte tells whether the rest is compressed, so values load whichever side of the
+/// threshold they were saved on. That byte also means values saved with the plain backend
+/// can't be loaded through `Compressed`, or the other way around, without a migration.
+///
+/// The decompressed size stored with the data is checked before anything is allocated: it must
+/// be above `THRESHOLD`, no more than LZ4 can expand the data to, and at most 16 MiB.
+#[cfg(feature = "compression")]
+#[derive(Copy, Clone, Debug)]
+pub struct Compressed<Ser = Bincode2, const THRESHOLD: usize = 1024>(PhantomData<Ser>);
+
+#[cfg(feature = "compression")]
+impl<Ser, const THRESHOLD: usize> Compressed<Ser, THRESHOLD> {
+    const RAW: u8 = 0;
+    const LZ4: u8 = 1;
+    const MAX_LEN: usize = 16 << 20;
+    // a single LZ4 byte never expands to more than 255 bytes
+    const MAX_RATIO: usize = 255;
+}
+
+#[cfg(feature = "compression")]
+impl<T, Ser: Serde<T>, const THRESHOLD: usize> Serde<T> for Compressed<Ser, THRESHOLD> {
+    fn serialize(obj: &T) -> StdResult<Vec<u8>> {
+        let bytes = Ser::serialize(obj)?;
+        if bytes.len() <= THRESHOLD {
+            return Ok([&[Self::RAW], bytes.as_slice()].concat());
+        }
+        let compressed = lz4_flex::compress_prepend_size(&bytes);
+        Ok([&[Self::LZ4], compressed.as_slice()].concat())
+    }
+
+    fn deserialize(data: &[u8]) -> StdResult<T> {
+        let error = |source: String| StorageError::Deserialize {
+            type_name: std::any::type_name::<T>(),
+            source,
+        };
+        match data.split_first() {
+            Some((&Self::RAW, bytes)) => Ser::deserialize(bytes),
+            Some((&Self::LZ4, compressed)) => {
+                if compressed.len() < 4 {
+                    return Err(error("missing decompressed size".to_string()).into());
+                }
+                let (size, block) = compressed.split_at(4);
+                let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize;
+                if size <= THRESHOLD
+                    || size > Self::MAX_LEN
+                    || size > block.len().saturating_mul(Self::MAX_RATIO)
+                {
+                    return Err(error(format!("invalid decompressed size {}", size)).into());
+                }
+                let bytes =
+                    lz4_flex::decompress(block, size).map_err(|err| error(err.to_string()))?;
+                Ser::deserialize(&bytes)
+            }
+            Some((flag, _)) => Err(error(format!("unknown compression flag {}", flag)).into()),
+            None => Err(error("no compression flag".to_string()).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Data {
+        pub name: String,
+        pub age: i32,
+    }
+
+    fn round_trip<Ser: Serde<Data>>() -> Vec<u8> {
+        let data = Data {
+            name: "John".to_string(),
+            age: 32,
+        };
+        let bytes = Ser::serialize(&data).unwrap();
+        assert_eq!(Ser::deserialize(&bytes).unwrap(), data);
+        bytes
+    }
+
+    #[test]
+    fn backends_round_trip() {
+        assert_eq!(round_trip::<Json>(), br#"{"name":"John","age":32}"#);
+        // length-prefixed string followed by the little-endian int
+        assert_eq!(
+            round_trip::<Bincode2>(),
+            b"\x04\x00\x00\x00\x00\x00\x00\x00John\x20\x00\x00\x00"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "borsh")]
+    fn borsh_round_trips() {
+        use crate::{Item, Map};
+        use cosmwasm_std::testing::MockStorage;
+        use cosmwasm_std::{ReadonlyStorage, Storage};
+
+        #[derive(borsh::BorshSerialize, borsh::BorshDeserialize, PartialEq, Debug)]
+        struct Account {
+            pub balance: u64,
+            pub frozen: bool,
+        }
+
+        const ACCOUNT: Item<Account, Borsh> = Item::new("account");
+        const ACCOUNTS: Map<&str, Account, Borsh> = Map::new("accounts");
+        let mut store = MockStorage::new();
+
+        let account = Account {
+            balance: 500,
+            frozen: true,
+        };
+        ACCOUNT.save(&mut store, &account).unwrap();
+        // fixed layout, no field names or length prefixes
+        assert_eq!(
+            store.get(b"account").unwrap(),
+            b"\xf4\x01\x00\x00\x00\x00\x00\x00\x01"
+        );
+        assert_eq!(ACCOUNT.load(&store).unwrap(), account);
+
+        ACCOUNTS.save(&mut store, "alice", &account).unwrap();
+        assert_eq!(ACCOUNTS.load(&store, "alice").unwrap(), account);
+        assert_eq!(ACCOUNTS.may_load(&store, "bob").unwrap(), None);
+
+        // trailing bytes are rejected
+        store.set(b"account", b"\xf4\x01\x00\x00\x00\x00\x00\x00\x01\x00");
+        ACCOUNT.load(&store).unwrap_err();
+    }
+
+    #[test]
+    #[cfg(feature = "protobuf")]
+    fn protobuf_round_trips() {
+        use crate::{Item, Map};
+        use cosmwasm_std::testing::MockStorage;
+        use cosmwasm_std::{ReadonlyStorage, Storage};
+
+        #[derive(Clone, PartialEq, prost::Message)]
+        struct FungibleTokenPacketData {
+            #[prost(string, tag = "1")]
+            pub denom: String,
+            #[prost(uint64, tag = "2")]
+            pub amount: u64,
+        }
+
+        const PACKET: Item<FungibleTokenPacketData, Proto> = Item::new("packet");
+        const PACKETS: Map<u64, FungibleTokenPacketData, Proto> = Map::new("packets");
+        let mut store = MockStorage::new();
+
+        let packet = FungibleTokenPacketData {
+            denom: "uscrt".to_string(),
+            amount: 150,
+        };
+        PACKET.save(&mut store, &packet).unwrap();
+        // field 1 as a length-delimited string, field 2 as a varint
+        assert_eq!(store.get(b"packet").unwrap(), b"\x0a\x05uscrt\x10\x96\x01");
+        assert_eq!(PACKET.load(&store).unwrap(), packet);
+
+        PACKETS.save(&mut store, 7, &packet).unwrap();
+        assert_eq!(PACKETS.load(&store, 7).unwrap(), packet);
+        assert_eq!(PACKETS.may_load(&store, 8).unwrap(), None);
+
+        // a truncated message is rejected
+        store.set(b"packet", b"\x0a\x05usc");
+        PACKET.load(&store).unwrap_err();
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn compresses_large_values() {
+        use crate::Item;
+        use cosmwasm_std::testing::MockStorage;
+        use cosmwasm_std::{ReadonlyStorage, Storage};
+
+        const SMALL: Item<Data, Compressed<Json, 64>> = Item::new("small");
+        const DOCUMENT: Item<Vec<Data>, Compressed<Json, 64>> = Item::new("document");
+        let mut store = MockStorage::new();
+
+        let data = Data {
+            name: "John".to_string(),
+            age: 32,
+        };
+        SMALL.save(&mut store, &data).unwrap();
+        assert_eq!(
+            store.get(b"small").unwrap(),
+            [b"\x00".as_slice(), br#"{"name":"John","age":32}"#].concat()
+        );
+        assert_eq!(SMALL.load(&store).unwrap(), data);
+
+        let document: Vec<Data> = (0..100)
+            .map(|age| Data {
+                name: "John".to_string(),
+                age,
+            })
+            .collect();
+        DOCUMENT.save(&mut store, &document).unwrap();
+        let stored = store.get(b"document").unwrap();
+        assert_eq!(stored[0], 1);
+        assert!(stored.len() < Json::serialize(&document).unwrap().len() / 2);
+        assert_eq!(DOCUMENT.load(&store).unwrap(), document);
+
+        // neither plain bytes nor corrupted data are taken for a value
+        store.set(b"small", br#"{"name":"John","age":32}"#);
+        SMALL.load(&store).unwrap_err();
+        store.set(b"document", &stored[..stored.len() / 2]);
+        DOCUMENT.load(&store).unwrap_err();
+
+        // a forged size is rejected before decompressing
+        for size in [64u32, 1 << 30, 255 * (stored.len() as u32 - 5) + 1] {
+            let forged = [&[1u8], &size.to_le_bytes()[..], &stored[5..]].concat();
+            store.set(b"document", &forged);
+            let err = DOCUMENT.load(&store).unwrap_err();
+            assert!(err.to_string().contains("invalid decompressed size"));
+        }
+        store.set(b"document", b"\x01\x00\x10");
+        DOCUMENT.load(&store).unwrap_err();
+    }
+}
@@ -1,35 +1,660 @@
+#[cfg(feature = "iterator")]
+use cosmwasm_std::Order;
 use cosmwasm_std::{StdResult, Storage};
 
-use crate::{keys::PrimaryKey, Map};
+use crate::error::StorageResult;
+use crate::iterable_map::IterableMapIter;
+#[cfg(feature = "iterator")]
+use crate::keys::Prefixer;
+use crate::keys::{KeyDeserialize, PrimaryKey};
+#[cfg(feature = "iterator")]
+use crate::prefix::Bound;
+use crate::serialization::{Bincode2, Serde};
+use crate::IterableMap;
+#[cfg(feature = "iterator")]
+use crate::{cursor::Cursor, Item};
 
-pub struct Set<'a, T> {
-    map: Map<'a, T, ()>,
+/// A set of keys, stored as the keys of an `IterableMap` with empty values, so that the members
+/// can be counted, iterated and paged using only `get` and `set`.
+///
+/// Members are iterated in insertion order, except that removing a member moves the most
+/// recently inserted one into its place. Ranging over them in key order needs the `iterator`
+/// feature.
+///
+/// Sets written before the index was kept have none, see `recount`
+pub struct Set<'a, T, Ser = Bincode2> {
+    members: IterableMap<'a, T, (), Ser>,
 }
 
-impl<'a, T> Set<'a, T> {
+impl<'a, T, Ser> Set<'a, T, Ser> {
+    #[cfg(feature = "iterator")]
+    const RECOUNT: &'static [u8] = b"_recount";
+
     pub const fn new(namespace: &'a str) -> Self {
-        let map = Map::new(namespace);
-        Self { map }
+        Self {
+            members: IterableMap::new(namespace),
+        }
+    }
+
+    /// Creates a set under a namespace only known at runtime, e.g. one built with `Namespace`
+    pub fn new_owned(namespace: impl Into<Vec<u8>>) -> Self {
+        Self {
+            members: IterableMap::new_owned(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.members.namespace()
+    }
+
+    /// Returns the number of members of the set
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.members.len(store)
     }
 
-    pub fn namespace(&self) -> &'a [u8] {
-        self.map.namespace()
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
     }
 }
 
-impl<'a, T> Set<'a, T>
+impl<'a, T, Ser> Set<'a, T, Ser>
 where
     T: PrimaryKey<'a>,
+    Ser: Serde<()>,
 {
-    pub fn save(&self, store: &mut dyn Storage, t: T) -> StdResult<()> {
-        self.map.save(store, t, &())
+    fn insert_raw(&self, store: &mut dyn Storage, joined_key: &[u8]) -> StorageResult<bool> {
+        if self.contains_raw(store, joined_key) {
+            return Ok(false);
+        }
+        self.members
+            .push_raw(store, joined_key, &Ser::serialize(&())?)?;
+        Ok(true)
+    }
+
+    fn remove_raw(&self, store: &mut dyn Storage, joined_key: &[u8]) -> StorageResult<bool> {
+        Ok(self.members.remove_raw(store, joined_key)?.is_some())
+    }
+
+    fn contains_raw(&self, store: &dyn Storage, joined_key: &[u8]) -> bool {
+        self.members.has_raw(store, joined_key)
+    }
+
+    /// Adds `t` to the set, returns whether it was newly inserted
+    pub fn save(&self, store: &mut dyn Storage, t: T) -> StorageResult<bool> {
+        self.insert_raw(store, &t.joined_key())
     }
 
     pub fn contains(&self, store: &dyn Storage, t: T) -> bool {
-        self.map.has(store, t)
+        self.members.has(store, t)
+    }
+
+    /// Removes `t` from the set, returns whether it was a member
+    pub fn remove(&self, store: &mut dyn Storage, t: T) -> StorageResult<bool> {
+        self.remove_raw(store, &t.joined_key())
+    }
+
+    /// Adds all the given members, returns the number of newly inserted ones
+    pub fn insert_all<I>(&self, store: &mut dyn Storage, members: I) -> StorageResult<u32>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut inserted = 0;
+        for t in members {
+            if self.save(store, t)? {
+                inserted += 1;
+            }
+        }
+        Ok(inserted)
+    }
+
+    /// Removes all the given members, returns the number of removed ones
+    pub fn remove_all<I>(&self, store: &mut dyn Storage, members: I) -> StorageResult<u32>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut removed = 0;
+        for t in members {
+            if self.remove(store, t)? {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Removes up to `limit` members, the last inserted first, and returns how many are left,
+    /// so a large set can be wiped over several transactions
+    pub fn clear(&self, store: &mut dyn Storage, limit: u32) -> StorageResult<u32> {
+        Ok(self.members.clear(store, limit)?)
+    }
+}
+
+impl<'a, T, Ser> Set<'a, T, Ser>
+where
+    T: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<()>,
+{
+    /// Returns the member at position `index` of the index, or None if `index` is out of bounds
+    pub fn get_at(&self, store: &dyn Storage, index: u32) -> StorageResult<Option<T::Output>> {
+        Ok(self.members.get_at(store, index)?.map(|(t, ())| t))
+    }
+
+    /// Returns an iterator over all the members of the set, in index order
+    pub fn iter<'c>(&'c self, store: &'c dyn Storage) -> SetIter<'a, 'c, T, Ser> {
+        SetIter {
+            members: self.members.iter(store),
+        }
+    }
+
+    /// Returns the members of page `page` in index order, where every page holds `page_size`
+    /// members
+    pub fn paging(
+        &self,
+        store: &dyn Storage,
+        page: u32,
+        page_size: u32,
+    ) -> StorageResult<Vec<T::Output>> {
+        let start = page.saturating_mul(page_size);
+        Ok(self
+            .iter(store)
+            .skip(start as usize)
+            .take(page_size as usize)
+            .collect::<StdResult<_>>()?)
+    }
+
+    /// Adds every member matching `predicate` to `dest`, returns the number of members newly
+    /// inserted into `dest`. Copying every member of two sets into a third one builds their union.
+    pub fn copy_into<P>(
+        &self,
+        store: &mut dyn Storage,
+        dest: &Set<'a, T, Ser>,
+        mut predicate: P,
+    ) -> StorageResult<u32>
+    where
+        P: FnMut(&T::Output) -> bool,
+    {
+        let members = self.matching_raw(store, |joined_key| {
+            Ok(predicate(&T::from_slice(joined_key)?))
+        })?;
+        dest.insert_all_raw(store, members)
+    }
+
+    /// Adds every member that is not a member of `other` to `dest`,
+    /// returns the number of members newly inserted into `dest`
+    pub fn difference_into(
+        &self,
+        store: &mut dyn Storage,
+        other: &Set<'a, T, Ser>,
+        dest: &Set<'a, T, Ser>,
+    ) -> StorageResult<u32> {
+        let members = self.matching_raw(store, |joined_key| {
+            Ok(!other.contains_raw(store, joined_key))
+        })?;
+        dest.insert_all_raw(store, members)
+    }
+
+    fn insert_all_raw(&self, store: &mut dyn Storage, members: Vec<Vec<u8>>) -> StorageResult<u32> {
+        let mut inserted = 0;
+        for joined_key in members {
+            if self.insert_raw(store, &joined_key)? {
+                inserted += 1;
+            }
+        }
+        Ok(inserted)
+    }
+
+    /// Returns the joined keys of all members matching `predicate`, in index order
+    fn matching_raw<P>(&self, store: &dyn Storage, mut predicate: P) -> StorageResult<Vec<Vec<u8>>>
+    where
+        P: FnMut(&[u8]) -> StdResult<bool>,
+    {
+        let mut matching = vec![];
+        for index in 0..self.len(store) {
+            let (joined_key, ()) = self.members.load_raw_at(store, index)?;
+            if predicate(&joined_key)? {
+                matching.push(joined_key);
+            }
+        }
+        Ok(matching)
+    }
+}
+
+#[cfg(feature = "migration")]
+impl<'a, T, Ser> Set<'a, T, Ser>
+where
+    T: PrimaryKey<'a>,
+    Ser: Serde<()>,
+{
+    /// export returns all members as raw joined keys and their stored values,
+    /// in the format of `Map::export`
+    pub fn export(&self, store: &dyn Storage) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.members.map().export(store)
+    }
+
+    /// import adds the members returned by `export`, keeping the index up to date.
+    /// The values are ignored
+    pub fn import<I>(&self, store: &mut dyn Storage, entries: I) -> StorageResult<()>
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        for (joined_key, _) in entries {
+            self.insert_raw(store, &joined_key)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, T, Ser> Set<'a, T, Ser>
+where
+    T: PrimaryKey<'a>,
+    Ser: Serde<()>,
+{
+    /// Indexes and counts up to `limit` members in key order, returns true once all were indexed.
+    /// Calling it again continues where it stopped.
+    ///
+    /// Sets written before the index was kept have none, call it from a migration until it
+    /// returns true, before the set is written to again. The first call drops the index and
+    /// length the set had, how far it got is stored under the namespace followed by `_recount`
+    pub fn recount(&self, store: &mut dyn Storage, limit: u32) -> StorageResult<bool> {
+        let item: Item<Cursor> = Item::new_owned([self.namespace(), Self::RECOUNT].concat());
+        let cursor = match item.may_load(store)? {
+            Some(cursor) => cursor,
+            None => {
+                self.members.set_len(store, 0);
+                Cursor::new(self.namespace())
+            }
+        };
+        cursor.validate(self.namespace())?;
+
+        let min = cursor.last_key().map(|k| Bound::ExclusiveRaw(k.to_vec()));
+        let keys: Vec<_> = self
+            .members
+            .map()
+            .keys_raw(store, min, None, Order::Ascending)
+            .take(limit as usize)
+            .collect();
+        let member = Ser::serialize(&())?;
+        for joined_key in &keys {
+            self.members.push_raw(store, joined_key, &member)?;
+        }
+        let next = cursor.advance(keys.last().cloned(), keys.len(), limit);
+        if next.is_finished() {
+            item.remove(store);
+            return Ok(true);
+        }
+        item.save(store, &next)?;
+        Ok(false)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, T, Ser> Set<'a, T, Ser>
+where
+    T: PrimaryKey<'a>,
+    T::Suffix: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<()>,
+{
+    /// Returns true if any member of a set of composite keys starts with `p`,
+    /// e.g. whether an operator holds any approval in a `Set<(&Addr, &Addr)>`
+    pub fn contains_prefix(&self, store: &dyn Storage, p: T::Prefix) -> bool {
+        self.members.map().has_prefix(store, p)
+    }
+
+    /// range_prefix iterates over the rest of the members starting with `p`, in key order
+    pub fn range_prefix<'c>(
+        &self,
+        store: &'c dyn Storage,
+        p: T::Prefix,
+        min: Option<Bound<'a, T::Suffix>>,
+        max: Option<Bound<'a, T::Suffix>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<<T::Suffix as KeyDeserialize>::Output>> + 'c>
+    where
+        <T::Suffix as KeyDeserialize>::Output: 'static,
+    {
+        self.members.map().prefix(p).keys(store, min, max, order)
+    }
+
+    /// Removes up to `limit` members starting with `p` in key order, returns the number removed.
+    /// Call it until it returns less than `limit` to remove them all over several transactions
+    pub fn remove_prefix(
+        &self,
+        store: &mut dyn Storage,
+        p: T::Prefix,
+        limit: u32,
+    ) -> StorageResult<u32> {
+        let joined_prefix = p.joined_prefix();
+        let suffixes: Vec<_> = self
+            .members
+            .map()
+            .prefix(p)
+            .keys_raw(store, None, None, Order::Ascending)
+            .take(limit as usize)
+            .collect();
+        let mut removed = 0;
+        for suffix in suffixes {
+            if self.remove_raw(store, &[joined_prefix.as_slice(), &suffix].concat())? {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(feature = "iterator")]
+impl<'a, T, Ser> Set<'a, T, Ser>
+where
+    T: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<()>,
+{
+    /// range iterates over the members of the set in key order, deserializing them
+    pub fn range<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, T>>,
+        max: Option<Bound<'a, T>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<T::Output>> + 'c>
+    where
+        T::Output: 'static,
+    {
+        self.members.map().keys(store, min, max, order)
+    }
+}
+
+/// An iterator over the members of a `Set`, in index order, created by `Set::iter`
+pub struct SetIter<'a, 'c, T, Ser = Bincode2> {
+    members: IterableMapIter<'a, 'c, T, (), Ser>,
+}
+
+impl<'a, 'c, T, Ser> Iterator for SetIter<'a, 'c, T, Ser>
+where
+    T: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<()>,
+{
+    type Item = StdResult<T::Output>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.members.next()?.map(|(t, ())| t))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.members.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        Some(self.members.nth(n)?.map(|(t, ())| t))
+    }
+}
+
+impl<'a, 'c, T, Ser> DoubleEndedIterator for SetIter<'a, 'c, T, Ser>
+where
+    T: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<()>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some(self.members.next_back()?.map(|(t, ())| t))
+    }
+}
+
+impl<'a, 'c, T, Ser> ExactSizeIterator for SetIter<'a, 'c, T, Ser>
+where
+    T: PrimaryKey<'a> + KeyDeserialize,
+    Ser: Serde<()>,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use cosmwasm_std::testing::MockStorage;
+
+    use super::*;
+    #[cfg(feature = "iterator")]
+    use crate::Map;
+
+    const MEMBERS: Set<&str> = Set::new("members");
+
+    #[test]
+    fn len_counts_distinct_members() {
+        let mut store = MockStorage::new();
+        assert!(MEMBERS.is_empty(&store));
+
+        assert!(MEMBERS.save(&mut store, "alice").unwrap());
+        assert!(MEMBERS.save(&mut store, "bob").unwrap());
+        assert!(!MEMBERS.save(&mut store, "alice").unwrap());
+        assert_eq!(MEMBERS.len(&store), 2);
+        assert!(MEMBERS.contains(&store, "alice"));
+
+        assert!(MEMBERS.remove(&mut store, "alice").unwrap());
+        assert!(!MEMBERS.remove(&mut store, "alice").unwrap());
+        assert!(!MEMBERS.remove(&mut store, "carol").unwrap());
+        assert_eq!(MEMBERS.len(&store), 1);
+        assert!(!MEMBERS.contains(&store, "alice"));
+
+        MEMBERS.remove(&mut store, "bob").unwrap();
+        assert!(MEMBERS.is_empty(&store));
+    }
+
+    #[test]
+    fn iter_and_paging_work() {
+        let mut store = MockStorage::new();
+        for member in ["carol", "alice", "dave", "bob", "erin"] {
+            MEMBERS.save(&mut store, member).unwrap();
+        }
+
+        let all: StdResult<Vec<_>> = MEMBERS.iter(&store).collect();
+        assert_eq!(all.unwrap(), vec!["carol", "alice", "dave", "bob", "erin"]);
+        assert_eq!(MEMBERS.iter(&store).len(), 5);
+        assert_eq!(MEMBERS.iter(&store).next_back().unwrap().unwrap(), "erin");
+
+        // removing a member moves the last one into its place
+        MEMBERS.remove(&mut store, "alice").unwrap();
+        assert_eq!(MEMBERS.get_at(&store, 1).unwrap(), Some("erin".to_string()));
+        assert_eq!(MEMBERS.get_at(&store, 4).unwrap(), None);
+
+        assert_eq!(
+            MEMBERS.paging(&store, 0, 3).unwrap(),
+            vec!["carol", "erin", "dave"]
+        );
+        assert_eq!(MEMBERS.paging(&store, 1, 3).unwrap(), vec!["bob"]);
+        assert!(MEMBERS.paging(&store, 2, 3).unwrap().is_empty());
+        assert!(MEMBERS
+            .paging(&store, u32::MAX, u32::MAX)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_works() {
+        let mut store = MockStorage::new();
+        for member in ["carol", "alice", "dave", "bob", "erin"] {
+            MEMBERS.save(&mut store, member).unwrap();
+        }
+
+        let all: StdResult<Vec<_>> = MEMBERS
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        // keys are sorted by their bytes
+        assert_eq!(all.unwrap(), vec!["alice", "bob", "carol", "dave", "erin"]);
+
+        let after_bob: StdResult<Vec<_>> = MEMBERS
+            .range(
+                &store,
+                Some(Bound::exclusive("bob")),
+                None,
+                Order::Descending,
+            )
+            .collect();
+        assert_eq!(after_bob.unwrap(), vec!["erin", "dave", "carol"]);
+    }
+
+    #[test]
+    fn clear_in_batches() {
+        let mut store = MockStorage::new();
+        for member in ["carol", "alice", "dave", "bob", "erin"] {
+            MEMBERS.save(&mut store, member).unwrap();
+        }
+
+        assert_eq!(MEMBERS.clear(&mut store, 2).unwrap(), 3);
+        assert!(!MEMBERS.contains(&store, "erin"));
+        assert!(!MEMBERS.contains(&store, "bob"));
+        assert!(MEMBERS.contains(&store, "dave"));
+        assert_eq!(MEMBERS.clear(&mut store, 2).unwrap(), 1);
+        assert!(MEMBERS.contains(&store, "carol"));
+        assert_eq!(MEMBERS.clear(&mut store, 2).unwrap(), 0);
+        assert!(MEMBERS.is_empty(&store));
+        assert_eq!(MEMBERS.clear(&mut store, 2).unwrap(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn recount_indexes_sets_written_without_one() {
+        let mut store = MockStorage::new();
+        // members saved before the length was counted
+        let map: Map<&str, ()> = Map::new("members");
+        for member in ["alice", "bob", "carol", "dave", "erin"] {
+            map.save(&mut store, member, &()).unwrap();
+        }
+        assert_eq!(MEMBERS.len(&store), 0);
+        assert!(MEMBERS.contains(&store, "erin"));
+        // they have no index to be removed from
+        assert!(MEMBERS.remove(&mut store, "erin").is_err());
+
+        assert!(!MEMBERS.recount(&mut store, 3).unwrap());
+        assert_eq!(MEMBERS.len(&store), 3);
+        assert!(MEMBERS.recount(&mut store, 3).unwrap());
+        assert_eq!(MEMBERS.len(&store), 5);
+        assert_eq!(
+            MEMBERS.paging(&store, 0, 10).unwrap(),
+            vec!["alice", "bob", "carol", "dave", "erin"]
+        );
+        // indexing them again keeps them
+        assert!(MEMBERS.recount(&mut store, 10).unwrap());
+        assert_eq!(MEMBERS.len(&store), 5);
+
+        assert!(MEMBERS.remove(&mut store, "alice").unwrap());
+        assert!(MEMBERS.save(&mut store, "frank").unwrap());
+        assert_eq!(
+            MEMBERS.paging(&store, 0, 10).unwrap(),
+            vec!["erin", "bob", "carol", "dave", "frank"]
+        );
+    }
+
+    #[test]
+    fn insert_and_remove_all() {
+        let mut store = MockStorage::new();
+        MEMBERS.save(&mut store, "alice").unwrap();
+
+        let inserted = MEMBERS
+            .insert_all(&mut store, ["alice", "bob", "carol", "bob"])
+            .unwrap();
+        assert_eq!(inserted, 2);
+        assert_eq!(MEMBERS.len(&store), 3);
+
+        assert_eq!(
+            MEMBERS
+                .remove_all(&mut store, ["bob", "dave", "bob"])
+                .unwrap(),
+            1
+        );
+        assert_eq!(MEMBERS.len(&store), 2);
+        assert!(!MEMBERS.contains(&store, "bob"));
+    }
+
+    #[test]
+    fn copy_and_difference_into_other_sets() {
+        const ADMINS: Set<&str> = Set::new("admins");
+        const RESULT: Set<&str> = Set::new("result");
+        let mut store = MockStorage::new();
+
+        MEMBERS
+            .insert_all(&mut store, ["alice", "bob", "carol", "dave"])
+            .unwrap();
+        ADMINS.insert_all(&mut store, ["bob", "erin"]).unwrap();
+
+        // union of the members starting with a consonant and the admins
+        let copied = MEMBERS
+            .copy_into(&mut store, &RESULT, |m| !m.starts_with('a'))
+            .unwrap();
+        assert_eq!(copied, 3);
+        assert_eq!(ADMINS.copy_into(&mut store, &RESULT, |_| true).unwrap(), 1);
+        assert_eq!(
+            RESULT.paging(&store, 0, 10).unwrap(),
+            vec!["bob", "carol", "dave", "erin"]
+        );
+        assert_eq!(RESULT.len(&store), 4);
+
+        RESULT
+            .remove_all(&mut store, ["bob", "carol", "dave", "erin"])
+            .unwrap();
+        assert_eq!(
+            MEMBERS
+                .difference_into(&mut store, &ADMINS, &RESULT)
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            RESULT.paging(&store, 0, 10).unwrap(),
+            vec!["alice", "carol", "dave"]
+        );
+        // the sources are untouched
+        assert_eq!(MEMBERS.len(&store), 4);
+        assert_eq!(ADMINS.len(&store), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "migration")]
+    fn export_and_import() {
+        let mut store = MockStorage::new();
+        const MEMBERS: Set<&str> = Set::new("members");
+        const IMPORTED: Set<&str> = Set::new("imported");
+        MEMBERS.insert_all(&mut store, ["alice", "bob"]).unwrap();
+        IMPORTED.save(&mut store, "bob").unwrap();
+
+        let exported = MEMBERS.export(&store);
+        IMPORTED.import(&mut store, exported).unwrap();
+        assert!(IMPORTED.contains(&store, "alice"));
+        assert_eq!(IMPORTED.len(&store), 2);
     }
 
-    pub fn remove(&self, store: &mut dyn Storage, t: T) {
-        self.map.remove(store, t)
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn composite_members_by_prefix() {
+        // (operator, owner) approvals
+        const APPROVALS: Set<(&str, &str)> = Set::new("approvals");
+        let mut store = MockStorage::new();
+        APPROVALS
+            .insert_all(
+                &mut store,
+                [
+                    ("alice", "bob"),
+                    ("alice", "carol"),
+                    ("alice", "dave"),
+                    ("bob", "alice"),
+                ],
+            )
+            .unwrap();
+
+        assert!(APPROVALS.contains(&store, ("alice", "carol")));
+        assert!(APPROVALS.contains_prefix(&store, "alice"));
+        assert!(!APPROVALS.contains_prefix(&store, "carol"));
+        let owners: StdResult<Vec<_>> = APPROVALS
+            .range_prefix(&store, "alice", None, None, Order::Ascending)
+            .collect();
+        assert_eq!(owners.unwrap(), vec!["bob", "carol", "dave"]);
+        // the first element is length prefixed, so shorter operators sort first
+        let all: StdResult<Vec<_>> = APPROVALS
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(all.unwrap()[0], ("bob".to_string(), "alice".to_string()));
+
+        assert_eq!(APPROVALS.remove_prefix(&mut store, "alice", 2).unwrap(), 2);
+        assert_eq!(APPROVALS.len(&store), 2);
+        assert_eq!(APPROVALS.remove_prefix(&mut store, "alice", 2).unwrap(), 1);
+        assert_eq!(APPROVALS.remove_prefix(&mut store, "alice", 2).unwrap(), 0);
+        assert!(!APPROVALS.contains_prefix(&store, "alice"));
+        assert!(APPROVALS.contains(&store, ("bob", "alice")));
+        assert_eq!(APPROVALS.len(&store), 1);
     }
 }
@@ -0,0 +1,238 @@
+use cosmwasm_std::{Order, StdError, StdResult, Storage};
+
+use crate::cursor::Cursor;
+use crate::error::StorageResult;
+use crate::helpers::namespaces_with_key;
+use crate::keys::PrimaryKey;
+use crate::path::Path;
+use crate::prefix::Bound;
+use crate::serialization::{Bincode2, Serde};
+use crate::{Item, Map};
+
+/// Where a `ShadowMap` writes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriteMode {
+    /// Writes go to both layouts, so code still reading the old one sees them
+    Both,
+    /// Writes only go to the new layout
+    NewOnly,
+}
+
+/// ShadowMap moves a map to a new layout, a new namespace or serialization, while the contract
+/// keeps running: reads try the new layout first and fall back to the old one, and `backfill`
+/// copies the entries of the old layout over a few at a time, over as many transactions as needed.
+///
+/// Removals always apply to both layouts, so a removed entry can't come back from the old one.
+/// Once `backfill` is finished, the contract can switch to a plain `Map` on the new layout and
+/// clear the old one. Ranges only see one layout, see `old_map` and `new_map`. How far the
+/// backfill got is stored under the new namespace followed by `_backfill`.
+pub struct ShadowMap<'a, K, T, OldSer = Bincode2, NewSer = Bincode2> {
+    old: Map<'a, K, T, OldSer>,
+    new: Map<'a, K, T, NewSer>,
+    mode: WriteMode,
+}
+
+impl<'a, K, T, OldSer, NewSer> ShadowMap<'a, K, T, OldSer, NewSer> {
+    const BACKFILL: &'static [u8] = b"_backfill";
+
+    pub const fn new(old_namespace: &'a str, new_namespace: &'a str, mode: WriteMode) -> Self {
+        ShadowMap {
+            old: Map::new(old_namespace),
+            new: Map::new(new_namespace),
+            mode,
+        }
+    }
+
+    /// The map in the old layout
+    pub fn old_map(&self) -> &Map<'a, K, T, OldSer> {
+        &self.old
+    }
+
+    /// The map in the new layout
+    pub fn new_map(&self) -> &Map<'a, K, T, NewSer> {
+        &self.new
+    }
+
+    pub fn mode(&self) -> WriteMode {
+        self.mode
+    }
+
+    fn backfill_item(&self) -> Item<'static, Cursor> {
+        Item::new_owned([self.new.namespace(), Self::BACKFILL].concat())
+    }
+}
+
+impl<'a, K, T, OldSer, NewSer> ShadowMap<'a, K, T, OldSer, NewSer>
+where
+    K: PrimaryKey<'a>,
+    OldSer: Serde<T>,
+    NewSer: Serde<T>,
+{
+    /// Returns the value at `k` in the new layout, or in the old one if the new one has none
+    pub fn may_load(&self, store: &dyn Storage, k: K) -> StdResult<Option<T>> {
+        match self.new.may_load(store, k.clone())? {
+            Some(value) => Ok(Some(value)),
+            None => Ok(self.old.may_load(store, k)?),
+        }
+    }
+
+    /// Like `may_load`, returns `StdError::NotFound` if neither layout has a value at `k`
+    pub fn load(&self, store: &dyn Storage, k: K) -> StdResult<T> {
+        if self.new.has(store, k.clone()) {
+            return Ok(self.new.load(store, k)?);
+        }
+        Ok(self.old.load(store, k)?)
+    }
+
+    pub fn has(&self, store: &dyn Storage, k: K) -> bool {
+        self.new.has(store, k.clone()) || self.old.has(store, k)
+    }
+
+    /// Saves `data` at `k` in the new layout, and in the old one with `WriteMode::Both`
+    pub fn save(&self, store: &mut dyn Storage, k: K, data: &T) -> StorageResult<()> {
+        if self.mode == WriteMode::Both {
+            self.old.save(store, k.clone(), data)?;
+        }
+        self.new.save(store, k, data)
+    }
+
+    /// Removes the value at `k` from both layouts
+    pub fn remove(&self, store: &mut dyn Storage, k: K) {
+        self.old.remove(store, k.clone());
+        self.new.remove(store, k);
+    }
+
+    /// Loads the value at `k`, applies `action` and saves the result like `save`
+    pub fn update<A, E>(&self, store: &mut dyn Storage, k: K, action: A) -> Result<T, E>
+    where
+        A: FnOnce(Option<T>) -> Result<T, E>,
+        E: From<StdError>,
+    {
+        let output = action(self.may_load(store, k.clone())?)?;
+        self.save(store, k, &output).map_err(StdError::from)?;
+        Ok(output)
+    }
+
+    /// Copies up to `limit` entries of the old layout to the new one, skipping the ones the new
+    /// layout already has, which were written since. Returns true once every entry of the old
+    /// layout was visited, calling it again continues where it stopped
+    pub fn backfill(&self, store: &mut dyn Storage, limit: u32) -> StdResult<bool> {
+        let item = self.backfill_item();
+        let cursor = item
+            .may_load(store)?
+            .unwrap_or_else(|| Cursor::new(self.old.namespace()));
+        cursor.validate(self.old.namespace())?;
+        if cursor.is_finished() {
+            return Ok(true);
+        }
+
+        let min = cursor.last_key().map(|k| Bound::ExclusiveRaw(k.to_vec()));
+        let entries = self
+            .old
+            .range_raw(store, min, None, Order::Ascending)
+            .take(limit as usize)
+            .collect::<StdResult<Vec<_>>>()?;
+        let prefix = namespaces_with_key(&[self.new.namespace()], &[]);
+        for (key, value) in &entries {
+            let path = Path::<T, NewSer>::from_storage_key([prefix.as_slice(), key].concat());
+            if !path.has(store) {
+                path.save(store, value)?;
+            }
+        }
+
+        let last_key = entries.last().map(|(k, _)| k.clone());
+        let next = cursor.advance(last_key, entries.len(), limit);
+        item.save(store, &next)?;
+        Ok(next.is_finished())
+    }
+
+    /// Returns true once `backfill` visited every entry of the old layout
+    pub fn is_backfilled(&self, store: &dyn Storage) -> StdResult<bool> {
+        Ok(self
+            .backfill_item()
+            .may_load(store)?
+            .is_some_and(|cursor| cursor.is_finished()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    use crate::serialization::Json;
+
+    const BALANCES: ShadowMap<&str, u64, Json> =
+        ShadowMap::new("balances", "balances_v2", WriteMode::Both);
+
+    #[test]
+    fn reads_new_then_old() {
+        let mut store = MockStorage::new();
+        BALANCES.old_map().save(&mut store, "alice", &1).unwrap();
+        BALANCES.old_map().save(&mut store, "bob", &2).unwrap();
+        BALANCES.save(&mut store, "bob", &3).unwrap();
+
+        assert_eq!(BALANCES.load(&store, "alice").unwrap(), 1);
+        assert_eq!(BALANCES.load(&store, "bob").unwrap(), 3);
+        assert_eq!(BALANCES.old_map().load(&store, "bob").unwrap(), 3);
+        assert_eq!(BALANCES.may_load(&store, "carol").unwrap(), None);
+        assert!(BALANCES.load(&store, "carol").is_err());
+
+        BALANCES.remove(&mut store, "alice");
+        assert!(!BALANCES.has(&store, "alice"));
+    }
+
+    #[test]
+    fn new_only_writes() {
+        let mut store = MockStorage::new();
+        let balances: ShadowMap<&str, u64> =
+            ShadowMap::new("balances", "balances_v2", WriteMode::NewOnly);
+        balances.old_map().save(&mut store, "alice", &1).unwrap();
+
+        let updated = balances
+            .update(&mut store, "alice", |v| -> StdResult<_> {
+                Ok(v.unwrap() + 1)
+            })
+            .unwrap();
+        assert_eq!(updated, 2);
+        assert_eq!(balances.old_map().load(&store, "alice").unwrap(), 1);
+        assert_eq!(balances.new_map().load(&store, "alice").unwrap(), 2);
+    }
+
+    #[test]
+    fn backfills_over_several_calls() {
+        let mut store = MockStorage::new();
+        for (i, name) in ["alice", "bob", "carol", "dave", "eve"].iter().enumerate() {
+            BALANCES
+                .old_map()
+                .save(&mut store, name, &(i as u64))
+                .unwrap();
+        }
+        BALANCES.save(&mut store, "bob", &10).unwrap();
+        BALANCES.old_map().save(&mut store, "bob", &1).unwrap();
+
+        assert!(!BALANCES.backfill(&mut store, 2).unwrap());
+        assert!(!BALANCES.is_backfilled(&store).unwrap());
+        assert!(!BALANCES.backfill(&mut store, 2).unwrap());
+        assert!(BALANCES.backfill(&mut store, 2).unwrap());
+        assert!(BALANCES.is_backfilled(&store).unwrap());
+        assert!(BALANCES.backfill(&mut store, 2).unwrap());
+
+        let new = BALANCES
+            .new_map()
+            .range(&store, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        // bob was written since, so not overwritten
+        assert_eq!(
+            new,
+            vec![
+                ("alice".to_string(), 0),
+                ("bob".to_string(), 10),
+                ("carol".to_string(), 2),
+                ("dave".to_string(), 3),
+                ("eve".to_string(), 4),
+            ]
+        );
+    }
+}
@@ -0,0 +1,406 @@
+mod checkpoint_map;
+mod item;
+mod map;
+
+pub use checkpoint_map::CheckpointMap;
+pub use item::SnapshotItem;
+pub use map::SnapshotMap;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Order, StdError, StdResult, Storage};
+
+use crate::cursor::Cursor;
+use crate::helpers::namespaces_with_key;
+use crate::keys::int_key::CwIntKey;
+use crate::keys::{KeyDeserialize, Prefixer, PrimaryKey};
+use crate::map::Map;
+use crate::prefix::Bound;
+use crate::Item;
+
+/// Structure holding a map of checkpoints composited from
+/// height (as u64) and counter of how many times it has
+/// been checkpointed (as u32).
+/// Stores all changes in changelog.
+#[derive(Debug, Clone)]
+pub(crate) struct Snapshot<'a, K, T> {
+    checkpoints: Map<'a, u64, u32>,
+
+    // this stores all changes (key, height). Must differentiate between no data written,
+    // and explicit None (just inserted)
+    pub changelog: Map<'a, (K, u64), ChangeSet<T>>,
+
+    // How aggressive we are about checkpointing all data
+    strategy: Strategy,
+}
+
+impl<'a, K, T> Snapshot<'a, K, T> {
+    const PRUNED: &'static [u8] = b"_pruned";
+    const PRUNING: &'static [u8] = b"_pruning";
+
+    pub const fn new(
+        checkpoints: &'a str,
+        changelog: &'a str,
+        strategy: Strategy,
+    ) -> Snapshot<'a, K, T> {
+        Snapshot {
+            checkpoints: Map::new(checkpoints),
+            changelog: Map::new(changelog),
+            strategy,
+        }
+    }
+
+    pub fn add_checkpoint(&self, store: &mut dyn Storage, height: u64) -> StdResult<()> {
+        self.checkpoints
+            .update::<_, StdError>(store, height, |count| Ok(count.unwrap_or_default() + 1))?;
+        Ok(())
+    }
+
+    pub fn remove_checkpoint(&self, store: &mut dyn Storage, height: u64) -> StdResult<()> {
+        let count = self
+            .checkpoints
+            .may_load(store, height)?
+            .unwrap_or_default();
+        if count <= 1 {
+            self.checkpoints.remove(store, height);
+            Ok(())
+        } else {
+            Ok(self.checkpoints.save(store, height, &(count - 1))?)
+        }
+    }
+
+    /// Returns the height passed to the last `prune_below`, history before it is gone
+    pub fn pruned_below(&self, store: &dyn Storage) -> u64 {
+        store
+            .get(&self.pruned_key())
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_be_bytes)
+            .unwrap_or_default()
+    }
+
+    fn pruned_key(&self) -> Vec<u8> {
+        [self.checkpoints.namespace(), Self::PRUNED].concat()
+    }
+
+    fn pruning_key(&self) -> Vec<u8> {
+        [self.checkpoints.namespace(), Self::PRUNING].concat()
+    }
+}
+
+impl<'a, K, T> Snapshot<'a, K, T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+{
+    /// prune_below removes the checkpoints and changelog entries below `height`, reading at most
+    /// `limit` of each, and returns true while there is more to remove, so a long history can be
+    /// pruned over several transactions. How far it got is stored next to the checkpoints.
+    ///
+    /// From the first call on, loading at any height below `height` returns `StdError::NotFound`,
+    /// while loading at `height` or later is unaffected.
+    pub fn prune_below(&self, store: &mut dyn Storage, height: u64, limit: u32) -> StdResult<bool> {
+        let progress: Item<Cursor> = Item::new_owned(self.pruning_key());
+        let changelog_ns = self.changelog.namespace();
+        let cursor = if height > self.pruned_below(store) {
+            // the history below `height` can't be loaded anymore, so the whole changelog has to
+            // be checked again, including the part checked by an earlier call
+            store.set(&self.pruned_key(), &height.to_be_bytes());
+            Cursor::new(changelog_ns)
+        } else {
+            match progress.may_load(store)? {
+                Some(cursor) => cursor,
+                None => return Ok(false),
+            }
+        };
+        cursor.validate(changelog_ns)?;
+        let height = self.pruned_below(store);
+
+        let checkpoints = self
+            .checkpoints
+            .keys(
+                store,
+                None,
+                Some(Bound::exclusive(height)),
+                Order::Ascending,
+            )
+            .take(limit as usize)
+            .collect::<StdResult<Vec<_>>>()?;
+        let checkpoints_left = checkpoints.len() == limit as usize;
+        for checkpoint in checkpoints {
+            self.checkpoints.remove(store, checkpoint);
+        }
+
+        // the changelog is ordered by key first, so every entry has to be checked.
+        // The height is always the last 8 bytes of the joined key.
+        let changelog_prefix = namespaces_with_key(&[changelog_ns], &[]);
+        let min = cursor.last_key().map(|k| Bound::ExclusiveRaw(k.to_vec()));
+        let keys: Vec<Vec<u8>> = self
+            .changelog
+            .keys_raw(store, min, None, Order::Ascending)
+            .take(limit as usize)
+            .collect();
+        let next = cursor.advance(keys.last().cloned(), keys.len(), limit);
+        for key in keys {
+            let stale = match key.len().checked_sub(8) {
+                Some(split) => {
+                    let mut buf = [0u8; 8];
+                    buf.copy_from_slice(&key[split..]);
+                    u64::from_cw_bytes(buf) < height
+                }
+                None => false,
+            };
+            if stale {
+                store.remove(&[changelog_prefix.as_slice(), &key].concat());
+            }
+        }
+
+        if next.is_finished() && !checkpoints_left {
+            progress.remove(store);
+            return Ok(false);
+        }
+        progress.save(store, &next)?;
+        Ok(true)
+    }
+
+    /// should_checkpoint looks at the strategy and determines if we want to checkpoint
+    pub fn should_checkpoint(&self, store: &dyn Storage, k: &K) -> StdResult<bool> {
+        match self.strategy {
+            Strategy::EveryBlock => Ok(true),
+            Strategy::Never => Ok(false),
+            Strategy::Selected => self.should_checkpoint_selected(store, k),
+        }
+    }
+
+    /// this is just pulled out from above for the selected block
+    fn should_checkpoint_selected(&self, store: &dyn Storage, k: &K) -> StdResult<bool> {
+        // most recent checkpoint
+        let checkpoint = self
+            .checkpoints
+            .range(store, None, None, Order::Descending)
+            .next()
+            .transpose()?;
+        if let Some((height, _)) = checkpoint {
+            // any changelog for the given key since then?
+            let start = Bound::inclusive(height);
+            let first = self
+                .changelog
+                .prefix(k.clone())
+                .range_raw(store, Some(start), None, Order::Ascending)
+                .next()
+                .transpose()?;
+            if first.is_none() {
+                // there must be at least one open checkpoint and no changelog for the given height since then
+                return Ok(true);
+            }
+        }
+        // otherwise, we don't save this
+        Ok(false)
+    }
+
+    // If there is no checkpoint for that height, or it was pruned, then we return StdError::NotFound
+    pub fn assert_checkpointed(&self, store: &dyn Storage, height: u64) -> StdResult<()> {
+        if height < self.pruned_below(store) {
+            return Err(StdError::not_found("checkpoint"));
+        }
+        let has = match self.strategy {
+            Strategy::EveryBlock => true,
+            Strategy::Never => false,
+            Strategy::Selected => self.checkpoints.may_load(store, height)?.is_some(),
+        };
+        match has {
+            true => Ok(()),
+            false => Err(StdError::not_found("checkpoint")),
+        }
+    }
+
+    pub fn has_changelog(&self, store: &dyn Storage, key: K, height: u64) -> StdResult<bool> {
+        Ok(self.changelog.may_load(store, (key, height))?.is_some())
+    }
+
+    pub fn write_changelog(
+        &self,
+        store: &mut dyn Storage,
+        key: K,
+        height: u64,
+        old: Option<T>,
+    ) -> StdResult<()> {
+        Ok(self
+            .changelog
+            .save(store, (key, height), &ChangeSet { old })?)
+    }
+
+    // may_load_at_height reads historical data from given checkpoints.
+    // Returns StdError::NotFound if we have no checkpoint, and can give no data.
+    // Returns Ok(None) if there is a checkpoint, but no cached data (no changes since the
+    // checkpoint. Caller should query current state).
+    // Return Ok(Some(x)) if there is a checkpoint and data written to changelog, returning the state at that time
+    pub fn may_load_at_height(
+        &self,
+        store: &dyn Storage,
+        key: K,
+        height: u64,
+    ) -> StdResult<Option<Option<T>>> {
+        self.assert_checkpointed(store, height)?;
+
+        // this will look for the first snapshot of height >= given height
+        // If None, there is no snapshot since that time.
+        let start = Bound::inclusive(height);
+        let first = self
+            .changelog
+            .prefix(key)
+            .range_raw(store, Some(start), None, Order::Ascending)
+            .next();
+
+        if let Some(r) = first {
+            // if we found a match, return this last one
+            r.map(|(_, v)| Some(v.old))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Strategy {
+    EveryBlock,
+    Never,
+    /// Only writes for linked blocks - does a few more reads to save some writes.
+    /// Probably uses more gas, but less total disk usage.
+    ///
+    /// Note that you need a trusted source (eg. own contract) to set/remove checkpoints.
+    /// Useful when the checkpoint setting happens in the same contract as the snapshotting.
+    Selected,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeSet<T> {
+    pub old: Option<T>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    type TestSnapshot = Snapshot<'static, &'static str, u64>;
+
+    const NEVER: TestSnapshot = Snapshot::new("never__check", "never__change", Strategy::Never);
+    const EVERY: TestSnapshot =
+        Snapshot::new("every__check", "every__change", Strategy::EveryBlock);
+    const SELECT: TestSnapshot =
+        Snapshot::new("select__check", "select__change", Strategy::Selected);
+
+    const DUMMY_KEY: &str = "dummy";
+
+    #[test]
+    fn should_checkpoint() {
+        let storage = MockStorage::new();
+
+        assert_eq!(NEVER.should_checkpoint(&storage, &DUMMY_KEY), Ok(false));
+        assert_eq!(EVERY.should_checkpoint(&storage, &DUMMY_KEY), Ok(true));
+        assert_eq!(SELECT.should_checkpoint(&storage, &DUMMY_KEY), Ok(false));
+    }
+
+    #[test]
+    fn assert_checkpointed() {
+        let mut storage = MockStorage::new();
+
+        assert_eq!(
+            NEVER.assert_checkpointed(&storage, 1),
+            Err(StdError::not_found("checkpoint"))
+        );
+        assert_eq!(EVERY.assert_checkpointed(&storage, 1), Ok(()));
+        assert_eq!(
+            SELECT.assert_checkpointed(&storage, 1),
+            Err(StdError::not_found("checkpoint"))
+        );
+
+        // Add a checkpoint at 1
+        NEVER.add_checkpoint(&mut storage, 1).unwrap();
+        EVERY.add_checkpoint(&mut storage, 1).unwrap();
+        SELECT.add_checkpoint(&mut storage, 1).unwrap();
+
+        assert_eq!(
+            NEVER.assert_checkpointed(&storage, 1),
+            Err(StdError::not_found("checkpoint"))
+        );
+        assert_eq!(EVERY.assert_checkpointed(&storage, 1), Ok(()));
+        assert_eq!(SELECT.assert_checkpointed(&storage, 1), Ok(()));
+
+        // Remove checkpoint
+        NEVER.remove_checkpoint(&mut storage, 1).unwrap();
+        EVERY.remove_checkpoint(&mut storage, 1).unwrap();
+        SELECT.remove_checkpoint(&mut storage, 1).unwrap();
+
+        assert_eq!(
+            NEVER.assert_checkpointed(&storage, 1),
+            Err(StdError::not_found("checkpoint"))
+        );
+        assert_eq!(EVERY.assert_checkpointed(&storage, 1), Ok(()));
+        assert_eq!(
+            SELECT.assert_checkpointed(&storage, 1),
+            Err(StdError::not_found("checkpoint"))
+        );
+    }
+
+    #[test]
+    fn has_changelog() {
+        let mut storage = MockStorage::new();
+
+        assert_eq!(EVERY.has_changelog(&storage, DUMMY_KEY, 1), Ok(false));
+        assert_eq!(EVERY.has_changelog(&storage, DUMMY_KEY, 2), Ok(false));
+
+        // Write a changelog at 2
+        EVERY
+            .write_changelog(&mut storage, DUMMY_KEY, 2, Some(3))
+            .unwrap();
+
+        assert_eq!(EVERY.has_changelog(&storage, DUMMY_KEY, 1), Ok(false));
+        assert_eq!(EVERY.has_changelog(&storage, DUMMY_KEY, 2), Ok(true));
+        assert_eq!(EVERY.has_changelog(&storage, DUMMY_KEY, 3), Ok(false));
+    }
+
+    #[test]
+    fn may_load_at_height() {
+        let mut storage = MockStorage::new();
+
+        assert_eq!(
+            NEVER.may_load_at_height(&storage, DUMMY_KEY, 3),
+            Err(StdError::not_found("checkpoint"))
+        );
+        assert_eq!(EVERY.may_load_at_height(&storage, DUMMY_KEY, 3), Ok(None));
+        assert_eq!(
+            SELECT.may_load_at_height(&storage, DUMMY_KEY, 3),
+            Err(StdError::not_found("checkpoint"))
+        );
+
+        // Add a checkpoint at 3
+        SELECT.add_checkpoint(&mut storage, 3).unwrap();
+        assert_eq!(SELECT.may_load_at_height(&storage, DUMMY_KEY, 3), Ok(None));
+
+        // Write a changelog at 3
+        EVERY
+            .write_changelog(&mut storage, DUMMY_KEY, 3, Some(100))
+            .unwrap();
+        SELECT
+            .write_changelog(&mut storage, DUMMY_KEY, 3, Some(101))
+            .unwrap();
+
+        assert_eq!(
+            EVERY.may_load_at_height(&storage, DUMMY_KEY, 2),
+            Ok(Some(Some(100)))
+        );
+        assert_eq!(
+            EVERY.may_load_at_height(&storage, DUMMY_KEY, 3),
+            Ok(Some(Some(100)))
+        );
+        assert_eq!(
+            SELECT.may_load_at_height(&storage, DUMMY_KEY, 3),
+            Ok(Some(Some(101)))
+        );
+
+        // Nothing was written after height 3
+        assert_eq!(EVERY.may_load_at_height(&storage, DUMMY_KEY, 4), Ok(None));
+    }
+}
@@ -0,0 +1,187 @@
+use cosmwasm_std::{StdResult, Storage, Uint128};
+
+use crate::error::{StorageError, StorageResult};
+use crate::keys::{KeyDeserialize, Prefixer, PrimaryKey};
+use crate::snapshot::{SnapshotMap, Strategy};
+
+/// CheckpointMap keeps the balance of every key at every block height, e.g. the voting power
+/// of an address for governance, so a vote counts the balance at the start of the proposal.
+///
+/// It is a `SnapshotMap` checkpointing every block: every change records the balance at the
+/// start of its block, and `balance_at` finds the first change at or after a height with a single
+/// ordered seek, however long the history is. A zero balance is stored as no entry.
+pub struct CheckpointMap<'a, K> {
+    balances: SnapshotMap<'a, K, Uint128>,
+}
+
+impl<'a, K> CheckpointMap<'a, K> {
+    /// Creates a `CheckpointMap` storing the current balances under `pk` and the previous ones
+    /// under `changelog`. `checkpoints` only keeps how far the history was pruned.
+    pub const fn new(pk: &'a str, checkpoints: &'a str, changelog: &'a str) -> Self {
+        CheckpointMap {
+            balances: SnapshotMap::new(pk, checkpoints, changelog, Strategy::EveryBlock),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.balances.namespace()
+    }
+}
+
+impl<'a, K> CheckpointMap<'a, K>
+where
+    K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+{
+    /// The underlying `SnapshotMap`, e.g. to range over the current balances
+    pub fn snapshots(&self) -> &SnapshotMap<'a, K, Uint128> {
+        &self.balances
+    }
+
+    /// Returns the current balance of `k`, zero if it has none
+    pub fn balance(&self, store: &dyn Storage, k: K) -> StdResult<Uint128> {
+        Ok(self.balances.may_load(store, k)?.unwrap_or_default())
+    }
+
+    /// Returns the balance of `k` at the start of block `height`, before any change made during
+    /// that block. Returns `StdError::NotFound` if the history at `height` was pruned
+    pub fn balance_at(&self, store: &dyn Storage, k: K, height: u64) -> StdResult<Uint128> {
+        Ok(self
+            .balances
+            .may_load_at_height(store, k, height)?
+            .unwrap_or_default())
+    }
+
+    /// Sets the balance of `k` at `height`, which must not be below the height of earlier writes
+    pub fn save(
+        &self,
+        store: &mut dyn Storage,
+        k: K,
+        balance: Uint128,
+        height: u64,
+    ) -> StorageResult<()> {
+        if balance.is_zero() {
+            self.balances.remove(store, k, height)?;
+        } else {
+            self.balances.save(store, k, &balance, height)?;
+        }
+        Ok(())
+    }
+
+    /// Adds `amount` to the balance of `k` at `height`, returns the new balance.
+    /// Returns `StorageError::Overflow` and changes nothing if it would overflow
+    pub fn increase(
+        &self,
+        store: &mut dyn Storage,
+        k: K,
+        amount: Uint128,
+        height: u64,
+    ) -> StorageResult<Uint128> {
+        let balance = self
+            .balance(store, k.clone())?
+            .u128()
+            .checked_add(amount.u128())
+            .ok_or(StorageError::Overflow {
+                collection: "CheckpointMap",
+            })?;
+        self.save(store, k, Uint128(balance), height)?;
+        Ok(Uint128(balance))
+    }
+
+    /// Subtracts `amount` from the balance of `k` at `height`, returns the new balance.
+    /// Returns `StorageError::InsufficientFunds` and changes nothing if the balance is less
+    /// than `amount`
+    pub fn decrease(
+        &self,
+        store: &mut dyn Storage,
+        k: K,
+        amount: Uint128,
+        height: u64,
+    ) -> StorageResult<Uint128> {
+        let available = self.balance(store, k.clone())?;
+        let balance = available.u128().checked_sub(amount.u128()).ok_or_else(|| {
+            StorageError::InsufficientFunds {
+                available: available.to_string(),
+                required: amount.to_string(),
+            }
+        })?;
+        self.save(store, k, Uint128(balance), height)?;
+        Ok(Uint128(balance))
+    }
+
+    /// Removes the history before `height`, keeping the current balances. Like
+    /// `SnapshotMap::prune_below` it reads at most `limit` entries and returns true while there is
+    /// more to remove. `balance_at` returns `StdError::NotFound` for any height below `height`
+    /// from the first call on
+    pub fn prune_below(&self, store: &mut dyn Storage, height: u64, limit: u32) -> StdResult<bool> {
+        self.balances.prune_below(store, height, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::HumanAddr as Addr;
+
+    const VOTES: CheckpointMap<&Addr> =
+        CheckpointMap::new("votes", "votes__check", "votes__change");
+
+    #[test]
+    fn balances_at_past_heights() {
+        let mut store = MockStorage::new();
+        let alice = Addr::from("alice");
+
+        VOTES
+            .increase(&mut store, &alice, Uint128(100), 10)
+            .unwrap();
+        VOTES.increase(&mut store, &alice, Uint128(50), 10).unwrap();
+        VOTES.decrease(&mut store, &alice, Uint128(30), 20).unwrap();
+        VOTES.save(&mut store, &alice, Uint128::zero(), 30).unwrap();
+        assert!(!VOTES.snapshots().has(&store, &alice));
+
+        assert_eq!(
+            VOTES.balance_at(&store, &alice, 5).unwrap(),
+            Uint128::zero()
+        );
+        // changes take effect after the block they were made in
+        assert_eq!(
+            VOTES.balance_at(&store, &alice, 10).unwrap(),
+            Uint128::zero()
+        );
+        assert_eq!(VOTES.balance_at(&store, &alice, 11).unwrap(), Uint128(150));
+        assert_eq!(VOTES.balance_at(&store, &alice, 20).unwrap(), Uint128(150));
+        assert_eq!(VOTES.balance_at(&store, &alice, 25).unwrap(), Uint128(120));
+        assert_eq!(
+            VOTES.balance_at(&store, &alice, 31).unwrap(),
+            Uint128::zero()
+        );
+        assert_eq!(VOTES.balance(&store, &alice).unwrap(), Uint128::zero());
+    }
+
+    #[test]
+    fn insufficient_balance_changes_nothing() {
+        let mut store = MockStorage::new();
+        let bob = Addr::from("bob");
+        VOTES.increase(&mut store, &bob, Uint128(10), 1).unwrap();
+
+        assert!(VOTES.decrease(&mut store, &bob, Uint128(11), 2).is_err());
+        assert!(VOTES
+            .increase(&mut store, &bob, Uint128(u128::MAX), 2)
+            .is_err());
+        assert_eq!(VOTES.balance(&store, &bob).unwrap(), Uint128(10));
+        assert_eq!(VOTES.balance_at(&store, &bob, 3).unwrap(), Uint128(10));
+    }
+
+    #[test]
+    fn pruned_history() {
+        let mut store = MockStorage::new();
+        let carol = Addr::from("carol");
+        VOTES.increase(&mut store, &carol, Uint128(1), 1).unwrap();
+        VOTES.increase(&mut store, &carol, Uint128(1), 5).unwrap();
+
+        assert!(!VOTES.prune_below(&mut store, 4, 100).unwrap());
+        assert!(VOTES.balance_at(&store, &carol, 3).is_err());
+        assert_eq!(VOTES.balance_at(&store, &carol, 4).unwrap(), Uint128(1));
+        assert_eq!(VOTES.balance(&store, &carol).unwrap(), Uint128(2));
+    }
+}
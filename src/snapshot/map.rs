@@ -0,0 +1,522 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use cosmwasm_std::{Order, StdError, StdResult, Storage, KV};
+
+use crate::error::StorageResult;
+use crate::keys::{KeyDeserialize, Prefixer, PrimaryKey};
+use crate::map::Map;
+use crate::path::Path;
+use crate::prefix::{Bound, Prefix};
+use crate::snapshot::{ChangeSet, Snapshot, Strategy};
+
+/// Map that maintains a snapshots of one or more checkpoints.
+/// We can query historical data as well as current state.
+/// What data is snapshotted depends on the Strategy.
+pub struct SnapshotMap<'a, K, T> {
+    primary: Map<'a, K, T>,
+    snapshots: Snapshot<'a, K, T>,
+}
+
+impl<'a, K, T> SnapshotMap<'a, K, T> {
+    /// Creates a `SnapshotMap` storing the current values under `pk`, the checkpointed
+    /// heights under `checkpoints` and the previous values under `changelog`.
+    pub const fn new(
+        pk: &'a str,
+        checkpoints: &'a str,
+        changelog: &'a str,
+        strategy: Strategy,
+    ) -> Self {
+        SnapshotMap {
+            primary: Map::new(pk),
+            snapshots: Snapshot::new(checkpoints, changelog, strategy),
+        }
+    }
+
+    pub fn changelog(&self) -> &Map<'a, (K, u64), ChangeSet<T>> {
+        &self.snapshots.changelog
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.primary.namespace()
+    }
+
+    pub fn add_checkpoint(&self, store: &mut dyn Storage, height: u64) -> StdResult<()> {
+        self.snapshots.add_checkpoint(store, height)
+    }
+
+    pub fn remove_checkpoint(&self, store: &mut dyn Storage, height: u64) -> StdResult<()> {
+        self.snapshots.remove_checkpoint(store, height)
+    }
+}
+
+impl<'a, K, T> SnapshotMap<'a, K, T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    K: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+{
+    pub fn key(&self, k: K) -> Path<T> {
+        self.primary.key(k)
+    }
+
+    /// save will store the value at `k`, recording the previous value in the changelog
+    /// at `height` if the strategy requires it
+    pub fn save(&self, store: &mut dyn Storage, k: K, data: &T, height: u64) -> StorageResult<()> {
+        if self.snapshots.should_checkpoint(store, &k)? {
+            self.write_change(store, k.clone(), height)?;
+        }
+        self.primary.save(store, k, data)
+    }
+
+    /// remove will delete the value at `k`, recording the previous value in the changelog
+    /// at `height` if the strategy requires it
+    pub fn remove(&self, store: &mut dyn Storage, k: K, height: u64) -> StdResult<()> {
+        if self.snapshots.should_checkpoint(store, &k)? {
+            self.write_change(store, k.clone(), height)?;
+        }
+        self.primary.remove(store, k);
+        Ok(())
+    }
+
+    /// load will return an error if no data is set at the given key, or on parse error
+    pub fn load(&self, store: &dyn Storage, k: K) -> StdResult<T> {
+        Ok(self.primary.load(store, k)?)
+    }
+
+    /// may_load will parse the data stored at the key if present, returns Ok(None) if no data there.
+    /// returns an error on issues parsing
+    pub fn may_load(&self, store: &dyn Storage, k: K) -> StdResult<Option<T>> {
+        Ok(self.primary.may_load(store, k)?)
+    }
+
+    /// has returns true or false if any data is at this key, without parsing or interpreting the
+    /// contents.
+    pub fn has(&self, store: &dyn Storage, k: K) -> bool {
+        self.primary.has(store, k)
+    }
+
+    /// may_load_at_height returns the value stored at `k` at the *start* of block `height`,
+    /// that is before any changes made during that block.
+    /// Returns `StdError::NotFound` if `height` was not checkpointed.
+    pub fn may_load_at_height(
+        &self,
+        store: &dyn Storage,
+        k: K,
+        height: u64,
+    ) -> StdResult<Option<T>> {
+        let snapshot = self
+            .snapshots
+            .may_load_at_height(store, k.clone(), height)?;
+
+        if let Some(r) = snapshot {
+            Ok(r)
+        } else {
+            // otherwise, return current value
+            self.may_load(store, k)
+        }
+    }
+
+    pub fn assert_checkpointed(&self, store: &dyn Storage, height: u64) -> StdResult<()> {
+        self.snapshots.assert_checkpointed(store, height)
+    }
+
+    /// prune_below removes the history before `height` to bound storage growth, keeping the
+    /// current value. It reads at most `limit` checkpoints and changes, and returns true while
+    /// there is more to remove. Loading at any height below `height` returns `StdError::NotFound`
+    /// from the first call on.
+    pub fn prune_below(&self, store: &mut dyn Storage, height: u64, limit: u32) -> StdResult<bool> {
+        self.snapshots.prune_below(store, height, limit)
+    }
+
+    /// Loads the data, perform the specified action, and store the result
+    /// in the database. This is shorthand for some common sequences, which may be useful.
+    ///
+    /// If the data exists, `action(Some(value))` is called. Otherwise `action(None)` is called.
+    ///
+    /// This is a bit more customized than needed to only read "old" value 1 time, not 2 per naive approach
+    pub fn update<A, E>(
+        &self,
+        store: &mut dyn Storage,
+        k: K,
+        height: u64,
+        action: A,
+    ) -> Result<T, E>
+    where
+        A: FnOnce(Option<T>) -> Result<T, E>,
+        E: From<StdError>,
+    {
+        let input = self.may_load(store, k.clone())?;
+        let output = action(input)?;
+        self.save(store, k, &output, height)
+            .map_err(StdError::from)?;
+        Ok(output)
+    }
+
+    fn write_change(&self, store: &mut dyn Storage, k: K, height: u64) -> StdResult<()> {
+        // if there is already data in the changelog for this key and block, do not write more
+        if self.snapshots.has_changelog(store, k.clone(), height)? {
+            return Ok(());
+        }
+        // otherwise, store the previous value
+        let old = self.primary.may_load(store, k.clone())?;
+        self.snapshots.write_changelog(store, k, height, old)
+    }
+
+    pub fn prefix(&self, p: K::Prefix) -> Prefix<K::Suffix, T, K::Suffix>
+    where
+        K::Suffix: PrimaryKey<'a>,
+    {
+        self.primary.prefix(p)
+    }
+
+    pub fn sub_prefix(&self, p: K::SubPrefix) -> Prefix<K::SuperSuffix, T, K::SuperSuffix>
+    where
+        K::SuperSuffix: PrimaryKey<'a>,
+    {
+        self.primary.sub_prefix(p)
+    }
+
+    pub fn range_raw<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<KV<T>>> + 'c>
+    where
+        T: 'c,
+    {
+        self.primary.range_raw(store, min, max, order)
+    }
+
+    pub fn keys_raw<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + 'c>
+    where
+        T: 'c,
+    {
+        self.primary.keys_raw(store, min, max, order)
+    }
+
+    pub fn range<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<(K::Output, T)>> + 'c>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.primary.range(store, min, max, order)
+    }
+
+    pub fn keys<'c>(
+        &self,
+        store: &'c dyn Storage,
+        min: Option<Bound<'a, K>>,
+        max: Option<Bound<'a, K>>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = StdResult<K::Output>> + 'c>
+    where
+        T: 'c,
+        K::Output: 'static,
+    {
+        self.primary.keys(store, min, max, order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    type TestMap = SnapshotMap<'static, &'static str, u64>;
+    type TestMapCompositeKey = SnapshotMap<'static, (&'static str, &'static str), u64>;
+
+    const NEVER: TestMap =
+        SnapshotMap::new("never", "never__check", "never__change", Strategy::Never);
+    const EVERY: TestMap = SnapshotMap::new(
+        "every",
+        "every__check",
+        "every__change",
+        Strategy::EveryBlock,
+    );
+    const EVERY_COMPOSITE_KEY: TestMapCompositeKey = SnapshotMap::new(
+        "every",
+        "every__check",
+        "every__change",
+        Strategy::EveryBlock,
+    );
+    const SELECT: TestMap = SnapshotMap::new(
+        "select",
+        "select__check",
+        "select__change",
+        Strategy::Selected,
+    );
+
+    // Fills a map &[u8] -> u64 with the following writes:
+    // 1: A = 5
+    // 2: B = 7
+    // 3: C = 1, A = 8
+    // 4: B = None, C = 13
+    // 5: A = None, D = 22
+    // Final values -> C = 13, D = 22
+    // Values at beginning of 3 -> A = 5, B = 7
+    // Values at beginning of 5 -> A = 8, C = 13
+    fn init_data(map: &TestMap, storage: &mut dyn Storage) {
+        map.save(storage, "A", &5, 1).unwrap();
+        map.save(storage, "B", &7, 2).unwrap();
+
+        // checkpoint 3
+        map.add_checkpoint(storage, 3).unwrap();
+
+        // also use update to set - to ensure this works
+        map.save(storage, "C", &1, 3).unwrap();
+        map.update(storage, "A", 3, |_| -> StdResult<u64> { Ok(8) })
+            .unwrap();
+
+        map.remove(storage, "B", 4).unwrap();
+        map.save(storage, "C", &13, 4).unwrap();
+
+        // checkpoint 5
+        map.add_checkpoint(storage, 5).unwrap();
+        map.remove(storage, "A", 5).unwrap();
+        map.update(storage, "D", 5, |_| -> StdResult<u64> { Ok(22) })
+            .unwrap();
+        // and delete it later (unknown if all data present)
+        map.remove_checkpoint(storage, 5).unwrap();
+    }
+
+    const FINAL_VALUES: &[(&str, Option<u64>)] =
+        &[("A", None), ("B", None), ("C", Some(13)), ("D", Some(22))];
+
+    const VALUES_START_3: &[(&str, Option<u64>)] =
+        &[("A", Some(5)), ("B", Some(7)), ("C", None), ("D", None)];
+
+    const VALUES_START_5: &[(&str, Option<u64>)] =
+        &[("A", Some(8)), ("B", None), ("C", Some(13)), ("D", None)];
+
+    // Same as `init_data`, but we have a composite key for testing range.
+    fn init_data_composite_key(map: &TestMapCompositeKey, storage: &mut dyn Storage) {
+        map.save(storage, ("A", "B"), &5, 1).unwrap();
+        map.save(storage, ("B", "A"), &7, 2).unwrap();
+
+        // checkpoint 3
+        map.add_checkpoint(storage, 3).unwrap();
+
+        // also use update to set - to ensure this works
+        map.save(storage, ("B", "B"), &1, 3).unwrap();
+        map.update(storage, ("A", "B"), 3, |_| -> StdResult<u64> { Ok(8) })
+            .unwrap();
+
+        map.remove(storage, ("B", "A"), 4).unwrap();
+        map.save(storage, ("B", "B"), &13, 4).unwrap();
+
+        // checkpoint 5
+        map.add_checkpoint(storage, 5).unwrap();
+        map.remove(storage, ("A", "B"), 5).unwrap();
+        map.update(storage, ("C", "A"), 5, |_| -> StdResult<u64> { Ok(22) })
+            .unwrap();
+        // and delete it later (unknown if all data present)
+        map.remove_checkpoint(storage, 5).unwrap();
+    }
+
+    fn assert_final_values(map: &TestMap, storage: &dyn Storage) {
+        for (k, v) in FINAL_VALUES.iter().cloned() {
+            assert_eq!(v, map.may_load(storage, k).unwrap());
+        }
+    }
+
+    fn assert_values_at_height(
+        map: &TestMap,
+        storage: &dyn Storage,
+        height: u64,
+        values: &[(&str, Option<u64>)],
+    ) {
+        for (k, v) in values.iter().cloned() {
+            assert_eq!(v, map.may_load_at_height(storage, k, height).unwrap());
+        }
+    }
+
+    fn assert_missing_checkpoint(map: &TestMap, storage: &dyn Storage, height: u64) {
+        for k in &["A", "B", "C", "D"] {
+            assert!(map.may_load_at_height(storage, *k, height).is_err());
+        }
+    }
+
+    #[test]
+    fn never_works_like_normal_map() {
+        let mut storage = MockStorage::new();
+        init_data(&NEVER, &mut storage);
+        assert_final_values(&NEVER, &storage);
+
+        // historical queries return error
+        assert_missing_checkpoint(&NEVER, &storage, 3);
+        assert_missing_checkpoint(&NEVER, &storage, 5);
+    }
+
+    #[test]
+    fn every_blocks_stores_present_and_past() {
+        let mut storage = MockStorage::new();
+        init_data(&EVERY, &mut storage);
+        assert_final_values(&EVERY, &storage);
+
+        // historical queries return historical values
+        assert_values_at_height(&EVERY, &storage, 3, VALUES_START_3);
+        assert_values_at_height(&EVERY, &storage, 5, VALUES_START_5);
+    }
+
+    #[test]
+    fn selected_shows_3_not_5() {
+        let mut storage = MockStorage::new();
+        init_data(&SELECT, &mut storage);
+        assert_final_values(&SELECT, &storage);
+
+        // historical queries return historical values
+        assert_values_at_height(&SELECT, &storage, 3, VALUES_START_3);
+        // never checkpointed
+        assert_missing_checkpoint(&NEVER, &storage, 1);
+        // deleted checkpoint
+        assert_missing_checkpoint(&NEVER, &storage, 5);
+    }
+
+    #[test]
+    fn handle_multiple_writes_in_one_block() {
+        let mut storage = MockStorage::new();
+
+        EVERY.save(&mut storage, "A", &5, 1).unwrap();
+        EVERY.save(&mut storage, "B", &7, 2).unwrap();
+        EVERY.save(&mut storage, "C", &2, 2).unwrap();
+
+        // update and save - A query at 3 => 5, at 4 => 12
+        EVERY
+            .update(&mut storage, "A", 3, |_| -> StdResult<u64> { Ok(9) })
+            .unwrap();
+        EVERY.save(&mut storage, "A", &12, 3).unwrap();
+        assert_eq!(Some(5), EVERY.may_load_at_height(&storage, "A", 2).unwrap());
+        assert_eq!(Some(5), EVERY.may_load_at_height(&storage, "A", 3).unwrap());
+        assert_eq!(
+            Some(12),
+            EVERY.may_load_at_height(&storage, "A", 4).unwrap()
+        );
+
+        // save and remove - B query at 4 => 7, at 5 => None
+        EVERY.save(&mut storage, "B", &17, 4).unwrap();
+        EVERY.remove(&mut storage, "B", 4).unwrap();
+        assert_eq!(Some(7), EVERY.may_load_at_height(&storage, "B", 3).unwrap());
+        assert_eq!(Some(7), EVERY.may_load_at_height(&storage, "B", 4).unwrap());
+        assert_eq!(None, EVERY.may_load_at_height(&storage, "B", 5).unwrap());
+
+        // remove and update - C query at 5 => 2, at 6 => 16
+        EVERY.remove(&mut storage, "C", 5).unwrap();
+        EVERY
+            .update(&mut storage, "C", 5, |_| -> StdResult<u64> { Ok(16) })
+            .unwrap();
+        assert_eq!(Some(2), EVERY.may_load_at_height(&storage, "C", 4).unwrap());
+        assert_eq!(Some(2), EVERY.may_load_at_height(&storage, "C", 5).unwrap());
+        assert_eq!(
+            Some(16),
+            EVERY.may_load_at_height(&storage, "C", 6).unwrap()
+        );
+    }
+
+    #[test]
+    fn prune_below_keeps_later_history() {
+        let mut storage = MockStorage::new();
+        init_data(&EVERY, &mut storage);
+
+        assert!(!EVERY.prune_below(&mut storage, 4, 100).unwrap());
+        assert_final_values(&EVERY, &storage);
+        assert_missing_checkpoint(&EVERY, &storage, 3);
+        assert_values_at_height(&EVERY, &storage, 4, &[("A", Some(8)), ("B", Some(7))]);
+        assert_values_at_height(&EVERY, &storage, 5, VALUES_START_5);
+
+        // only the changes made at 4 and 5 are left
+        let changes = EVERY
+            .changelog()
+            .keys(&storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                ("A".to_string(), 5),
+                ("B".to_string(), 4),
+                ("C".to_string(), 4),
+                ("D".to_string(), 5)
+            ]
+        );
+
+        // pruning below an earlier height does nothing
+        assert!(!EVERY.prune_below(&mut storage, 2, 100).unwrap());
+        assert_missing_checkpoint(&EVERY, &storage, 3);
+    }
+
+    #[test]
+    fn prune_below_in_batches() {
+        let mut storage = MockStorage::new();
+        init_data(&EVERY, &mut storage);
+
+        let mut calls = 1;
+        while EVERY.prune_below(&mut storage, 4, 2).unwrap() {
+            // older history is unreachable before the pruning is done
+            assert_missing_checkpoint(&EVERY, &storage, 3);
+            assert_values_at_height(&EVERY, &storage, 4, &[("A", Some(8)), ("B", Some(7))]);
+            calls += 1;
+        }
+        assert!(calls > 2);
+        assert_final_values(&EVERY, &storage);
+        assert_values_at_height(&EVERY, &storage, 5, VALUES_START_5);
+        let changes = EVERY
+            .changelog()
+            .keys(&storage, None, None, Order::Ascending)
+            .count();
+        assert_eq!(changes, 4);
+        assert!(!EVERY.prune_below(&mut storage, 4, 2).unwrap());
+    }
+
+    #[test]
+    fn prune_below_removes_checkpoints() {
+        let mut storage = MockStorage::new();
+        init_data(&SELECT, &mut storage);
+
+        assert!(!SELECT.prune_below(&mut storage, 4, 100).unwrap());
+        assert_final_values(&SELECT, &storage);
+        assert_missing_checkpoint(&SELECT, &storage, 3);
+        assert_eq!(
+            SELECT.snapshots.checkpoints.may_load(&storage, 3).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn range_composite_key() {
+        let mut store = MockStorage::new();
+        init_data_composite_key(&EVERY_COMPOSITE_KEY, &mut store);
+
+        let all: StdResult<Vec<_>> = EVERY_COMPOSITE_KEY
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        let all = all.unwrap();
+        assert_eq!(2, all.len());
+        assert_eq!(
+            all,
+            vec![
+                (("B".to_string(), "B".to_string()), 13),
+                (("C".to_string(), "A".to_string()), 22)
+            ]
+        );
+
+        let all: StdResult<Vec<_>> = EVERY_COMPOSITE_KEY
+            .prefix("B")
+            .range(&store, None, None, Order::Ascending)
+            .collect();
+        assert_eq!(all.unwrap(), vec![("B".to_string(), 13)]);
+    }
+}
@@ -0,0 +1,138 @@
+use cosmwasm_std::{StdResult, Storage};
+
+use crate::error::{StorageError, StorageResult};
+use crate::queue::{load_u32, save_u32};
+use crate::Map;
+
+/// A last-in, first-out stack of items, where every item is stored under its position
+/// and the number of items is kept in a separate counter
+pub struct Stack<'a, T> {
+    map: Map<'a, u32, T>,
+}
+
+impl<'a, T> Stack<'a, T> {
+    const LEN: &'static [u8] = b"_len";
+
+    pub const fn new(namespace: &'a str) -> Self {
+        Self {
+            map: Map::new(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+
+    /// Returns the number of items on the stack
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.with_namespace_suffix(Self::LEN, |ns| load_u32(store, ns))
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    fn set_len(&self, store: &mut dyn Storage, len: u32) {
+        self.with_namespace_suffix(Self::LEN, |ns| save_u32(store, ns, len))
+    }
+
+    fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
+        let namespace = &[self.namespace(), namespace].concat();
+        f(namespace)
+    }
+}
+
+impl<'a, T> Stack<'a, T>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    /// Pushes an item on top of the stack
+    pub fn push(&self, store: &mut dyn Storage, t: &T) -> StorageResult<()> {
+        let len = self.len(store);
+        if len == u32::MAX {
+            return Err(StorageError::CapacityExceeded {
+                collection: "Stack",
+            });
+        }
+
+        self.map.save(store, len, t)?;
+        self.set_len(store, len + 1);
+
+        Ok(())
+    }
+
+    /// Pops the item off the top of the stack, returns None if the stack is empty
+    pub fn pop(&self, store: &mut dyn Storage) -> StdResult<Option<T>> {
+        let len = self.len(store);
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let top = len - 1;
+        let popped = self.map.load(store, top)?;
+        self.map.remove(store, top);
+        self.set_len(store, top);
+
+        Ok(Some(popped))
+    }
+
+    /// Returns the item on top of the stack without removing it, returns None if the stack is empty
+    pub fn peek(&self, store: &dyn Storage) -> StdResult<Option<T>> {
+        match self.len(store) {
+            0 => Ok(None),
+            len => Ok(self.map.load(store, len - 1).map(Some)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cosmwasm_std::testing::MockStorage;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, proptest_derive::Arbitrary)]
+    enum Op {
+        Push(u8),
+        Pop,
+    }
+
+    const STACK: Stack<u8> = Stack::new("stack");
+
+    proptest! {
+        #[test]
+        fn impl_matches_model(ops: Vec<Op>) {
+            let mut model = Vec::new();
+            let mut store = MockStorage::new();
+            for op in ops {
+                match op {
+                    Op::Push(u) => {
+                        model.push(u);
+                        STACK.push(&mut store, &u).unwrap();
+                    }
+                    Op::Pop => {
+                        let impl_res = STACK.pop(&mut store).unwrap();
+                        prop_assert_eq!(model.pop(), impl_res, "pop results differ");
+                    }
+                }
+
+                prop_assert_eq!(STACK.len(&store), model.len() as u32, "len results differ");
+                prop_assert_eq!(STACK.is_empty(&store), model.is_empty(), "is_empty results differ");
+                prop_assert_eq!(STACK.peek(&store).unwrap(), model.last().copied(), "peek results differ");
+            }
+        }
+    }
+
+    #[test]
+    fn pop_deletes_slot() {
+        let mut store = MockStorage::new();
+        STACK.push(&mut store, &1).unwrap();
+        STACK.push(&mut store, &2).unwrap();
+
+        assert_eq!(STACK.pop(&mut store).unwrap(), Some(2));
+        assert!(!STACK.map.has(&store, 1));
+        assert_eq!(STACK.pop(&mut store).unwrap(), Some(1));
+        assert_eq!(STACK.pop(&mut store).unwrap(), None);
+    }
+}
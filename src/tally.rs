@@ -0,0 +1,149 @@
+use cosmwasm_std::{StdResult, Storage, Uint128};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::{StorageError, StorageResult};
+use crate::keys::PrimaryKey;
+use crate::numeric::Numeric;
+use crate::{Item, Map};
+
+/// Tally maps keys to weights, e.g. votes per option or stake per voter, and keeps the total
+/// of all weights up to date.
+///
+/// Keys whose weight drops to zero are removed. The total is stored next to the weights, under
+/// the namespace followed by `_total`.
+pub struct Tally<'a, K, W = Uint128> {
+    weights: Map<'a, K, W>,
+}
+
+impl<'a, K, W> Tally<'a, K, W> {
+    const TOTAL: &'static [u8] = b"_total";
+
+    pub const fn new(namespace: &'a str) -> Self {
+        Tally {
+            weights: Map::new(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.weights.namespace()
+    }
+
+    fn total_item(&self) -> Item<'static, W> {
+        Item::new_owned([self.namespace(), Self::TOTAL].concat())
+    }
+}
+
+impl<'a, K, W> Tally<'a, K, W>
+where
+    K: PrimaryKey<'a>,
+    W: Numeric + PartialEq + Serialize + DeserializeOwned,
+{
+    /// Returns the total of all weights
+    pub fn total(&self, store: &dyn Storage) -> StdResult<W> {
+        Ok(self.total_item().load_or_default(store)?)
+    }
+
+    /// Returns the weight of `k`, zero if it has none
+    pub fn weight(&self, store: &dyn Storage, k: K) -> StdResult<W> {
+        Ok(self.weights.load_or_default(store, k)?)
+    }
+
+    /// Adds `amount` to the weight of `k`, returns the new weight.
+    /// Returns an error and changes nothing if the total would overflow
+    pub fn add_weight(&self, store: &mut dyn Storage, k: K, amount: W) -> StorageResult<W> {
+        let total = self
+            .total(store)?
+            .checked_add(amount)
+            .ok_or(StorageError::Overflow {
+                collection: "Tally",
+            })?;
+        // a weight is never greater than the total, so it can't overflow either
+        let weight = self.weights.add(store, k, amount)?;
+        self.total_item().save(store, &total)?;
+        Ok(weight)
+    }
+
+    /// Subtracts `amount` from the weight of `k`, returns the new weight.
+    /// Returns `StorageError::InsufficientFunds` and changes nothing if the weight is less
+    /// than `amount`
+    pub fn sub_weight(&self, store: &mut dyn Storage, k: K, amount: W) -> StorageResult<W> {
+        let path = self.weights.key(k);
+        let available = path.may_load(store)?.unwrap_or_default();
+        let weight =
+            available
+                .checked_sub(amount)
+                .ok_or_else(|| StorageError::InsufficientFunds {
+                    available: available.to_string(),
+                    required: amount.to_string(),
+                })?;
+        if weight == W::default() {
+            path.remove(store);
+        } else {
+            path.save(store, &weight)?;
+        }
+        // the total is at least the weight
+        let total = self.total(store)?.saturating_sub(amount);
+        self.total_item().save(store, &total)?;
+        Ok(weight)
+    }
+
+    /// Removes the weight of `k`, returns the weight it had
+    pub fn remove(&self, store: &mut dyn Storage, k: K) -> StdResult<W> {
+        let weight = self.weight(store, k.clone())?;
+        self.sub_weight(store, k, weight)?;
+        Ok(weight)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    const VOTES: Tally<&str> = Tally::new("votes");
+
+    #[test]
+    fn keeps_the_total() {
+        let mut store = MockStorage::new();
+        assert_eq!(VOTES.total(&store).unwrap(), Uint128(0));
+
+        VOTES.add_weight(&mut store, "yes", Uint128(10)).unwrap();
+        VOTES.add_weight(&mut store, "no", Uint128(4)).unwrap();
+        assert_eq!(
+            VOTES.add_weight(&mut store, "yes", Uint128(5)).unwrap(),
+            Uint128(15)
+        );
+        assert_eq!(VOTES.total(&store).unwrap(), Uint128(19));
+
+        assert_eq!(
+            VOTES.sub_weight(&mut store, "yes", Uint128(3)).unwrap(),
+            Uint128(12)
+        );
+        assert_eq!(VOTES.total(&store).unwrap(), Uint128(16));
+
+        assert_eq!(
+            VOTES.sub_weight(&mut store, "no", Uint128(5)).unwrap_err(),
+            StorageError::InsufficientFunds {
+                available: "4".to_string(),
+                required: "5".to_string(),
+            }
+        );
+        assert_eq!(VOTES.total(&store).unwrap(), Uint128(16));
+
+        assert_eq!(VOTES.remove(&mut store, "no").unwrap(), Uint128(4));
+        assert_eq!(VOTES.weight(&store, "no").unwrap(), Uint128(0));
+        assert!(!VOTES.weights.has(&store, "no"));
+        assert_eq!(VOTES.total(&store).unwrap(), Uint128(12));
+    }
+
+    #[test]
+    fn overflow_changes_nothing() {
+        let mut store = MockStorage::new();
+        let tally: Tally<&str, u8> = Tally::new("small");
+
+        tally.add_weight(&mut store, "a", 200).unwrap();
+        assert!(tally.add_weight(&mut store, "b", 100).is_err());
+        assert_eq!(tally.weight(&store, "b").unwrap(), 0);
+        assert_eq!(tally.total(&store).unwrap(), 200);
+    }
+}
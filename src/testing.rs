@@ -0,0 +1,327 @@
+//! Model-based property testing, for contracts checking their own storage wrappers the way the
+//! collections of this crate are checked.
+//!
+//! Enabled by the `testing` feature, which is meant for dev-dependencies only. A collection runs
+//! a sequence of operations, generated by `map_ops` or `queue_ops`, next to a reference model,
+//! a `BTreeMap` or a bounded `VecDeque`, and every result is compared with the model's. `Map`,
+//! `CountedMap`, `IterableMap` and `Queue` implement `TestedMap` or `TestedQueue` already, a
+//! wrapper implements them by forwarding to its own methods.
+//!
+//! `MemoryStorage` fails writes on demand, to test what a composite operation leaves behind
+//! when it runs out of gas halfway. `FlakyStorage` wraps any storage and fails chosen
+//! operations on chosen namespaces.
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use secret_storage_lite::testing::{check_map_model, map_ops};
+//! use secret_storage_lite::Map;
+//!
+//! const BALANCES: Map<String, u64> = Map::new("balances");
+//!
+//! proptest! {
+//!     // marked `#[test]` in a test module
+//!     fn balances_behave_like_a_map(ops in map_ops("[a-c]", any::<u64>(), 0..50)) {
+//!         check_map_model(&BALANCES, ops)?;
+//!     }
+//! }
+//! # balances_behave_like_a_map();
+//! ```
+
+mod flaky_storage;
+mod memory_storage;
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Debug;
+
+use cosmwasm_std::testing::MockStorage;
+use cosmwasm_std::{StdError, StdResult, Storage};
+use proptest::collection::{vec, SizeRange};
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+
+use crate::keys::PrimaryKey;
+use crate::queue::Queue;
+use crate::serialization::Serde;
+use crate::{CountedMap, IterableMap, Map};
+
+pub use flaky_storage::{Failure, FlakyStorage, Operation};
+pub use memory_storage::MemoryStorage;
+
+/// A map-like collection checked by `check_map_model`
+pub trait TestedMap<K, V> {
+    fn save(&self, store: &mut dyn Storage, k: &K, v: &V) -> StdResult<()>;
+
+    fn remove(&self, store: &mut dyn Storage, k: &K) -> StdResult<()>;
+
+    fn may_load(&self, store: &dyn Storage, k: &K) -> StdResult<Option<V>>;
+
+    /// Returns the number of entries, None if the collection doesn't count them
+    fn len(&self, _store: &dyn Storage) -> Option<u32> {
+        None
+    }
+}
+
+/// A queue-like collection checked by `check_queue_model`
+pub trait TestedQueue<T> {
+    /// Appends `t`, returns false and leaves the queue untouched if it is full
+    fn push_back(&self, store: &mut dyn Storage, t: &T) -> StdResult<bool>;
+
+    fn pop_front(&self, store: &mut dyn Storage) -> StdResult<Option<T>>;
+
+    fn len(&self, store: &dyn Storage) -> u32;
+
+    /// Returns the number of items the queue can hold
+    fn capacity(&self) -> u32 {
+        u32::MAX
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MapOp<K, V> {
+    Save(K, V),
+    Remove(K),
+}
+
+#[derive(Debug, Clone)]
+pub enum QueueOp<T> {
+    Push(T),
+    Pop,
+}
+
+/// Generates sequences of saves and removes of keys from `keys`.
+/// A few distinct keys make keys more likely to be saved twice or removed after a save
+pub fn map_ops<K, V>(
+    keys: K,
+    values: V,
+    len: impl Into<SizeRange>,
+) -> impl Strategy<Value = Vec<MapOp<K::Value, V::Value>>>
+where
+    K: Strategy + Clone,
+    V: Strategy,
+{
+    let op = prop_oneof![
+        (keys.clone(), values).prop_map(|(k, v)| MapOp::Save(k, v)),
+        keys.prop_map(MapOp::Remove),
+    ];
+    vec(op, len)
+}
+
+/// Generates sequences of pushes of items from `items` and pops
+pub fn queue_ops<T>(
+    items: T,
+    len: impl Into<SizeRange>,
+) -> impl Strategy<Value = Vec<QueueOp<T::Value>>>
+where
+    T: Strategy,
+    T::Value: Clone,
+{
+    let op = prop_oneof![items.prop_map(QueueOp::Push), Just(QueueOp::Pop)];
+    vec(op, len)
+}
+
+fn fail(err: StdError) -> TestCaseError {
+    TestCaseError::fail(err.to_string())
+}
+
+/// Runs `ops` on `map` in a fresh `MockStorage` and on a `BTreeMap`, failing as soon as a key
+/// touched so far, or the length, differs between the two
+pub fn check_map_model<K, V, M>(map: &M, ops: Vec<MapOp<K, V>>) -> Result<(), TestCaseError>
+where
+    K: Ord + Clone + Debug,
+    V: Clone + PartialEq + Debug,
+    M: TestedMap<K, V>,
+{
+    let mut store = MockStorage::new();
+    let mut model = BTreeMap::new();
+    let mut touched = BTreeSet::new();
+    for op in ops {
+        match op {
+            MapOp::Save(k, v) => {
+                map.save(&mut store, &k, &v).map_err(fail)?;
+                touched.insert(k.clone());
+                model.insert(k, v);
+            }
+            MapOp::Remove(k) => {
+                map.remove(&mut store, &k).map_err(fail)?;
+                model.remove(&k);
+                touched.insert(k);
+            }
+        }
+
+        if let Some(len) = map.len(&store) {
+            prop_assert_eq!(len as usize, model.len(), "len differs");
+        }
+        for k in &touched {
+            let loaded = map.may_load(&store, k).map_err(fail)?;
+            prop_assert_eq!(loaded.as_ref(), model.get(k), "value of {:?} differs", k);
+        }
+    }
+    Ok(())
+}
+
+/// Runs `ops` on `queue` in a fresh `MockStorage` and on a `VecDeque` bounded to its capacity,
+/// failing as soon as a result or the length differs between the two
+pub fn check_queue_model<T, Q>(queue: &Q, ops: Vec<QueueOp<T>>) -> Result<(), TestCaseError>
+where
+    T: PartialEq + Debug,
+    Q: TestedQueue<T>,
+{
+    let mut store = MockStorage::new();
+    let mut model = VecDeque::new();
+    let capacity = queue.capacity() as usize;
+    for op in ops {
+        match op {
+            QueueOp::Push(t) => {
+                let pushed = queue.push_back(&mut store, &t).map_err(fail)?;
+                prop_assert_eq!(pushed, model.len() < capacity, "push result differs");
+                if pushed {
+                    model.push_back(t);
+                }
+            }
+            QueueOp::Pop => {
+                let popped = queue.pop_front(&mut store).map_err(fail)?;
+                prop_assert_eq!(popped, model.pop_front(), "pop result differs");
+            }
+        }
+        prop_assert_eq!(queue.len(&store) as usize, model.len(), "len differs");
+    }
+    Ok(())
+}
+
+impl<'a, K, V, Ser> TestedMap<K, V> for Map<'a, K, V, Ser>
+where
+    K: PrimaryKey<'a>,
+    Ser: Serde<V>,
+{
+    fn save(&self, store: &mut dyn Storage, k: &K, v: &V) -> StdResult<()> {
+        Ok(Map::save(self, store, k.clone(), v)?)
+    }
+
+    fn remove(&self, store: &mut dyn Storage, k: &K) -> StdResult<()> {
+        Map::remove(self, store, k.clone());
+        Ok(())
+    }
+
+    fn may_load(&self, store: &dyn Storage, k: &K) -> StdResult<Option<V>> {
+        Ok(Map::may_load(self, store, k.clone())?)
+    }
+}
+
+impl<'a, K, V, Ser> TestedMap<K, V> for CountedMap<'a, K, V, Ser>
+where
+    K: PrimaryKey<'a>,
+    Ser: Serde<V>,
+{
+    fn save(&self, store: &mut dyn Storage, k: &K, v: &V) -> StdResult<()> {
+        Ok(CountedMap::save(self, store, k.clone(), v)?)
+    }
+
+    fn remove(&self, store: &mut dyn Storage, k: &K) -> StdResult<()> {
+        CountedMap::remove(self, store, k.clone());
+        Ok(())
+    }
+
+    fn may_load(&self, store: &dyn Storage, k: &K) -> StdResult<Option<V>> {
+        Ok(CountedMap::may_load(self, store, k.clone())?)
+    }
+
+    fn len(&self, store: &dyn Storage) -> Option<u32> {
+        Some(CountedMap::len(self, store))
+    }
+}
+
+impl<'a, K, V, Ser> TestedMap<K, V> for IterableMap<'a, K, V, Ser>
+where
+    K: PrimaryKey<'a>,
+    Ser: Serde<V>,
+{
+    fn save(&self, store: &mut dyn Storage, k: &K, v: &V) -> StdResult<()> {
+        Ok(IterableMap::save(self, store, k.clone(), v)?)
+    }
+
+    fn remove(&self, store: &mut dyn Storage, k: &K) -> StdResult<()> {
+        IterableMap::remove(self, store, k.clone()).map(|_| ())
+    }
+
+    fn may_load(&self, store: &dyn Storage, k: &K) -> StdResult<Option<V>> {
+        IterableMap::may_load(self, store, k.clone())
+    }
+
+    fn len(&self, store: &dyn Storage) -> Option<u32> {
+        Some(IterableMap::len(self, store))
+    }
+}
+
+impl<'a, T, Ser: Serde<T>> TestedQueue<T> for Queue<'a, T, Ser> {
+    fn push_back(&self, store: &mut dyn Storage, t: &T) -> StdResult<bool> {
+        Ok(Queue::push_back(self, store, t)?)
+    }
+
+    fn pop_front(&self, store: &mut dyn Storage) -> StdResult<Option<T>> {
+        Ok(Queue::pop_front(self, store)?)
+    }
+
+    fn len(&self, store: &dyn Storage) -> u32 {
+        Queue::len(self, store)
+    }
+
+    fn capacity(&self) -> u32 {
+        self.max_capacity()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Item;
+
+    const COUNTED: CountedMap<u8, u16> = CountedMap::new("counted");
+    const ITERABLE: IterableMap<u8, u16> = IterableMap::new("iterable");
+    const QUEUE: Queue<u8> = Queue::with_capacity("queue", 5);
+
+    /// A wrapper keeping its entries in a single `Item`
+    struct InOneItem(Item<'static, BTreeMap<u8, u16>>);
+
+    impl TestedMap<u8, u16> for InOneItem {
+        fn save(&self, store: &mut dyn Storage, k: &u8, v: &u16) -> StdResult<()> {
+            let mut all = self.0.load_or_default(store)?;
+            all.insert(*k, *v);
+            Ok(self.0.save(store, &all)?)
+        }
+
+        fn remove(&self, store: &mut dyn Storage, k: &u8) -> StdResult<()> {
+            let mut all = self.0.load_or_default(store)?;
+            // a bug the model catches
+            if *k != 3 {
+                all.remove(k);
+            }
+            Ok(self.0.save(store, &all)?)
+        }
+
+        fn may_load(&self, store: &dyn Storage, k: &u8) -> StdResult<Option<u16>> {
+            Ok(self.0.load_or_default(store)?.get(k).copied())
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn collections_match_their_models(
+            map in map_ops(0u8..8, any::<u16>(), 0..40),
+            queue in queue_ops(any::<u8>(), 0..40),
+        ) {
+            check_map_model(&COUNTED, map.clone())?;
+            check_map_model(&ITERABLE, map)?;
+            check_queue_model(&QUEUE, queue)?;
+        }
+    }
+
+    #[test]
+    fn finds_differences_from_the_model() {
+        let buggy = InOneItem(Item::new("buggy"));
+        let ops = vec![MapOp::Save(3, 1), MapOp::Save(4, 2), MapOp::Remove(3)];
+        let err = check_map_model(&buggy, ops).unwrap_err();
+        assert!(err.to_string().contains("value of 3 differs"), "{}", err);
+
+        check_map_model(&buggy, vec![MapOp::Save(4, 2), MapOp::Remove(4)]).unwrap();
+    }
+}
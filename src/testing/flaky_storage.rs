@@ -0,0 +1,266 @@
+use std::cell::Cell;
+
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, KV};
+use cosmwasm_std::{ReadonlyStorage, Storage};
+
+use crate::helpers::split_namespace;
+
+/// A storage operation a `FlakyStorage` can fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Get,
+    /// Every entry a range returns counts as one
+    Range,
+    Set,
+    Remove,
+}
+
+/// How a `FlakyStorage` fails an operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// Panics like a contract running out of gas
+    Panic,
+    /// Reads find nothing and writes are lost, so loads return errors
+    Missing,
+}
+
+struct Rule {
+    namespace: Vec<u8>,
+    operation: Operation,
+    /// Fails only the nth matching operation, counted from 1, or every one
+    nth: Option<u32>,
+    seen: Cell<u32>,
+    failure: Failure,
+}
+
+impl Rule {
+    /// Counts the operation if it matches, returns the failure if it is to fail
+    fn check(&self, namespace: &[u8], operation: Operation) -> Option<Failure> {
+        if self.operation != operation || self.namespace != namespace {
+            return None;
+        }
+        self.seen.set(self.seen.get() + 1);
+        match self.nth {
+            Some(nth) if nth != self.seen.get() => None,
+            _ => Some(self.failure),
+        }
+    }
+}
+
+/// FlakyStorage wraps a `Storage` and fails the operations on a namespace it is told to,
+/// to test how the transactional wrapper, a migration or a handler copes with a failure halfway.
+///
+/// Namespaces are matched like `CountingStorage` groups them: the length-prefixed top level
+/// namespace of a key, or the whole key if it has none.
+pub struct FlakyStorage<'a> {
+    storage: &'a mut dyn Storage,
+    rules: Vec<Rule>,
+}
+
+impl<'a> FlakyStorage<'a> {
+    pub fn new(storage: &'a mut dyn Storage) -> Self {
+        FlakyStorage {
+            storage,
+            rules: vec![],
+        }
+    }
+
+    /// Fails every `operation` on `namespace`
+    pub fn fail_on(&mut self, namespace: &[u8], operation: Operation, failure: Failure) {
+        self.add_rule(namespace, operation, None, failure)
+    }
+
+    /// Fails only the `nth` `operation` on `namespace` from now, counted from 1
+    pub fn fail_on_nth(
+        &mut self,
+        namespace: &[u8],
+        operation: Operation,
+        nth: u32,
+        failure: Failure,
+    ) {
+        assert!(nth > 0, "operations are counted from 1");
+        self.add_rule(namespace, operation, Some(nth), failure)
+    }
+
+    /// Removes all failures, e.g. to check the state left behind
+    pub fn clear_failures(&mut self) {
+        self.rules.clear();
+    }
+
+    fn add_rule(
+        &mut self,
+        namespace: &[u8],
+        operation: Operation,
+        nth: Option<u32>,
+        failure: Failure,
+    ) {
+        self.rules.push(Rule {
+            namespace: namespace.to_vec(),
+            operation,
+            nth,
+            seen: Cell::new(0),
+            failure,
+        });
+    }
+
+    /// Returns the failure of `operation` on `key`, panicking for `Failure::Panic`
+    fn check(&self, key: &[u8], operation: Operation) -> Option<Failure> {
+        let namespace = match split_namespace(key) {
+            ([], _) => key,
+            (namespace, _) => namespace,
+        };
+        let mut failure = None;
+        for rule in &self.rules {
+            // every rule counts the operation, even if an earlier one fails it
+            failure = failure.or(rule.check(namespace, operation));
+        }
+        if failure == Some(Failure::Panic) {
+            panic!(
+                "{:?} on namespace {:?} failed as requested",
+                operation,
+                String::from_utf8_lossy(namespace)
+            );
+        }
+        failure
+    }
+}
+
+impl<'a> ReadonlyStorage for FlakyStorage<'a> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        match self.check(key, Operation::Get) {
+            Some(_) => None,
+            None => self.storage.get(key),
+        }
+    }
+
+    #[cfg(feature = "iterator")]
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = KV> + 'b> {
+        let iter = self
+            .storage
+            .range(start, end, order)
+            .filter(move |(k, _)| self.check(k, Operation::Range).is_none());
+        Box::new(iter)
+    }
+}
+
+impl<'a> Storage for FlakyStorage<'a> {
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        if self.check(key, Operation::Set).is_none() {
+            self.storage.set(key, value)
+        }
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        if self.check(key, Operation::Remove).is_none() {
+            self.storage.remove(key)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::StdResult;
+
+    use crate::migrator::Migrator;
+    use crate::{transactional, Item, Map};
+
+    const CONFIG: Item<u64> = Item::new("config");
+    const BALANCES: Map<&str, u64> = Map::new_unhashed("balances");
+
+    #[test]
+    fn fails_only_the_chosen_operations() {
+        let mut store = MockStorage::new();
+        let mut flaky = FlakyStorage::new(&mut store);
+        flaky.fail_on(b"config", Operation::Get, Failure::Missing);
+        flaky.fail_on_nth(b"balances", Operation::Set, 2, Failure::Missing);
+
+        CONFIG.save(&mut flaky, &1).unwrap();
+        assert!(CONFIG.load(&flaky).is_err());
+        BALANCES.save(&mut flaky, "alice", &1).unwrap();
+        BALANCES.save(&mut flaky, "bob", &2).unwrap();
+        BALANCES.save(&mut flaky, "carol", &3).unwrap();
+        assert!(!BALANCES.has(&flaky, "bob"));
+        assert!(BALANCES.has(&flaky, "carol"));
+
+        flaky.clear_failures();
+        assert_eq!(CONFIG.load(&flaky).unwrap(), 1);
+    }
+
+    #[test]
+    fn panics_on_request() {
+        let mut store = MockStorage::new();
+        let mut flaky = FlakyStorage::new(&mut store);
+        flaky.fail_on(b"balances", Operation::Remove, Failure::Panic);
+        BALANCES.save(&mut flaky, "alice", &1).unwrap();
+
+        let result = catch_unwind(AssertUnwindSafe(|| BALANCES.remove(&mut flaky, "alice")));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert_eq!(
+            message,
+            r#"Remove on namespace "balances" failed as requested"#
+        );
+        assert!(BALANCES.has(&flaky, "alice"));
+    }
+
+    #[test]
+    fn failed_transactions_write_nothing() {
+        let mut store = MockStorage::new();
+        let mut flaky = FlakyStorage::new(&mut store);
+        flaky.fail_on(b"config", Operation::Get, Failure::Missing);
+
+        let result = transactional(&mut flaky, |tx| -> StdResult<()> {
+            BALANCES.save(tx, "alice", &1)?;
+            CONFIG.load(tx)?;
+            BALANCES.save(tx, "bob", &2)?;
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert!(!BALANCES.has(&flaky, "alice"));
+    }
+
+    #[test]
+    fn failed_migration_steps_keep_the_version() {
+        let mut store = MockStorage::new();
+        let migrator = Migrator::new("version")
+            .add_step(0, |store| Ok(CONFIG.save(store, &1)?))
+            .add_step(1, |store| {
+                let config = CONFIG.load(store)?;
+                Ok(CONFIG.save(store, &(config + 1))?)
+            });
+        let mut flaky = FlakyStorage::new(&mut store);
+        flaky.fail_on_nth(b"config", Operation::Get, 1, Failure::Missing);
+
+        assert!(migrator.migrate(&mut flaky, 2).is_err());
+        assert_eq!(migrator.version(&flaky).unwrap(), 1);
+        // the next attempt continues with the failed step
+        assert_eq!(migrator.migrate(&mut flaky, 2).unwrap(), 2);
+        assert_eq!(CONFIG.load(&flaky).unwrap(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn ranges_skip_failed_entries() {
+        let mut store = MockStorage::new();
+        for name in ["alice", "bob", "carol"] {
+            BALANCES.save(&mut store, name, &1).unwrap();
+        }
+        let mut flaky = FlakyStorage::new(&mut store);
+        flaky.fail_on_nth(b"balances", Operation::Range, 2, Failure::Missing);
+
+        let keys = BALANCES
+            .keys(&flaky, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(keys.len(), 2);
+    }
+}
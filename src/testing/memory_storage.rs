@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+#[cfg(feature = "iterator")]
+use std::ops::Bound;
+
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, KV};
+use cosmwasm_std::{ReadonlyStorage, Storage};
+
+/// MemoryStorage is an in-memory `Storage` that can fail like a contract running out of gas:
+/// once its keys and values take more than a byte limit, or on a chosen write.
+///
+/// A failing write panics before it is applied, as a `Storage` can't return errors. A test
+/// catches the panic with `std::panic::catch_unwind` and then inspects what a composite
+/// operation wrote before it, which a real chain would have reverted.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStorage {
+    data: BTreeMap<Vec<u8>, Vec<u8>>,
+    byte_limit: Option<u64>,
+    failing_write: Option<u32>,
+    writes: u32,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a storage whose keys and values may take at most `limit` bytes in total
+    pub fn with_byte_limit(limit: u64) -> Self {
+        MemoryStorage {
+            byte_limit: Some(limit),
+            ..Self::default()
+        }
+    }
+
+    /// Makes the `nth` write from now, counting sets and removes from 1, panic
+    pub fn fail_on_write(&mut self, nth: u32) {
+        assert!(nth > 0, "writes are counted from 1");
+        self.failing_write = Some(self.writes + nth);
+    }
+
+    /// Returns the number of sets and removes so far
+    pub fn writes(&self) -> u32 {
+        self.writes
+    }
+
+    /// Returns the total size of the keys and values stored
+    pub fn bytes_used(&self) -> u64 {
+        self.data
+            .iter()
+            .map(|(key, value)| (key.len() + value.len()) as u64)
+            .sum()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn count_write(&mut self) {
+        self.writes += 1;
+        if self.failing_write == Some(self.writes) {
+            self.failing_write = None;
+            panic!("write {} failed as requested", self.writes);
+        }
+    }
+}
+
+impl ReadonlyStorage for MemoryStorage {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.data.get(key).cloned()
+    }
+
+    #[cfg(feature = "iterator")]
+    fn range<'a>(
+        &'a self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = KV> + 'a> {
+        let start = start.map_or(Bound::Unbounded, |s| Bound::Included(s.to_vec()));
+        let end = end.map_or(Bound::Unbounded, |e| Bound::Excluded(e.to_vec()));
+        if let (Bound::Included(s), Bound::Excluded(e)) = (&start, &end) {
+            if s >= e {
+                return Box::new(std::iter::empty());
+            }
+        }
+        let iter = self
+            .data
+            .range((start, end))
+            .map(|(k, v)| (k.clone(), v.clone()));
+        match order {
+            Order::Ascending => Box::new(iter),
+            Order::Descending => Box::new(iter.rev()),
+        }
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.count_write();
+        if let Some(limit) = self.byte_limit {
+            let replaced = self.data.get(key).map_or(0, |old| key.len() + old.len());
+            let used = self.bytes_used() - replaced as u64 + (key.len() + value.len()) as u64;
+            if used > limit {
+                panic!(
+                    "storage limit of {} bytes exceeded, writing {} bytes would use {}",
+                    limit,
+                    key.len() + value.len(),
+                    used
+                );
+            }
+        }
+        self.data.insert(key.to_vec(), value.to_vec());
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.count_write();
+        self.data.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use super::*;
+    use cosmwasm_std::StdResult;
+
+    use crate::{transactional, Map};
+
+    const BALANCES: Map<&str, u64> = Map::new_unhashed("balances");
+
+    #[test]
+    fn fails_on_the_chosen_write() {
+        let mut store = MemoryStorage::new();
+        BALANCES.save(&mut store, "alice", &1).unwrap();
+
+        store.fail_on_write(2);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            BALANCES
+                .save_many(&mut store, [("bob", &2), ("carol", &3), ("dave", &4)])
+                .unwrap()
+        }));
+        assert!(result.is_err());
+        // the write before the failing one is left behind
+        assert_eq!(store.writes(), 3);
+        assert_eq!(BALANCES.may_load(&store, "bob").unwrap(), Some(2));
+        assert_eq!(BALANCES.may_load(&store, "carol").unwrap(), None);
+
+        // only that write fails
+        BALANCES.save(&mut store, "carol", &3).unwrap();
+    }
+
+    #[test]
+    fn transactions_write_nothing_before_failing() {
+        let mut store = MemoryStorage::new();
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            transactional(&mut store, |tx| -> StdResult<()> {
+                BALANCES.save(tx, "alice", &1)?;
+                BALANCES.save(tx, "bob", &2)?;
+                panic!("out of gas")
+            })
+        }));
+        assert!(result.is_err());
+        assert!(store.is_empty());
+        assert_eq!(store.writes(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "storage limit of 50 bytes exceeded")]
+    fn limits_the_bytes_stored() {
+        let mut store = MemoryStorage::with_byte_limit(50);
+        // 15 bytes of key and 8 of value each
+        BALANCES.save(&mut store, "alice", &1).unwrap();
+        BALANCES.save(&mut store, "carol", &2).unwrap();
+        assert_eq!(store.bytes_used(), 46);
+        // overwriting takes no more room
+        BALANCES.save(&mut store, "alice", &3).unwrap();
+        BALANCES.remove(&mut store, "carol");
+        BALANCES.save(&mut store, "carol", &2).unwrap();
+
+        BALANCES.save(&mut store, "dave", &4).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn ranges_like_mock_storage() {
+        use cosmwasm_std::testing::MockStorage;
+
+        let mut memory = MemoryStorage::new();
+        let mut mock = MockStorage::new();
+        for key in [&b"a"[..], b"ab", b"b", b"c"] {
+            memory.set(key, key);
+            mock.set(key, key);
+        }
+        for (start, end) in [
+            (None, None),
+            (Some(&b"ab"[..]), Some(&b"c"[..])),
+            (Some(b"b"), Some(b"a")),
+        ] {
+            for order in [Order::Ascending, Order::Descending] {
+                assert_eq!(
+                    memory.range(start, end, order).collect::<Vec<_>>(),
+                    mock.range(start, end, order).collect::<Vec<_>>()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use cosmwasm_std::{Order, StdResult, Storage};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::{StorageError, StorageResult};
+use crate::keys::{Height, KeyDeserialize, Prefixer, PrimaryKey, Timestamp};
+use crate::prefix::Bound;
+use crate::queue::{load_u32, save_u32};
+use crate::serialization::{Bincode2, Serde};
+use crate::Map;
+
+/// TimeQueue holds items until they mature, e.g. unbonding tokens.
+///
+/// Items are pushed with a maturity, a `Timestamp` by default or a `Height`, and stored under
+/// it, so `pop_mature` only reads the items it returns. Items maturing at the same time are
+/// returned in the order they were pushed, `push` returns the sequence number ordering them, so
+/// an item can be removed with `remove` before it matures.
+pub struct TimeQueue<'a, T, M = Timestamp, Ser = Bincode2> {
+    map: Map<'a, (M, u32), T, Ser>,
+}
+
+/// A `TimeQueue` whose items mature at a block height
+pub type HeightQueue<'a, T, Ser = Bincode2> = TimeQueue<'a, T, Height, Ser>;
+
+impl<'a, T, M, Ser> TimeQueue<'a, T, M, Ser> {
+    const LEN: &'static [u8] = b"_len";
+    const SEQ: &'static [u8] = b"_seq";
+
+    pub const fn new(namespace: &'a str) -> Self {
+        Self {
+            map: Map::new(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+
+    /// Returns the number of items in the queue, mature or not
+    pub fn len(&self, store: &dyn Storage) -> u32 {
+        self.with_namespace_suffix(Self::LEN, |ns| load_u32(store, ns))
+    }
+
+    pub fn is_empty(&self, store: &dyn Storage) -> bool {
+        self.len(store) == 0
+    }
+
+    fn set_len(&self, store: &mut dyn Storage, len: u32) {
+        self.with_namespace_suffix(Self::LEN, |ns| save_u32(store, ns, len))
+    }
+
+    /// Returns the sequence number the next item is pushed with
+    pub(crate) fn next_seq(&self, store: &dyn Storage) -> u32 {
+        self.with_namespace_suffix(Self::SEQ, |ns| load_u32(store, ns))
+    }
+
+    fn with_namespace_suffix<R, F: FnOnce(&[u8]) -> R>(&self, namespace: &[u8], f: F) -> R {
+        let namespace = &[self.namespace(), namespace].concat();
+        f(namespace)
+    }
+}
+
+impl<'a, T, M, Ser> TimeQueue<'a, T, M, Ser>
+where
+    T: Serialize + DeserializeOwned + 'static,
+    M: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize<Output = M> + Copy + 'static,
+    Ser: Serde<T>,
+{
+    /// Adds an item maturing at `maturity`, returns its sequence number to `remove` it with
+    pub fn push(&self, store: &mut dyn Storage, maturity: M, t: &T) -> StorageResult<u32> {
+        let len = self.len(store);
+        if len == u32::MAX {
+            return Err(StorageError::CapacityExceeded {
+                collection: "TimeQueue",
+            });
+        }
+
+        // the sequence only orders items of the same maturity, so it may wrap around:
+        // a key is only reused if an item is still pending 2^32 pushes later at the same maturity
+        let seq = self.next_seq(store);
+        self.map.save(store, (maturity, seq), t)?;
+        self.with_namespace_suffix(Self::SEQ, |ns| save_u32(store, ns, seq.wrapping_add(1)));
+        self.set_len(store, len + 1);
+        Ok(seq)
+    }
+
+    /// Removes the item pushed at `maturity` with sequence number `seq` and returns it,
+    /// None if it was removed or popped before
+    pub fn remove(&self, store: &mut dyn Storage, maturity: M, seq: u32) -> StdResult<Option<T>> {
+        let t = self.map.may_load(store, (maturity, seq))?;
+        if t.is_some() {
+            self.map.remove(store, (maturity, seq));
+            let len = self.len(store);
+            self.set_len(store, len - 1);
+        }
+        Ok(t)
+    }
+
+    /// Returns the maturity of the item maturing first, None if the queue is empty
+    pub fn next_maturity(&self, store: &dyn Storage) -> StdResult<Option<M>> {
+        self.map
+            .keys(store, None, None, Order::Ascending)
+            .next()
+            .transpose()
+            .map(|key| key.map(|(maturity, _)| maturity))
+    }
+
+    /// Returns up to `limit` items that matured at or before `now`, with their maturity,
+    /// without removing them
+    pub fn mature(&self, store: &dyn Storage, now: M, limit: u32) -> StdResult<Vec<(M, T)>> {
+        self.map
+            .range(
+                store,
+                None,
+                Some(Bound::inclusive((now, u32::MAX))),
+                Order::Ascending,
+            )
+            .take(limit as usize)
+            .map(|entry| entry.map(|((maturity, _), t)| (maturity, t)))
+            .collect()
+    }
+
+    /// Removes and returns up to `limit` items that matured at or before `now`,
+    /// the earliest maturity first
+    pub fn pop_mature(&self, store: &mut dyn Storage, now: M, limit: u32) -> StdResult<Vec<T>> {
+        let entries = self.pop_mature_entries(store, now, limit)?;
+        Ok(entries.into_iter().map(|(_, t)| t).collect())
+    }
+
+    /// Like `pop_mature`, but returns the items with their maturity and sequence number
+    pub(crate) fn pop_mature_entries(
+        &self,
+        store: &mut dyn Storage,
+        now: M,
+        limit: u32,
+    ) -> StdResult<Vec<((M, u32), T)>> {
+        let entries = self
+            .map
+            .range(
+                store,
+                None,
+                Some(Bound::inclusive((now, u32::MAX))),
+                Order::Ascending,
+            )
+            .take(limit as usize)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for (key, _) in &entries {
+            self.map.remove(store, *key);
+        }
+        let len = self.len(store);
+        self.set_len(store, len - entries.len() as u32);
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    #[test]
+    fn pops_mature_items_in_order() {
+        let mut store = MockStorage::new();
+        let unbonding: TimeQueue<u64> = TimeQueue::new("unbonding");
+        assert_eq!(unbonding.next_maturity(&store).unwrap(), None);
+
+        unbonding.push(&mut store, Timestamp(300), &3).unwrap();
+        unbonding.push(&mut store, Timestamp(100), &1).unwrap();
+        unbonding.push(&mut store, Timestamp(200), &2).unwrap();
+        unbonding.push(&mut store, Timestamp(100), &10).unwrap();
+        assert_eq!(unbonding.len(&store), 4);
+        assert_eq!(
+            unbonding.next_maturity(&store).unwrap(),
+            Some(Timestamp(100))
+        );
+
+        assert_eq!(
+            unbonding.pop_mature(&mut store, Timestamp(99), 10).unwrap(),
+            Vec::<u64>::new()
+        );
+        assert_eq!(
+            unbonding.mature(&store, Timestamp(200), 10).unwrap(),
+            vec![
+                (Timestamp(100), 1),
+                (Timestamp(100), 10),
+                (Timestamp(200), 2)
+            ]
+        );
+        assert_eq!(
+            unbonding.pop_mature(&mut store, Timestamp(200), 2).unwrap(),
+            vec![1, 10]
+        );
+        assert_eq!(
+            unbonding
+                .pop_mature(&mut store, Timestamp(250), 10)
+                .unwrap(),
+            vec![2]
+        );
+        assert_eq!(unbonding.len(&store), 1);
+        assert_eq!(
+            unbonding
+                .pop_mature(&mut store, Timestamp(u64::MAX), 10)
+                .unwrap(),
+            vec![3]
+        );
+        assert!(unbonding.is_empty(&store));
+    }
+
+    #[test]
+    fn removes_items_by_sequence_number() {
+        let mut store = MockStorage::new();
+        let unbonding: TimeQueue<u64> = TimeQueue::new("unbonding");
+        let first = unbonding.push(&mut store, Timestamp(100), &1).unwrap();
+        let second = unbonding.push(&mut store, Timestamp(100), &2).unwrap();
+        assert_eq!((first, second), (0, 1));
+
+        assert_eq!(
+            unbonding.remove(&mut store, Timestamp(100), first).unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            unbonding.remove(&mut store, Timestamp(100), first).unwrap(),
+            None
+        );
+        // the maturity is part of the key
+        assert_eq!(
+            unbonding
+                .remove(&mut store, Timestamp(200), second)
+                .unwrap(),
+            None
+        );
+        assert_eq!(unbonding.len(&store), 1);
+        assert_eq!(
+            unbonding
+                .pop_mature(&mut store, Timestamp(100), 10)
+                .unwrap(),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn height_queue() {
+        let mut store = MockStorage::new();
+        let queue: HeightQueue<String> = HeightQueue::new("queue");
+        queue
+            .push(&mut store, Height(10), &"b".to_string())
+            .unwrap();
+        queue.push(&mut store, Height(5), &"a".to_string()).unwrap();
+
+        assert_eq!(
+            queue.pop_mature(&mut store, Height(10), 10).unwrap(),
+            vec!["a".to_string(), "b".to_string()]
+        );
+    }
+}
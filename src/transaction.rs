@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+#[cfg(feature = "iterator")]
+use std::iter::Peekable;
+#[cfg(feature = "iterator")]
+use std::ops::Bound;
+
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, KV};
+use cosmwasm_std::{ReadonlyStorage, Storage};
+
+/// A pending change to a single key
+enum Delta {
+    Set(Vec<u8>),
+    Delete,
+}
+
+/// StorageTransaction wraps a `Storage`, buffering all writes and deletes in memory.
+/// Reads see the buffered changes on top of the wrapped storage.
+///
+/// Nothing is written to the wrapped storage until `commit` is called,
+/// `rollback` (or simply dropping the transaction) discards all buffered changes.
+pub struct StorageTransaction<'a> {
+    storage: &'a mut dyn Storage,
+    local_state: BTreeMap<Vec<u8>, Delta>,
+}
+
+impl<'a> StorageTransaction<'a> {
+    pub fn new(storage: &'a mut dyn Storage) -> Self {
+        StorageTransaction {
+            storage,
+            local_state: BTreeMap::new(),
+        }
+    }
+
+    /// Writes all buffered changes to the wrapped storage
+    pub fn commit(self) {
+        for (key, delta) in self.local_state {
+            match delta {
+                Delta::Set(value) => self.storage.set(&key, &value),
+                Delta::Delete => self.storage.remove(&key),
+            }
+        }
+    }
+
+    /// Discards all buffered changes, leaving the wrapped storage untouched
+    pub fn rollback(self) {}
+}
+
+impl<'a> ReadonlyStorage for StorageTransaction<'a> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        match self.local_state.get(key) {
+            Some(Delta::Set(value)) => Some(value.clone()),
+            Some(Delta::Delete) => None,
+            None => self.storage.get(key),
+        }
+    }
+
+    #[cfg(feature = "iterator")]
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = KV> + 'b> {
+        let base = self.storage.range(start, end, order);
+
+        // an empty range, BTreeMap::range panics on inverted bounds
+        if let (Some(start), Some(end)) = (start, end) {
+            if start >= end {
+                return Box::new(std::iter::empty());
+            }
+        }
+        let bounds = (
+            start.map_or(Bound::Unbounded, |s| Bound::Included(s.to_vec())),
+            end.map_or(Bound::Unbounded, |e| Bound::Excluded(e.to_vec())),
+        );
+        let local: Box<dyn Iterator<Item = (&Vec<u8>, &Delta)>> = match order {
+            Order::Ascending => Box::new(self.local_state.range(bounds)),
+            Order::Descending => Box::new(self.local_state.range(bounds).rev()),
+        };
+
+        Box::new(MergeOverlay {
+            base: base.peekable(),
+            local: local.peekable(),
+            order,
+        })
+    }
+}
+
+impl<'a> Storage for StorageTransaction<'a> {
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.local_state
+            .insert(key.to_vec(), Delta::Set(value.to_vec()));
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.local_state.insert(key.to_vec(), Delta::Delete);
+    }
+}
+
+/// Runs `action` in a new transaction over `storage`, committing the changes
+/// it made only if it succeeds
+pub fn transactional<T, E, F>(storage: &mut dyn Storage, action: F) -> Result<T, E>
+where
+    F: FnOnce(&mut StorageTransaction) -> Result<T, E>,
+{
+    let mut transaction = StorageTransaction::new(storage);
+    let result = action(&mut transaction)?;
+    transaction.commit();
+    Ok(result)
+}
+
+/// Merges the buffered changes into the ordered entries of the wrapped storage
+#[cfg(feature = "iterator")]
+struct MergeOverlay<'b, B, L>
+where
+    B: Iterator<Item = KV>,
+    L: Iterator<Item = (&'b Vec<u8>, &'b Delta)>,
+{
+    base: Peekable<B>,
+    local: Peekable<L>,
+    order: Order,
+}
+
+#[cfg(feature = "iterator")]
+impl<'b, B, L> Iterator for MergeOverlay<'b, B, L>
+where
+    B: Iterator<Item = KV>,
+    L: Iterator<Item = (&'b Vec<u8>, &'b Delta)>,
+{
+    type Item = KV;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let take_local = match (self.base.peek(), self.local.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (Some((base_key, _)), Some((local_key, _))) => {
+                    if base_key == *local_key {
+                        // the buffered change shadows the stored entry
+                        self.base.next();
+                        true
+                    } else {
+                        match self.order {
+                            Order::Ascending => *local_key < base_key,
+                            Order::Descending => *local_key > base_key,
+                        }
+                    }
+                }
+            };
+
+            if !take_local {
+                return self.base.next();
+            }
+            match self.local.next() {
+                Some((key, Delta::Set(value))) => return Some((key.clone(), value.clone())),
+                // deleted entries are skipped
+                Some((_, Delta::Delete)) => continue,
+                None => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::{StdError, StdResult};
+
+    use crate::{Item, Map};
+
+    const CONFIG: Item<u64> = Item::new("config");
+    const BALANCES: Map<&str, u64> = Map::new("balances");
+
+    #[test]
+    fn commit_writes_changes() {
+        let mut store = MockStorage::new();
+        BALANCES.save(&mut store, "alice", &10).unwrap();
+        BALANCES.save(&mut store, "bob", &20).unwrap();
+
+        let mut tx = StorageTransaction::new(&mut store);
+        CONFIG.save(&mut tx, &1).unwrap();
+        BALANCES.save(&mut tx, "alice", &15).unwrap();
+        BALANCES.remove(&mut tx, "bob");
+
+        // reads see the buffered changes
+        assert_eq!(CONFIG.load(&tx).unwrap(), 1);
+        assert_eq!(BALANCES.load(&tx, "alice").unwrap(), 15);
+        assert!(!BALANCES.has(&tx, "bob"));
+        tx.commit();
+
+        assert_eq!(CONFIG.load(&store).unwrap(), 1);
+        assert_eq!(BALANCES.load(&store, "alice").unwrap(), 15);
+        assert!(!BALANCES.has(&store, "bob"));
+    }
+
+    #[test]
+    fn rollback_discards_changes() {
+        let mut store = MockStorage::new();
+        BALANCES.save(&mut store, "alice", &10).unwrap();
+
+        let mut tx = StorageTransaction::new(&mut store);
+        CONFIG.save(&mut tx, &1).unwrap();
+        BALANCES.remove(&mut tx, "alice");
+        tx.rollback();
+
+        assert_eq!(CONFIG.may_load(&store).unwrap(), None);
+        assert_eq!(BALANCES.load(&store, "alice").unwrap(), 10);
+    }
+
+    #[test]
+    fn transactional_commits_only_on_success() {
+        let mut store = MockStorage::new();
+
+        let res: StdResult<()> = transactional(&mut store, |tx| {
+            BALANCES.save(tx, "alice", &10)?;
+            Err(StdError::generic_err("oops"))
+        });
+        assert!(res.is_err());
+        assert!(!BALANCES.has(&store, "alice"));
+
+        let res = transactional(&mut store, |tx| -> StdResult<_> {
+            BALANCES.save(tx, "alice", &10)?;
+            BALANCES.save(tx, "bob", &20)?;
+            Ok(2)
+        });
+        assert_eq!(res.unwrap(), 2);
+        assert_eq!(BALANCES.load(&store, "alice").unwrap(), 10);
+        assert_eq!(BALANCES.load(&store, "bob").unwrap(), 20);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_merges_changes() {
+        let mut store = MockStorage::new();
+        store.set(b"a", b"1");
+        store.set(b"c", b"3");
+        store.set(b"e", b"5");
+
+        let mut tx = StorageTransaction::new(&mut store);
+        tx.set(b"b", b"2");
+        tx.set(b"c", b"33");
+        tx.remove(b"e");
+        tx.set(b"f", b"6");
+
+        let all: Vec<_> = tx.range(None, None, Order::Ascending).collect();
+        assert_eq!(
+            all,
+            vec![
+                (b"a".to_vec(), b"1".to_vec()),
+                (b"b".to_vec(), b"2".to_vec()),
+                (b"c".to_vec(), b"33".to_vec()),
+                (b"f".to_vec(), b"6".to_vec()),
+            ]
+        );
+
+        let all: Vec<_> = tx
+            .range(Some(b"b"), Some(b"f"), Order::Descending)
+            .collect();
+        assert_eq!(
+            all,
+            vec![
+                (b"c".to_vec(), b"33".to_vec()),
+                (b"b".to_vec(), b"2".to_vec()),
+            ]
+        );
+
+        assert_eq!(
+            tx.range(Some(b"f"), Some(b"b"), Order::Ascending).count(),
+            0
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use cosmwasm_std::{StdResult, Storage};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::any::type_name;
+
+use crate::error::{StorageError, StorageResult};
+use crate::helpers::{serialize, split_namespace};
+use crate::keys::PrimaryKey;
+use crate::path::Path;
+use crate::Map;
+
+/// The value stored for every key, alongside its version.
+/// A removed entry keeps its version, with no value.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Versioned<T> {
+    version: u64,
+    value: Option<T>,
+}
+
+/// VersionedMap works like a `Map`, but every write to an entry increments the entry's version.
+/// `save_if_version` and `remove_if_version` only write if the entry is still at the version
+/// the caller read, so handlers racing across submessages can't overwrite each other's changes.
+///
+/// Entries that were never written are at version 0. Removing an entry keeps its version,
+/// so a removed and re-created entry never repeats a version.
+pub struct VersionedMap<'a, K, T> {
+    map: Map<'a, K, Versioned<T>>,
+}
+
+impl<'a, K, T> VersionedMap<'a, K, T> {
+    pub const fn new(namespace: &'a str) -> Self {
+        VersionedMap {
+            map: Map::new(namespace),
+        }
+    }
+
+    pub fn namespace(&self) -> &[u8] {
+        self.map.namespace()
+    }
+}
+
+impl<'a, K, T> VersionedMap<'a, K, T>
+where
+    T: Serialize + DeserializeOwned,
+    K: PrimaryKey<'a>,
+{
+    /// save will serialize the model and store it at the given key,
+    /// returns the new version of the entry or an error on serialization issues
+    pub fn save(&self, store: &mut dyn Storage, k: K, data: &T) -> StorageResult<u64> {
+        let path = self.map.key(k);
+        let current = self.current_version(store, &path)?;
+        self.write(store, &path, current, Some(data))
+    }
+
+    /// save_if_version saves like `save`, but only if the entry is at version `expected`,
+    /// returns `StorageError::VersionMismatch` otherwise
+    pub fn save_if_version(
+        &self,
+        store: &mut dyn Storage,
+        k: K,
+        data: &T,
+        expected: u64,
+    ) -> StorageResult<u64> {
+        let path = self.map.key(k);
+        let current = self.checked_version(store, &path, expected)?;
+        self.write(store, &path, current, Some(data))
+    }
+
+    /// remove removes the value at the given key, returns the new version of the entry.
+    /// Removing an absent entry leaves it untouched.
+    pub fn remove(&self, store: &mut dyn Storage, k: K) -> StorageResult<u64> {
+        let path = self.map.key(k);
+        match path.may_load(store)? {
+            Some(Versioned {
+                version,
+                value: Some(_),
+            }) => self.write(store, &path, version, None),
+            Some(Versioned { version, .. }) => Ok(version),
+            None => Ok(0),
+        }
+    }
+
+    /// remove_if_version removes like `remove`, but only if the entry is at version `expected`,
+    /// returns `StorageError::VersionMismatch` otherwise
+    pub fn remove_if_version(
+        &self,
+        store: &mut dyn Storage,
+        k: K,
+        expected: u64,
+    ) -> StorageResult<u64> {
+        let path = self.map.key(k);
+        let current = self.checked_version(store, &path, expected)?;
+        match path.may_load(store)? {
+            Some(Versioned { value: Some(_), .. }) => self.write(store, &path, current, None),
+            _ => Ok(current),
+        }
+    }
+
+    /// load will return an error if no data is set at the given key, or on parse error
+    pub fn load(&self, store: &dyn Storage, k: K) -> StdResult<T> {
+        self.load_with_version(store, k).map(|(data, _)| data)
+    }
+
+    /// load_with_version returns the data at the given key along with its version, to pass
+    /// to `save_if_version` later. Returns an error if no data is set, or on parse error
+    pub fn load_with_version(&self, store: &dyn Storage, k: K) -> StdResult<(T, u64)> {
+        let path = self.map.key(k);
+        match path.may_load(store)? {
+            Some(Versioned {
+                version,
+                value: Some(data),
+            }) => Ok((data, version)),
+            _ => Err(StorageError::NotFound {
+                type_name: type_name::<T>(),
+                namespace: String::from_utf8_lossy(self.namespace()).into_owned(),
+                key: split_namespace(&path).1.to_vec(),
+            }
+            .into()),
+        }
+    }
+
+    /// may_load will parse the data stored at the key if present, returns Ok(None) if no data there.
+    /// returns an error on issues parsing
+    pub fn may_load(&self, store: &dyn Storage, k: K) -> StdResult<Option<T>> {
+        Ok(self.map.may_load(store, k)?.and_then(|v| v.value))
+    }
+
+    /// has returns true if data is at this key
+    pub fn has(&self, store: &dyn Storage, k: K) -> StdResult<bool> {
+        Ok(self.may_load(store, k)?.is_some())
+    }
+
+    /// Returns the version of the entry at the given key, 0 if it was never written
+    pub fn version(&self, store: &dyn Storage, k: K) -> StdResult<u64> {
+        self.current_version(store, &self.map.key(k))
+    }
+
+    fn current_version(&self, store: &dyn Storage, path: &Path<Versioned<T>>) -> StdResult<u64> {
+        Ok(path.may_load(store)?.map_or(0, |v| v.version))
+    }
+
+    fn checked_version(
+        &self,
+        store: &dyn Storage,
+        path: &Path<Versioned<T>>,
+        expected: u64,
+    ) -> StorageResult<u64> {
+        let actual = self.current_version(store, path)?;
+        if actual != expected {
+            return Err(StorageError::VersionMismatch { expected, actual });
+        }
+        Ok(actual)
+    }
+
+    fn write(
+        &self,
+        store: &mut dyn Storage,
+        path: &Path<Versioned<T>>,
+        current: u64,
+        data: Option<&T>,
+    ) -> StorageResult<u64> {
+        let version = current.checked_add(1).ok_or(StorageError::Overflow {
+            collection: "VersionedMap",
+        })?;
+        // serializes just like a `Versioned<T>`, without having to clone `data`
+        let value = Versioned {
+            version,
+            value: data,
+        };
+        store.set(path, &serialize(&value)?);
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::StdError;
+
+    const ORDERS: VersionedMap<&str, String> = VersionedMap::new("orders");
+
+    fn assert_mismatch(res: StorageResult<u64>, expected: u64, actual: u64) {
+        assert_eq!(
+            res.unwrap_err(),
+            StorageError::VersionMismatch { expected, actual }
+        );
+    }
+
+    #[test]
+    fn versions_increase_on_every_write() {
+        let mut store = MockStorage::new();
+        assert_eq!(ORDERS.version(&store, "1").unwrap(), 0);
+
+        assert_eq!(
+            ORDERS.save(&mut store, "1", &"open".to_string()).unwrap(),
+            1
+        );
+        assert_eq!(
+            ORDERS.save(&mut store, "1", &"filled".to_string()).unwrap(),
+            2
+        );
+        assert_eq!(
+            ORDERS.load_with_version(&store, "1").unwrap(),
+            ("filled".to_string(), 2)
+        );
+
+        // removal keeps counting
+        assert_eq!(ORDERS.remove(&mut store, "1").unwrap(), 3);
+        assert_eq!(ORDERS.remove(&mut store, "1").unwrap(), 3);
+        assert!(!ORDERS.has(&store, "1").unwrap());
+        assert!(matches!(
+            ORDERS.load(&store, "1"),
+            Err(StdError::NotFound { .. })
+        ));
+        assert_eq!(
+            ORDERS.save(&mut store, "1", &"open".to_string()).unwrap(),
+            4
+        );
+
+        // other keys are independent
+        assert_eq!(ORDERS.remove(&mut store, "2").unwrap(), 0);
+        assert_eq!(ORDERS.version(&store, "2").unwrap(), 0);
+    }
+
+    #[test]
+    fn compare_and_swap() {
+        let mut store = MockStorage::new();
+
+        // creating expects version 0
+        assert_mismatch(
+            ORDERS.save_if_version(&mut store, "1", &"open".to_string(), 1),
+            1,
+            0,
+        );
+        assert_eq!(
+            ORDERS
+                .save_if_version(&mut store, "1", &"open".to_string(), 0)
+                .unwrap(),
+            1
+        );
+
+        // two handlers read the same version, only the first one to write wins
+        let (_, read_version) = ORDERS.load_with_version(&store, "1").unwrap();
+        ORDERS
+            .save_if_version(&mut store, "1", &"filled".to_string(), read_version)
+            .unwrap();
+        assert_mismatch(
+            ORDERS.save_if_version(&mut store, "1", &"cancelled".to_string(), read_version),
+            1,
+            2,
+        );
+        assert_eq!(ORDERS.load(&store, "1").unwrap(), "filled");
+
+        assert_mismatch(ORDERS.remove_if_version(&mut store, "1", 1), 1, 2);
+        assert_eq!(ORDERS.remove_if_version(&mut store, "1", 2).unwrap(), 3);
+        // a handler that read the entry before it was removed can't re-create it
+        assert_mismatch(
+            ORDERS.save_if_version(&mut store, "1", &"open".to_string(), 0),
+            0,
+            3,
+        );
+    }
+}